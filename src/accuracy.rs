@@ -0,0 +1,190 @@
+use crate::json;
+use crate::pgn_ast::Line;
+use crate::uci::Score;
+
+/// Evaluation of the starting position, in centipawns for white.
+pub const INITIAL_CENTIPAWNS: i32 = 15;
+
+/// The `[%eval ...]` annotation of a comment, such as `[%eval 0.31]` or `[%eval #-3]`, as a
+/// score for white.
+pub fn eval_annotation(comment: &str) -> Option<Score> {
+    let start = comment.find("[%eval ")? + "[%eval ".len();
+    let end = start + comment[start..].find(']')?;
+    // Some tools append the depth, as in `0.31,20`
+    let value = comment[start..end].split(',').next()?.trim();
+
+    match value.strip_prefix('#') {
+        Some(moves) => moves.parse().ok().map(Score::Mate),
+        None => {
+            let pawns: f64 = value.parse().ok()?;
+            Some(Score::Centipawns((pawns * 100.0).round() as i32))
+        }
+    }
+}
+
+/// Evaluations for white after each mainline move, from their `[%eval]` annotations. A mating
+/// move needs none.
+pub fn mainline_evals(line: &Line) -> Vec<Option<Score>> {
+    line.moves
+        .iter()
+        .enumerate()
+        .map(|(ply, node)| {
+            let annotated = node
+                .comments
+                .iter()
+                .find_map(|comment| eval_annotation(comment));
+            let mate = node.suffix.contains('#').then(|| {
+                let sign = if ply % 2 == 0 { 1 } else { -1 };
+                Score::Centipawns(sign * 1000)
+            });
+            annotated.or(mate)
+        })
+        .collect()
+}
+
+/// Chances of winning for white, from 0 to 100, with the coefficient fitted by Lichess on
+/// rated games.
+pub fn win_percent(score: Score) -> f64 {
+    let centipawns = f64::from(score.centipawns().clamp(-1000, 1000));
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * centipawns).exp()) - 1.0)
+}
+
+/// Accuracy of a move from the mover's win percentages before and after it.
+pub fn move_accuracy(before: f64, after: f64) -> f64 {
+    if after >= before {
+        return 100.0;
+    }
+    let raw =
+        103.1668100711649 * (-0.04354415386753951 * (before - after)).exp() - 3.166924740191411;
+    // Lichess adds one point for the imperfection of the analysis
+    (raw + 1.0).clamp(0.0, 100.0)
+}
+
+fn standard_deviation(values: &[f64]) -> f64 {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64;
+    variance.sqrt()
+}
+
+/// Accuracy percentage of each player over a game, white first; None for a player without an
+/// evaluated move.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct GameAccuracy {
+    pub white: Option<f64>,
+    pub black: Option<f64>,
+}
+
+impl GameAccuracy {
+    /// Lichess' game accuracy from the evaluations after each move: the mean of the move
+    /// accuracies weighted by how volatile the game was around each move, averaged with their
+    /// harmonic mean so that blunders weigh in. The evaluations must be for white, as from
+    /// `mainline_evals`; moves next to a missing evaluation are left out.
+    pub fn of(evals: &[Option<Score>]) -> GameAccuracy {
+        let wins: Vec<Option<f64>> = [Some(Score::Centipawns(INITIAL_CENTIPAWNS))]
+            .into_iter()
+            .chain(evals.iter().copied())
+            .map(|score| score.map(win_percent))
+            .collect();
+
+        let window = (evals.len() / 10).clamp(2, 8);
+        // Missing evaluations count as unchanged for the volatility
+        let mut known = 50.0;
+        let filled: Vec<f64> = wins
+            .iter()
+            .map(|win| {
+                known = win.unwrap_or(known);
+                known
+            })
+            .collect();
+        let windows: Vec<&[f64]> = (0..window.saturating_sub(2))
+            .map(|_| &filled[..window.min(filled.len())])
+            .chain(filled.windows(window))
+            .collect();
+
+        let mut sides: [Vec<(f64, f64)>; 2] = [Vec::new(), Vec::new()];
+        for (ply, pair) in wins.windows(2).enumerate() {
+            let (Some(before), Some(after)) = (pair[0], pair[1]) else {
+                continue;
+            };
+            let (before, after) = if ply % 2 == 0 {
+                (before, after)
+            } else {
+                (100.0 - before, 100.0 - after)
+            };
+            let weight = windows
+                .get(ply)
+                .map_or(0.5, |values| standard_deviation(values).clamp(0.5, 12.0));
+            sides[ply % 2].push((move_accuracy(before, after), weight));
+        }
+
+        let [white, black] = sides.map(|moves| {
+            if moves.is_empty() {
+                return None;
+            }
+            let weighted = moves
+                .iter()
+                .map(|(accuracy, weight)| accuracy * weight)
+                .sum::<f64>()
+                / moves.iter().map(|(_, weight)| weight).sum::<f64>();
+            let harmonic = if moves.iter().any(|(accuracy, _)| *accuracy <= 0.0) {
+                0.0
+            } else {
+                moves.len() as f64
+                    / moves
+                        .iter()
+                        .map(|(accuracy, _)| 1.0 / accuracy)
+                        .sum::<f64>()
+            };
+            Some((weighted + harmonic) / 2.0)
+        });
+
+        GameAccuracy { white, black }
+    }
+
+    /// `WhiteAccuracy` and `BlackAccuracy` tags for the players with an accuracy.
+    pub fn tags(&self) -> Vec<(&'static str, String)> {
+        [("WhiteAccuracy", self.white), ("BlackAccuracy", self.black)]
+            .into_iter()
+            .filter_map(|(tag, accuracy)| Some((tag, format!("{:.1}", accuracy?))))
+            .collect()
+    }
+}
+
+/// Per-game accuracies of both players, for the JSON report.
+#[derive(Default)]
+pub struct AccuracyReport {
+    games: Vec<String>,
+}
+
+impl AccuracyReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_game(
+        &mut self,
+        game_number: usize,
+        white: &str,
+        black: &str,
+        accuracy: GameAccuracy,
+    ) {
+        let percent = |accuracy: Option<f64>| {
+            accuracy.map_or("null".to_string(), |value| format!("{value:.1}"))
+        };
+        self.games.push(json::object([
+            ("game", game_number.to_string()),
+            ("white", json::string(white)),
+            ("black", json::string(black)),
+            ("white_accuracy", percent(accuracy.white)),
+            ("black_accuracy", percent(accuracy.black)),
+        ]));
+    }
+
+    pub fn to_json(&self) -> String {
+        json::array(self.games.iter().cloned())
+    }
+}
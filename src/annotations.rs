@@ -0,0 +1,56 @@
+use chess::utils::string_to_square;
+
+use crate::notation::{write_moves, OutputNotation};
+use crate::position::Position;
+
+/// Where to insert `{ FEN: ... }` comments describing the position after a move.
+#[derive(Clone, Copy, Default)]
+pub struct FenInjection {
+    /// After every N plies.
+    pub every: Option<usize>,
+    pub captures: bool,
+    pub checks: bool,
+}
+
+impl FenInjection {
+    pub fn is_enabled(&self) -> bool {
+        self.every.is_some() || self.captures || self.checks
+    }
+
+    fn wants(&self, ply: usize, before: &Position, after: &Position, mv: &str) -> bool {
+        let every = self
+            .every
+            .is_some_and(|every| (ply + 1).is_multiple_of(every));
+        let capture = self.captures
+            && before.is_capture(string_to_square(&mv[0..2]), string_to_square(&mv[2..4]));
+        let check = self.checks && after.in_check(after.side_to_move);
+
+        every || capture || check
+    }
+
+    /// The move list in `notation` with FEN comments after the selected moves.
+    /// `positions` holds the position before each move followed by the final one.
+    pub fn render(
+        &self,
+        positions: &[Position],
+        moves: &[String],
+        notation: &dyn OutputNotation,
+    ) -> String {
+        let written = write_moves(notation, positions, moves);
+        let mut movetext = String::new();
+
+        for (ply, (mv, written)) in moves.iter().zip(written).enumerate() {
+            if ply > 0 {
+                movetext.push_str(notation.separator());
+            }
+            movetext.push_str(&written);
+            if let (Some(before), Some(after)) = (positions.get(ply), positions.get(ply + 1)) {
+                if self.wants(ply, before, after, mv) {
+                    movetext.push_str(&format!(" {{ FEN: {} }}", after.to_fen()));
+                }
+            }
+        }
+
+        movetext
+    }
+}
@@ -0,0 +1,190 @@
+//! The boards SAN is resolved against. The processor only needs piece lookup, move legality
+//! and move play from its board, so any move generator can back it through `BoardBackend`.
+
+use chess::bitboard::BitBoardGetter;
+use chess::board::Board;
+use chess::legal_moves::is_move_possible::is_possible;
+use chess::legal_moves::misc::{Color, Square};
+
+use crate::position::{Piece, Position};
+
+/// A board the processor plays games on. Side to move, castling rights and clocks are tracked
+/// by the processor, so a backend only has to know where the pieces are.
+pub trait BoardBackend {
+    fn name(&self) -> &'static str;
+
+    /// Sets up the initial position.
+    fn reset(&mut self);
+
+    /// Squares of the pieces of a color and kind.
+    fn pieces(&self, color: Color, piece: Piece) -> Vec<Square>;
+
+    /// Whether the piece on `from` can move to `to` without leaving its king in check.
+    /// Castling is not asked for, the processor playing it through `castle`.
+    fn is_possible(&self, from: Square, to: Square) -> bool;
+
+    /// Plays a move found possible. `promotion` is only given when `supports_promotion`.
+    fn play_move(&mut self, from: Square, to: Square, promotion: Option<Piece>);
+
+    /// Plays `O-O` or `O-O-O` for a color.
+    fn castle(&mut self, castle: &str, color: Color);
+
+    /// Whether pawns reaching the last rank can be promoted.
+    fn supports_promotion(&self) -> bool {
+        false
+    }
+
+    /// Sets up the pieces of a position, for games that do not start from the initial one.
+    /// Returns false when the backend can only start from the initial position.
+    fn set_position(&mut self, _position: &Position) -> bool {
+        false
+    }
+}
+
+/// The board of the `chess` crate, the default backend. It does not play promotions and only
+/// starts from the initial position.
+pub struct ChessCrateBackend {
+    board: Board,
+}
+
+impl ChessCrateBackend {
+    pub fn new() -> Self {
+        ChessCrateBackend {
+            board: Board::init(),
+        }
+    }
+}
+
+impl Default for ChessCrateBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBackend for ChessCrateBackend {
+    fn name(&self) -> &'static str {
+        "chess"
+    }
+
+    fn reset(&mut self) {
+        self.board = Board::init();
+    }
+
+    fn pieces(&self, color: Color, piece: Piece) -> Vec<Square> {
+        self.board
+            .get_bitboard(&color, &piece.to_type())
+            .get_occupied_squares()
+    }
+
+    fn is_possible(&self, from: Square, to: Square) -> bool {
+        is_possible(&self.board, &(from, to))
+    }
+
+    fn play_move(&mut self, from: Square, to: Square, _promotion: Option<Piece>) {
+        self.board.play_move(&(from, to));
+    }
+
+    fn castle(&mut self, castle: &str, color: Color) {
+        self.board.castle(castle, &color);
+    }
+}
+
+/// The crate's own mailbox position, which also plays promotions and starts from any position.
+pub struct MailboxBackend {
+    position: Position,
+}
+
+impl MailboxBackend {
+    pub fn new() -> Self {
+        MailboxBackend {
+            position: Position::initial(),
+        }
+    }
+}
+
+impl Default for MailboxBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBackend for MailboxBackend {
+    fn name(&self) -> &'static str {
+        "mailbox"
+    }
+
+    fn reset(&mut self) {
+        self.position = Position::initial();
+    }
+
+    fn pieces(&self, color: Color, piece: Piece) -> Vec<Square> {
+        (0..64)
+            .filter(|square| self.position.piece_at(*square) == Some((color, piece)))
+            .collect()
+    }
+
+    fn is_possible(&self, from: Square, to: Square) -> bool {
+        let Some((color, _)) = self.position.piece_at(from) else {
+            return false;
+        };
+        // The processor asks for either side, the position answers for its side to move
+        let mut position = self.position;
+        position.side_to_move = color;
+        position.pseudo_legal_targets(from).contains(&to)
+            && !position.play(from, to, None).in_check(color)
+    }
+
+    fn play_move(&mut self, from: Square, to: Square, promotion: Option<Piece>) {
+        if let Some((color, _)) = self.position.piece_at(from) {
+            self.position.side_to_move = color;
+        }
+        self.position = self.position.play(from, to, promotion);
+    }
+
+    fn castle(&mut self, castle: &str, color: Color) {
+        let king = if color == Color::White { 4 } else { 60 };
+        let to = if castle == "O-O" { king + 2 } else { king - 2 };
+        self.position.side_to_move = color;
+        self.position = self.position.play(king, to, None);
+    }
+
+    fn supports_promotion(&self) -> bool {
+        true
+    }
+
+    fn set_position(&mut self, position: &Position) -> bool {
+        self.position = *position;
+        true
+    }
+}
+
+/// The backends that can be chosen with `--backend`, `shakmaty` with the feature of that name.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum BackendKind {
+    #[default]
+    Chess,
+    Mailbox,
+    #[cfg(feature = "shakmaty")]
+    Shakmaty,
+}
+
+impl BackendKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "chess" => Some(BackendKind::Chess),
+            "mailbox" => Some(BackendKind::Mailbox),
+            #[cfg(feature = "shakmaty")]
+            "shakmaty" => Some(BackendKind::Shakmaty),
+            _ => None,
+        }
+    }
+
+    pub fn create(self) -> Box<dyn BoardBackend> {
+        match self {
+            BackendKind::Chess => Box::new(ChessCrateBackend::new()),
+            BackendKind::Mailbox => Box::new(MailboxBackend::new()),
+            #[cfg(feature = "shakmaty")]
+            BackendKind::Shakmaty => Box::new(crate::shakmaty_backend::ShakmatyBackend::new()),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! A compact binary encoding of games. A file starts with [`MAGIC`], then each game is a header
+//! block of its tags followed by its mainline, each move stored as its index among the legal
+//! moves of the position, which takes a single byte but in rare positions with more than 127
+//! moves. Numbers are LEB128 varints and strings are UTF-8 preceded by their length.
+//!
+//! Comments, NAGs and variations are not kept.
+
+use chess::legal_moves::misc::Color;
+
+use crate::position::{coordinate_move, parse_coordinate_move, Position};
+use crate::san::to_san;
+
+/// The start of an encoded file, with the format version last.
+pub const MAGIC: &[u8; 5] = b"PGNC\x01";
+
+fn push_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn push_str(bytes: &mut Vec<u8>, value: &str) {
+    push_varint(bytes, value.len());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+/// The position a game starts from: its FEN tag, or the initial position.
+pub fn start_position(tags: &[(String, String)]) -> Result<Position, String> {
+    match tags.iter().find(|(tag, _)| tag == "FEN") {
+        Some((_, fen)) => Position::from_fen(fen),
+        None => Ok(Position::initial()),
+    }
+}
+
+/// Appends a game, given by its tags and coordinate moves, to an encoded file.
+pub fn encode_game(
+    bytes: &mut Vec<u8>,
+    tags: &[(String, String)],
+    moves: &[String],
+) -> Result<(), String> {
+    let mut position = start_position(tags)?;
+    let mut indices = Vec::with_capacity(moves.len());
+    for mv in moves {
+        let coordinate = parse_coordinate_move(mv);
+        let legal = position.legal_moves();
+        let index = legal
+            .iter()
+            .position(|legal| Some(*legal) == coordinate)
+            .ok_or_else(|| format!("Illegal move {mv}"))?;
+        let (from, to, promotion) = legal[index];
+        position = position.play(from, to, promotion);
+        indices.push(index);
+    }
+
+    push_varint(bytes, tags.len());
+    for (tag, value) in tags {
+        push_str(bytes, tag);
+        push_str(bytes, value);
+    }
+    push_varint(bytes, indices.len());
+    for index in indices {
+        push_varint(bytes, index);
+    }
+    Ok(())
+}
+
+/// A game read back from the binary encoding.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DecodedGame {
+    pub tags: Vec<(String, String)>,
+    /// Mainline moves in coordinate notation.
+    pub moves: Vec<String>,
+}
+
+impl DecodedGame {
+    /// Numbered SAN movetext of the moves followed by the result.
+    pub fn movetext(&self) -> Result<String, String> {
+        let mut position = start_position(&self.tags)?;
+        let mut words = Vec::new();
+        for (ply, mv) in self.moves.iter().enumerate() {
+            let (from, to, promotion) =
+                parse_coordinate_move(mv).ok_or_else(|| format!("Invalid move {mv}"))?;
+            if position.side_to_move == Color::White {
+                words.push(format!("{}.", position.fullmove_number));
+            } else if ply == 0 {
+                words.push(format!("{}...", position.fullmove_number));
+            }
+            words.push(to_san(&position, from, to, promotion));
+            position = position.play(from, to, promotion);
+        }
+        let result = self
+            .tags
+            .iter()
+            .find(|(tag, _)| tag == "Result")
+            .map_or("*", |(_, value)| value.as_str());
+        words.push(result.to_string());
+        Ok(words.join(" "))
+    }
+}
+
+/// Reads the varints and strings of an encoded file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn varint(&mut self) -> Result<usize, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .bytes
+                .get(self.offset)
+                .ok_or("Encoded games end in the middle of a number")?;
+            self.offset += 1;
+            value |= usize::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+        }
+        Err(format!("Number too long at byte {}", self.offset))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.varint()?;
+        let bytes = self
+            .offset
+            .checked_add(length)
+            .and_then(|end| self.bytes.get(self.offset..end))
+            .ok_or("Encoded games end in the middle of a tag")?;
+        self.offset += length;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "Tag is not UTF-8".to_string())
+    }
+}
+
+/// Reads all games of an encoded file.
+pub fn decode(bytes: &[u8]) -> Result<Vec<DecodedGame>, String> {
+    let body = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or("Not an encoded game file, or of another version")?;
+    let mut reader = Reader {
+        bytes: body,
+        offset: 0,
+    };
+    let mut games = Vec::new();
+
+    while reader.offset < body.len() {
+        let game_number = games.len() + 1;
+        let tag_count = reader.varint()?;
+        let tags = (0..tag_count)
+            .map(|_| Ok((reader.string()?, reader.string()?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut position = start_position(&tags)?;
+        let move_count = reader.varint()?;
+        // Each move takes a byte at least, which bounds what a corrupt count can allocate
+        let mut moves = Vec::with_capacity(move_count.min(body.len() - reader.offset));
+        for ply in 0..move_count {
+            let index = reader.varint()?;
+            let (from, to, promotion) = *position
+                .legal_moves()
+                .get(index)
+                .ok_or_else(|| format!("Game {game_number}, ply {}: no move {index}", ply + 1))?;
+            moves.push(coordinate_move(from, to, promotion));
+            position = position.play(from, to, promotion);
+        }
+        games.push(DecodedGame { tags, moves });
+    }
+
+    Ok(games)
+}
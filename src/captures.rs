@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use chess::legal_moves::misc::Color;
+use chess::utils::string_to_square;
+
+use crate::json;
+use crate::position::{Piece, Position};
+
+/// Capture and exchange metrics of one game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GameCaptures {
+    pub captures: usize,
+    /// 1-based ply of the first capture.
+    pub first_capture_ply: Option<usize>,
+    /// 1-based ply after which neither side has a queen, when both started with one.
+    pub queen_trade_ply: Option<usize>,
+    /// Mean absolute material difference over all positions, in pawns.
+    pub average_imbalance: f64,
+}
+
+impl GameCaptures {
+    /// `positions` holds the position before each move followed by the final one.
+    pub fn of(positions: &[Position], moves: &[String]) -> Self {
+        let mut captures = 0;
+        let mut first_capture_ply = None;
+
+        for (ply, (position, mv)) in positions.iter().zip(moves).enumerate() {
+            if position.is_capture(string_to_square(&mv[0..2]), string_to_square(&mv[2..4])) {
+                captures += 1;
+                first_capture_ply.get_or_insert(ply + 1);
+            }
+        }
+
+        let queens = |position: &Position| {
+            position.count(Color::White, Piece::Queen) + position.count(Color::Black, Piece::Queen)
+        };
+        let queen_trade_ply = positions
+            .first()
+            .filter(|start| {
+                start.count(Color::White, Piece::Queen) > 0
+                    && start.count(Color::Black, Piece::Queen) > 0
+            })
+            .and_then(|_| positions.iter().position(|position| queens(position) == 0));
+
+        let imbalance: u32 = positions
+            .iter()
+            .map(|position| {
+                position
+                    .material(Color::White)
+                    .abs_diff(position.material(Color::Black))
+            })
+            .sum();
+
+        GameCaptures {
+            captures,
+            first_capture_ply,
+            queen_trade_ply,
+            average_imbalance: imbalance as f64 / positions.len().max(1) as f64,
+        }
+    }
+
+    fn to_json(self, game_number: usize) -> String {
+        let optional = |ply: Option<usize>| ply.map_or("null".to_string(), |ply| ply.to_string());
+        json::object([
+            ("game", game_number.to_string()),
+            ("captures", self.captures.to_string()),
+            ("first_capture_ply", optional(self.first_capture_ply)),
+            ("queen_trade_ply", optional(self.queen_trade_ply)),
+            (
+                "average_imbalance",
+                format!("{:.3}", self.average_imbalance),
+            ),
+        ])
+    }
+}
+
+/// Capture metrics of every game fed, with their aggregates.
+#[derive(Default)]
+pub struct CaptureStats {
+    games: Vec<(usize, GameCaptures)>,
+}
+
+impl CaptureStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_game(&mut self, game_number: usize, captures: GameCaptures) {
+        self.games.push((game_number, captures));
+    }
+
+    /// Number of games by ply of their first capture.
+    pub fn first_capture_distribution(&self) -> BTreeMap<usize, usize> {
+        let mut distribution = BTreeMap::new();
+        for ply in self
+            .games
+            .iter()
+            .filter_map(|(_, game)| game.first_capture_ply)
+        {
+            *distribution.entry(ply).or_default() += 1;
+        }
+        distribution
+    }
+
+    /// `{"games": [...], "aggregate": {...}}`
+    pub fn to_json(&self) -> String {
+        let count = self.games.len().max(1) as f64;
+        let captures: usize = self.games.iter().map(|(_, game)| game.captures).sum();
+        let queen_trades: Vec<usize> = self
+            .games
+            .iter()
+            .filter_map(|(_, game)| game.queen_trade_ply)
+            .collect();
+        let average_queen_trade = if queen_trades.is_empty() {
+            "null".to_string()
+        } else {
+            format!(
+                "{:.1}",
+                queen_trades.iter().sum::<usize>() as f64 / queen_trades.len() as f64
+            )
+        };
+        let imbalance: f64 = self
+            .games
+            .iter()
+            .map(|(_, game)| game.average_imbalance)
+            .sum();
+        let distribution = json::object(
+            self.first_capture_distribution()
+                .into_iter()
+                .map(|(ply, games)| (ply.to_string(), games.to_string())),
+        );
+
+        let aggregate = json::object([
+            ("games", self.games.len().to_string()),
+            (
+                "average_captures",
+                json::decimal(captures as f64 / count, 2),
+            ),
+            ("queen_trades", queen_trades.len().to_string()),
+            ("average_queen_trade_ply", average_queen_trade),
+            ("average_imbalance", format!("{:.3}", imbalance / count)),
+            ("first_capture_ply", distribution),
+        ]);
+        let games = json::array(
+            self.games
+                .iter()
+                .map(|(game_number, game)| game.to_json(*game_number)),
+        );
+
+        json::object([("games", games), ("aggregate", aggregate)])
+    }
+}
@@ -0,0 +1,105 @@
+use chess::legal_moves::misc::Color;
+use chess::utils::string_to_square;
+
+use crate::json;
+use crate::position::{color_index, Piece, Position};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CastlingSide {
+    King,
+    Queen,
+}
+
+/// How and when each side castled in a game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GameCastling {
+    /// Side and 1-based ply of the castling move, by color index.
+    pub castled: [Option<(CastlingSide, usize)>; 2],
+}
+
+impl GameCastling {
+    /// `positions` holds the position before each move followed by the final one.
+    pub fn of(positions: &[Position], moves: &[String]) -> Self {
+        let mut castled = [None; 2];
+
+        for (ply, (position, mv)) in positions.iter().zip(moves).enumerate() {
+            let (from, to) = (string_to_square(&mv[0..2]), string_to_square(&mv[2..4]));
+            if let Some((color, Piece::King)) = position.piece_at(from) {
+                if from.abs_diff(to) == 2 {
+                    let side = if to > from {
+                        CastlingSide::King
+                    } else {
+                        CastlingSide::Queen
+                    };
+                    castled[color_index(color)] = Some((side, ply + 1));
+                }
+            }
+        }
+
+        GameCastling { castled }
+    }
+
+    /// Whether both sides castled, on different wings.
+    pub fn opposite(&self) -> bool {
+        matches!(self.castled, [Some((white, _)), Some((black, _))] if white != black)
+    }
+}
+
+/// Castling habits of each player color over the games fed.
+#[derive(Default)]
+pub struct CastlingStats {
+    games: usize,
+    opposite: usize,
+    /// By color index: king side count, queen side count, never castled, sum of castling plies.
+    sides: [[usize; 4]; 2],
+}
+
+impl CastlingStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_game(&mut self, castling: &GameCastling) {
+        self.games += 1;
+        if castling.opposite() {
+            self.opposite += 1;
+        }
+        for (counts, castled) in self.sides.iter_mut().zip(castling.castled) {
+            match castled {
+                Some((CastlingSide::King, ply)) => {
+                    counts[0] += 1;
+                    counts[3] += ply;
+                }
+                Some((CastlingSide::Queen, ply)) => {
+                    counts[1] += 1;
+                    counts[3] += ply;
+                }
+                None => counts[2] += 1,
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let side = |color: Color| {
+            let [king, queen, never, plies] = self.sides[color_index(color)];
+            let average_ply = if king + queen == 0 {
+                "null".to_string()
+            } else {
+                json::decimal(plies as f64 / (king + queen) as f64, 1)
+            };
+            json::object([
+                ("king_side", king.to_string()),
+                ("queen_side", queen.to_string()),
+                ("never", never.to_string()),
+                ("average_ply", average_ply),
+            ])
+        };
+
+        json::object([
+            ("games", self.games.to_string()),
+            ("opposite_castling", self.opposite.to_string()),
+            ("white", side(Color::White)),
+            ("black", side(Color::Black)),
+        ])
+    }
+}
@@ -0,0 +1,201 @@
+//! Screening of players for statistically unusual play, to point human reviewers at the
+//! performances worth a closer look. Nothing here is evidence of cheating on its own.
+
+use std::collections::BTreeMap;
+
+/// Players standing this many standard deviations from the others on a measure are flagged.
+pub const FLAG_Z_SCORE: f64 = 2.0;
+
+/// Fewer other players with enough moves than this give no meaningful spread, and no flags.
+pub const MIN_POPULATION: usize = 5;
+
+/// The clock time of a `[%clk 0:03:05.2]` annotation, in seconds.
+pub fn clock_annotation(comment: &str) -> Option<f64> {
+    let start = comment.find("[%clk ")? + "[%clk ".len();
+    let end = start + comment[start..].find(']')?;
+    comment[start..end]
+        .trim()
+        .split(':')
+        .try_fold(0.0, |seconds, part| {
+            part.parse::<f64>().ok().map(|value| seconds * 60.0 + value)
+        })
+}
+
+/// Base time and increment in seconds of a TimeControl tag such as `300+3`; None for other
+/// kinds of control.
+pub fn base_and_increment(time_control: &str) -> Option<(f64, f64)> {
+    let (base, increment) = time_control.split_once('+').unwrap_or((time_control, "0"));
+    Some((base.parse().ok()?, increment.parse().ok()?))
+}
+
+/// Seconds spent on each move of each side, white first, from the clock after each move.
+/// Moves next to a missing clock are left out.
+pub fn move_times(clocks: &[Option<f64>], base: f64, increment: f64) -> [Vec<f64>; 2] {
+    let mut times = [Vec::new(), Vec::new()];
+    let mut previous = [Some(base); 2];
+
+    for (ply, clock) in clocks.iter().enumerate() {
+        let side = ply % 2;
+        if let (Some(before), Some(after)) = (previous[side], *clock) {
+            times[side].push((before - after + increment).max(0.0));
+        }
+        previous[side] = *clock;
+    }
+    times
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn standard_deviation(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    (values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64)
+        .sqrt()
+}
+
+/// What the screen gathers about one player across the input.
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct PlayerScreen {
+    pub games: usize,
+    /// Moves compared with the engine's choice, and how many matched it.
+    pub engine_moves: usize,
+    pub engine_matches: usize,
+    /// Centipawns lost by each evaluated move.
+    pub losses: Vec<u32>,
+    /// Seconds spent on each timed move.
+    pub times: Vec<f64>,
+}
+
+impl PlayerScreen {
+    pub fn match_rate(&self) -> Option<f64> {
+        (self.engine_moves > 0).then(|| self.engine_matches as f64 / self.engine_moves as f64)
+    }
+
+    pub fn average_loss(&self) -> Option<f64> {
+        (!self.losses.is_empty()).then(|| {
+            let losses: Vec<f64> = self.losses.iter().map(|loss| f64::from(*loss)).collect();
+            mean(&losses)
+        })
+    }
+
+    /// Spread of the move times relative to their mean; low values mean an even pace.
+    pub fn time_variation(&self) -> Option<f64> {
+        let mean = mean(&self.times);
+        (self.times.len() >= 2 && mean > 0.0).then(|| standard_deviation(&self.times) / mean)
+    }
+
+    /// Moves seen by any of the measures.
+    pub fn moves(&self) -> usize {
+        self.engine_moves
+            .max(self.losses.len())
+            .max(self.times.len())
+    }
+}
+
+/// A measure of the screen, with which side of the population is unusual.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Measure {
+    EngineMatch,
+    EvalLoss,
+    TimeVariation,
+}
+
+impl Measure {
+    pub const ALL: [Measure; 3] = [
+        Measure::EngineMatch,
+        Measure::EvalLoss,
+        Measure::TimeVariation,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Measure::EngineMatch => "high engine match rate",
+            Measure::EvalLoss => "low eval loss",
+            Measure::TimeVariation => "uniform move times",
+        }
+    }
+
+    fn value(self, player: &PlayerScreen) -> Option<f64> {
+        match self {
+            Measure::EngineMatch => player.match_rate(),
+            Measure::EvalLoss => player.average_loss(),
+            Measure::TimeVariation => player.time_variation(),
+        }
+    }
+
+    /// Whether high values are the unusual ones.
+    fn suspicious_when_high(self) -> bool {
+        self == Measure::EngineMatch
+    }
+}
+
+/// A screened player with the measures on which they stand out.
+pub struct ScreenResult<'a> {
+    pub name: &'a str,
+    pub player: &'a PlayerScreen,
+    pub flags: Vec<Measure>,
+}
+
+#[derive(Default)]
+pub struct CheatScreen {
+    pub players: BTreeMap<String, PlayerScreen>,
+}
+
+impl CheatScreen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn player(&mut self, name: &str) -> &mut PlayerScreen {
+        self.players.entry(name.to_string()).or_default()
+    }
+
+    /// Players with at least `min_moves` moves, flagged where they sit `FLAG_Z_SCORE` standard
+    /// deviations on the unusual side of the other players with enough moves.
+    pub fn results(&self, min_moves: usize) -> Vec<ScreenResult<'_>> {
+        let screened: Vec<(&String, &PlayerScreen)> = self
+            .players
+            .iter()
+            .filter(|(_, player)| player.moves() >= min_moves)
+            .collect();
+
+        let is_unusual = |measure: Measure, name: &str, value: f64| {
+            let others: Vec<f64> = screened
+                .iter()
+                .filter(|(other, _)| other.as_str() != name)
+                .filter_map(|(_, player)| measure.value(player))
+                .collect();
+            let deviation = standard_deviation(&others);
+            if others.len() < MIN_POPULATION || deviation == 0.0 {
+                return false;
+            }
+            let z = (value - mean(&others)) / deviation;
+            if measure.suspicious_when_high() {
+                z >= FLAG_Z_SCORE
+            } else {
+                z <= -FLAG_Z_SCORE
+            }
+        };
+
+        screened
+            .iter()
+            .map(|(name, player)| ScreenResult {
+                name,
+                player,
+                flags: Measure::ALL
+                    .into_iter()
+                    .filter(|measure| {
+                        measure
+                            .value(player)
+                            .is_some_and(|value| is_unusual(*measure, name, value))
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
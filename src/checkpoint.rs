@@ -0,0 +1,49 @@
+use std::fs;
+use std::io;
+
+/// Progress of a long run, written next to the output so that it can be resumed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Checkpoint {
+    /// Byte offset in the input of the first game not yet processed.
+    pub input_offset: usize,
+    /// Number of input games consumed so far.
+    pub games: usize,
+    /// Length of the output file when the checkpoint was taken.
+    pub output_len: u64,
+}
+
+impl Checkpoint {
+    pub fn path_for(output_path: &str) -> String {
+        format!("{output_path}.checkpoint")
+    }
+
+    pub fn load(path: &str) -> io::Result<Checkpoint> {
+        let contents = fs::read_to_string(path)?;
+        let fields: Vec<u64> = contents
+            .split_whitespace()
+            .filter_map(|field| field.parse().ok())
+            .collect();
+
+        match fields[..] {
+            [input_offset, games, output_len] => Ok(Checkpoint {
+                input_offset: input_offset as usize,
+                games: games as usize,
+                output_len,
+            }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Malformed checkpoint file: {path}"),
+            )),
+        }
+    }
+
+    /// Writes the checkpoint atomically, so an interruption never leaves a truncated file.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let temporary = format!("{path}.tmp");
+        fs::write(
+            &temporary,
+            format!("{} {} {}\n", self.input_offset, self.games, self.output_len),
+        )?;
+        fs::rename(temporary, path)
+    }
+}
@@ -0,0 +1,1072 @@
+use crate::annotations::FenInjection;
+use crate::backend::BackendKind;
+use crate::crosstable::CrosstableFormat;
+use crate::duplicates::ConflictPolicy;
+use crate::filters::{EloMode, GameFilter, PgnDate, TimeClass};
+use crate::input_format::InputFormat;
+use crate::language;
+use crate::lichess;
+use crate::notation::{output_notation, InputNotation, OutputNotation};
+use crate::pawn_structure::StructureTemplate;
+use crate::pgn_preprocessor::RepairMode;
+use crate::redact::Redaction;
+use crate::roster::SEVEN_TAG_ROSTER;
+use crate::sacrifices::SacrificeFilter;
+use crate::selfplay::{SelfplaySettings, TimeControl};
+use crate::sharding::ShardKey;
+use crate::study::DEFAULT_CHAPTER_TEMPLATE;
+use crate::tag_selection::TagSelection;
+use crate::tree::LineSelection;
+
+/// Command line options. Positional arguments are the input and output paths.
+#[derive(Default)]
+pub struct Options {
+    pub input: Option<String>,
+    /// File listing input paths one per line, `-` for standard input, read as one input.
+    pub files_from: Option<String>,
+    pub output: Option<String>,
+    pub check_roster: bool,
+    pub fix_roster: bool,
+    /// Report ECO and Opening tags inconsistent with the embedded ECO classification.
+    pub check_openings: bool,
+    /// Replace ECO and Opening tags inconsistent with the embedded ECO classification.
+    pub fix_openings: bool,
+    /// Report SAN moves naming more or less of their origin square than the position needs.
+    pub check_disambiguation: bool,
+    /// Rewrite over-disambiguated SAN moves of the mainline as SAN spells them.
+    pub fix_disambiguation: bool,
+    /// Drop games without any processed move, such as header-only games.
+    pub skip_empty_games: bool,
+    pub filter: GameFilter,
+    /// Emit a position dataset with each unique position once instead of the games.
+    pub unique_positions: bool,
+    /// Keep only quiet positions in position datasets: no check, no check available and no
+    /// hanging piece.
+    pub quiet_positions: bool,
+    /// Explain how each SAN token is resolved.
+    pub trace: bool,
+    /// Time the processing of every game and report this many of the slowest.
+    pub profile_games: Option<usize>,
+    /// Give up on games longer than this many plies instead of the default limit.
+    pub ply_limit: Option<usize>,
+    /// Verify the tracked board state after every ply.
+    pub check_invariants: bool,
+    /// Suggest (--suggest) or play (--auto-repair) the move most likely meant by an illegal token.
+    pub repair: RepairMode,
+    /// Cap on the output waiting for a slow sink, in megabytes. The input is not counted.
+    pub max_queued_mb: Option<usize>,
+    /// Notation of the moves in the written games, coordinate moves by default.
+    pub format: Option<&'static dyn OutputNotation>,
+    /// Notation of the input moves.
+    pub input_notation: InputNotation,
+    /// Fill in missing results from a mated or stalemated final position, or a final '#',
+    /// marking them with a ResultInferred tag.
+    pub infer_results: bool,
+    /// Polyglot book whose moves are annotated in the output, with the `Random64` numbers
+    /// of `polyglot_keys`.
+    pub polyglot_book: Option<String>,
+    pub polyglot_keys: Option<String>,
+    /// Format of the input, detected when reading standard input and PGN otherwise.
+    pub input_format: Option<InputFormat>,
+    /// Position, as FEN, that games without a FEN tag start from instead of the initial one.
+    pub start_fen: Option<String>,
+    /// Ignore missing or spurious 'x' capture markers, warning about them.
+    pub lenient_captures: bool,
+    /// The board SAN is resolved against.
+    pub backend: BackendKind,
+    /// Resume after a failing token when skipping or replacing it lets the game go on.
+    pub recover: bool,
+    /// Number of games to pick at random among those passing the header filters.
+    pub sample: Option<usize>,
+    /// Seed for random choices; a time-based seed is used and reported when absent.
+    pub seed: Option<u64>,
+    /// Make the output a function of the input and options alone, so that runs can be
+    /// checksummed: random choices use seed 0 unless `--seed` is given.
+    pub canonical: bool,
+    /// Write a checkpoint next to the output after this many input games.
+    pub checkpoint_every: Option<usize>,
+    /// Continue from the checkpoint of an interrupted run, appending to the output.
+    pub resume: bool,
+    /// Append to an existing output only the games it does not hold yet.
+    pub update: bool,
+    /// Split the input reading tags only, the movetext of games passing the header filters
+    /// being read when they are processed.
+    pub header_scan: bool,
+    /// Scan the input for games passing the header filters first, then read only those,
+    /// never holding the whole input in memory.
+    pub two_pass: bool,
+    /// Add SourceFile and SourceBytes tags giving the input file of each game and its byte
+    /// range in that file, end excluded.
+    pub source_tags: bool,
+    /// Route games to one file per key in the output directory.
+    pub shard_by: Option<ShardKey>,
+    pub fen_injection: FenInjection,
+    /// Flip the board and swap the colors of every game written, black moving first.
+    pub mirror: bool,
+    /// Hide personally identifying tags in the output.
+    pub redact: Option<Redaction>,
+    /// Tags written out, from --keep-tags and --drop-tags.
+    pub tag_selection: TagSelection,
+    /// Also emit each side line as a standalone game starting at its branch point.
+    pub expand_variations: bool,
+    /// Which line of annotated games to process and write.
+    pub line: LineSelection,
+    /// Path of the square heatmaps to write, as SVG when it ends in `.svg` and JSON otherwise.
+    pub heatmaps: Option<String>,
+    /// Path of the JSON capture and exchange statistics to write.
+    pub capture_stats: Option<String>,
+    /// Path of the SAN token sequence of each game, as JSON lines when it ends in `.jsonl`
+    /// and tab-separated text otherwise.
+    pub san_tokens: Option<String>,
+    /// Path of the SAN token counts over all games, as JSON when it ends in `.json` and
+    /// tab-separated text otherwise.
+    pub san_vocabulary: Option<String>,
+    /// Path of the table giving each ply in SAN and UCI with its move number and color, in
+    /// fixed-width columns when it ends in `.txt` and tab-separated otherwise.
+    pub move_table: Option<String>,
+    /// Path of the white and black moves of each game as separate sequences, in the output
+    /// notation, as JSON lines when it ends in `.jsonl` and tab-separated text otherwise.
+    pub color_split: Option<String>,
+    /// Path of the JSON lines giving the material of each side after every ply.
+    pub material: Option<String>,
+    /// Path of the life of each piece, from its start square to its capture: per game as
+    /// JSON lines when it ends in `.jsonl`, and tab-separated totals per piece otherwise.
+    pub piece_lifetimes: Option<String>,
+    /// Add a Motifs tag listing the tactical patterns found in the game.
+    pub patterns: bool,
+    /// Add an Endgame tag with the type of the first endgame position reached.
+    pub classify_endgames: bool,
+    /// Keep games whose endgame type contains this text (case-insensitive).
+    pub endgame: Option<String>,
+    /// Add a Sacrifices tag listing the moves giving up material, as `12. queen`.
+    pub tag_sacrifices: bool,
+    /// Keep games with a sacrifice of this piece before this move.
+    pub sacrifice: Option<SacrificeFilter>,
+    /// Path of the JSON castling statistics to write.
+    pub castling_stats: Option<String>,
+    /// Ply at which pawn structures are taken for --structure and --group-structures;
+    /// the final position when absent.
+    pub structure_ply: Option<usize>,
+    /// Keep games whose pawn structure matches any of these templates.
+    pub structures: Vec<StructureTemplate>,
+    /// Path of the per-structure game counts to write.
+    pub group_structures: Option<String>,
+    /// Add WhiteAccuracy and BlackAccuracy tags computed from the [%eval] annotations.
+    pub accuracy: bool,
+    /// Path of the JSON per-game accuracy report to write.
+    pub accuracy_report: Option<String>,
+    /// Path of the JSON report of strength estimates from move quality to write.
+    pub estimate_ratings: Option<String>,
+    /// Path of the CSV of per-position features and result labels to write.
+    pub features: Option<String>,
+    /// Path of the bitboard-plane training file to write.
+    #[cfg(feature = "tensors")]
+    pub tensor_output: Option<String>,
+}
+
+/// Settings of the clean subcommand, which rewrites annotated games keeping their comments
+/// and side lines.
+#[derive(Default)]
+pub struct CleanSettings {
+    pub input: Option<String>,
+    pub output: Option<String>,
+    /// Remove engine evaluations, lines and judgments, keeping human comments.
+    pub strip_engine_comments: bool,
+    /// Remove comments detected as written in another language than this ISO 639-1 code.
+    pub comment_language: Option<String>,
+    /// Spell Cyrillic player names in Latin letters.
+    pub transliterate_names: bool,
+    /// Rewrite over-disambiguated SAN moves of the mainline as SAN spells them.
+    pub fix_disambiguation: bool,
+    /// Add WhiteAccuracy and BlackAccuracy tags computed from the [%eval] annotations.
+    pub accuracy: bool,
+    /// Hide personally identifying tags.
+    pub redact: Option<Redaction>,
+    pub tag_selection: TagSelection,
+    /// Write one copy of duplicate games, chosen or merged by this policy.
+    pub dedupe: Option<ConflictPolicy>,
+    /// Check engine lines in comments, rewriting legal ones as numbered SAN and reporting
+    /// the others.
+    pub verify_pv: bool,
+}
+
+/// Settings of the screen subcommand.
+pub struct ScreenSettings {
+    pub input: String,
+    /// Engine whose choices are compared with the moves played.
+    pub engine: Option<String>,
+    /// Search time per position, in milliseconds.
+    pub movetime: u32,
+    /// Opening plies left out of the engine comparison.
+    pub skip_plies: usize,
+    /// Players with fewer moves are not reported.
+    pub min_moves: usize,
+}
+
+/// Settings of the study subcommand.
+pub struct StudySettings {
+    pub input: String,
+    /// Name of the study, written in a StudyName tag.
+    pub name: Option<String>,
+    /// Template of chapter names, tags written as `{White}`.
+    pub chapter_name: String,
+    /// Where the chapters are written; stdout when absent.
+    pub output: Option<String>,
+    /// Id of a Lichess study the chapters are also added to.
+    pub push: Option<String>,
+}
+
+/// Settings of the fens subcommand.
+pub struct FenSettings {
+    /// A FEN per line.
+    pub input: String,
+    /// Engine searching each position for the evaluation and tablebase probe.
+    pub engine: Option<String>,
+    /// Search time per position, in milliseconds.
+    pub movetime: u32,
+    /// Report the engine's evaluation and best move.
+    pub eval: bool,
+    /// Syzygy directory handed to the engine, whose outcome is reported for positions with
+    /// few enough men.
+    pub tablebase: Option<String>,
+    /// Report the phase, endgame type, static evaluation and quiescence.
+    pub classify: bool,
+    /// Where the table is written; stdout when absent.
+    pub output: Option<String>,
+}
+
+/// Settings of the prep subcommand.
+pub struct PrepSettings {
+    pub input: String,
+    pub opponent: String,
+    /// Whether I play white, the opponent's games with black being studied, or the reverse.
+    pub my_color_white: bool,
+    /// Number of the opponent's most recent games studied.
+    pub recent: usize,
+    /// Plies of each game entering the opening tree.
+    pub depth: usize,
+    /// Moves played in fewer games are left out of the report.
+    pub min_games: usize,
+    /// Where the representative games are written; stdout when absent.
+    pub output: Option<String>,
+}
+
+/// Settings of the gen subcommand.
+pub struct GenSettings {
+    pub games: usize,
+    /// Seed for the moves; a time-based seed is used and reported when absent.
+    pub seed: Option<u64>,
+    /// Plies after which a game is left unfinished.
+    pub max_plies: usize,
+    /// Games whose openings are followed by frequency for the first `book_depth` plies.
+    pub book: Option<String>,
+    pub book_depth: usize,
+    /// Where the games are written; stdout when absent.
+    pub output: Option<String>,
+}
+
+/// Settings of the openings subcommand.
+pub struct OpeningSettings {
+    pub input: String,
+    pub engine: String,
+    /// Plies of each game entering the tree.
+    pub depth: usize,
+    /// Nodes reached by fewer games are neither evaluated nor reported.
+    pub min_games: usize,
+    /// Search time per node, in milliseconds.
+    pub movetime: u32,
+    /// Number of openings to report.
+    pub top: usize,
+}
+
+pub enum Command {
+    /// Process the games of the input (the default).
+    Crunch(Box<Options>),
+    /// Rewrite annotated games through the cleaning stages.
+    Clean(CleanSettings),
+    /// Compare two versions of the same games.
+    Diff { left: String, right: String },
+    /// Play games between two UCI engines.
+    Selfplay(SelfplaySettings),
+    /// Report openings whose results diverge most from a UCI engine's evaluation.
+    Openings(OpeningSettings),
+    /// Run crunch jobs read as NDJSON from stdin, or from the connections of a Unix socket.
+    Worker { socket: Option<String> },
+    /// Print the crosstable of each event with Buchholz and Sonneborn-Berger tie-breaks.
+    Crosstable {
+        input: String,
+        format: CrosstableFormat,
+    },
+    /// Group games into events and print their standings, as JSON with `json` set.
+    Events { input: String, json: bool },
+    /// Extract the mutual games of two players and summarize their score.
+    HeadToHead {
+        input: String,
+        players: [String; 2],
+        /// Where the games are written; stdout when absent.
+        output: Option<String>,
+    },
+    /// Print the byte range and tags of each game, reading tag sections only.
+    Headers {
+        input: String,
+        /// Tags printed as columns.
+        tags: Vec<String>,
+    },
+    /// Write the `.pgi` index of a PGN file, next to it.
+    Index {
+        input: String,
+        /// Tags whose values the index keeps.
+        tags: Vec<String>,
+    },
+    /// Print a game of an indexed PGN file by its number, reading that game alone.
+    Game { input: String, number: u64 },
+    /// Write the mainlines of games in the compact binary encoding.
+    Encode {
+        input: String,
+        output: String,
+        /// Append to an existing output only the games it does not hold yet.
+        update: bool,
+    },
+    /// Turn games in the binary encoding back into PGN.
+    Decode {
+        input: String,
+        /// Where the games are written; stdout when absent.
+        output: Option<String>,
+    },
+    /// Write games as the chapters of a PGN to import as a Lichess study.
+    Study(StudySettings),
+    /// Analyse positions given as FEN lines instead of games.
+    Fens(FenSettings),
+    /// Generate random legal games, optionally following an opening book.
+    Gen(GenSettings),
+    /// Collect DGT LiveChess snapshots, read in order, into the final game of each board.
+    LiveChess {
+        inputs: Vec<String>,
+        /// Where the games are written; stdout when absent.
+        output: Option<String>,
+    },
+    /// Count move generation nodes to a depth on the `--backend` board, checking them against
+    /// published values.
+    Perft {
+        depth: usize,
+        /// The root position; the initial position when absent.
+        fen: Option<String>,
+        /// Print the count below each root move.
+        divide: bool,
+        /// Also compare the chess crate's move legality with the move generator, from the
+        /// initial position.
+        check_crate: bool,
+    },
+    /// Report an opponent's recent openings with a given color, with representative games.
+    Prep(PrepSettings),
+    /// Screen players for unusual engine agreement, eval loss and move times.
+    Screen(ScreenSettings),
+    /// Score a UCI engine on the bm/am positions of an EPD test suite.
+    TestSuite {
+        engine: String,
+        suite: String,
+        /// Search time per position, in milliseconds.
+        movetime: u32,
+    },
+}
+
+fn flag_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {flag}"))
+}
+
+fn date_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<PgnDate, String> {
+    let value = flag_value(args, flag)?;
+    PgnDate::parse(&value).ok_or_else(|| format!("Invalid date for {flag}: {value}"))
+}
+
+fn number_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<u32, String> {
+    let value = flag_value(args, flag)?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number for {flag}: {value}"))
+}
+
+/// Applies `--redact MODE` or `--redact-salt TEXT`, the latter implying hash mode.
+fn set_redaction(
+    redaction: &mut Option<Redaction>,
+    flag: &str,
+    value: String,
+) -> Result<(), String> {
+    match (flag, redaction.as_mut()) {
+        ("--redact-salt", Some(Redaction::Hash { salt })) => *salt = value,
+        ("--redact-salt", Some(Redaction::Strip)) => {
+            return Err("--redact-salt only applies to --redact hash".to_string())
+        }
+        ("--redact-salt", None) => *redaction = Some(Redaction::Hash { salt: value }),
+        // Keep a salt given before the mode
+        (_, Some(Redaction::Hash { .. })) if value == "hash" => {}
+        _ => {
+            let mode = Redaction::parse(&value)
+                .ok_or_else(|| format!("Invalid value for {flag}: {value}"))?;
+            let salted = matches!(redaction, Some(Redaction::Hash { salt }) if !salt.is_empty());
+            if mode == Redaction::Strip && salted {
+                return Err("--redact-salt only applies to --redact hash".to_string());
+            }
+            *redaction = Some(mode);
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check-roster" => options.check_roster = true,
+            "--fix-roster" => options.fix_roster = true,
+            "--check-openings" => options.check_openings = true,
+            "--fix-openings" => options.fix_openings = true,
+            "--check-disambiguation" => options.check_disambiguation = true,
+            "--fix-disambiguation" => options.fix_disambiguation = true,
+            "--skip-empty-games" => options.skip_empty_games = true,
+            "--unique-positions" => options.unique_positions = true,
+            "--quiet-positions" => options.quiet_positions = true,
+            "--trace" => options.trace = true,
+            "--profile-games" => {
+                options.profile_games = Some(number_value(&mut args, &arg)? as usize)
+            }
+            "--ply-limit" => options.ply_limit = Some(number_value(&mut args, &arg)? as usize),
+            "--check-invariants" => options.check_invariants = true,
+            "--suggest" => options.repair = RepairMode::Suggest,
+            "--auto-repair" => options.repair = RepairMode::Apply,
+            "--recover" => options.recover = true,
+            "--lenient-captures" => options.lenient_captures = true,
+            "--backend" => {
+                let value = flag_value(&mut args, &arg)?;
+                options.backend = BackendKind::parse(&value)
+                    .ok_or_else(|| format!("Invalid value for --backend: {value}"))?;
+            }
+            "--max-queued-mb" => {
+                options.max_queued_mb = Some(number_value(&mut args, &arg)? as usize)
+            }
+            "--format" => {
+                let value = flag_value(&mut args, &arg)?;
+                options.format = Some(
+                    output_notation(&value)
+                        .ok_or_else(|| format!("Invalid value for --format: {value}"))?,
+                );
+            }
+            "--input-notation" => {
+                let value = flag_value(&mut args, &arg)?;
+                options.input_notation = InputNotation::parse(&value)
+                    .ok_or_else(|| format!("Invalid value for --input-notation: {value}"))?;
+            }
+            "--files-from" => options.files_from = Some(flag_value(&mut args, &arg)?),
+            "--start-fen" => options.start_fen = Some(flag_value(&mut args, &arg)?),
+            "--input-format" => {
+                let value = flag_value(&mut args, &arg)?;
+                options.input_format = Some(
+                    InputFormat::parse(&value)
+                        .ok_or_else(|| format!("Invalid value for --input-format: {value}"))?,
+                );
+            }
+            "--expand-variations" => options.expand_variations = true,
+            "--mirror" => options.mirror = true,
+            "--infer-results" => options.infer_results = true,
+            "--polyglot-book" => options.polyglot_book = Some(flag_value(&mut args, &arg)?),
+            "--polyglot-keys" => options.polyglot_keys = Some(flag_value(&mut args, &arg)?),
+            "--sample" => options.sample = Some(number_value(&mut args, &arg)? as usize),
+            "--checkpoint-every" => {
+                options.checkpoint_every = Some(number_value(&mut args, &arg)? as usize)
+            }
+            "--resume" => options.resume = true,
+            "--update" => options.update = true,
+            "--source-tags" => options.source_tags = true,
+            "--header-scan" => options.header_scan = true,
+            "--two-pass" => options.two_pass = true,
+            "--fen-every" => {
+                options.fen_injection.every = Some(number_value(&mut args, &arg)? as usize)
+            }
+            "--fen-at" => {
+                for moment in flag_value(&mut args, &arg)?.split(',') {
+                    match moment.trim() {
+                        "captures" => options.fen_injection.captures = true,
+                        "checks" => options.fen_injection.checks = true,
+                        moment => return Err(format!("Invalid value for --fen-at: {moment}")),
+                    }
+                }
+            }
+            "--line" => {
+                let value = flag_value(&mut args, &arg)?;
+                options.line = LineSelection::parse(&value)
+                    .ok_or_else(|| format!("Invalid value for --line: {value}"))?;
+            }
+            "--heatmaps" => options.heatmaps = Some(flag_value(&mut args, &arg)?),
+            "--capture-stats" => options.capture_stats = Some(flag_value(&mut args, &arg)?),
+            "--san-tokens" => options.san_tokens = Some(flag_value(&mut args, &arg)?),
+            "--san-vocabulary" => options.san_vocabulary = Some(flag_value(&mut args, &arg)?),
+            "--move-table" => options.move_table = Some(flag_value(&mut args, &arg)?),
+            "--color-split" => options.color_split = Some(flag_value(&mut args, &arg)?),
+            "--material" => options.material = Some(flag_value(&mut args, &arg)?),
+            "--piece-lifetimes" => options.piece_lifetimes = Some(flag_value(&mut args, &arg)?),
+            "--patterns" => options.patterns = true,
+            "--classify-endgames" => options.classify_endgames = true,
+            "--endgame" => options.endgame = Some(flag_value(&mut args, &arg)?),
+            "--tag-sacrifices" => options.tag_sacrifices = true,
+            "--sacrifice" => {
+                options.sacrifice = Some(SacrificeFilter::parse(&flag_value(&mut args, &arg)?)?)
+            }
+            "--accuracy" => options.accuracy = true,
+            "--accuracy-report" => options.accuracy_report = Some(flag_value(&mut args, &arg)?),
+            "--estimate-ratings" => options.estimate_ratings = Some(flag_value(&mut args, &arg)?),
+            "--features" => options.features = Some(flag_value(&mut args, &arg)?),
+            "--castling-stats" => options.castling_stats = Some(flag_value(&mut args, &arg)?),
+            "--structure-ply" => {
+                options.structure_ply = Some(number_value(&mut args, &arg)? as usize)
+            }
+            "--structure" => options
+                .structures
+                .push(StructureTemplate::parse(&flag_value(&mut args, &arg)?)?),
+            "--group-structures" => options.group_structures = Some(flag_value(&mut args, &arg)?),
+            "--redact" | "--redact-salt" => {
+                let value = flag_value(&mut args, &arg)?;
+                set_redaction(&mut options.redact, &arg, value)?;
+            }
+            "--keep-tags" => options
+                .tag_selection
+                .keep
+                .extend(TagSelection::parse_list(&flag_value(&mut args, &arg)?)),
+            "--drop-tags" => options
+                .tag_selection
+                .drop
+                .extend(TagSelection::parse_list(&flag_value(&mut args, &arg)?)),
+            "--shard-by" => {
+                let value = flag_value(&mut args, &arg)?;
+                let key = ShardKey::parse(&value)
+                    .ok_or_else(|| format!("Invalid value for --shard-by: {value}"))?;
+                options.shard_by = Some(key);
+            }
+            "--seed" => {
+                let value = flag_value(&mut args, &arg)?;
+                let seed = value
+                    .parse()
+                    .map_err(|_| format!("Invalid seed: {value}"))?;
+                options.seed = Some(seed);
+            }
+            "--canonical" => options.canonical = true,
+            "--after" => options.filter.after = Some(date_value(&mut args, &arg)?),
+            "--before" => options.filter.before = Some(date_value(&mut args, &arg)?),
+            "--event" => options.filter.event = Some(flag_value(&mut args, &arg)?),
+            "--min-elo" => options.filter.min_elo = Some(number_value(&mut args, &arg)?),
+            "--max-elo" => options.filter.max_elo = Some(number_value(&mut args, &arg)?),
+            "--elo-mode" => {
+                options.filter.elo_mode = match flag_value(&mut args, &arg)?.as_str() {
+                    "both" => EloMode::Both,
+                    "either" => EloMode::Either,
+                    mode => return Err(format!("Invalid value for --elo-mode: {mode}")),
+                }
+            }
+            "--time-control" => {
+                let value = flag_value(&mut args, &arg)?;
+                let time_class = TimeClass::parse(&value)
+                    .ok_or_else(|| format!("Invalid value for --time-control: {value}"))?;
+                options.filter.time_class = Some(time_class);
+            }
+            "--min-plies" => {
+                options.filter.min_plies = Some(number_value(&mut args, &arg)? as usize)
+            }
+            "--max-plies" => {
+                options.filter.max_plies = Some(number_value(&mut args, &arg)? as usize)
+            }
+            "--exclude-termination" => options.filter.excluded_terminations.extend(
+                flag_value(&mut args, &arg)?
+                    .split(',')
+                    .map(|termination| termination.trim().to_string()),
+            ),
+            #[cfg(feature = "tensors")]
+            "--tensors" => options.tensor_output = Some(flag_value(&mut args, &arg)?),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option: {flag}")),
+            _ if options.input.is_none() => options.input = Some(arg),
+            _ if options.output.is_none() => options.output = Some(arg),
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    // The listed files are the input, so the only argument is the output
+    if options.files_from.is_some() && options.output.is_none() {
+        options.output = options.input.take();
+    }
+
+    Ok(options)
+}
+
+fn parse_clean<I: Iterator<Item = String>>(mut args: I) -> Result<CleanSettings, String> {
+    let mut settings = CleanSettings::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strip-engine-comments" => settings.strip_engine_comments = true,
+            "--comment-language" => {
+                let value = flag_value(&mut args, &arg)?;
+                if !language::is_supported(&value) {
+                    return Err(format!("Unsupported language for {arg}: {value}"));
+                }
+                settings.comment_language = Some(value);
+            }
+            "--transliterate-names" => settings.transliterate_names = true,
+            "--fix-disambiguation" => settings.fix_disambiguation = true,
+            "--accuracy" => settings.accuracy = true,
+            "--verify-pv" => settings.verify_pv = true,
+            "--redact" | "--redact-salt" => {
+                let value = flag_value(&mut args, &arg)?;
+                set_redaction(&mut settings.redact, &arg, value)?;
+            }
+            "--dedupe" => {
+                let value = flag_value(&mut args, &arg)?;
+                settings.dedupe = Some(
+                    ConflictPolicy::parse(&value)
+                        .ok_or_else(|| format!("Invalid value for {arg}: {value}"))?,
+                );
+            }
+            "--keep-tags" => settings
+                .tag_selection
+                .keep
+                .extend(TagSelection::parse_list(&flag_value(&mut args, &arg)?)),
+            "--drop-tags" => settings
+                .tag_selection
+                .drop
+                .extend(TagSelection::parse_list(&flag_value(&mut args, &arg)?)),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option: {flag}")),
+            _ if settings.input.is_none() => settings.input = Some(arg),
+            _ if settings.output.is_none() => settings.output = Some(arg),
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    Ok(settings)
+}
+
+const SELFPLAY_USAGE: &str = "Usage: selfplay <engine> <engine> [--games N] [--tc BASE+INC[,BASE+INC]] [--max-plies N] [output.pgn]";
+
+fn parse_selfplay<I: Iterator<Item = String>>(mut args: I) -> Result<SelfplaySettings, String> {
+    let first = args.next().ok_or(SELFPLAY_USAGE)?;
+    let second = args.next().ok_or(SELFPLAY_USAGE)?;
+    let default_control = TimeControl {
+        base: 60_000,
+        increment: 1_000,
+    };
+    let mut settings = SelfplaySettings {
+        engines: [first, second],
+        games: 2,
+        time_controls: [default_control; 2],
+        max_plies: 400,
+        output: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => settings.games = number_value(&mut args, &arg)? as usize,
+            "--max-plies" => settings.max_plies = number_value(&mut args, &arg)? as usize,
+            "--tc" => {
+                let value = flag_value(&mut args, &arg)?;
+                let controls: Option<Vec<TimeControl>> =
+                    value.split(',').map(TimeControl::parse).collect();
+                settings.time_controls = match controls.as_deref() {
+                    Some([control]) => [*control; 2],
+                    Some([first, second]) => [*first, *second],
+                    _ => return Err(format!("Invalid value for --tc: {value}")),
+                };
+            }
+            flag if flag.starts_with("--") => return Err(format!("Unknown option: {flag}")),
+            _ if settings.output.is_none() => settings.output = Some(arg),
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    Ok(settings)
+}
+
+/// Takes `--backend NAME` out of the arguments of any command, for the commands replaying
+/// moves to play them on that board. The default backend when it is absent.
+pub fn take_backend(args: &mut Vec<String>) -> Result<BackendKind, String> {
+    let mut backend = BackendKind::default();
+    while let Some(index) = args.iter().position(|arg| arg == "--backend") {
+        args.remove(index);
+        if index == args.len() {
+            return Err("Missing value for --backend".to_string());
+        }
+        let value = args.remove(index);
+        backend = BackendKind::parse(&value)
+            .ok_or_else(|| format!("Invalid value for --backend: {value}"))?;
+    }
+    Ok(backend)
+}
+
+pub fn parse_command<I: Iterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.peekable();
+
+    match args.peek().map(String::as_str) {
+        Some("diff") => {
+            args.next();
+            let left = args.next().ok_or("Usage: diff <left.pgn> <right.pgn>")?;
+            let right = args.next().ok_or("Usage: diff <left.pgn> <right.pgn>")?;
+            Ok(Command::Diff { left, right })
+        }
+        Some("clean") => {
+            args.next();
+            parse_clean(args).map(Command::Clean)
+        }
+        Some("worker") => {
+            args.next();
+            let socket = match args.next().as_deref() {
+                None => None,
+                Some("--socket") => Some(flag_value(&mut args, "--socket")?),
+                Some(arg) => return Err(format!("Unexpected argument: {arg}")),
+            };
+            Ok(Command::Worker { socket })
+        }
+        Some("test-suite") => {
+            const USAGE: &str = "Usage: test-suite <engine> <suite.epd> [--movetime MS]";
+            args.next();
+            let engine = args.next().ok_or(USAGE)?;
+            let suite = args.next().ok_or(USAGE)?;
+            let mut movetime = 1000;
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--movetime" => movetime = number_value(&mut args, &arg)?,
+                    _ => return Err(format!("Unexpected argument: {arg}")),
+                }
+            }
+            Ok(Command::TestSuite {
+                engine,
+                suite,
+                movetime,
+            })
+        }
+        Some("openings") => {
+            const USAGE: &str = "Usage: openings <input.pgn> <engine> [--depth PLIES] [--min-games N] [--movetime MS] [--top N]";
+            args.next();
+            let mut settings = OpeningSettings {
+                input: args.next().ok_or(USAGE)?,
+                engine: args.next().ok_or(USAGE)?,
+                depth: 12,
+                min_games: 20,
+                movetime: 500,
+                top: 20,
+            };
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--depth" => settings.depth = number_value(&mut args, &arg)? as usize,
+                    "--min-games" => settings.min_games = number_value(&mut args, &arg)? as usize,
+                    "--movetime" => settings.movetime = number_value(&mut args, &arg)?,
+                    "--top" => settings.top = number_value(&mut args, &arg)? as usize,
+                    _ => return Err(format!("Unexpected argument: {arg}")),
+                }
+            }
+            Ok(Command::Openings(settings))
+        }
+        Some("crosstable") => {
+            const USAGE: &str = "Usage: crosstable <input.pgn> [--format text|markdown|html]";
+            args.next();
+            let input = args.next().ok_or(USAGE)?;
+            let mut format = CrosstableFormat::default();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--format" => {
+                        let value = flag_value(&mut args, &arg)?;
+                        format = CrosstableFormat::parse(&value)
+                            .ok_or_else(|| format!("Unknown crosstable format: {value}"))?;
+                    }
+                    _ => return Err(format!("Unexpected argument: {arg}")),
+                }
+            }
+            Ok(Command::Crosstable { input, format })
+        }
+        Some("events") => {
+            const USAGE: &str = "Usage: events <input.pgn> [--json]";
+            args.next();
+            let input = args.next().ok_or(USAGE)?;
+            let mut json = false;
+            for arg in args {
+                match arg.as_str() {
+                    "--json" => json = true,
+                    _ => return Err(format!("Unexpected argument: {arg}")),
+                }
+            }
+            Ok(Command::Events { input, json })
+        }
+        Some("h2h") => {
+            const USAGE: &str = "Usage: h2h <input.pgn> <player> <player> [output.pgn]";
+            args.next();
+            let input = args.next().ok_or(USAGE)?;
+            let players = [args.next().ok_or(USAGE)?, args.next().ok_or(USAGE)?];
+            let output = args.next();
+            if let Some(arg) = args.next() {
+                return Err(format!("Unexpected argument: {arg}"));
+            }
+            Ok(Command::HeadToHead {
+                input,
+                players,
+                output,
+            })
+        }
+        Some("livechess") => {
+            const USAGE: &str = "Usage: livechess <input.pgn>... [--output output.pgn]";
+            args.next();
+            let mut inputs = Vec::new();
+            let mut output = None;
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--output" => output = Some(flag_value(&mut args, &arg)?),
+                    flag if flag.starts_with("--") => {
+                        return Err(format!("Unknown option: {flag}"))
+                    }
+                    _ => inputs.push(arg),
+                }
+            }
+            if inputs.is_empty() {
+                return Err(USAGE.to_string());
+            }
+            Ok(Command::LiveChess { inputs, output })
+        }
+        Some("headers") => {
+            const USAGE: &str = "Usage: headers <input.pgn> [--tags TAG,TAG...]";
+            args.next();
+            let input = args.next().ok_or(USAGE)?;
+            let mut tags = SEVEN_TAG_ROSTER.map(str::to_string).to_vec();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--tags" => tags = TagSelection::parse_list(&flag_value(&mut args, &arg)?),
+                    _ => return Err(format!("Unexpected argument: {arg}")),
+                }
+            }
+            Ok(Command::Headers { input, tags })
+        }
+        Some("index") => {
+            const USAGE: &str = "Usage: index <input.pgn> [--tags TAG,TAG...]";
+            args.next();
+            let input = args.next().ok_or(USAGE)?;
+            let mut tags = SEVEN_TAG_ROSTER.map(str::to_string).to_vec();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--tags" => tags = TagSelection::parse_list(&flag_value(&mut args, &arg)?),
+                    _ => return Err(format!("Unexpected argument: {arg}")),
+                }
+            }
+            Ok(Command::Index { input, tags })
+        }
+        Some("game") => {
+            const USAGE: &str = "Usage: game <input.pgn> <number>";
+            args.next();
+            let input = args.next().ok_or(USAGE)?;
+            let number = args
+                .next()
+                .ok_or(USAGE)?
+                .parse()
+                .map_err(|_| "The game number must be a positive integer".to_string())?;
+            if let Some(arg) = args.next() {
+                return Err(format!("Unexpected argument: {arg}"));
+            }
+            Ok(Command::Game { input, number })
+        }
+        Some("encode") => {
+            const USAGE: &str = "Usage: encode <input.pgn> <output.pgnc> [--update]";
+            args.next();
+            let input = args.next().ok_or(USAGE)?;
+            let output = args.next().ok_or(USAGE)?;
+            let mut update = false;
+            for arg in args {
+                match arg.as_str() {
+                    "--update" => update = true,
+                    _ => return Err(format!("Unexpected argument: {arg}")),
+                }
+            }
+            Ok(Command::Encode {
+                input,
+                output,
+                update,
+            })
+        }
+        Some("decode") => {
+            const USAGE: &str = "Usage: decode <input.pgnc> [output.pgn]";
+            args.next();
+            let input = args.next().ok_or(USAGE)?;
+            let output = args.next();
+            if let Some(arg) = args.next() {
+                return Err(format!("Unexpected argument: {arg}"));
+            }
+            Ok(Command::Decode { input, output })
+        }
+        Some("study") => {
+            const USAGE: &str =
+                "Usage: study <input.pgn> [--name STUDY] [--chapter-name TEMPLATE] [--push STUDY_ID] [output.pgn]";
+            args.next();
+            let mut settings = StudySettings {
+                input: args.next().ok_or(USAGE)?,
+                name: None,
+                chapter_name: DEFAULT_CHAPTER_TEMPLATE.to_string(),
+                output: None,
+                push: None,
+            };
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--name" => settings.name = Some(flag_value(&mut args, &arg)?),
+                    "--chapter-name" => settings.chapter_name = flag_value(&mut args, &arg)?,
+                    "--push" => {
+                        let value = flag_value(&mut args, &arg)?;
+                        let id = lichess::study_id(&value)
+                            .ok_or_else(|| format!("Not a Lichess study id or URL: {value}"))?;
+                        settings.push = Some(id.to_string());
+                    }
+                    _ if arg.starts_with("--") || settings.output.is_some() => {
+                        return Err(format!("Unexpected argument: {arg}"))
+                    }
+                    _ => settings.output = Some(arg),
+                }
+            }
+            Ok(Command::Study(settings))
+        }
+        Some("fens") => {
+            const USAGE: &str = "Usage: fens <input.fen> [--engine COMMAND] [--movetime MS] [--eval] [--tablebase SYZYGY_PATH] [--classify] [output.tsv]";
+            args.next();
+            let mut settings = FenSettings {
+                input: args.next().ok_or(USAGE)?,
+                engine: None,
+                movetime: 100,
+                eval: false,
+                tablebase: None,
+                classify: false,
+                output: None,
+            };
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--engine" => settings.engine = Some(flag_value(&mut args, &arg)?),
+                    "--movetime" => settings.movetime = number_value(&mut args, &arg)?,
+                    "--eval" => settings.eval = true,
+                    "--tablebase" => settings.tablebase = Some(flag_value(&mut args, &arg)?),
+                    "--classify" => settings.classify = true,
+                    _ if arg.starts_with("--") || settings.output.is_some() => {
+                        return Err(format!("Unexpected argument: {arg}"))
+                    }
+                    _ => settings.output = Some(arg),
+                }
+            }
+            if !settings.eval && settings.tablebase.is_none() && !settings.classify {
+                return Err("Select at least one of --eval, --tablebase and --classify".to_string());
+            }
+            if (settings.eval || settings.tablebase.is_some()) && settings.engine.is_none() {
+                return Err("--eval and --tablebase need an --engine".to_string());
+            }
+            Ok(Command::Fens(settings))
+        }
+        Some("gen") => {
+            args.next();
+            let mut settings = GenSettings {
+                games: 10,
+                seed: None,
+                max_plies: 300,
+                book: None,
+                book_depth: 16,
+                output: None,
+            };
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--games" => settings.games = number_value(&mut args, &arg)? as usize,
+                    "--seed" => {
+                        let value = flag_value(&mut args, &arg)?;
+                        let seed = value
+                            .parse()
+                            .map_err(|_| format!("Invalid seed: {value}"))?;
+                        settings.seed = Some(seed);
+                    }
+                    "--max-plies" => settings.max_plies = number_value(&mut args, &arg)? as usize,
+                    "--book" => settings.book = Some(flag_value(&mut args, &arg)?),
+                    "--book-depth" => settings.book_depth = number_value(&mut args, &arg)? as usize,
+                    _ if arg.starts_with("--") || settings.output.is_some() => {
+                        return Err(format!("Unexpected argument: {arg}"))
+                    }
+                    _ => settings.output = Some(arg),
+                }
+            }
+            Ok(Command::Gen(settings))
+        }
+        Some("perft") => {
+            const USAGE: &str = "Usage: perft <depth> [--fen FEN] [--divide] [--check-crate]";
+            args.next();
+            let value = args.next().ok_or(USAGE)?;
+            let depth = value
+                .parse()
+                .map_err(|_| format!("Invalid depth: {value}"))?;
+            let (mut fen, mut divide, mut check_crate) = (None, false, false);
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--fen" => fen = Some(flag_value(&mut args, &arg)?),
+                    "--divide" => divide = true,
+                    "--check-crate" => check_crate = true,
+                    _ => return Err(format!("Unexpected argument: {arg}")),
+                }
+            }
+            Ok(Command::Perft {
+                depth,
+                fen,
+                divide,
+                check_crate,
+            })
+        }
+        Some("prep") => {
+            const USAGE: &str = "Usage: prep <input.pgn> <opponent> <white|black> [--recent N] [--depth PLIES] [--min-games N] [output.pgn]";
+            args.next();
+            let input = args.next().ok_or(USAGE)?;
+            let opponent = args.next().ok_or(USAGE)?;
+            let my_color_white = match args.next().ok_or(USAGE)?.as_str() {
+                "white" => true,
+                "black" => false,
+                color => return Err(format!("Unknown color: {color}")),
+            };
+            let mut settings = PrepSettings {
+                input,
+                opponent,
+                my_color_white,
+                recent: 50,
+                depth: 10,
+                min_games: 2,
+                output: None,
+            };
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--recent" => settings.recent = number_value(&mut args, &arg)? as usize,
+                    "--depth" => settings.depth = number_value(&mut args, &arg)? as usize,
+                    "--min-games" => settings.min_games = number_value(&mut args, &arg)? as usize,
+                    _ if arg.starts_with("--") || settings.output.is_some() => {
+                        return Err(format!("Unexpected argument: {arg}"))
+                    }
+                    _ => settings.output = Some(arg),
+                }
+            }
+            Ok(Command::Prep(settings))
+        }
+        Some("screen") => {
+            const USAGE: &str = "Usage: screen <input.pgn> [--engine COMMAND] [--movetime MS] [--skip-plies N] [--min-moves N]";
+            args.next();
+            let mut settings = ScreenSettings {
+                input: args.next().ok_or(USAGE)?,
+                engine: None,
+                movetime: 100,
+                skip_plies: 16,
+                min_moves: 40,
+            };
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--engine" => settings.engine = Some(flag_value(&mut args, &arg)?),
+                    "--movetime" => settings.movetime = number_value(&mut args, &arg)?,
+                    "--skip-plies" => settings.skip_plies = number_value(&mut args, &arg)? as usize,
+                    "--min-moves" => settings.min_moves = number_value(&mut args, &arg)? as usize,
+                    _ => return Err(format!("Unexpected argument: {arg}")),
+                }
+            }
+            Ok(Command::Screen(settings))
+        }
+        Some("selfplay") => {
+            args.next();
+            parse_selfplay(args).map(Command::Selfplay)
+        }
+        _ => parse_args(args).map(|options| Command::Crunch(Box::new(options))),
+    }
+}
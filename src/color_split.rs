@@ -0,0 +1,86 @@
+//! The moves of each side of a game as two sequences, for tools studying or training on one
+//! color's moves. The sequences are aligned by move number: the moves at the same index were
+//! played in the same full move, so a game set up with black to move has no first white move.
+
+use std::io::{self, Write};
+
+use chess::legal_moves::misc::Color;
+
+use crate::json;
+use crate::position::Position;
+
+/// Stands for the white move missing before a first black move in tab-separated output.
+pub const MISSING_MOVE: &str = "--";
+
+/// The moves of a game split by color.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ColorSplit {
+    /// Number of the full move the first moves of the sequences belong to.
+    pub first_move: u32,
+    /// White's moves, None first when the game starts with black's move.
+    pub white: Vec<Option<String>>,
+    pub black: Vec<String>,
+}
+
+impl ColorSplit {
+    /// Splits written moves. `positions` holds the position before each move.
+    pub fn of(positions: &[Position], moves: &[String]) -> Self {
+        let Some(start) = positions.first() else {
+            return ColorSplit {
+                first_move: 1,
+                ..ColorSplit::default()
+            };
+        };
+        let mut split = ColorSplit {
+            first_move: start.fullmove_number,
+            white: Vec::new(),
+            black: Vec::new(),
+        };
+        if start.side_to_move == Color::Black && !moves.is_empty() {
+            split.white.push(None);
+        }
+        for (mv, position) in moves.iter().zip(positions) {
+            match position.side_to_move {
+                Color::White => split.white.push(Some(mv.clone())),
+                Color::Black => split.black.push(mv.clone()),
+            }
+        }
+        split
+    }
+
+    /// Writes the split on a line: the game number, the first move number, then white's and
+    /// black's moves separated by tabs, or as a JSON line with null for a missing white move.
+    pub fn write<W: Write>(
+        &self,
+        writer: &mut W,
+        game_number: usize,
+        as_json: bool,
+    ) -> io::Result<()> {
+        if as_json {
+            let white = json::array(self.white.iter().map(|mv| match mv {
+                Some(mv) => json::string(mv),
+                None => "null".to_string(),
+            }));
+            let black = json::array(self.black.iter().map(|mv| json::string(mv)));
+            let line = json::object([
+                ("game", game_number.to_string()),
+                ("first_move", self.first_move.to_string()),
+                ("white", white),
+                ("black", black),
+            ]);
+            return writeln!(writer, "{line}");
+        }
+        let white: Vec<&str> = self
+            .white
+            .iter()
+            .map(|mv| mv.as_deref().unwrap_or(MISSING_MOVE))
+            .collect();
+        writeln!(
+            writer,
+            "{game_number}\t{}\t{}\t{}",
+            self.first_move,
+            white.join(" "),
+            self.black.join(" ")
+        )
+    }
+}
@@ -0,0 +1,125 @@
+use std::borrow::Cow;
+use std::mem;
+
+use crate::pgn_ast::Line;
+use crate::san::SanMove;
+
+/// Command annotations written by engines and analysis tools, such as `[%eval 0.31]`. Others,
+/// like clock times and arrows, are kept.
+const ENGINE_COMMANDS: [&str; 3] = ["eval", "depth", "wdl"];
+
+/// Openings of the move judgments added by analysis servers, as in
+/// `Mistake. Nf3 was best.`
+const JUDGMENTS: [&str; 5] = [
+    "Inaccuracy.",
+    "Mistake.",
+    "Blunder.",
+    "Checkmate is now unavoidable.",
+    "Lost forced checkmate sequence.",
+];
+
+/// Removes engine command annotations from a comment, keeping the rest of it.
+pub fn strip_engine_commands(comment: &str) -> Cow<'_, str> {
+    let mut kept = String::new();
+    let mut rest = comment;
+    let mut stripped = false;
+
+    while let Some(start) = rest.find("[%") {
+        let Some(length) = rest[start..].find(']') else {
+            break;
+        };
+        let command = &rest[start + 2..start + length];
+        let name = command.split_whitespace().next().unwrap_or_default();
+        kept.push_str(&rest[..start]);
+        if ENGINE_COMMANDS.contains(&name) {
+            stripped = true;
+        } else {
+            kept.push_str(&rest[start..=start + length]);
+        }
+        rest = &rest[start + length + 1..];
+    }
+    if !stripped {
+        return Cow::Borrowed(comment);
+    }
+    kept.push_str(rest);
+
+    Cow::Owned(kept.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Whether a comment holds a move judgment such as `(0.23 → 1.05) Mistake. Nf3 was best.`
+pub fn is_judgment(comment: &str) -> bool {
+    let text = match comment.strip_prefix('(') {
+        Some(scored) => scored.split_once(')').map_or("", |(_, text)| text.trim()),
+        None => comment,
+    };
+    text.ends_with(" was best.") && JUDGMENTS.iter().any(|opening| text.starts_with(opening))
+}
+
+/// Whether a word is a SAN move, its origin being at most a square.
+pub fn is_san(word: &str) -> bool {
+    match SanMove::parse(word) {
+        Ok(SanMove::Castling { .. }) => true,
+        Ok(SanMove::Normal { origin, .. }) => {
+            origin.len() <= 2 && origin.chars().all(|c| matches!(c, 'a'..='h' | '1'..='8'))
+        }
+        Err(_) => false,
+    }
+}
+
+/// A word of engine output: a move number, a SAN move or a score, depth or time such as
+/// `+0.31/20` or `1.2s`.
+pub fn is_engine_word(word: &str) -> bool {
+    let word = word.trim_matches(['(', ')', ',']);
+    let is_score = word.chars().any(|c| c.is_ascii_digit())
+        && word.chars().all(|c| "+-#./=0123456789sM".contains(c));
+    // Moves may be glued to their number, as in `18.Nf5`
+    let unnumbered = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    is_score || is_san(word) || is_san(unnumbered)
+}
+
+/// Whether a comment was written by a machine rather than a person: a move judgment, or
+/// nothing but engine output such as `+0.45/18 19. Nf5 Qd7 20. Qg4`.
+pub fn is_engine_comment(comment: &str) -> bool {
+    let mut words = comment.split_whitespace().peekable();
+    is_judgment(comment) || (words.peek().is_some() && words.all(is_engine_word))
+}
+
+/// Removes engine comments and command annotations from a line and its side lines, returning
+/// the number of comments removed or shortened. A move judged by an analysis server loses its
+/// side lines as well, since they hold the engine's suggestion.
+pub fn strip_engine_comments(line: &mut Line) -> usize {
+    let mut changed = strip_comments(&mut line.comments);
+
+    for node in &mut line.moves {
+        if node.comments.iter().any(|comment| is_judgment(comment)) {
+            node.variations.clear();
+        }
+        changed += strip_comments(&mut node.comments);
+        for variation in &mut node.variations {
+            changed += strip_engine_comments(variation);
+        }
+    }
+
+    changed
+}
+
+fn strip_comments(comments: &mut Vec<Cow<'_, str>>) -> usize {
+    let mut changed = 0;
+
+    for comment in mem::take(comments) {
+        let stripped = match strip_engine_commands(&comment) {
+            Cow::Owned(text) => Some(text),
+            Cow::Borrowed(_) => None,
+        };
+        let edited = stripped.is_some();
+        let comment = stripped.map_or(comment, Cow::Owned);
+        if comment.is_empty() || is_engine_comment(&comment) {
+            changed += 1;
+            continue;
+        }
+        changed += usize::from(edited);
+        comments.push(comment);
+    }
+
+    changed
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use crate::events::{points, Event, Standing};
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum CrosstableFormat {
+    #[default]
+    Text,
+    Markdown,
+    Html,
+}
+
+impl CrosstableFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "text" => Some(CrosstableFormat::Text),
+            "markdown" | "md" => Some(CrosstableFormat::Markdown),
+            "html" => Some(CrosstableFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+/// A player's line of the crosstable.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CrosstableRow {
+    pub standing: Standing,
+    /// Sum of the points of the player's opponents, once per finished game against them.
+    pub buchholz: f64,
+    /// Sum of the points of the opponents the player beat and half those of the ones they drew.
+    pub sonneborn_berger: f64,
+    /// The player's games of each round column, as in `Standing::round_cell`.
+    pub cells: Vec<String>,
+}
+
+/// The crosstable of an event, players ranked by points, then Buchholz, then
+/// Sonneborn-Berger, both tie-breaks computed from the event's own games.
+pub struct Crosstable {
+    pub title: String,
+    pub rounds: Vec<Option<u32>>,
+    pub rows: Vec<CrosstableRow>,
+}
+
+/// A number with at most two decimals and no trailing zeros, such as `7.25` or `6`.
+fn decimal(value: f64) -> String {
+    let text = format!("{value:.2}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Crosstable {
+    pub fn of(event: &Event) -> Self {
+        let standings = event.standings();
+        let scores: HashMap<&str, f64> = standings
+            .iter()
+            .map(|standing| {
+                (
+                    standing.player.as_str(),
+                    f64::from(standing.half_points) / 2.0,
+                )
+            })
+            .collect();
+
+        let mut rows: Vec<CrosstableRow> = standings
+            .iter()
+            .map(|standing| {
+                let (mut buchholz, mut sonneborn_berger) = (0.0, 0.0);
+                for game in &standing.games {
+                    let Some(half_points) = game.half_points else {
+                        continue;
+                    };
+                    let opponent = scores[game.opponent.as_str()];
+                    buchholz += opponent;
+                    sonneborn_berger += opponent * f64::from(half_points) / 2.0;
+                }
+                CrosstableRow {
+                    standing: standing.clone(),
+                    buchholz,
+                    sonneborn_berger,
+                    cells: Vec::new(),
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            b.standing
+                .half_points
+                .cmp(&a.standing.half_points)
+                .then(b.buchholz.total_cmp(&a.buchholz))
+                .then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger))
+                .then(a.standing.player.cmp(&b.standing.player))
+        });
+
+        let rounds = event.round_columns();
+        let ranks: HashMap<&str, usize> = rows
+            .iter()
+            .enumerate()
+            .map(|(rank, row)| (row.standing.player.as_str(), rank + 1))
+            .collect();
+        let cells: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| {
+                rounds
+                    .iter()
+                    .map(|round| row.standing.round_cell(*round, &ranks))
+                    .collect()
+            })
+            .collect();
+        for (row, cells) in rows.iter_mut().zip(cells) {
+            row.cells = cells;
+        }
+
+        Crosstable {
+            title: event.heading(),
+            rounds,
+            rows,
+        }
+    }
+
+    /// Column headings and the cells of each row, all as text.
+    fn table(&self) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut headings: Vec<String> = ["Rank", "Player", "Pts"].map(str::to_string).to_vec();
+        headings.extend(
+            self.rounds
+                .iter()
+                .map(|round| round.map_or("R?".to_string(), |round| format!("R{round}"))),
+        );
+        headings.extend(["Buchholz", "SB"].map(str::to_string));
+
+        let rows = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(rank, row)| {
+                let mut cells = vec![
+                    (rank + 1).to_string(),
+                    row.standing.player.clone(),
+                    points(row.standing.half_points),
+                ];
+                cells.extend(row.cells.iter().cloned());
+                cells.push(decimal(row.buchholz));
+                cells.push(decimal(row.sonneborn_berger));
+                cells
+            })
+            .collect();
+        (headings, rows)
+    }
+
+    pub fn render(&self, format: CrosstableFormat) -> String {
+        let (headings, rows) = self.table();
+        // The player column is the only one aligned left
+        const PLAYER: usize = 1;
+
+        match format {
+            CrosstableFormat::Text => {
+                let widths: Vec<usize> = (0..headings.len())
+                    .map(|column| {
+                        rows.iter()
+                            .map(|row| row[column].chars().count())
+                            .chain([headings[column].len()])
+                            .max()
+                            .unwrap_or(0)
+                    })
+                    .collect();
+                let line = |cells: &[String]| {
+                    let cells: Vec<String> = cells
+                        .iter()
+                        .zip(&widths)
+                        .enumerate()
+                        .map(|(column, (cell, width))| match column {
+                            PLAYER => format!("{cell:width$}"),
+                            _ => format!("{cell:>width$}"),
+                        })
+                        .collect();
+                    format!("{}\n", cells.join("  ").trim_end())
+                };
+
+                let mut text = format!("{}\n", self.title);
+                text.push_str(&line(&headings));
+                for row in &rows {
+                    text.push_str(&line(row));
+                }
+                text
+            }
+            CrosstableFormat::Markdown => {
+                let line = |cells: &[String]| {
+                    let cells: Vec<String> =
+                        cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+                    format!("| {} |\n", cells.join(" | "))
+                };
+                let alignments: Vec<String> = (0..headings.len())
+                    .map(|column| match column {
+                        PLAYER => ":---".to_string(),
+                        _ => "---:".to_string(),
+                    })
+                    .collect();
+
+                let mut text = format!("### {}\n\n", self.title);
+                text.push_str(&line(&headings));
+                text.push_str(&line(&alignments));
+                for row in &rows {
+                    text.push_str(&line(row));
+                }
+                text
+            }
+            CrosstableFormat::Html => {
+                let line = |cells: &[String], tag: &str| {
+                    let cells: String = cells
+                        .iter()
+                        .map(|cell| format!("<{tag}>{}</{tag}>", escape_html(cell)))
+                        .collect();
+                    format!("<tr>{cells}</tr>\n")
+                };
+
+                let mut html = format!(
+                    "<h2>{}</h2>\n<table class=\"crosstable\">\n",
+                    escape_html(&self.title)
+                );
+                html.push_str(&line(&headings, "th"));
+                for row in &rows {
+                    html.push_str(&line(row, "td"));
+                }
+                html.push_str("</table>\n");
+                html
+            }
+        }
+    }
+}
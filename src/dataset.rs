@@ -0,0 +1,40 @@
+use std::collections::HashSet;
+
+use crate::eval::Quiescence;
+use crate::position::Position;
+
+/// Emits each position (by Zobrist hash) at most once across all games it is fed.
+#[derive(Default)]
+pub struct UniquePositions {
+    seen: HashSet<u64>,
+    /// Leave out positions with checks or hanging pieces.
+    quiet_only: bool,
+}
+
+impl UniquePositions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_quiet_only(&mut self, quiet_only: bool) {
+        self.quiet_only = quiet_only;
+    }
+
+    /// Appends a `fen<TAB>move<TAB>result` line for every position of the game not seen before.
+    pub fn collect(
+        &mut self,
+        positions: &[Position],
+        moves: &[String],
+        result: &str,
+        output: &mut String,
+    ) {
+        for (position, mv) in positions.iter().zip(moves) {
+            if self.quiet_only && Quiescence::of(position) != Quiescence::Quiet {
+                continue;
+            }
+            if self.seen.insert(position.zobrist()) {
+                output.push_str(&format!("{}\t{mv}\t{result}\n", position.to_fen()));
+            }
+        }
+    }
+}
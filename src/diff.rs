@@ -0,0 +1,103 @@
+use crate::pgn_ast::Game;
+use crate::pgn_cleaner::RawGame;
+
+fn ply_label(ply: usize) -> String {
+    let number = ply / 2 + 1;
+    if ply.is_multiple_of(2) {
+        format!("{number}.")
+    } else {
+        format!("{number}...")
+    }
+}
+
+/// Lists the differences between two versions of the same game: headers first, then ply by ply.
+pub fn diff_games(left: &RawGame, right: &RawGame) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    for (tag, value) in &left.tags {
+        match right.tag(tag) {
+            None => differences.push(format!("header {tag}: only in left ({value})")),
+            Some(other) if other != value => {
+                differences.push(format!("header {tag}: \"{value}\" vs \"{other}\""))
+            }
+            _ => {}
+        }
+    }
+    for (tag, value) in &right.tags {
+        if left.tag(tag).is_none() {
+            differences.push(format!("header {tag}: only in right ({value})"));
+        }
+    }
+
+    // Only the mainline is compared, side lines skipped
+    let left = Game::parse(left);
+    let right = Game::parse(right);
+    let (left_prelude, left_plies) = (&left.moves.comments, &left.moves.moves);
+    let (right_prelude, right_plies) = (&right.moves.comments, &right.moves.moves);
+
+    if left_prelude != right_prelude {
+        differences.push(format!(
+            "initial comment: {left_prelude:?} vs {right_prelude:?}"
+        ));
+    }
+
+    for (ply, (left_ply, right_ply)) in left_plies.iter().zip(right_plies).enumerate() {
+        let label = ply_label(ply);
+        let (left_move, right_move) = (left_ply.written(), right_ply.written());
+        if left_move != right_move {
+            differences.push(format!(
+                "ply {} ({label}): move {left_move} vs {right_move}",
+                ply + 1
+            ));
+            // Everything after diverging moves differs as well
+            return differences;
+        }
+        if left_ply.comments != right_ply.comments {
+            differences.push(format!(
+                "ply {} ({label} {left_move}): comment {:?} vs {:?}",
+                ply + 1,
+                left_ply.comments,
+                right_ply.comments
+            ));
+        }
+    }
+
+    if left_plies.len() != right_plies.len() {
+        differences.push(format!(
+            "length: {} plies vs {} plies",
+            left_plies.len(),
+            right_plies.len()
+        ));
+    }
+
+    differences
+}
+
+/// Lists the differences between two files holding versions of the same games, in order: the
+/// game counts, the differences of each pair, then the games found on one side only.
+pub fn diff_databases(left: &[RawGame], right: &[RawGame]) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    if left.len() != right.len() {
+        differences.push(format!(
+            "Game count differs: {} vs {}",
+            left.len(),
+            right.len()
+        ));
+    }
+    for (game_index, (left, right)) in left.iter().zip(right).enumerate() {
+        for difference in diff_games(left, right) {
+            differences.push(format!("Game {}: {difference}", game_index + 1));
+        }
+    }
+    let (longer, side) = if left.len() > right.len() {
+        (left, "left")
+    } else {
+        (right, "right")
+    };
+    for game_index in left.len().min(right.len())..longer.len() {
+        differences.push(format!("Game {}: only in {side}", game_index + 1));
+    }
+
+    differences
+}
@@ -0,0 +1,111 @@
+//! Lint for SAN moves whose disambiguation does not fit the position: `Nbd2` when only one
+//! knight can go to d2, or `Nd2` when two can. Machine-generated PGNs often get it wrong.
+
+use crate::pgn_cleaner::{mainline_spans, RawGame};
+use crate::position::Position;
+use crate::san::{to_san, SanMove};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Disambiguation {
+    /// Several pieces of the kind can play the move as written.
+    Under,
+    /// The move names more of the origin square than needed to tell it apart.
+    Over,
+}
+
+/// A mainline move with unfitting disambiguation.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DisambiguationIssue {
+    /// Byte offset of the move in the movetext.
+    pub offset: usize,
+    pub written: String,
+    pub kind: Disambiguation,
+    /// The move as SAN spells it, without check suffix; unknown for ambiguous moves.
+    pub canonical: Option<String>,
+}
+
+impl DisambiguationIssue {
+    pub fn describe(&self) -> String {
+        match (self.kind, &self.canonical) {
+            (Disambiguation::Over, Some(canonical)) => {
+                format!(
+                    "{} is over-disambiguated, {canonical} suffices",
+                    self.written
+                )
+            }
+            _ => format!(
+                "{} is under-disambiguated, several pieces can play it",
+                self.written
+            ),
+        }
+    }
+}
+
+/// Checks the disambiguation of a SAN move in a position. Castling, illegal moves and moves
+/// written as SAN would write them give None.
+pub fn check_move(position: &Position, san: &str) -> Option<(Disambiguation, Option<String>)> {
+    let SanMove::Normal { origin, .. } = SanMove::parse(san).ok()? else {
+        return None;
+    };
+    let (from, to, promotion) = match position.parse_san(san) {
+        Ok(mv) => mv,
+        Err(message) if message.starts_with("Ambiguous") => {
+            return Some((Disambiguation::Under, None))
+        }
+        Err(_) => return None,
+    };
+
+    let canonical = to_san(position, from, to, promotion);
+    let canonical = canonical.trim_end_matches(['+', '#']);
+    let needed = match SanMove::parse(canonical).ok()? {
+        SanMove::Normal { origin, .. } => origin.len(),
+        SanMove::Castling { .. } => return None,
+    };
+    (origin.len() > needed).then(|| (Disambiguation::Over, Some(canonical.to_string())))
+}
+
+/// Disambiguation issues of the mainline, which is followed until a move cannot be played.
+pub fn lint_game(game: &RawGame) -> Vec<DisambiguationIssue> {
+    let mut position = match game.tag("FEN") {
+        Some(fen) => match Position::from_fen(fen) {
+            Ok(position) => position,
+            Err(_) => return Vec::new(),
+        },
+        None => Position::initial(),
+    };
+
+    let mut issues = Vec::new();
+    for (offset, san) in mainline_spans(&game.movetext) {
+        if let Some((kind, canonical)) = check_move(&position, san) {
+            issues.push(DisambiguationIssue {
+                offset,
+                written: san.to_string(),
+                kind,
+                canonical,
+            });
+        }
+        let Ok((from, to, promotion)) = position.parse_san(san) else {
+            break;
+        };
+        position = position.play(from, to, promotion);
+    }
+    issues
+}
+
+/// Rewrites the over-disambiguated moves of the issues as SAN spells them, keeping their
+/// suffixes. Returns the number of moves rewritten.
+pub fn normalize(game: &mut RawGame, issues: &[DisambiguationIssue]) -> usize {
+    let mut rewritten = 0;
+    // From the end, so that earlier offsets stay valid
+    for issue in issues.iter().rev() {
+        let Some(canonical) = &issue.canonical else {
+            continue;
+        };
+        let end = issue.offset + issue.written.len();
+        if game.movetext.get(issue.offset..end) == Some(issue.written.as_str()) {
+            game.movetext.replace_range(issue.offset..end, canonical);
+            rewritten += 1;
+        }
+    }
+    rewritten
+}
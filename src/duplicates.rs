@@ -0,0 +1,122 @@
+//! Duplicate games in merged inputs, such as the same game downloaded from two sites. Copies
+//! are recognized by their players and mainline, so that they match even when their event
+//! details, dates or comments differ, and a policy decides what the copy written out holds.
+//! Copies spelling moves differently, as `Ngf3` for `Nf3`, match once the moves are rewritten
+//! with `--fix-disambiguation`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::filters::PgnDate;
+use crate::pgn_ast::{Game, Line};
+use crate::redact::fnv1a;
+
+/// What to keep of duplicate games.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ConflictPolicy {
+    /// The copy met first, in input order.
+    First,
+    /// The copy with the latest Date, the first among copies as recent.
+    Newest,
+    /// The copy met first, with the comments of later copies that it lacks.
+    MergeComments,
+}
+
+impl ConflictPolicy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "first" => Some(ConflictPolicy::First),
+            "newest" => Some(ConflictPolicy::Newest),
+            "merge-comments" => Some(ConflictPolicy::MergeComments),
+            _ => None,
+        }
+    }
+}
+
+/// Hash of a game's players and mainline SAN moves.
+pub fn duplicate_key(game: &Game) -> u64 {
+    let mut key = String::new();
+    for name in ["White", "Black"] {
+        key.push_str(game.tag(name).unwrap_or("?").trim());
+        key.push('\0');
+    }
+    key.push_str(&game.moves.sans().join(" "));
+    fnv1a(key.as_bytes())
+}
+
+/// The earliest day a game's Date, or its UTCDate, may denote; 0 when unknown.
+fn date_order(game: &Game) -> u32 {
+    game.tag("Date")
+        .and_then(PgnDate::parse)
+        .or_else(|| game.tag("UTCDate").and_then(PgnDate::parse))
+        .map_or(0, |date| date.earliest())
+}
+
+/// Adds to `kept` the comments of `other` it does not hold, before the first move and after
+/// each mainline move. Returns the number of comments added.
+pub fn merge_comments<'a>(kept: &mut Line<'a>, other: &Line<'a>) -> usize {
+    let mut added = 0;
+    let mut merge = |into: &mut Vec<Cow<'a, str>>, from: &[Cow<'a, str>]| {
+        for comment in from {
+            if !into.contains(comment) {
+                into.push(comment.clone());
+                added += 1;
+            }
+        }
+    };
+    merge(&mut kept.comments, &other.comments);
+    for (node, other) in kept.moves.iter_mut().zip(&other.moves) {
+        merge(&mut node.comments, &other.comments);
+    }
+    added
+}
+
+/// The games of the input with one copy of each duplicate, in the order of their first copies.
+pub struct Deduplicator<'a> {
+    policy: ConflictPolicy,
+    positions: HashMap<u64, usize>,
+    games: Vec<Game<'a>>,
+    /// Copies left out.
+    pub duplicates: usize,
+    /// Comments taken from later copies under [`ConflictPolicy::MergeComments`].
+    pub merged_comments: usize,
+}
+
+impl<'a> Deduplicator<'a> {
+    pub fn new(policy: ConflictPolicy) -> Self {
+        Deduplicator {
+            policy,
+            positions: HashMap::new(),
+            games: Vec::new(),
+            duplicates: 0,
+            merged_comments: 0,
+        }
+    }
+
+    /// Adds a game under its [`duplicate_key`], which is taken before any change to the
+    /// game, such as redaction, could hide the copies it has.
+    pub fn add(&mut self, key: u64, game: Game<'a>) {
+        let Some(&index) = self.positions.get(&key) else {
+            self.positions.insert(key, self.games.len());
+            self.games.push(game);
+            return;
+        };
+        self.duplicates += 1;
+        let kept = &mut self.games[index];
+        match self.policy {
+            ConflictPolicy::First => {}
+            ConflictPolicy::Newest => {
+                if date_order(&game) > date_order(kept) {
+                    *kept = game;
+                }
+            }
+            ConflictPolicy::MergeComments => {
+                self.merged_comments += merge_comments(&mut kept.moves, &game.moves);
+            }
+        }
+    }
+
+    pub fn into_games(self) -> Vec<Game<'a>> {
+        self.games
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use chess::legal_moves::misc::Color;
+
+use crate::pgn_cleaner::RawGame;
+use crate::position::{parse_coordinate_move, Position};
+
+/// An opening of the embedded table, reached by its coordinate moves.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EcoEntry {
+    /// Code of the opening itself.
+    pub code: &'static str,
+    /// Last code of the games whose latest table position is this opening's; the range from
+    /// `code` takes in the lines branching off it that the table does not list.
+    pub last: &'static str,
+    pub name: &'static str,
+    pub moves: &'static str,
+}
+
+const fn entry(
+    code: &'static str,
+    last: &'static str,
+    name: &'static str,
+    moves: &'static str,
+) -> EcoEntry {
+    EcoEntry {
+        code,
+        last,
+        name,
+        moves,
+    }
+}
+
+/// The main openings, enough to catch the usual mislabelled imports. Positions reached by
+/// several openings, such as 1.e4 e5, are left out.
+pub const ECO_TABLE: [EcoEntry; 36] = [
+    entry("A10", "A39", "English Opening", "c2c4"),
+    entry("A09", "A09", "Réti Opening", "g1f3 d7d5 c2c4"),
+    entry("A80", "A99", "Dutch Defense", "d2d4 f7f5"),
+    entry("A56", "A79", "Benoni Defense", "d2d4 g8f6 c2c4 c7c5 d4d5"),
+    entry(
+        "A57",
+        "A59",
+        "Benko Gambit",
+        "d2d4 g8f6 c2c4 c7c5 d4d5 b7b5",
+    ),
+    entry("B01", "B01", "Scandinavian Defense", "e2e4 d7d5"),
+    entry("B02", "B05", "Alekhine Defense", "e2e4 g8f6"),
+    entry("B06", "B06", "Modern Defense", "e2e4 g7g6"),
+    entry(
+        "B07",
+        "B09",
+        "Pirc Defense",
+        "e2e4 d7d6 d2d4 g8f6 b1c3 g7g6",
+    ),
+    entry("B10", "B19", "Caro-Kann Defense", "e2e4 c7c6"),
+    entry("B20", "B99", "Sicilian Defense", "e2e4 c7c5"),
+    entry(
+        "B22",
+        "B22",
+        "Sicilian Defense: Alapin Variation",
+        "e2e4 c7c5 c2c3",
+    ),
+    entry(
+        "B33",
+        "B33",
+        "Sicilian Defense: Sveshnikov Variation",
+        "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4 g8f6 b1c3 e7e5",
+    ),
+    entry(
+        "B70",
+        "B79",
+        "Sicilian Defense: Dragon Variation",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 g7g6",
+    ),
+    entry(
+        "B90",
+        "B99",
+        "Sicilian Defense: Najdorf Variation",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6",
+    ),
+    entry("C00", "C19", "French Defense", "e2e4 e7e6"),
+    entry(
+        "C15",
+        "C19",
+        "French Defense: Winawer Variation",
+        "e2e4 e7e6 d2d4 d7d5 b1c3 f8b4",
+    ),
+    entry("C25", "C29", "Vienna Game", "e2e4 e7e5 b1c3"),
+    entry("C30", "C39", "King's Gambit", "e2e4 e7e5 f2f4"),
+    entry("C41", "C41", "Philidor Defense", "e2e4 e7e5 g1f3 d7d6"),
+    entry("C42", "C43", "Petrov's Defense", "e2e4 e7e5 g1f3 g8f6"),
+    entry("C44", "C45", "Scotch Game", "e2e4 e7e5 g1f3 b8c6 d2d4"),
+    entry(
+        "C47",
+        "C49",
+        "Four Knights Game",
+        "e2e4 e7e5 g1f3 b8c6 b1c3 g8f6",
+    ),
+    entry("C50", "C59", "Italian Game", "e2e4 e7e5 g1f3 b8c6 f1c4"),
+    entry("C60", "C99", "Ruy Lopez", "e2e4 e7e5 g1f3 b8c6 f1b5"),
+    entry(
+        "C65",
+        "C67",
+        "Ruy Lopez: Berlin Defense",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6",
+    ),
+    entry(
+        "C68",
+        "C99",
+        "Ruy Lopez: Morphy Defense",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6",
+    ),
+    entry("D06", "D69", "Queen's Gambit", "d2d4 d7d5 c2c4"),
+    entry("D10", "D19", "Slav Defense", "d2d4 d7d5 c2c4 c7c6"),
+    entry(
+        "D20",
+        "D29",
+        "Queen's Gambit Accepted",
+        "d2d4 d7d5 c2c4 d5c4",
+    ),
+    entry(
+        "D30",
+        "D69",
+        "Queen's Gambit Declined",
+        "d2d4 d7d5 c2c4 e7e6",
+    ),
+    entry(
+        "D43",
+        "D49",
+        "Semi-Slav Defense",
+        "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6 b1c3 e7e6",
+    ),
+    entry(
+        "D80",
+        "D99",
+        "Grünfeld Defense",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 d7d5",
+    ),
+    entry("E00", "E09", "Catalan Opening", "d2d4 g8f6 c2c4 e7e6 g2g3"),
+    entry(
+        "E20",
+        "E59",
+        "Nimzo-Indian Defense",
+        "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4",
+    ),
+    entry(
+        "E61",
+        "E99",
+        "King's Indian Defense",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7",
+    ),
+];
+
+/// Words of opening names that do not tell openings apart.
+const GENERIC_WORDS: [&str; 5] = ["defense", "defence", "opening", "game", "variation"];
+
+fn position_key(position: &Position) -> (String, bool) {
+    (position.placement(), position.side_to_move == Color::White)
+}
+
+/// Words of the opening family in a name, the part before any `:` or `,`, lowercased without
+/// punctuation, accents or generic words: "Queen's Gambit Declined: Ragozin" gives
+/// `["queens", "gambit", "declined"]`.
+pub fn family_words(name: &str) -> Vec<String> {
+    let family = name.split([':', ',']).next().unwrap_or_default();
+    family
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .map(|word| {
+            word.chars()
+                .filter_map(|c| match c.to_ascii_lowercase() {
+                    'é' | 'è' | 'É' => Some('e'),
+                    'ü' | 'Ü' => Some('u'),
+                    c if c.is_ascii_alphanumeric() => Some(c),
+                    _ => None,
+                })
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty() && !GENERIC_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Whether a tag value is an ECO code such as `B90`.
+pub fn is_eco_code(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 3
+        && (b'A'..=b'E').contains(&bytes[0])
+        && bytes[1..].iter().all(u8::is_ascii_digit)
+}
+
+impl EcoEntry {
+    pub fn covers_code(&self, code: &str) -> bool {
+        (self.code..=self.last).contains(&code)
+    }
+
+    /// Whether an opening name belongs to the family of this one, one family being allowed to
+    /// name the other more precisely ("Queen's Gambit" and "Queen's Gambit Declined").
+    pub fn covers_name(&self, name: &str) -> bool {
+        let (ours, theirs) = (family_words(self.name), family_words(name));
+        ours.starts_with(&theirs) || theirs.starts_with(&ours)
+    }
+}
+
+/// Classifies games by the latest position they share with the embedded table, so that
+/// transpositions land on the same opening.
+pub struct EcoClassifier {
+    positions: HashMap<(String, bool), &'static EcoEntry>,
+}
+
+impl Default for EcoClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EcoClassifier {
+    pub fn new() -> Self {
+        let mut positions = HashMap::new();
+        for entry in &ECO_TABLE {
+            let mut position = Position::initial();
+            for mv in entry.moves.split(' ') {
+                let (from, to, promotion) =
+                    parse_coordinate_move(mv).expect("table moves are coordinate moves");
+                position = position.play(from, to, promotion);
+            }
+            positions.insert(position_key(&position), entry);
+        }
+        EcoClassifier { positions }
+    }
+
+    /// The opening of the latest table position among the positions of a game played from the
+    /// standard start.
+    pub fn classify(&self, positions: &[Position]) -> Option<&'static EcoEntry> {
+        positions
+            .iter()
+            .rev()
+            .find_map(|position| self.positions.get(&position_key(position)).copied())
+    }
+}
+
+/// The ECO and Opening tags of a game disagreeing with its classification. Missing tags and
+/// placeholders are not checked.
+pub fn mismatched_tags(game: &RawGame, entry: &EcoEntry) -> Vec<&'static str> {
+    let mut mismatched = Vec::new();
+    if game
+        .tag("ECO")
+        .is_some_and(|code| is_eco_code(code) && !entry.covers_code(code))
+    {
+        mismatched.push("ECO");
+    }
+    if game
+        .tag("Opening")
+        .is_some_and(|name| name != "?" && !entry.covers_name(name))
+    {
+        mismatched.push("Opening");
+    }
+    mismatched
+}
+
+/// Replaces mismatched tags with the classification. A replaced Opening tag takes the
+/// Variation tag along, since it qualified the wrong opening.
+pub fn fix_tags(game: &mut RawGame, entry: &EcoEntry, mismatched: &[&str]) {
+    for (tag, value) in &mut game.tags {
+        if !mismatched.contains(&tag.as_str()) {
+            continue;
+        }
+        *value = match tag.as_str() {
+            "ECO" => entry.code,
+            _ => entry.name,
+        }
+        .to_string();
+    }
+    if mismatched.contains(&"Opening") {
+        game.tags.retain(|(tag, _)| tag != "Variation");
+    }
+}
@@ -0,0 +1,83 @@
+use chess::legal_moves::misc::Color;
+
+use crate::position::{Piece, Position};
+
+/// Largest number of men, kings included, for which a position counts as an endgame to classify.
+pub const MAX_MEN: usize = 7;
+
+/// One side's material without the king, such as `R+2P`, or `K` for a bare king.
+fn material_signature(position: &Position, color: Color) -> String {
+    let parts: Vec<String> = [
+        Piece::Queen,
+        Piece::Rook,
+        Piece::Bishop,
+        Piece::Knight,
+        Piece::Pawn,
+    ]
+    .iter()
+    .filter_map(|piece| match position.count(color, *piece) {
+        0 => None,
+        1 => Some(piece.letter().to_string()),
+        count => Some(format!("{count}{}", piece.letter())),
+    })
+    .collect();
+
+    if parts.is_empty() {
+        "K".to_string()
+    } else {
+        parts.join("+")
+    }
+}
+
+fn is_light_square(square: usize) -> bool {
+    (square / 8 + square % 8) % 2 == 1
+}
+
+/// Endgame type of a position with at most [`MAX_MEN`] men, such as `R+P vs R`, the side with
+/// more material first. Bishop endings with a bishop each on different colors are marked as such.
+pub fn classify(position: &Position) -> Option<String> {
+    let men = position.squares.iter().flatten().count();
+    if men > MAX_MEN {
+        return None;
+    }
+
+    let (mut strong, mut weak) = (Color::White, Color::Black);
+    if position.material(Color::Black) > position.material(Color::White) {
+        (strong, weak) = (weak, strong);
+    }
+    let mut class = format!(
+        "{} vs {}",
+        material_signature(position, strong),
+        material_signature(position, weak)
+    );
+
+    let only_bishops_and_pawns = [Piece::Queen, Piece::Rook, Piece::Knight]
+        .iter()
+        .all(|piece| {
+            position.count(Color::White, *piece) + position.count(Color::Black, *piece) == 0
+        });
+    let bishops: Vec<(Color, bool)> = position
+        .squares
+        .iter()
+        .enumerate()
+        .filter_map(|(square, occupant)| match occupant {
+            Some((color, Piece::Bishop)) => Some((*color, is_light_square(square))),
+            _ => None,
+        })
+        .collect();
+    if only_bishops_and_pawns {
+        if let [(first, first_light), (second, second_light)] = bishops[..] {
+            if first != second && first_light != second_light {
+                class.push_str(", opposite-colored bishops");
+            }
+        }
+    }
+
+    Some(class)
+}
+
+/// Class of the first endgame position reached, if any.
+/// `positions` holds the position before each move followed by the final one.
+pub fn endgame_class(positions: &[Position]) -> Option<String> {
+    positions.iter().find_map(classify)
+}
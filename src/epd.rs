@@ -0,0 +1,122 @@
+use crate::position::{coordinate_move, Position};
+
+/// A line of an EPD file: a position and its operations, such as `bm Nf3; id "WAC.001";`.
+pub struct EpdRecord {
+    pub position: Position,
+    /// Opcodes with their operands, quotes removed, in file order.
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl EpdRecord {
+    /// Parses an EPD line. The `hmvc` and `fmvn` operations set the move counters.
+    pub fn parse(line: &str) -> Result<EpdRecord, String> {
+        let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+        if fields.len() < 4 {
+            return Err(format!("EPD needs 4 position fields: {line}"));
+        }
+        let mut position = Position::from_fen(&fields.join(" "))?;
+
+        // Skip the four position fields, then split operations on unquoted semicolons
+        let mut rest = line.trim_start();
+        for _ in 0..4 {
+            rest = rest
+                .trim_start()
+                .split_once(char::is_whitespace)
+                .map_or("", |(_, rest)| rest);
+        }
+        let operations: Vec<(String, Vec<String>)> = split_operations(rest)
+            .into_iter()
+            .filter_map(|operation| {
+                let (opcode, operands) = operation.split_first()?;
+                Some((opcode.clone(), operands.to_vec()))
+            })
+            .collect();
+
+        for (opcode, operands) in &operations {
+            let value = operands.first().and_then(|value| value.parse().ok());
+            match (opcode.as_str(), value) {
+                ("hmvc", Some(value)) => position.halfmove_clock = value,
+                ("fmvn", Some(value)) => position.fullmove_number = value,
+                _ => {}
+            }
+        }
+
+        Ok(EpdRecord {
+            position,
+            operations,
+        })
+    }
+
+    /// Operands of the first operation with this opcode.
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// Coordinate forms of the SAN operands of an operation, skipping those that are not legal.
+    fn coordinate_operands(&self, opcode: &str) -> Option<Vec<String>> {
+        let operands = self.operands(opcode)?;
+        Some(
+            operands
+                .iter()
+                .filter_map(|san| self.position.parse_san(san).ok())
+                .map(|(from, to, promotion)| coordinate_move(from, to, promotion))
+                .collect(),
+        )
+    }
+
+    /// Whether a coordinate move is one of the best moves (`bm`) and none of the avoid moves
+    /// (`am`). None when the record has neither operation.
+    pub fn solved_by(&self, mv: &str) -> Option<bool> {
+        let best = self.coordinate_operands("bm");
+        let avoid = self.coordinate_operands("am");
+        if best.is_none() && avoid.is_none() {
+            return None;
+        }
+
+        let is_best = best.is_none_or(|best| best.iter().any(|best| best == mv));
+        let is_avoided = avoid.is_some_and(|avoid| avoid.iter().any(|avoid| avoid == mv));
+        Some(is_best && !is_avoided)
+    }
+
+    /// The `id` operand, if any.
+    pub fn id(&self) -> Option<&str> {
+        self.operands("id")?.first().map(String::as_str)
+    }
+}
+
+/// Splits operations into words, keeping quoted operands whole.
+fn split_operations(text: &str) -> Vec<Vec<String>> {
+    let mut operations = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                operations.push(std::mem::take(&mut words));
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    if !words.is_empty() {
+        operations.push(words);
+    }
+
+    operations
+}
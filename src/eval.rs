@@ -0,0 +1,124 @@
+//! A static evaluation of material and piece placement, and a quiescence test telling quiet
+//! positions from tactical ones, for selecting dataset positions without an engine.
+
+use chess::legal_moves::misc::{Color, Square};
+
+use crate::position::{Piece, Position};
+
+/// Material value in centipawns.
+pub fn centipawns(piece: Piece) -> i32 {
+    match piece {
+        Piece::Pawn => 100,
+        Piece::Knight => 320,
+        Piece::Bishop => 330,
+        Piece::Rook => 500,
+        Piece::Queen => 900,
+        Piece::King => 0,
+    }
+}
+
+/// Distance of a square from the nearest edge, 0 on the rim to 3 in the centre.
+fn centrality(square: Square) -> i32 {
+    let (file, rank) = (square % 8, square / 8);
+    i32::from(file.min(7 - file).min(rank).min(7 - rank))
+}
+
+/// Piece-square bonus in centipawns, seen from the piece's own side. Kings shelter while
+/// queens are on the board and centralize once they are gone.
+fn placement(piece: Piece, color: Color, square: Square, endgame: bool) -> i32 {
+    let rank = if color == Color::White {
+        square / 8
+    } else {
+        7 - square / 8
+    };
+    match piece {
+        Piece::Pawn => {
+            let central = (3..=4).contains(&(square % 8)) && rank >= 3;
+            8 * (i32::from(rank) - 1) + if central { 10 } else { 0 }
+        }
+        Piece::Knight => 10 * centrality(square) - 15,
+        Piece::Bishop => 5 * centrality(square),
+        Piece::Rook if rank == 6 => 20,
+        Piece::Rook => 0,
+        Piece::Queen => 3 * centrality(square),
+        Piece::King if endgame => 10 * centrality(square),
+        Piece::King => -10 * centrality(square) - 5 * i32::from(rank.min(3)),
+    }
+}
+
+/// Material and placement balance in centipawns, positive when white is better.
+pub fn evaluate(position: &Position) -> i32 {
+    let endgame = position.count(Color::White, Piece::Queen) == 0
+        && position.count(Color::Black, Piece::Queen) == 0;
+    (0..64)
+        .filter_map(|square| Some((square, position.piece_at(square)?)))
+        .map(|(square, (color, piece))| {
+            let score = centipawns(piece) + placement(piece, color, square, endgame);
+            if color == Color::White {
+                score
+            } else {
+                -score
+            }
+        })
+        .sum()
+}
+
+/// Pieces of `color` the opponent can win: attacked and undefended, or attacked by a piece
+/// worth less. Kings are left out, checks being told apart on their own.
+pub fn hanging_pieces(position: &Position, color: Color) -> Vec<Square> {
+    (0..64)
+        .filter(|square| match position.piece_at(*square) {
+            Some((owner, piece)) if owner == color && piece != Piece::King => {
+                let attackers = position.attackers(*square, !color);
+                let cheapest = attackers
+                    .iter()
+                    .filter_map(|attacker| position.piece_at(*attacker))
+                    .map(|(_, attacker)| centipawns(attacker))
+                    .min();
+                cheapest.is_some_and(|cheapest| {
+                    cheapest < centipawns(piece) || !position.is_attacked(*square, color)
+                })
+            }
+            _ => false,
+        })
+        .collect()
+}
+
+/// Legal moves of the side to move that give check.
+pub fn checking_moves(position: &Position) -> usize {
+    let opponent = !position.side_to_move;
+    position
+        .legal_moves()
+        .into_iter()
+        .filter(|(from, to, promotion)| position.play(*from, *to, *promotion).in_check(opponent))
+        .count()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Quiescence {
+    /// No check, no check available and nothing hanging: the static evaluation can be trusted.
+    Quiet,
+    Tactical,
+}
+
+impl Quiescence {
+    pub fn of(position: &Position) -> Self {
+        let tactical = position.in_check(position.side_to_move)
+            || [Color::White, Color::Black]
+                .iter()
+                .any(|color| !hanging_pieces(position, *color).is_empty())
+            || checking_moves(position) > 0;
+        if tactical {
+            Quiescence::Tactical
+        } else {
+            Quiescence::Quiet
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quiescence::Quiet => "quiet",
+            Quiescence::Tactical => "tactical",
+        }
+    }
+}
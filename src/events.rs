@@ -0,0 +1,357 @@
+//! Reconstruction of tournaments from the games of a database dump: games are grouped into
+//! events by their Event, Site and date tags, ordered by round, and scored into standings.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::json;
+use crate::pgn_ast::GameResult;
+use crate::pgn_cleaner::RawGame;
+
+/// The tags identifying an event. The date is the EventDate tag when present, so that
+/// tournaments spanning several days stay whole, and the game's Date otherwise.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct EventKey {
+    pub event: String,
+    pub site: String,
+    pub date: String,
+}
+
+impl EventKey {
+    pub fn of(game: &RawGame) -> Self {
+        let tag = |name| game.tag(name).unwrap_or("?").to_string();
+        EventKey {
+            event: tag("Event"),
+            site: tag("Site"),
+            date: game
+                .tag("EventDate")
+                .or_else(|| game.tag("Date"))
+                .unwrap_or("????.??.??")
+                .to_string(),
+        }
+    }
+}
+
+/// The numbers of a Round tag such as `3` or `3.1`, for a sub-round; None when it is not
+/// numbered, as with `?` or `-`.
+pub fn parse_round(round: &str) -> Option<Vec<u32>> {
+    round.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// Orders rounds numerically, sub-rounds after their round and unnumbered rounds last.
+pub fn compare_rounds(a: &Option<Vec<u32>>, b: &Option<Vec<u32>>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct EventGame {
+    /// The Round tag as written.
+    pub round: String,
+    pub white: String,
+    pub black: String,
+    /// None for unfinished games and unknown results.
+    pub result: Option<GameResult>,
+}
+
+impl EventGame {
+    /// Half points scored by white and black, for finished games.
+    pub fn half_points(&self) -> Option<[u32; 2]> {
+        match self.result? {
+            GameResult::WhiteWins => Some([2, 0]),
+            GameResult::BlackWins => Some([0, 2]),
+            GameResult::Draw => Some([1, 1]),
+            GameResult::Unknown => None,
+        }
+    }
+
+    /// The main round number, which sub-rounds share.
+    pub fn main_round(&self) -> Option<u32> {
+        parse_round(&self.round).map(|numbers| numbers[0])
+    }
+}
+
+/// A game seen from one of its players.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PlayerGame {
+    pub round: Option<u32>,
+    pub opponent: String,
+    pub white: bool,
+    /// Half points scored, None for an unfinished game.
+    pub half_points: Option<u32>,
+}
+
+/// A player's score in an event, with their games in round order.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Standing {
+    pub player: String,
+    pub half_points: u32,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub games: Vec<PlayerGame>,
+}
+
+impl Standing {
+    /// The player's games of a round, each as result sign (`+`, `=`, `-` or `*` when
+    /// unfinished), opponent rank and color, as in `+4w`.
+    pub fn round_cell(&self, round: Option<u32>, ranks: &HashMap<&str, usize>) -> String {
+        self.games
+            .iter()
+            .filter(|game| game.round == round)
+            .map(|game| {
+                let sign = match game.half_points {
+                    Some(2) => '+',
+                    Some(1) => '=',
+                    Some(_) => '-',
+                    None => '*',
+                };
+                let color = if game.white { 'w' } else { 'b' };
+                format!("{sign}{}{color}", ranks[game.opponent.as_str()])
+            })
+            .collect()
+    }
+}
+
+/// Formats half points as points, such as `2.5`.
+pub fn points(half_points: u32) -> String {
+    match half_points % 2 {
+        0 => (half_points / 2).to_string(),
+        _ => format!("{}.5", half_points / 2),
+    }
+}
+
+pub struct Event {
+    pub key: EventKey,
+    /// Games in round order.
+    pub games: Vec<EventGame>,
+}
+
+/// Groups games into events in order of first appearance, each event's games sorted by
+/// round and otherwise kept in input order.
+pub fn group_events(games: &[RawGame]) -> Vec<Event> {
+    let mut events: Vec<Event> = Vec::new();
+    let mut indices: HashMap<EventKey, usize> = HashMap::new();
+
+    for game in games {
+        let key = EventKey::of(game);
+        let index = *indices.entry(key.clone()).or_insert_with(|| {
+            events.push(Event {
+                key,
+                games: Vec::new(),
+            });
+            events.len() - 1
+        });
+        let tag = |name| game.tag(name).unwrap_or("?").to_string();
+        events[index].games.push(EventGame {
+            round: tag("Round"),
+            white: tag("White"),
+            black: tag("Black"),
+            result: game
+                .tag("Result")
+                .and_then(GameResult::parse)
+                .filter(|result| *result != GameResult::Unknown),
+        });
+    }
+
+    for event in &mut events {
+        event
+            .games
+            .sort_by(|a, b| compare_rounds(&parse_round(&a.round), &parse_round(&b.round)));
+    }
+    events
+}
+
+impl Event {
+    /// Players by points, then by wins, then by name.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = Vec::new();
+        let mut indices: HashMap<&str, usize> = HashMap::new();
+
+        for game in &self.games {
+            let scores = game.half_points();
+            for (side, (player, opponent)) in
+                [(&game.white, &game.black), (&game.black, &game.white)]
+                    .into_iter()
+                    .enumerate()
+            {
+                let index = *indices.entry(player).or_insert_with(|| {
+                    standings.push(Standing {
+                        player: player.clone(),
+                        half_points: 0,
+                        wins: 0,
+                        draws: 0,
+                        losses: 0,
+                        games: Vec::new(),
+                    });
+                    standings.len() - 1
+                });
+                let standing = &mut standings[index];
+                let half_points = scores.map(|scores| scores[side]);
+                match half_points {
+                    Some(2) => standing.wins += 1,
+                    Some(1) => standing.draws += 1,
+                    Some(_) => standing.losses += 1,
+                    None => {}
+                }
+                standing.half_points += half_points.unwrap_or(0);
+                standing.games.push(PlayerGame {
+                    round: game.main_round(),
+                    opponent: opponent.clone(),
+                    white: side == 0,
+                    half_points,
+                });
+            }
+        }
+
+        standings.sort_by(|a, b| {
+            b.half_points
+                .cmp(&a.half_points)
+                .then(b.wins.cmp(&a.wins))
+                .then(a.player.cmp(&b.player))
+        });
+        standings
+    }
+
+    /// The main round numbers played, in order.
+    pub fn rounds(&self) -> Vec<u32> {
+        let mut rounds: Vec<u32> = self
+            .games
+            .iter()
+            .filter_map(EventGame::main_round)
+            .collect();
+        rounds.dedup();
+        rounds
+    }
+
+    /// The main rounds as crosstable columns, followed by None when some games have no round
+    /// number.
+    pub fn round_columns(&self) -> Vec<Option<u32>> {
+        let mut rounds: Vec<Option<u32>> = self.rounds().into_iter().map(Some).collect();
+        if self.games.iter().any(|game| game.main_round().is_none()) {
+            rounds.push(None);
+        }
+        rounds
+    }
+
+    pub fn heading(&self) -> String {
+        format!(
+            "{} ({}, {}): {} games",
+            self.key.event,
+            self.key.site,
+            self.key.date,
+            self.games.len()
+        )
+    }
+
+    /// The heading followed by the crosstable: each player's points and their games of every
+    /// round, as in `Standing::round_cell`. Games without a round number come in a last `R?`
+    /// column.
+    pub fn to_text(&self) -> String {
+        let standings = self.standings();
+        let ranks: HashMap<&str, usize> = standings
+            .iter()
+            .enumerate()
+            .map(|(rank, standing)| (standing.player.as_str(), rank + 1))
+            .collect();
+        let rounds = self.round_columns();
+
+        let cells: Vec<Vec<String>> = standings
+            .iter()
+            .map(|standing| {
+                rounds
+                    .iter()
+                    .map(|round| standing.round_cell(*round, &ranks))
+                    .collect()
+            })
+            .collect();
+
+        let name_width = standings
+            .iter()
+            .map(|standing| standing.player.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Player".len());
+        let round_widths: Vec<usize> = rounds
+            .iter()
+            .enumerate()
+            .map(|(column, round)| {
+                let heading = round.map_or(2, |round| round.to_string().len() + 1);
+                cells
+                    .iter()
+                    .map(|row| row[column].len())
+                    .max()
+                    .unwrap_or(0)
+                    .max(heading)
+            })
+            .collect();
+
+        let mut text = format!("{}\n", self.heading());
+        text.push_str(&format!(
+            "{:>4}  {:name_width$}  {:>5}",
+            "Rank", "Player", "Pts"
+        ));
+        for (round, width) in rounds.iter().zip(&round_widths) {
+            let heading = round.map_or("R?".to_string(), |round| format!("R{round}"));
+            text.push_str(&format!("  {heading:>width$}"));
+        }
+        text.push('\n');
+
+        for (rank, (standing, row)) in standings.iter().zip(&cells).enumerate() {
+            let mut line = format!(
+                "{:>4}  {:name_width$}  {:>5}",
+                rank + 1,
+                standing.player,
+                points(standing.half_points)
+            );
+            for (cell, width) in row.iter().zip(&round_widths) {
+                line.push_str(&format!("  {cell:>width$}"));
+            }
+            // Rounds a player sat out leave blank cells
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn to_json(&self) -> String {
+        let games = self.games.iter().map(|game| {
+            json::object([
+                ("round", json::string(&game.round)),
+                ("white", json::string(&game.white)),
+                ("black", json::string(&game.black)),
+                (
+                    "result",
+                    game.result
+                        .map_or("null".to_string(), |result| json::string(result.marker())),
+                ),
+            ])
+        });
+        let standings = self
+            .standings()
+            .into_iter()
+            .enumerate()
+            .map(|(rank, standing)| {
+                json::object([
+                    ("rank", (rank + 1).to_string()),
+                    ("player", json::string(&standing.player)),
+                    ("points", points(standing.half_points)),
+                    ("wins", standing.wins.to_string()),
+                    ("draws", standing.draws.to_string()),
+                    ("losses", standing.losses.to_string()),
+                ])
+            });
+
+        json::object([
+            ("event", json::string(&self.key.event)),
+            ("site", json::string(&self.key.site)),
+            ("date", json::string(&self.key.date)),
+            ("games", json::array(games)),
+            ("standings", json::array(standings)),
+        ])
+    }
+}
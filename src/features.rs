@@ -0,0 +1,163 @@
+use std::io::{self, Write};
+
+use chess::legal_moves::misc::{Color, Square};
+
+use crate::eval::{evaluate, Quiescence};
+use crate::pgn_ast::GameResult;
+use crate::position::{offset_square, Piece, Position, KING_OFFSETS};
+
+/// Columns of the feature file. Per-color features come white first.
+pub const COLUMNS: [&str; 17] = [
+    "game",
+    "ply",
+    "white_to_move",
+    "white_material",
+    "black_material",
+    "material_balance",
+    "white_bishop_pair",
+    "black_bishop_pair",
+    "white_mobility",
+    "black_mobility",
+    "white_king_zone_attacks",
+    "black_king_zone_attacks",
+    "white_king_shelter",
+    "black_king_shelter",
+    "static_eval",
+    "quiet",
+    "result",
+];
+
+/// Handcrafted features of a position for training simple evaluators.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PositionFeatures {
+    pub white_to_move: bool,
+    /// Material of each side in pawns, white first.
+    pub material: [u32; 2],
+    pub bishop_pair: [bool; 2],
+    /// Pseudo-legal moves of each side, as if it were to move.
+    pub mobility: [usize; 2],
+    /// Squares next to each side's king (or under it) attacked by the opponent.
+    pub king_zone_attacks: [usize; 2],
+    /// Own pawns on the three files around each side's king, one or two ranks ahead of it.
+    pub king_shelter: [usize; 2],
+    /// Material and placement balance in centipawns, from white's point of view.
+    pub static_eval: i32,
+    pub quiescence: Quiescence,
+}
+
+fn attack_map(position: &Position, color: Color) -> u64 {
+    (0..64)
+        .filter(|square| matches!(position.piece_at(*square), Some((owner, _)) if owner == color))
+        .flat_map(|square| position.attacked_squares(square))
+        .fold(0, |map, square| map | 1 << square)
+}
+
+fn king_zone(king: Square) -> impl Iterator<Item = Square> {
+    KING_OFFSETS
+        .iter()
+        .filter_map(move |offset| offset_square(king, *offset))
+        .chain([king])
+}
+
+fn king_shelter(position: &Position, color: Color, king: Square) -> usize {
+    let forward = if color == Color::White { 1 } else { -1 };
+    (-1..=1)
+        .flat_map(|file| [(file, forward), (file, 2 * forward)])
+        .filter_map(|offset| offset_square(king, offset))
+        .filter(|square| position.piece_at(*square) == Some((color, Piece::Pawn)))
+        .count()
+}
+
+impl PositionFeatures {
+    pub fn of(position: &Position) -> Self {
+        let colors = [Color::White, Color::Black];
+        let attacks = colors.map(|color| attack_map(position, color));
+
+        let mobility = colors.map(|color| {
+            (0..64)
+                .filter(|square| matches!(position.piece_at(*square), Some((owner, _)) if owner == color))
+                .map(|square| position.pseudo_legal_targets(square).len())
+                .sum()
+        });
+        let king_zone_attacks = [(Color::White, attacks[1]), (Color::Black, attacks[0])].map(
+            |(color, enemy_attacks)| {
+                position.king_square(color).map_or(0, |king| {
+                    king_zone(king)
+                        .filter(|square| enemy_attacks & 1 << square != 0)
+                        .count()
+                })
+            },
+        );
+        let king_shelter = colors.map(|color| {
+            position
+                .king_square(color)
+                .map_or(0, |king| king_shelter(position, color, king))
+        });
+
+        PositionFeatures {
+            white_to_move: position.side_to_move == Color::White,
+            material: colors.map(|color| position.material(color)),
+            bishop_pair: colors.map(|color| position.count(color, Piece::Bishop) >= 2),
+            mobility,
+            king_zone_attacks,
+            king_shelter,
+            static_eval: evaluate(position),
+            quiescence: Quiescence::of(position),
+        }
+    }
+
+    /// Material of white minus material of black, in pawns.
+    pub fn material_balance(&self) -> i64 {
+        i64::from(self.material[0]) - i64::from(self.material[1])
+    }
+}
+
+pub fn write_header<W: Write>(writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "{}", COLUMNS.join(","))
+}
+
+/// Writes a CSV row for every position of a game, the final one included, labelled with the
+/// result from white's point of view (1, 0 or -1). Unfinished games are skipped, and so are
+/// tactical positions when `quiet_only` is set.
+pub fn write_game<W: Write>(
+    writer: &mut W,
+    game_number: usize,
+    positions: &[Position],
+    result: &str,
+    quiet_only: bool,
+) -> io::Result<()> {
+    let label = match GameResult::parse(result) {
+        Some(GameResult::WhiteWins) => 1,
+        Some(GameResult::BlackWins) => -1,
+        Some(GameResult::Draw) => 0,
+        Some(GameResult::Unknown) | None => return Ok(()),
+    };
+
+    for (ply, position) in positions.iter().enumerate() {
+        let features = PositionFeatures::of(position);
+        if quiet_only && features.quiescence != Quiescence::Quiet {
+            continue;
+        }
+        let flag = |value: bool| u8::from(value);
+        writeln!(
+            writer,
+            "{game_number},{ply},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{label}",
+            flag(features.white_to_move),
+            features.material[0],
+            features.material[1],
+            features.material_balance(),
+            flag(features.bishop_pair[0]),
+            flag(features.bishop_pair[1]),
+            features.mobility[0],
+            features.mobility[1],
+            features.king_zone_attacks[0],
+            features.king_zone_attacks[1],
+            features.king_shelter[0],
+            features.king_shelter[1],
+            features.static_eval,
+            flag(features.quiescence == Quiescence::Quiet),
+        )?;
+    }
+
+    Ok(())
+}
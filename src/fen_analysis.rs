@@ -0,0 +1,88 @@
+//! Analysis of bare positions, one FEN per line, for datasets that have no games: an engine
+//! evaluation, a tablebase verdict and the classifications computed on game positions.
+
+use chess::legal_moves::misc::Color;
+
+use crate::endgames::{classify, MAX_MEN};
+use crate::eval::{evaluate, Quiescence};
+use crate::phases::Phase;
+use crate::position::Position;
+use crate::uci::{Score, Search};
+
+/// Engine scores at least this large are tablebase wins rather than evaluations; engines
+/// report them below mate scores but well above any material balance.
+pub const TABLEBASE_WIN: i32 = 10_000;
+
+/// The positions of the input with their line numbers, blank lines and `#` comments left
+/// out. The counters of a FEN are optional.
+pub fn fen_lines(input: &str) -> impl Iterator<Item = (usize, Result<Position, String>)> + '_ {
+    input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| (number, Position::from_fen(line)))
+}
+
+/// Classifications of a position that need no engine.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PositionClass {
+    pub phase: Phase,
+    /// Endgame type, for positions with at most [`MAX_MEN`] men.
+    pub endgame: Option<String>,
+    /// Static evaluation in centipawns, positive when white is better.
+    pub static_eval: i32,
+    pub quiescence: Quiescence,
+}
+
+impl PositionClass {
+    pub fn of(position: &Position) -> Self {
+        PositionClass {
+            phase: Phase::of(position),
+            endgame: classify(position),
+            static_eval: evaluate(position),
+            quiescence: Quiescence::of(position),
+        }
+    }
+}
+
+/// An engine score in centipawns from white's point of view.
+pub fn white_centipawns(position: &Position, score: Score) -> i32 {
+    match position.side_to_move {
+        Color::White => score.centipawns(),
+        Color::Black => -score.centipawns(),
+    }
+}
+
+/// Tablebase outcome for the side to move.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Wdl {
+    /// The outcome a search backed by tablebases found, or None when the position has too
+    /// many men or the engine did not hit its tablebases. Wins spoiled by the fifty-move rule
+    /// score near zero and count as draws.
+    pub fn of(position: &Position, search: &Search) -> Option<Self> {
+        let men = position.squares.iter().flatten().count();
+        if men > MAX_MEN || search.tbhits == 0 {
+            return None;
+        }
+        match search.score?.centipawns() {
+            score if score >= TABLEBASE_WIN => Some(Wdl::Win),
+            score if score <= -TABLEBASE_WIN => Some(Wdl::Loss),
+            _ => Some(Wdl::Draw),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Wdl::Win => "win",
+            Wdl::Draw => "draw",
+            Wdl::Loss => "loss",
+        }
+    }
+}
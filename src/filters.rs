@@ -0,0 +1,182 @@
+use crate::pgn_cleaner::RawGame;
+
+/// A possibly partial PGN date such as `2020.??.??`. Unknown parts are `None`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PgnDate {
+    pub year: u32,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+}
+
+impl PgnDate {
+    /// Parses `YYYY.MM.DD` (PGN) or `YYYY-MM-DD` (command line). Returns None when the year is unknown.
+    pub fn parse(date: &str) -> Option<Self> {
+        let mut parts = date.trim().split(['.', '-']);
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next().and_then(|part| part.parse().ok());
+        let day = parts.next().and_then(|part| part.parse().ok());
+
+        Some(PgnDate { year, month, day })
+    }
+
+    /// The earliest day the date may denote, as a sortable `YYYYMMDD` number.
+    pub fn earliest(&self) -> u32 {
+        self.year * 10000 + self.month.unwrap_or(1) * 100 + self.day.unwrap_or(1)
+    }
+
+    fn latest(&self) -> u32 {
+        self.year * 10000 + self.month.unwrap_or(12) * 100 + self.day.unwrap_or(31)
+    }
+}
+
+/// Speed category derived from the TimeControl tag, using Lichess' estimate of base + 40 × increment.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TimeClass {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+}
+
+impl TimeClass {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "bullet" => Some(TimeClass::Bullet),
+            "blitz" => Some(TimeClass::Blitz),
+            "rapid" => Some(TimeClass::Rapid),
+            "classical" => Some(TimeClass::Classical),
+            _ => None,
+        }
+    }
+
+    /// Classifies a TimeControl tag value such as `300+3`, `40/7200:3600` or `*60`.
+    pub fn from_time_control(time_control: &str) -> Option<Self> {
+        let first_period = time_control.split(':').next()?;
+        let period = first_period
+            .split_once('/')
+            .map_or(first_period, |(_, seconds)| seconds);
+        let period = period.trim_start_matches('*');
+
+        let (base, increment) = period.split_once('+').unwrap_or((period, "0"));
+        let base: u32 = base.parse().ok()?;
+        let increment: u32 = increment.parse().ok()?;
+
+        Some(match base + 40 * increment {
+            0..180 => TimeClass::Bullet,
+            180..480 => TimeClass::Blitz,
+            480..1500 => TimeClass::Rapid,
+            _ => TimeClass::Classical,
+        })
+    }
+}
+
+/// Whether rating bounds must hold for both players or for at least one of them.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum EloMode {
+    #[default]
+    Both,
+    Either,
+}
+
+/// Convenience filters on game headers.
+#[derive(Default)]
+pub struct GameFilter {
+    /// Keep games played on or after this date.
+    pub after: Option<PgnDate>,
+    /// Keep games played strictly before this date.
+    pub before: Option<PgnDate>,
+    /// Keep games whose Event tag contains this text (case-insensitive).
+    pub event: Option<String>,
+    pub min_elo: Option<u32>,
+    pub max_elo: Option<u32>,
+    pub elo_mode: EloMode,
+    pub time_class: Option<TimeClass>,
+    pub min_plies: Option<usize>,
+    pub max_plies: Option<usize>,
+    /// Termination tag values (case-insensitive) whose games are dropped, e.g. "Abandoned".
+    pub excluded_terminations: Vec<String>,
+}
+
+impl GameFilter {
+    pub fn matches(&self, game: &RawGame) -> bool {
+        if self.after.is_some() || self.before.is_some() {
+            let Some(date) = game_date(game) else {
+                return false;
+            };
+            // Partial dates match if any day they may denote lies within the range
+            if self
+                .after
+                .is_some_and(|after| date.latest() < after.earliest())
+            {
+                return false;
+            }
+            if self
+                .before
+                .is_some_and(|before| date.earliest() >= before.earliest())
+            {
+                return false;
+            }
+        }
+
+        if let Some(event) = &self.event {
+            let matches_event = game
+                .tag("Event")
+                .is_some_and(|tag| tag.to_lowercase().contains(&event.to_lowercase()));
+            if !matches_event {
+                return false;
+            }
+        }
+
+        if self.min_elo.is_some() || self.max_elo.is_some() {
+            let in_band = |tag: &str| {
+                game.tag(tag)
+                    .and_then(|elo| elo.parse::<u32>().ok())
+                    .is_some_and(|elo| {
+                        self.min_elo.is_none_or(|min| elo >= min)
+                            && self.max_elo.is_none_or(|max| elo <= max)
+                    })
+            };
+            let matches_elo = match self.elo_mode {
+                EloMode::Both => in_band("WhiteElo") && in_band("BlackElo"),
+                EloMode::Either => in_band("WhiteElo") || in_band("BlackElo"),
+            };
+            if !matches_elo {
+                return false;
+            }
+        }
+
+        if let Some(time_class) = self.time_class {
+            let game_class = game
+                .tag("TimeControl")
+                .and_then(TimeClass::from_time_control);
+            if game_class != Some(time_class) {
+                return false;
+            }
+        }
+
+        if let Some(termination) = game.tag("Termination") {
+            let excluded = self
+                .excluded_terminations
+                .iter()
+                .any(|excluded| excluded.eq_ignore_ascii_case(termination));
+            if excluded {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Game length bounds, checked once the game has been processed.
+    pub fn matches_plies(&self, plies: usize) -> bool {
+        self.min_plies.is_none_or(|min| plies >= min)
+            && self.max_plies.is_none_or(|max| plies <= max)
+    }
+}
+
+/// The game date, taken from Date and falling back to UTCDate.
+pub fn game_date(game: &RawGame) -> Option<PgnDate> {
+    game.tag("Date")
+        .and_then(PgnDate::parse)
+        .or_else(|| game.tag("UTCDate").and_then(PgnDate::parse))
+}
@@ -0,0 +1,164 @@
+//! Sidecar index files (`.pgi`) giving direct access to any game of a large PGN file. An index
+//! starts with [`MAGIC`], the length of the indexed input and the names of the tags it keeps,
+//! followed by a fixed-size record per game, so that the record of game N is found by seeking
+//! rather than scanning, and last the tag values the records point to.
+//!
+//! Numbers are little-endian, 8 bytes long but for the 4-byte lengths of strings and the tag
+//! count.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::source::Source;
+
+/// The start of an index file, with the format version last.
+pub const MAGIC: &[u8; 5] = b"PGNI\x01";
+
+/// Bytes of the record of each game: its offset, end, hash and where its tag values are.
+const RECORD_SIZE: u64 = 32;
+
+/// The index of a PGN file, next to it.
+pub fn index_path(input: &str) -> String {
+    format!("{input}.pgi")
+}
+
+/// What an index holds about a game.
+#[derive(Clone, PartialEq, Debug)]
+pub struct IndexEntry {
+    /// Byte range of the game in the input.
+    pub offset: u64,
+    pub end: u64,
+    /// The [`game_hash`](crate::incremental::game_hash) of the game.
+    pub hash: u64,
+    /// Values of the indexed tags, empty when missing.
+    pub values: Vec<String>,
+}
+
+fn push_str(bytes: &mut Vec<u8>, value: &str) {
+    bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+/// An index of the games of an input `input_len` bytes long.
+pub fn write_index(input_len: u64, tags: &[String], entries: &[IndexEntry]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&input_len.to_le_bytes());
+    bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(tags.len() as u32).to_le_bytes());
+    for tag in tags {
+        push_str(&mut bytes, tag);
+    }
+
+    let mut values = Vec::new();
+    let values_start = bytes.len() as u64 + RECORD_SIZE * entries.len() as u64;
+    for entry in entries {
+        let pointer = values_start + values.len() as u64;
+        for field in [entry.offset, entry.end, entry.hash, pointer] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        for value in &entry.values {
+            push_str(&mut values, value);
+        }
+    }
+    bytes.extend_from_slice(&values);
+    bytes
+}
+
+/// An index file read on demand: opening it reads the header alone, and each lookup reads a
+/// single record.
+pub struct GameIndex<R> {
+    reader: R,
+    /// Length of the input when it was indexed.
+    pub input_len: u64,
+    /// The tags whose values the index keeps.
+    pub tags: Vec<String>,
+    games: u64,
+    records_start: u64,
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_str<R: Read>(reader: &mut R) -> io::Result<String> {
+    let mut bytes = vec![0; read_u32(reader)? as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Index tag is not UTF-8"))
+}
+
+impl<R: Read + Seek> GameIndex<R> {
+    pub fn open(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a game index, or of another version",
+            ));
+        }
+        let input_len = read_u64(&mut reader)?;
+        let games = read_u64(&mut reader)?;
+        let tags = (0..read_u32(&mut reader)?)
+            .map(|_| read_str(&mut reader))
+            .collect::<io::Result<Vec<_>>>()?;
+        let records_start = reader.stream_position()?;
+        Ok(GameIndex {
+            reader,
+            input_len,
+            tags,
+            games,
+            records_start,
+        })
+    }
+
+    /// Number of games indexed.
+    pub fn len(&self) -> u64 {
+        self.games
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games == 0
+    }
+
+    /// The entry of a game, by its 1-based number; None past the last game.
+    pub fn entry(&mut self, number: u64) -> io::Result<Option<IndexEntry>> {
+        if number == 0 || number > self.games {
+            return Ok(None);
+        }
+        let record = self.records_start + (number - 1) * RECORD_SIZE;
+        self.reader.seek(SeekFrom::Start(record))?;
+        let offset = read_u64(&mut self.reader)?;
+        let end = read_u64(&mut self.reader)?;
+        let hash = read_u64(&mut self.reader)?;
+        let pointer = read_u64(&mut self.reader)?;
+        self.reader.seek(SeekFrom::Start(pointer))?;
+        let values = (0..self.tags.len())
+            .map(|_| read_str(&mut self.reader))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Some(IndexEntry {
+            offset,
+            end,
+            hash,
+            values,
+        }))
+    }
+}
+
+/// The text of an indexed game, read from its byte range alone.
+pub fn read_game(source: &dyn Source, entry: &IndexEntry) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    source
+        .open(entry.offset)?
+        .take(entry.end - entry.offset)
+        .read_to_end(&mut bytes)?;
+    String::from_utf8(bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Input is not UTF-8"))
+}
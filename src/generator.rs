@@ -0,0 +1,128 @@
+//! Random legal games for stress-testing the parser and downstream tools: moves are drawn
+//! uniformly from the legal moves, or by frequency from an opening book while in it.
+
+use std::collections::HashMap;
+
+use chess::legal_moves::misc::Color;
+
+use crate::openings::OpeningTree;
+use crate::position::{coordinate_move, parse_coordinate_move, Piece, Position};
+use crate::prep::children;
+use crate::sampling::Rng;
+use crate::san::to_san;
+
+/// A generated game, with moves in coordinate notation.
+pub struct GeneratedGame {
+    pub moves: Vec<String>,
+    pub result: &'static str,
+    /// Why the game ended, for the Termination tag.
+    pub termination: &'static str,
+}
+
+/// Whether neither side has mating material left: bare kings, or a single minor piece.
+pub fn insufficient_material(position: &Position) -> bool {
+    let mut minors = 0;
+    for (_, piece) in position.squares.iter().flatten() {
+        match piece {
+            Piece::King => {}
+            Piece::Knight | Piece::Bishop => minors += 1,
+            _ => return false,
+        }
+    }
+    minors <= 1
+}
+
+/// A continuation of `line` in the book, drawn in proportion to the games that played it.
+pub fn book_move(book: &OpeningTree, line: &str, rng: &mut Rng) -> Option<String> {
+    let continuations = children(book, line);
+    let total: usize = continuations.iter().map(|(_, stats)| stats.games).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut pick = rng.below(total);
+    for (continuation, stats) in continuations {
+        if pick < stats.games {
+            return continuation.rsplit(' ').next().map(str::to_string);
+        }
+        pick -= stats.games;
+    }
+    None
+}
+
+/// Plays random legal moves from the initial position until the game ends or `max_plies`
+/// are played, the latter being recorded as unfinished.
+pub fn random_game(rng: &mut Rng, book: Option<&OpeningTree>, max_plies: usize) -> GeneratedGame {
+    let mut position = Position::initial();
+    let mut moves: Vec<String> = Vec::new();
+    let mut in_book = book.is_some();
+    let mut repetitions: HashMap<u64, usize> = HashMap::new();
+    repetitions.insert(position.zobrist(), 1);
+
+    let (result, termination) = loop {
+        let color = position.side_to_move;
+        let legal = position.legal_moves();
+        if legal.is_empty() {
+            break if position.in_check(color) {
+                (
+                    if color == Color::White { "0-1" } else { "1-0" },
+                    "checkmate",
+                )
+            } else {
+                ("1/2-1/2", "stalemate")
+            };
+        }
+        if position.fifty_move_rule() {
+            break ("1/2-1/2", "fifty-move rule");
+        }
+        if insufficient_material(&position) {
+            break ("1/2-1/2", "insufficient material");
+        }
+        if moves.len() >= max_plies {
+            break ("*", "unterminated");
+        }
+
+        // A book move is only played when legal, so that a corrupt book cannot derail the game
+        let from_book = book
+            .filter(|_| in_book)
+            .and_then(|book| book_move(book, &moves.join(" "), rng))
+            .and_then(|mv| {
+                legal
+                    .iter()
+                    .copied()
+                    .find(|(from, to, promotion)| coordinate_move(*from, *to, *promotion) == mv)
+            });
+        in_book = from_book.is_some();
+        let (from, to, promotion) = from_book.unwrap_or_else(|| legal[rng.below(legal.len())]);
+        moves.push(coordinate_move(from, to, promotion));
+        position = position.play(from, to, promotion);
+
+        let seen = repetitions.entry(position.zobrist()).or_insert(0);
+        *seen += 1;
+        if *seen >= 3 {
+            break ("1/2-1/2", "threefold repetition");
+        }
+    };
+
+    GeneratedGame {
+        moves,
+        result,
+        termination,
+    }
+}
+
+/// Movetext of coordinate moves from the initial position, in numbered SAN.
+pub fn san_movetext(moves: &[String]) -> String {
+    let mut position = Position::initial();
+    let mut words = Vec::new();
+    for (ply, mv) in moves.iter().enumerate() {
+        let Some((from, to, promotion)) = parse_coordinate_move(mv) else {
+            break;
+        };
+        if ply.is_multiple_of(2) {
+            words.push(format!("{}.", ply / 2 + 1));
+        }
+        words.push(to_san(&position, from, to, promotion));
+        position = position.play(from, to, promotion);
+    }
+    words.join(" ")
+}
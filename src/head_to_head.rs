@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use crate::eco::EcoEntry;
+use crate::events::points;
+use crate::pgn_ast::GameResult;
+use crate::pgn_cleaner::RawGame;
+
+/// Whether a player name contains the searched text, ignoring case, so that `carlsen` finds
+/// `Carlsen, Magnus`.
+pub fn name_matches(query: &str, name: &str) -> bool {
+    name.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// Whether a game is between the two players, and then whether the first one had white.
+pub fn first_player_white(game: &RawGame, players: &[String; 2]) -> Option<bool> {
+    let white = game.tag("White").unwrap_or_default();
+    let black = game.tag("Black").unwrap_or_default();
+    if name_matches(&players[0], white) && name_matches(&players[1], black) {
+        Some(true)
+    } else if name_matches(&players[1], white) && name_matches(&players[0], black) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// The opening a game is summarized under: its ECO and Opening tags, or else its
+/// classification by the embedded ECO table, or `?`.
+pub fn opening_label(game: &RawGame, classification: Option<&EcoEntry>) -> String {
+    let tags: Vec<&str> = ["ECO", "Opening"]
+        .into_iter()
+        .filter_map(|tag| game.tag(tag))
+        .filter(|value| *value != "?")
+        .collect();
+    match (tags.is_empty(), classification) {
+        (false, _) => tags.join(" "),
+        (true, Some(entry)) => format!("{} {}", entry.code, entry.name),
+        (true, None) => "?".to_string(),
+    }
+}
+
+/// Wins, draws and losses of the first player over some games.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Record {
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Record {
+    pub fn half_points(&self) -> u32 {
+        (2 * self.wins + self.draws) as u32
+    }
+
+    /// The second player's half points, from the finished games.
+    pub fn opponent_half_points(&self) -> u32 {
+        (2 * self.losses + self.draws) as u32
+    }
+
+    fn add(&mut self, half_points: Option<u32>) {
+        self.games += 1;
+        match half_points {
+            Some(2) => self.wins += 1,
+            Some(1) => self.draws += 1,
+            Some(_) => self.losses += 1,
+            None => {}
+        }
+    }
+
+    /// The score as `4.5-2.5`, with the game count and `+3 =3 -1` record.
+    fn describe(&self) -> String {
+        format!(
+            "{} games, {}-{} (+{} ={} -{})",
+            self.games,
+            points(self.half_points()),
+            points(self.opponent_half_points()),
+            self.wins,
+            self.draws,
+            self.losses
+        )
+    }
+}
+
+/// The mutual games of two players, scored for the first one.
+pub struct HeadToHead {
+    pub players: [String; 2],
+    /// The first player's record with white and with black.
+    pub by_color: [Record; 2],
+    pub by_opening: BTreeMap<String, Record>,
+}
+
+impl HeadToHead {
+    pub fn new(players: [String; 2]) -> Self {
+        HeadToHead {
+            players,
+            by_color: [Record::default(); 2],
+            by_opening: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_game(&mut self, first_white: bool, result: Option<GameResult>, opening: &str) {
+        let white_half_points = match result {
+            Some(GameResult::WhiteWins) => Some(2),
+            Some(GameResult::Draw) => Some(1),
+            Some(GameResult::BlackWins) => Some(0),
+            Some(GameResult::Unknown) | None => None,
+        };
+        let half_points = white_half_points.map(|half_points| {
+            if first_white {
+                half_points
+            } else {
+                2 - half_points
+            }
+        });
+
+        self.by_color[usize::from(!first_white)].add(half_points);
+        self.by_opening
+            .entry(opening.to_string())
+            .or_default()
+            .add(half_points);
+    }
+
+    pub fn total(&self) -> Record {
+        let [white, black] = self.by_color;
+        Record {
+            games: white.games + black.games,
+            wins: white.wins + black.wins,
+            draws: white.draws + black.draws,
+            losses: white.losses + black.losses,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let [first, second] = &self.players;
+        let mut summary = format!("{first} vs {second}: {}\n", self.total().describe());
+        for (color, record) in ["white", "black"].iter().zip(&self.by_color) {
+            if record.games > 0 {
+                summary.push_str(&format!("{first} with {color}: {}\n", record.describe()));
+            }
+        }
+        if !self.by_opening.is_empty() {
+            summary.push_str("By opening:\n");
+        }
+        for (opening, record) in &self.by_opening {
+            summary.push_str(&format!("  {opening}: {}\n", record.describe()));
+        }
+        summary
+    }
+}
@@ -0,0 +1,138 @@
+use chess::legal_moves::misc::Color;
+use chess::utils::string_to_square;
+
+use crate::json;
+use crate::position::{color_index, color_name, Piece, Position};
+
+type SquareCounts = [u64; 64];
+
+/// Square statistics per player and piece type, accumulated over games.
+pub struct Heatmaps {
+    /// Positions in which a piece stood on each square, by color then piece index.
+    occupation: [[SquareCounts; 6]; 2],
+    /// Moves ending on each square, by color then piece index of the moving piece.
+    destinations: [[SquareCounts; 6]; 2],
+}
+
+impl Default for Heatmaps {
+    fn default() -> Self {
+        Heatmaps {
+            occupation: [[[0; 64]; 6]; 2],
+            destinations: [[[0; 64]; 6]; 2],
+        }
+    }
+}
+
+/// Counts as 8 rows of 8, from rank 8 down to rank 1 as on a diagram.
+fn matrix_json(counts: &SquareCounts) -> String {
+    json::array(
+        (0..8)
+            .rev()
+            .map(|rank| json::array((0..8).map(|file| counts[rank * 8 + file].to_string()))),
+    )
+}
+
+impl Heatmaps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a processed game. `positions` holds the position before each move followed by the final one.
+    pub fn add_game(&mut self, positions: &[Position], moves: &[String]) {
+        for position in positions {
+            for (square, occupant) in position.squares.iter().enumerate() {
+                if let Some((color, piece)) = occupant {
+                    self.occupation[color_index(*color)][piece.index()][square] += 1;
+                }
+            }
+        }
+
+        for (position, mv) in positions.iter().zip(moves) {
+            let (from, to) = (string_to_square(&mv[0..2]), string_to_square(&mv[2..4]));
+            if let Some((color, piece)) = position.piece_at(from) {
+                self.destinations[color_index(color)][piece.index()][to as usize] += 1;
+            }
+        }
+    }
+
+    fn maps(&self) -> Vec<(Color, &'static str, Piece, &SquareCounts)> {
+        let mut maps = Vec::new();
+        for color in [Color::White, Color::Black] {
+            for (kind, counts) in [
+                ("occupation", &self.occupation),
+                ("destinations", &self.destinations),
+            ] {
+                for piece in Piece::ALL {
+                    maps.push((
+                        color,
+                        kind,
+                        piece,
+                        &counts[color_index(color)][piece.index()],
+                    ));
+                }
+            }
+        }
+        maps
+    }
+
+    /// `{"white": {"pawn": {"occupation": [[...]], "destinations": [[...]]}, ...}, "black": ...}`
+    pub fn to_json(&self) -> String {
+        json::object([Color::White, Color::Black].map(|color| {
+            let pieces = json::object(Piece::ALL.map(|piece| {
+                let index = piece.index();
+                let color = color_index(color);
+                let kinds = json::object([
+                    ("occupation", matrix_json(&self.occupation[color][index])),
+                    (
+                        "destinations",
+                        matrix_json(&self.destinations[color][index]),
+                    ),
+                ]);
+                (piece.name(), kinds)
+            }));
+            (color_name(color), pieces)
+        }))
+    }
+
+    /// One board per player, statistic and piece type; square shading is relative to the
+    /// busiest square of its board.
+    pub fn to_svg(&self) -> String {
+        const CELL: usize = 16;
+        const BOARD: usize = 8 * CELL;
+        const GAP: usize = 24;
+
+        let maps = self.maps();
+        let width = 6 * (BOARD + GAP);
+        let height = 4 * (BOARD + GAP);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" font-family=\"sans-serif\" font-size=\"10\">\n"
+        );
+
+        for (index, (color, kind, piece, counts)) in maps.iter().enumerate() {
+            let x = (index % 6) * (BOARD + GAP);
+            let y = (index / 6) * (BOARD + GAP);
+            let busiest = counts.iter().copied().max().unwrap_or(0).max(1);
+
+            svg.push_str(&format!(
+                "<text x=\"{x}\" y=\"{}\">{} {} {}</text>\n",
+                y + 10,
+                color_name(*color),
+                piece.name(),
+                kind
+            ));
+            for rank in 0..8 {
+                for file in 0..8 {
+                    let intensity = counts[rank * 8 + file] as f64 / busiest as f64;
+                    svg.push_str(&format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{CELL}\" height=\"{CELL}\" fill=\"#c0392b\" fill-opacity=\"{intensity:.3}\" stroke=\"#ccc\" stroke-width=\"0.5\"/>\n",
+                        x + file * CELL,
+                        y + 14 + (7 - rank) * CELL,
+                    ));
+                }
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
@@ -0,0 +1,87 @@
+//! Incremental updates of a crunched database: games already in it, recognized by a hash of
+//! their roster tags and moves, are skipped, and the new ones are appended.
+
+use std::collections::HashSet;
+
+use crate::backend::BackendKind;
+use crate::binary::{self, MAGIC};
+use crate::pgn_cleaner::{mainline_moves, split_games};
+use crate::position::parse_coordinate_move;
+use crate::redact::fnv1a;
+use crate::PgnProcessor;
+
+/// Tags identifying a game besides its moves. The result is left out, as it may be inferred
+/// or corrected when crunching.
+pub const HASHED_TAGS: [&str; 6] = ["Event", "Site", "Date", "Round", "White", "Black"];
+
+/// Hash of a game's roster tags, missing ones counting as `?`, and coordinate moves.
+pub fn game_hash(tags: &[(String, String)], moves: &[String]) -> u64 {
+    let mut key = String::new();
+    for name in HASHED_TAGS {
+        let value = tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map_or("?", |(_, value)| value.trim());
+        key.push_str(value);
+        key.push('\0');
+    }
+    key.push_str(&moves.join(" "));
+    fnv1a(key.as_bytes())
+}
+
+/// Hashes of the games of a database.
+#[derive(Default)]
+pub struct KnownGames {
+    hashes: HashSet<u64>,
+}
+
+impl KnownGames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The games of a crunched output, in the binary encoding or PGN with coordinate or SAN
+    /// moves. SAN games are replayed on `backend`.
+    pub fn load(bytes: &[u8], backend: BackendKind) -> Result<Self, String> {
+        let mut known = KnownGames::new();
+        if bytes.starts_with(MAGIC) {
+            for game in binary::decode(bytes)? {
+                known.insert(game_hash(&game.tags, &game.moves));
+            }
+            return Ok(known);
+        }
+
+        let text = std::str::from_utf8(bytes).map_err(|_| "The database is not PGN")?;
+        let mut processor = PgnProcessor::new();
+        processor.set_backend(backend);
+        for game in split_games(text) {
+            let tokens = mainline_moves(&game.movetext);
+            let moves = if tokens.iter().all(|mv| parse_coordinate_move(mv).is_some()) {
+                tokens.into_iter().map(str::to_string).collect()
+            } else {
+                let moves = processor.process_game(&game);
+                processor.take_warnings();
+                moves
+            };
+            known.insert(game_hash(&game.tags, &moves));
+        }
+        Ok(known)
+    }
+
+    /// Records a game, returning whether it was new.
+    pub fn insert(&mut self, hash: u64) -> bool {
+        self.hashes.insert(hash)
+    }
+
+    pub fn contains(&self, hash: u64) -> bool {
+        self.hashes.contains(&hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+}
@@ -0,0 +1,158 @@
+//! Inputs other than PGN, turned into PGN before processing: bare SAN or UCI move lists and
+//! FEN positions followed by moves, as pasted from analysis boards and engine logs.
+
+use chess::legal_moves::misc::Color;
+
+use crate::pgn_cleaner::normalize_result;
+use crate::position::{parse_coordinate_move, Position};
+use crate::san::to_san;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InputFormat {
+    Pgn,
+    /// SAN moves without tags, which is PGN movetext.
+    San,
+    /// Coordinate moves from the initial position, one game per line, such as `e2e4 e7e5`.
+    Uci,
+    /// A FEN then moves in SAN or UCI, one game per line. A `moves` keyword as in UCI
+    /// `position` commands may separate them.
+    FenMoves,
+}
+
+impl InputFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "pgn" => Some(InputFormat::Pgn),
+            "san" => Some(InputFormat::San),
+            "uci" => Some(InputFormat::Uci),
+            "fen" => Some(InputFormat::FenMoves),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the first non-empty line: a tag, a FEN placement, coordinate
+    /// moves, and SAN otherwise.
+    pub fn detect(input: &str) -> Self {
+        let Some(first) = input.lines().map(str::trim).find(|line| !line.is_empty()) else {
+            return InputFormat::Pgn;
+        };
+        if first.starts_with('[') {
+            return InputFormat::Pgn;
+        }
+        let placement = first.split_whitespace().next().unwrap_or_default();
+        if placement.matches('/').count() == 7 {
+            return InputFormat::FenMoves;
+        }
+        let mut words = move_words(first).peekable();
+        if words.peek().is_some() && words.all(is_coordinate_move) {
+            InputFormat::Uci
+        } else {
+            InputFormat::San
+        }
+    }
+
+    /// The format of an input from its first bytes, as [`InputFormat::detect`] finds it, if they
+    /// hold its first non-empty line whole.
+    pub fn detect_prefix(prefix: &[u8]) -> Option<Self> {
+        let complete = &prefix[..prefix.iter().rposition(|byte| *byte == b'\n')? + 1];
+        let text = String::from_utf8_lossy(complete);
+        text.lines()
+            .any(|line| !line.trim().is_empty())
+            .then(|| InputFormat::detect(&text))
+    }
+
+    /// The input as PGN, each line of a UCI or FEN input becoming a game.
+    pub fn to_pgn(self, input: String) -> Result<String, String> {
+        self.to_pgn_from(input, &Position::initial())
+    }
+
+    /// The input as PGN like [`InputFormat::to_pgn`], the lines of a UCI input starting from
+    /// `start`, which SetUp and FEN tags then record.
+    pub fn to_pgn_from(self, input: String, start: &Position) -> Result<String, String> {
+        if matches!(self, InputFormat::Pgn | InputFormat::San) {
+            return Ok(input);
+        }
+
+        let mut pgn = String::new();
+        for (index, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let game = match self {
+                InputFormat::FenMoves => fen_game(line),
+                _ => moves_game(*start, move_words(line)).map(|movetext| {
+                    let fen = (*start != Position::initial()).then(|| start.to_fen());
+                    (fen, movetext)
+                }),
+            };
+            let (fen, movetext) =
+                game.map_err(|message| format!("Line {}: {message}", index + 1))?;
+            if let Some(fen) = fen {
+                pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{fen}\"]\n\n"));
+            }
+            pgn.push_str(&format!("{movetext} *\n\n"));
+        }
+        Ok(pgn)
+    }
+}
+
+/// The words of a line that can be moves: move numbers, also as in `12.e4`, and results are
+/// left out.
+fn move_words(line: &str) -> impl Iterator<Item = &str> {
+    line.split_whitespace()
+        .filter(|word| normalize_result(word).is_none())
+        .map(|word| word.rsplit_once('.').map_or(word, |(_, mv)| mv))
+        .filter(|word| !word.is_empty())
+}
+
+pub fn is_coordinate_move(word: &str) -> bool {
+    word.is_ascii() && parse_coordinate_move(word).is_some()
+}
+
+/// Splits a line into its FEN, the counters being optional, and the movetext of its moves.
+fn fen_game(line: &str) -> Result<(Option<String>, String), String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words.len() < 4 {
+        return Err(format!("Not a FEN: {line}"));
+    }
+    let counters = words[4..]
+        .iter()
+        .take(2)
+        .take_while(|word| word.parse::<u32>().is_ok())
+        .count();
+    let fen = words[..4 + counters].join(" ");
+    let position = Position::from_fen(&fen)?;
+    let rest = words[4 + counters..].join(" ");
+    let moves = move_words(&rest).filter(|word| *word != "moves");
+    let movetext = moves_game(position, moves)?;
+    Ok((Some(position.to_fen()), movetext))
+}
+
+/// Numbered SAN movetext of moves from a position, each given in SAN or as a coordinate move.
+pub fn moves_game<'a>(
+    mut position: Position,
+    moves: impl Iterator<Item = &'a str>,
+) -> Result<String, String> {
+    let mut words = Vec::new();
+    for (ply, mv) in moves.enumerate() {
+        let coordinate = Some(mv)
+            .filter(|mv| is_coordinate_move(mv))
+            .and_then(parse_coordinate_move)
+            .filter(|coordinate| position.legal_moves().contains(coordinate));
+        let (from, to, promotion) = match coordinate {
+            Some(coordinate) => coordinate,
+            None => position
+                .parse_san(mv)
+                .map_err(|_| format!("Illegal move {mv}"))?,
+        };
+        if position.side_to_move == Color::White {
+            words.push(format!("{}.", position.fullmove_number));
+        } else if ply == 0 {
+            words.push(format!("{}...", position.fullmove_number));
+        }
+        words.push(to_san(&position, from, to, promotion));
+        position = position.play(from, to, promotion);
+    }
+    Ok(words.join(" "))
+}
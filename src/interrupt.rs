@@ -0,0 +1,41 @@
+//! Ctrl-C handling that lets a run stop between games and flush its output.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Exit code of a run stopped by Ctrl-C, as shells report for SIGINT.
+pub const EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether Ctrl-C was pressed since `install`.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+mod ffi {
+    pub const SIGINT: i32 = 2;
+
+    extern "C" {
+        pub fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        pub fn _exit(status: i32) -> !;
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: i32) {
+    // A second Ctrl-C gives up on flushing
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        unsafe { ffi::_exit(EXIT_CODE) }
+    }
+}
+
+/// Makes Ctrl-C set a flag for the run to stop at the next game instead of killing the
+/// process, so that buffered output can be flushed. Elsewhere than on Unix, Ctrl-C keeps its
+/// default behavior.
+pub fn install() {
+    #[cfg(unix)]
+    unsafe {
+        ffi::signal(ffi::SIGINT, on_interrupt);
+    }
+}
@@ -0,0 +1,224 @@
+use std::borrow::Cow;
+
+use crate::pgn_ast::{Game, Line};
+
+/// Languages recognized in comments, by ISO 639-1 code, with common words that tell them
+/// apart. Any Cyrillic text reads as Russian.
+const STOPWORDS: [(&str, &[&str]); 7] = [
+    (
+        "en",
+        &[
+            "the", "and", "of", "to", "with", "this", "that", "was", "white", "black", "better",
+            "move", "but", "it", "for", "not", "after", "here",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "mit", "nicht", "ein", "eine", "weiß", "schwarz",
+            "besser", "zug", "auf", "den", "war", "hier",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "les", "et", "est", "avec", "pas", "une", "des", "blancs", "noirs", "meilleur",
+            "coup", "pour", "mais", "ici",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "los", "las", "y", "es", "con", "una", "del", "blancas", "negras", "mejor",
+            "jugada", "pero", "aquí",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "gli", "è", "con", "non", "della", "bianco", "nero", "meglio", "mossa", "per",
+            "ma", "che", "qui",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "het", "een", "en", "met", "niet", "wit", "zwart", "beter", "zet", "van", "maar",
+            "hier",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "as", "é", "com", "não", "uma", "brancas", "pretas", "melhor", "lance",
+            "mas", "aqui",
+        ],
+    ),
+];
+
+/// Whether a language code can be detected.
+pub fn is_supported(language: &str) -> bool {
+    language == "ru" || STOPWORDS.iter().any(|(code, _)| *code == language)
+}
+
+fn is_cyrillic(c: char) -> bool {
+    matches!(c, '\u{0400}'..='\u{04FF}')
+}
+
+/// Guesses the language of a text, or `None` when it has too few words to tell, such as a
+/// bare evaluation. Command annotations like `[%clk 0:03:00]` are ignored.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let mut prose = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[%") {
+        prose.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find(']')
+            .map_or("", |end| &rest[start + end + 1..]);
+    }
+    prose.push_str(rest);
+
+    let letters = prose.chars().filter(|c| c.is_alphabetic()).count();
+    let cyrillic = prose.chars().filter(|c| is_cyrillic(*c)).count();
+    if letters > 0 && cyrillic * 2 > letters {
+        return Some("ru");
+    }
+
+    let words: Vec<String> = prose
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut best = None;
+    let mut best_score = 0;
+    let mut tied = false;
+    for (code, stopwords) in STOPWORDS {
+        let score = words
+            .iter()
+            .filter(|word| stopwords.contains(&word.as_str()))
+            .count();
+        if score > best_score {
+            (best, best_score, tied) = (Some(code), score, false);
+        } else if score > 0 && score == best_score {
+            tied = true;
+        }
+    }
+
+    if tied {
+        None
+    } else {
+        best
+    }
+}
+
+/// Removes the comments of a line and its side lines written in another language, keeping
+/// those whose language cannot be told. Returns the number of comments removed.
+pub fn retain_language(line: &mut Line, language: &str) -> usize {
+    let mut removed = retain_comments(&mut line.comments, language);
+    for node in &mut line.moves {
+        removed += retain_comments(&mut node.comments, language);
+        for variation in &mut node.variations {
+            removed += retain_language(variation, language);
+        }
+    }
+    removed
+}
+
+fn retain_comments(comments: &mut Vec<Cow<'_, str>>, language: &str) -> usize {
+    let count = comments.len();
+    comments.retain(|comment| detect_language(comment).is_none_or(|found| found == language));
+    count - comments.len()
+}
+
+/// Latin spelling of a Cyrillic letter, in the common English romanization of player names.
+fn romanize(c: char) -> Option<&'static str> {
+    let lower = c.to_lowercase().next()?;
+    Some(match lower {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' => "g",
+        'д' => "d",
+        'е' | 'ё' | 'э' => "e",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'ї' => "yi",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+        _ => return None,
+    })
+}
+
+/// Spells Cyrillic text in Latin letters, such as `Карпов, Анатолий` as `Karpov, Anatoly`.
+/// Other characters are kept.
+pub fn transliterate(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(is_cyrillic) {
+        return Cow::Borrowed(text);
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut latin = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        // A final `ий` or `ый` reads as `y` in names
+        let ending = matches!(c, 'и' | 'ы')
+            && chars.get(i + 1) == Some(&'й')
+            && !chars.get(i + 2).is_some_and(|next| next.is_alphabetic());
+        if ending {
+            latin.push('y');
+            i += 2;
+            continue;
+        }
+
+        match romanize(c) {
+            // Capitals keep a capital initial only, as in `Zh` for `Ж`
+            Some(spelling) if c.is_uppercase() => {
+                let mut letters = spelling.chars();
+                latin.extend(letters.next().map(|first| first.to_ascii_uppercase()));
+                latin.extend(letters);
+            }
+            Some(spelling) => latin.push_str(spelling),
+            None => latin.push(c),
+        }
+        i += 1;
+    }
+
+    Cow::Owned(latin)
+}
+
+/// Spells the Cyrillic player names of a game in Latin letters, returning the number of tags
+/// changed.
+pub fn transliterate_players(game: &mut Game) -> usize {
+    let mut changed = 0;
+    for tag in &mut game.tags {
+        if !matches!(tag.name, "White" | "Black") {
+            continue;
+        }
+        if let Cow::Owned(latin) = transliterate(&tag.value) {
+            tag.value = Cow::Owned(latin);
+            changed += 1;
+        }
+    }
+    changed
+}
@@ -0,0 +1,294 @@
+use chess::legal_moves::misc::{Color, Square};
+use chess::utils::square_to_string;
+
+use crate::position::{color_name, offset_square, Piece, Position};
+use crate::san::{matches_origin, to_san, SanMove};
+
+/// Why a SAN move cannot be played, worked out by inspecting the position, as in
+/// `Nf3 impossible: knight on g1 is pinned`.
+pub fn explain_illegal(position: &Position, san: &str) -> String {
+    let reasons = match SanMove::parse(san) {
+        Ok(SanMove::Castling { queen_side }) => vec![castling_reason(position, queen_side)],
+        Ok(SanMove::Normal {
+            piece,
+            origin,
+            to,
+            promotion: _,
+        }) => move_reasons(position, piece, origin, to),
+        Err(message) => return message,
+    };
+
+    format!("{san} impossible: {}", reasons.join("; "))
+}
+
+fn castling_reason(position: &Position, queen_side: bool) -> String {
+    let color = position.side_to_move;
+    let (king, right) = match (color == Color::White, queen_side) {
+        (true, false) => (4, 0b0001),
+        (true, true) => (4, 0b0010),
+        (false, false) => (60, 0b0100),
+        (false, true) => (60, 0b1000),
+    };
+    let (between, crossed): (Vec<Square>, Vec<Square>) = if queen_side {
+        (vec![king - 1, king - 2, king - 3], vec![king - 1, king - 2])
+    } else {
+        (vec![king + 1, king + 2], vec![king + 1, king + 2])
+    };
+
+    if position.piece_at(king) != Some((color, Piece::King)) {
+        return "the king has left its home square".to_string();
+    }
+    if !position.castling.has(right) {
+        return "castling rights on that side were lost".to_string();
+    }
+    if position.in_check(color) {
+        return "the king is in check".to_string();
+    }
+    if let Some(blocker) = between
+        .iter()
+        .find(|square| position.piece_at(**square).is_some())
+    {
+        return format!("blocked by piece on {}", square_to_string(*blocker));
+    }
+    if let Some(attacked) = crossed
+        .iter()
+        .find(|square| position.is_attacked(**square, !color))
+    {
+        return format!("{} is attacked", square_to_string(*attacked));
+    }
+
+    "castling is not possible".to_string()
+}
+
+fn move_reasons(position: &Position, piece: Piece, origin: &str, to: Square) -> Vec<String> {
+    let color = position.side_to_move;
+    let target = square_to_string(to);
+
+    if let Some((_, occupant)) = position.piece_at(to).filter(|(owner, _)| *owner == color) {
+        return vec![format!("{target} is occupied by own {}", occupant.name())];
+    }
+
+    let pieces: Vec<Square> = (0..64)
+        .filter(|square| position.piece_at(*square) == Some((color, piece)))
+        .collect();
+    if pieces.is_empty() {
+        return vec![format!(
+            "no {} {} on the board",
+            color_name(color),
+            piece.name()
+        )];
+    }
+    // A pawn move without origin is a push along the target's file
+    let candidates: Vec<Square> = pieces
+        .into_iter()
+        .filter(|square| matches_origin(origin, *square))
+        .filter(|square| piece != Piece::Pawn || !origin.is_empty() || square % 8 == to % 8)
+        .collect();
+    if candidates.is_empty() {
+        let place = if origin.is_empty() {
+            &target[..1]
+        } else {
+            origin
+        };
+        return vec![format!("no {} on {place}", piece.name())];
+    }
+
+    let mut legal = Vec::new();
+    let mut reasons = Vec::new();
+    // Pieces that could never get there are only worth mentioning when no other piece comes close
+    let mut out_of_reach = Vec::new();
+    for from in candidates {
+        let name = format!("{} on {}", piece.name(), square_to_string(from));
+        if !position.pseudo_legal_targets(from).contains(&to) {
+            match unreachable_reason(position, from, to, &name) {
+                Some(reason) => reasons.push(reason),
+                None => out_of_reach.push(format!("{name} cannot reach {target}")),
+            }
+        } else if !position.play(from, to, None).in_check(color) {
+            legal.push(square_to_string(from));
+        } else if piece == Piece::King {
+            reasons.push(format!("king would be in check on {target}"));
+        } else if position.in_check(color) {
+            reasons.push(format!("{name} does not get the king out of check"));
+        } else {
+            reasons.push(format!("{name} is pinned"));
+        }
+    }
+
+    if legal.len() > 1 {
+        return vec![format!(
+            "ambiguous, {}s on {} can all reach {target}",
+            piece.name(),
+            legal.join(" and ")
+        )];
+    }
+    if reasons.is_empty() {
+        out_of_reach
+    } else {
+        reasons
+    }
+}
+
+/// What keeps a piece from a square it could otherwise move to, ignoring checks.
+/// None when the square is simply out of the piece's reach.
+fn unreachable_reason(position: &Position, from: Square, to: Square, name: &str) -> Option<String> {
+    let (color, piece) = position.piece_at(from)?;
+    let file_step = (to % 8) as i8 - (from % 8) as i8;
+    let rank_step = (to / 8) as i8 - (from / 8) as i8;
+
+    if piece == Piece::Pawn {
+        let forward = if color == Color::White { 1 } else { -1 };
+        if file_step.abs() == 1 && rank_step == forward {
+            return Some(format!(
+                "{name} has nothing to capture on {}",
+                square_to_string(to)
+            ));
+        }
+    }
+
+    // Sliding along a line, including pawn pushes, stops at the first occupied square
+    let slides = match piece {
+        Piece::Rook => file_step == 0 || rank_step == 0,
+        Piece::Bishop => file_step.abs() == rank_step.abs(),
+        Piece::Queen => file_step == 0 || rank_step == 0 || file_step.abs() == rank_step.abs(),
+        Piece::Pawn => file_step == 0,
+        Piece::Knight | Piece::King => false,
+    };
+    if slides {
+        let direction = (file_step.signum(), rank_step.signum());
+        let mut square = from;
+        while let Some(next) = offset_square(square, direction) {
+            if next == to {
+                break;
+            }
+            if position.piece_at(next).is_some() {
+                return Some(format!(
+                    "{name} is blocked by piece on {}",
+                    square_to_string(next)
+                ));
+            }
+            square = next;
+        }
+        if piece == Piece::Pawn && position.piece_at(to).is_some() {
+            return Some(format!(
+                "{name} is blocked by piece on {}",
+                square_to_string(to)
+            ));
+        }
+    }
+
+    None
+}
+
+/// Edit distance between two strings, counting insertions, deletions and substitutions.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != *b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// The legal move most likely meant by an illegal SAN token: moves of the same piece type to
+/// the target or a neighbouring square are preferred, then the closest SAN by edit distance.
+pub fn suggest_move(position: &Position, san: &str) -> Option<String> {
+    let legal = position.legal_moves();
+    let intended = match SanMove::parse(san) {
+        Ok(SanMove::Normal { piece, to, .. }) => Some((piece, to)),
+        _ => None,
+    };
+    let distance = |a: Square, b: Square| (a % 8).abs_diff(b % 8).max((a / 8).abs_diff(b / 8));
+
+    let nearby: Vec<(Square, Square, Option<Piece>)> = match intended {
+        Some((piece, target)) => {
+            let same_piece: Vec<_> = legal
+                .iter()
+                .copied()
+                .filter(|(from, _, _)| matches!(position.piece_at(*from), Some((_, moved)) if moved == piece))
+                .collect();
+            let close: Vec<_> = same_piece
+                .iter()
+                .copied()
+                .filter(|(_, to, _)| distance(*to, target) <= 1)
+                .collect();
+            if !close.is_empty() {
+                close
+            } else if !same_piece.is_empty() {
+                same_piece
+            } else {
+                legal
+            }
+        }
+        None => legal,
+    };
+
+    let wanted = san.trim_end_matches(['+', '#', '!', '?']);
+    nearby
+        .into_iter()
+        .filter(|(_, _, promotion)| matches!(promotion, None | Some(Piece::Queen)))
+        .map(|(from, to, promotion)| to_san(position, from, to, promotion))
+        .min_by_key(|candidate| edit_distance(wanted, candidate.trim_end_matches(['+', '#'])))
+}
+
+/// Number of following tokens that must play legally for a recovery to be accepted.
+const RECOVERY_LOOKAHEAD: usize = 3;
+
+/// How to get past a token that cannot be played.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Recovery {
+    /// Drop this many tokens, starting with the failing one.
+    Skip(usize),
+    /// Play this SAN move in place of the failing token.
+    Replace(String),
+}
+
+/// Whether the first tokens (up to the lookahead) can be played in turn from the position.
+fn continues(mut position: Position, tokens: &[&str]) -> bool {
+    for token in tokens.iter().take(RECOVERY_LOOKAHEAD) {
+        match position.parse_san(token) {
+            Ok((from, to, promotion)) => position = position.play(from, to, promotion),
+            Err(_) => return false,
+        }
+    }
+    true
+}
+
+/// A way past the failing `tokens[0]` after which the game goes on legally: dropping the token
+/// as noise, replacing it by the legal move closest to it, or dropping a whole move pair.
+pub fn find_recovery(position: &Position, tokens: &[&str]) -> Option<Recovery> {
+    let (failing, rest) = tokens.split_first()?;
+    if !rest.is_empty() && continues(*position, rest) {
+        return Some(Recovery::Skip(1));
+    }
+
+    // Legal moves closest to the token first
+    let wanted = failing.trim_end_matches(['+', '#', '!', '?']);
+    let mut replacements: Vec<(usize, String, Position)> = position
+        .legal_moves()
+        .into_iter()
+        .filter(|(_, _, promotion)| matches!(promotion, None | Some(Piece::Queen)))
+        .map(|(from, to, promotion)| {
+            let san = to_san(position, from, to, promotion);
+            let distance = edit_distance(wanted, san.trim_end_matches(['+', '#']));
+            (distance, san, position.play(from, to, promotion))
+        })
+        .collect();
+    replacements.sort_by_key(|(distance, _, _)| *distance);
+    if let Some((_, san, _)) = replacements
+        .into_iter()
+        .find(|(_, _, after)| continues(*after, rest))
+    {
+        return Some(Recovery::Replace(san));
+    }
+
+    (rest.len() > 1 && continues(*position, &rest[1..])).then_some(Recovery::Skip(2))
+}
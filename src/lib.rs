@@ -0,0 +1,83 @@
+pub mod accuracy;
+pub mod annotations;
+pub mod backend;
+pub mod binary;
+pub mod captures;
+pub mod castling_stats;
+pub mod cheat_screen;
+pub mod checkpoint;
+pub mod cli;
+pub mod color_split;
+pub mod comments;
+pub mod crosstable;
+pub mod dataset;
+pub mod diff;
+pub mod disambiguation;
+pub mod duplicates;
+pub mod eco;
+pub mod endgames;
+pub mod epd;
+pub mod eval;
+pub mod events;
+pub mod features;
+pub mod fen_analysis;
+pub mod filters;
+pub mod game_index;
+pub mod generator;
+pub mod head_to_head;
+pub mod heatmaps;
+pub mod incremental;
+pub mod input_format;
+pub mod interrupt;
+pub mod json;
+pub mod language;
+pub mod legality;
+pub mod lichess;
+pub mod livechess;
+pub mod material;
+pub mod mirror;
+pub mod move_table;
+pub mod notation;
+pub mod openings;
+pub mod output_queue;
+pub mod patterns;
+pub mod pawn_structure;
+pub mod perft;
+pub mod pgn_ast;
+pub mod pgn_cleaner;
+pub mod pgn_preprocessor;
+pub mod phases;
+pub mod piece_lifetimes;
+pub mod polyglot;
+pub mod position;
+pub mod prep;
+pub mod profiling;
+pub mod pv;
+pub mod rating_estimate;
+pub mod redact;
+pub mod result_inference;
+pub mod roster;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod sacrifices;
+pub mod sampling;
+pub mod san;
+pub mod san_tokens;
+pub mod see;
+pub mod selfplay;
+#[cfg(feature = "shakmaty")]
+pub mod shakmaty_backend;
+pub mod sharding;
+pub mod source;
+pub mod study;
+pub mod tag_selection;
+#[cfg(feature = "tensors")]
+pub mod tensor_export;
+mod test;
+pub mod tree;
+pub mod two_pass;
+pub mod uci;
+pub mod worker;
+pub mod writer;
+
+pub use pgn_preprocessor::PgnProcessor;
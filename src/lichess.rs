@@ -0,0 +1,77 @@
+//! Publishing study chapters to Lichess through its API, with curl as for HTTP inputs. The
+//! API token and the chapter are handed to curl as a configuration on its standard input, so
+//! that the token never appears among the arguments other users can list.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Environment variable holding the API token, which needs the `study:write` scope.
+pub const TOKEN_VARIABLE: &str = "LICHESS_TOKEN";
+
+pub const API_URL: &str = "https://lichess.org/api";
+
+/// The id of a study given by itself or as its URL, such as
+/// `https://lichess.org/study/AbCd1234`, the chapter part of a URL left out.
+pub fn study_id(study: &str) -> Option<&str> {
+    let path = study.split(['?', '#']).next().unwrap_or_default();
+    let id = match path.split_once("/study/") {
+        Some((_, rest)) => rest.split('/').next().unwrap_or_default(),
+        None => path,
+    };
+    (id.len() == 8 && id.chars().all(|c| c.is_ascii_alphanumeric())).then_some(id)
+}
+
+/// A string of a curl configuration, in quotes with its special characters escaped.
+fn config_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The curl configuration of the request importing a chapter.
+pub fn import_config(token: &str, name: &str, pgn: &str) -> String {
+    [
+        format!(
+            "header = {}",
+            config_string(&format!("Authorization: Bearer {token}"))
+        ),
+        format!(
+            "data-urlencode = {}",
+            config_string(&format!("name={name}"))
+        ),
+        format!("data-urlencode = {}", config_string(&format!("pgn={pgn}"))),
+    ]
+    .join("\n")
+        + "\n"
+}
+
+/// Adds a chapter to a study.
+pub fn import_chapter(study: &str, token: &str, name: &str, pgn: &str) -> io::Result<()> {
+    let url = format!("{API_URL}/study/{study}/import-pgn");
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--config", "-", &url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|error| io::Error::new(error.kind(), format!("Cannot run curl: {error}")))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(import_config(token, name, pgn).as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "Import of chapter {name} into study {study} failed: {status}"
+        )));
+    }
+    Ok(())
+}
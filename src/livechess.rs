@@ -0,0 +1,77 @@
+//! Ingestion of DGT LiveChess output. LiveChess rewrites a game every time a move is made on
+//! its board, so a capture of its files holds many snapshots of each game, later ones
+//! extending, or correcting, earlier ones. Collecting them keeps the last snapshot per board.
+
+use crate::pgn_cleaner::{mainline_moves, RawGame};
+
+/// The board a snapshot belongs to: the event, the round (`3.12` for board 12 of round 3 in
+/// LiveChess output) and the Board tag if present. Players are added when the round does not
+/// tell the boards apart, which is the case without a board number.
+pub fn board_key(game: &RawGame) -> String {
+    let tag = |name| game.tag(name).unwrap_or("?");
+    let round = tag("Round");
+    match game.tag("Board") {
+        Some(board) => format!("{}|{round}|{board}", tag("Event")),
+        None if round.contains('.') => format!("{}|{round}", tag("Event")),
+        None => format!("{}|{round}|{}|{}", tag("Event"), tag("White"), tag("Black")),
+    }
+}
+
+/// The latest snapshot of a board's game and how it came about.
+pub struct BoardGame {
+    pub key: String,
+    pub game: RawGame,
+    /// Snapshots read for the board, duplicates included.
+    pub snapshots: usize,
+    /// Snapshots whose moves did not extend the previous one, such as takebacks or
+    /// corrections by the operator.
+    pub rewrites: usize,
+}
+
+/// Snapshots grouped by board, in the order boards first appear.
+#[derive(Default)]
+pub struct LiveChessIngest {
+    boards: Vec<BoardGame>,
+    /// Snapshots identical to the one before them.
+    pub duplicates: usize,
+}
+
+impl LiveChessIngest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a snapshot, read after all those added before.
+    pub fn add(&mut self, game: RawGame) {
+        let key = board_key(&game);
+        let Some(board) = self.boards.iter_mut().find(|board| board.key == key) else {
+            self.boards.push(BoardGame {
+                key,
+                game,
+                snapshots: 1,
+                rewrites: 0,
+            });
+            return;
+        };
+
+        board.snapshots += 1;
+        if game.movetext.trim() == board.game.movetext.trim() && game.tags == board.game.tags {
+            self.duplicates += 1;
+            return;
+        }
+        let previous = mainline_moves(&board.game.movetext);
+        let moves = mainline_moves(&game.movetext);
+        if !moves.starts_with(&previous) {
+            board.rewrites += 1;
+        }
+        board.game = game;
+    }
+
+    pub fn boards(&self) -> &[BoardGame] {
+        &self.boards
+    }
+
+    pub fn into_games(self) -> Vec<RawGame> {
+        self.boards.into_iter().map(|board| board.game).collect()
+    }
+}
@@ -1,61 +1,2152 @@
-mod pgn_cleaner;
-mod pgn_preprocessor;
-mod test;
-
+use std::cmp::Reverse;
 use std::env;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
-use std::path::Path;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::process;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use pgn_crunker::accuracy::{mainline_evals, AccuracyReport, GameAccuracy};
+use pgn_crunker::backend::BackendKind;
+use pgn_crunker::binary::{self, encode_game};
+use pgn_crunker::captures::{CaptureStats, GameCaptures};
+use pgn_crunker::castling_stats::{CastlingStats, GameCastling};
+use pgn_crunker::cheat_screen::{base_and_increment, clock_annotation, move_times, CheatScreen};
+use pgn_crunker::checkpoint::Checkpoint;
+use pgn_crunker::cli::{
+    self, CleanSettings, Command, FenSettings, GenSettings, OpeningSettings, PrepSettings,
+    ScreenSettings, StudySettings,
+};
+use pgn_crunker::color_split::ColorSplit;
+use pgn_crunker::comments::strip_engine_comments;
+use pgn_crunker::crosstable::{Crosstable, CrosstableFormat};
+use pgn_crunker::dataset::UniquePositions;
+use pgn_crunker::diff::diff_databases;
+use pgn_crunker::duplicates::{duplicate_key, ConflictPolicy, Deduplicator};
+use pgn_crunker::eco::{self, EcoClassifier};
+use pgn_crunker::endgames::endgame_class;
+use pgn_crunker::epd::EpdRecord;
+use pgn_crunker::events::group_events;
+use pgn_crunker::fen_analysis::{fen_lines, white_centipawns, PositionClass, Wdl};
+use pgn_crunker::filters::game_date;
+use pgn_crunker::game_index::{self, GameIndex, IndexEntry};
+use pgn_crunker::generator::{random_game, san_movetext};
+use pgn_crunker::head_to_head::{first_player_white, name_matches, opening_label, HeadToHead};
+use pgn_crunker::heatmaps::Heatmaps;
+use pgn_crunker::incremental::{game_hash, KnownGames};
+use pgn_crunker::input_format::InputFormat;
+use pgn_crunker::language::{retain_language, transliterate_players};
+use pgn_crunker::livechess::LiveChessIngest;
+use pgn_crunker::material::MaterialTrajectory;
+use pgn_crunker::mirror::{mirror_move, mirror_position, mirror_result, mirror_tags};
+use pgn_crunker::notation::{write_moves, Uci};
+use pgn_crunker::openings::OpeningTree;
+use pgn_crunker::output_queue::{self, OutputQueue};
+use pgn_crunker::pawn_structure::{PawnStructure, StructureGroups};
+use pgn_crunker::pgn_ast::{Game, GameResult, TagPair};
+use pgn_crunker::pgn_cleaner::{
+    load_movetext, mainline_moves, mainline_spans, scan_headers, split_games, variations,
+    GameReader, RawGame,
+};
+use pgn_crunker::piece_lifetimes::{piece_lives, LifetimeStats, PieceLife};
+use pgn_crunker::polyglot::{annotate_book_moves, PolyglotBook, PolyglotKeys};
+use pgn_crunker::position::Position;
+use pgn_crunker::prep::{main_lines, report_heading, tree_report};
+use pgn_crunker::profiling::{GameProfiler, GameTiming};
+use pgn_crunker::pv::verify_pvs;
+use pgn_crunker::rating_estimate::{centipawn_losses, RatingEstimates};
+use pgn_crunker::result_inference::{self, infer_result, INFERRED_TAG};
+use pgn_crunker::sacrifices::{sacrifices, Sacrifice};
+use pgn_crunker::sampling::{sample_indices, Rng};
+use pgn_crunker::san_tokens::{self, Vocabulary};
+use pgn_crunker::selfplay::{play_game, SelfplaySettings};
+use pgn_crunker::sharding::ShardWriter;
+use pgn_crunker::study::{chapter_name, make_chapter, LICHESS_MAX_CHAPTERS};
+use pgn_crunker::tree::{game_json, longest_line, LineSelection};
+use pgn_crunker::uci::Engine;
+use pgn_crunker::{
+    disambiguation, features, interrupt, json, lichess, move_table, patterns, perft, roster,
+    source, two_pass, worker, writer, PgnProcessor,
+};
 
-use pgn_preprocessor::PgnProcessor;
+fn read_pgn(input_path: Option<&str>) -> io::Result<String> {
+    read_pgn_from(input_path, 0)
+}
+
+/// Reads the input verbatim from a byte offset, so that game offsets match the file.
+fn read_pgn_from(input_path: Option<&str>, offset: usize) -> io::Result<String> {
+    let source = source::open_source(input_path)?;
+    source::read_to_string(source.as_ref(), offset as u64)
+}
 
 fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    let input = if args.len() > 1 {
-        // Read from file
-        let path = Path::new(&args[1]);
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let backend = cli::take_backend(&mut args).map_err(|message| invalid_input(&message))?;
+    let command =
+        cli::parse_command(args.into_iter()).map_err(|message| invalid_input(&message))?;
+
+    match command {
+        Command::Crunch(mut options) => {
+            options.backend = backend;
+            crunch(&options)
+        }
+        Command::Clean(settings) => clean(&settings),
+        Command::Diff { left, right } => diff(&left, &right),
+        Command::Selfplay(settings) => selfplay(&settings),
+        Command::Openings(settings) => openings(&settings, backend),
+        Command::Crosstable { input, format } => crosstable(&input, format),
+        Command::Events { input, json } => events(&input, json),
+        Command::HeadToHead {
+            input,
+            players,
+            output,
+        } => head_to_head(&input, players, output.as_deref(), backend),
+        Command::Headers { input, tags } => headers(&input, &tags),
+        Command::Index { input, tags } => index(&input, &tags, backend),
+        Command::Game { input, number } => game(&input, number),
+        Command::Encode {
+            input,
+            output,
+            update,
+        } => encode(&input, &output, update, backend),
+        Command::Decode { input, output } => decode(&input, output.as_deref()),
+        Command::Study(settings) => study(&settings),
+        Command::Fens(settings) => fens(&settings),
+        Command::Gen(settings) => gen(&settings, backend),
+        Command::LiveChess { inputs, output } => livechess(&inputs, output.as_deref()),
+        Command::Perft {
+            depth,
+            fen,
+            divide,
+            check_crate,
+        } => perft(depth, fen.as_deref(), divide, check_crate, backend),
+        Command::Prep(settings) => prep(&settings, backend),
+        Command::Screen(settings) => screen(&settings, backend),
+        Command::Worker { socket } => worker(socket.as_deref()),
+        Command::TestSuite {
+            engine,
+            suite,
+            movetime,
+        } => test_suite(&engine, &suite, movetime),
+    }
+}
+
+fn worker(socket: Option<&str>) -> io::Result<()> {
+    let Some(socket) = socket else {
+        return worker::serve(io::stdin().lock(), io::stdout(), worker::run_job);
+    };
+
+    #[cfg(unix)]
+    {
+        let listener = std::os::unix::net::UnixListener::bind(socket)?;
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let reader = io::BufReader::new(stream.try_clone()?);
+            if let Err(error) = worker::serve(reader, stream, worker::run_job) {
+                eprintln!("Warning: connection closed: {error}");
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    Err(invalid_input(&format!(
+        "Cannot listen on {socket}: Unix sockets are not supported"
+    )))
+}
+
+/// A processor playing games on the board chosen with `--backend`.
+fn processor_on(backend: BackendKind) -> PgnProcessor {
+    let mut processor = PgnProcessor::new();
+    processor.set_backend(backend);
+    processor
+}
+
+fn openings(settings: &OpeningSettings, backend: BackendKind) -> io::Result<()> {
+    let games = split_games(&read_pgn(Some(&settings.input))?);
+    let mut processor = processor_on(backend);
+    let mut tree = OpeningTree::new(settings.depth);
+
+    for game in &games {
+        let moves = processor.process_game(game);
+        processor.take_warnings();
+        if processor.is_corrupt() {
+            continue;
+        }
+        let result = game
+            .tag("Result")
+            .or_else(|| game.termination())
+            .unwrap_or("*");
+        tree.add_game(&moves, result);
+    }
+
+    let mut engine = Engine::start(&settings.engine)?;
+    for (line, stats) in tree.nodes.iter_mut() {
+        if stats.games < settings.min_games {
+            continue;
+        }
+        let moves: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+        let search = engine.search(None, &moves, &format!("movetime {}", settings.movetime))?;
+        // Engines score from the side to move
+        let sign = if moves.len().is_multiple_of(2) { 1 } else { -1 };
+        stats.eval = search.score.map(|score| sign * score.centipawns());
+    }
+
+    println!("games\tscore\teval\texpected\tdivergence\tmoves");
+    for (line, stats) in tree
+        .divergent(settings.min_games)
+        .into_iter()
+        .take(settings.top)
+    {
+        println!(
+            "{}\t{:.3}\t{}\t{:.3}\t{:+.3}\t{}",
+            stats.games,
+            stats.practical_score(),
+            stats.eval.unwrap_or_default(),
+            stats.expected_score().unwrap_or_default(),
+            stats.divergence().unwrap_or_default(),
+            if line.is_empty() { "(start)" } else { line },
+        );
+    }
+
+    Ok(())
+}
+
+fn crosstable(input_path: &str, format: CrosstableFormat) -> io::Result<()> {
+    let events = group_events(&split_games(&read_pgn(Some(input_path))?));
+    let tables: Vec<String> = events
+        .iter()
+        .map(|event| Crosstable::of(event).render(format))
+        .collect();
+    print!("{}", tables.join("\n"));
+    Ok(())
+}
+
+fn events(input_path: &str, as_json: bool) -> io::Result<()> {
+    let events = group_events(&split_games(&read_pgn(Some(input_path))?));
+
+    if as_json {
+        println!(
+            "{}",
+            json::array(events.iter().map(|event| event.to_json()))
+        );
+    } else {
+        let tables: Vec<String> = events.iter().map(|event| event.to_text()).collect();
+        print!("{}", tables.join("\n"));
+    }
+    Ok(())
+}
+
+fn head_to_head(
+    input_path: &str,
+    players: [String; 2],
+    output_path: Option<&str>,
+    backend: BackendKind,
+) -> io::Result<()> {
+    let games = split_games(&read_pgn(Some(input_path))?);
+    let mut processor = processor_on(backend);
+    let classifier = EcoClassifier::new();
+    let mut head_to_head = HeadToHead::new(players);
+    let mut output = String::new();
+
+    for game in &games {
+        let Some(first_white) = first_player_white(game, &head_to_head.players) else {
+            continue;
+        };
+        let classification = match game.tag("FEN") {
+            Some(_) => None,
+            None => {
+                processor.process_game(game);
+                processor.take_warnings();
+                classifier.classify(processor.positions())
+            }
+        };
+        let result = game.tag("Result").and_then(GameResult::parse);
+        head_to_head.add_game(first_white, result, &opening_label(game, classification));
+        writer::write_parsed_game(&Game::parse(game), &mut output);
+    }
+
+    match output_path {
+        Some(path) => fs::write(path, output)?,
+        None => print!("{output}"),
+    }
+    eprint!("{}", head_to_head.summary());
+    Ok(())
+}
+
+fn livechess(inputs: &[String], output_path: Option<&str>) -> io::Result<()> {
+    let mut ingest = LiveChessIngest::new();
+    let mut snapshots = 0;
+    for input in inputs {
+        for game in split_games(&read_pgn(Some(input))?) {
+            snapshots += 1;
+            ingest.add(game);
+        }
+    }
+
+    for board in ingest.boards() {
+        if board.rewrites > 0 {
+            eprintln!(
+                "Warning: board {}, {} - {}, moves rewritten by {} snapshots",
+                board.game.tag("Round").unwrap_or("?"),
+                board.game.tag("White").unwrap_or("?"),
+                board.game.tag("Black").unwrap_or("?"),
+                board.rewrites
+            );
+        }
+    }
+    eprintln!(
+        "Read {snapshots} snapshots of {} boards, {} duplicates",
+        ingest.boards().len(),
+        ingest.duplicates
+    );
+
+    let mut output = String::new();
+    for game in ingest.into_games() {
+        writer::write_parsed_game(&Game::parse(&game), &mut output);
+    }
+    match output_path {
+        Some(path) => {
+            fs::write(path, output)?;
+            println!("Output written to {path}");
+        }
+        None => print!("{output}"),
+    }
+    Ok(())
+}
+
+fn headers(input_path: &str, tags: &[String]) -> io::Result<()> {
+    let input = read_pgn(Some(input_path))?;
+    let mut output = BufWriter::new(io::stdout().lock());
+    writeln!(output, "game\tstart\tend\t{}", tags.join("\t"))?;
+    for (index, game) in scan_headers(&input).iter().enumerate() {
+        let values: Vec<&str> = tags
+            .iter()
+            .map(|tag| game.tag(tag).unwrap_or_default())
+            .collect();
+        writeln!(
+            output,
+            "{}\t{}\t{}\t{}",
+            index + 1,
+            game.offset,
+            game.end,
+            values.join("\t")
+        )?;
+    }
+    output.flush()
+}
+
+fn index(input_path: &str, tags: &[String], backend: BackendKind) -> io::Result<()> {
+    // The index is checked against the length of the file it was written for
+    let metadata = fs::metadata(input_path)?;
+    if !metadata.is_file() {
+        return Err(invalid_input(&format!(
+            "Cannot index {input_path}: not a file"
+        )));
+    }
+    let input = read_pgn(Some(input_path))?;
+    let mut processor = processor_on(backend);
+    let entries: Vec<IndexEntry> = split_games(&input)
+        .iter()
+        .map(|game| {
+            let moves = processor.process_game(game);
+            processor.take_warnings();
+            IndexEntry {
+                offset: game.offset as u64,
+                end: game.end as u64,
+                hash: game_hash(&game.tags, &moves),
+                values: tags
+                    .iter()
+                    .map(|tag| game.tag(tag).unwrap_or_default().to_string())
+                    .collect(),
+            }
+        })
+        .collect();
+
+    let path = game_index::index_path(input_path);
+    fs::write(
+        &path,
+        game_index::write_index(metadata.len(), tags, &entries),
+    )?;
+    eprintln!("Indexed {} games", entries.len());
+    println!("Output written to {path}");
+    Ok(())
+}
+
+fn game(input_path: &str, number: u64) -> io::Result<()> {
+    let path = game_index::index_path(input_path);
+    let file = File::open(&path).map_err(|error| {
+        invalid_input(&format!(
+            "Cannot open {path}: {error}; write it with the index command"
+        ))
+    })?;
+    let mut index = GameIndex::open(io::BufReader::new(file))?;
+    if index.input_len != fs::metadata(input_path)?.len() {
+        return Err(invalid_input(&format!(
+            "{path} is out of date; write it again with the index command"
+        )));
+    }
+    let entry = index.entry(number)?.ok_or_else(|| {
+        invalid_input(&format!(
+            "No game {number}: {input_path} holds {} games",
+            index.len()
+        ))
+    })?;
+
+    let source = source::open_source(Some(input_path))?;
+    print!("{}", game_index::read_game(source.as_ref(), &entry)?);
+    Ok(())
+}
+
+fn encode(
+    input_path: &str,
+    output_path: &str,
+    update: bool,
+    backend: BackendKind,
+) -> io::Result<()> {
+    let input = read_pgn(Some(input_path))?;
+    let mut processor = processor_on(backend);
+    let existing = match fs::read(output_path) {
+        Ok(existing) if update => Some(existing),
+        Err(error) if update && error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => None,
+    };
+    let mut known = match &existing {
+        Some(existing) => {
+            KnownGames::load(existing, backend).map_err(|message| invalid_input(&message))?
+        }
+        None => KnownGames::new(),
+    };
+    // Bytes of an updated output that were already there
+    let kept = existing.as_ref().map_or(0, Vec::len);
+    let mut bytes = existing.unwrap_or_else(|| binary::MAGIC.to_vec());
+    let (mut encoded, mut skipped, mut present) = (0, 0, 0);
+
+    for (index, game) in split_games(&input).iter().enumerate() {
+        let moves = processor.process_game(game);
+        processor.take_warnings();
+        if update && !processor.is_corrupt() && !known.insert(game_hash(&game.tags, &moves)) {
+            present += 1;
+            continue;
+        }
+        let result = if processor.is_corrupt() {
+            Err("moves could not be processed".to_string())
+        } else {
+            encode_game(&mut bytes, &game.tags, &moves)
+        };
+        match result {
+            Ok(()) => encoded += 1,
+            Err(error) => {
+                eprintln!("Warning: game {}, skipped: {error}", index + 1);
+                skipped += 1;
+            }
+        }
+    }
+
+    fs::write(output_path, &bytes)?;
+    if update {
+        eprintln!("Skipped {present} games already in {output_path}");
+    }
+    eprintln!(
+        "Encoded {encoded} games in {} bytes, {:.1}% of the input; skipped {skipped}",
+        bytes.len() - kept,
+        100.0 * (bytes.len() - kept) as f64 / input.len().max(1) as f64
+    );
+    println!("Output written to {output_path}");
+    Ok(())
+}
+
+fn decode(input_path: &str, output_path: Option<&str>) -> io::Result<()> {
+    let games =
+        binary::decode(&fs::read(input_path)?).map_err(|message| invalid_input(&message))?;
+    let mut output: Box<dyn Write> = match output_path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout()),
+    };
+
+    for game in &games {
+        let movetext = game.movetext().map_err(|message| invalid_input(&message))?;
+        let mut pgn = String::new();
+        writer::write_game(&game.tags, &movetext, &mut pgn);
+        output.write_all(pgn.as_bytes())?;
+    }
+    output.flush()?;
+
+    if let Some(path) = output_path {
+        println!("Output written to {path}");
+    }
+    Ok(())
+}
+
+fn study(settings: &StudySettings) -> io::Result<()> {
+    let input = read_pgn(Some(&settings.input))?;
+    let games = split_games(&input);
+    let token = match &settings.push {
+        Some(_) => Some(env::var(lichess::TOKEN_VARIABLE).map_err(|_| {
+            invalid_input(&format!(
+                "--push needs a Lichess API token with the study:write scope in {}",
+                lichess::TOKEN_VARIABLE
+            ))
+        })?),
+        None => None,
+    };
+    if games.len() > LICHESS_MAX_CHAPTERS {
+        let message = format!(
+            "{} chapters, more than the {LICHESS_MAX_CHAPTERS} a Lichess study holds",
+            games.len()
+        );
+        if settings.push.is_some() {
+            return Err(invalid_input(&message));
+        }
+        eprintln!("Warning: {message}");
+    }
+
+    let mut output = String::new();
+    let mut chapters = Vec::new();
+    for raw in &games {
+        let mut game = Game::parse(raw);
+        let name = chapter_name(&settings.chapter_name, &game);
+        make_chapter(&mut game, settings.name.as_deref(), name.clone());
+        let mut pgn = String::new();
+        writer::write_parsed_game(&game, &mut pgn);
+        output.push_str(&pgn);
+        chapters.push((name, pgn));
+    }
+
+    match &settings.output {
+        Some(path) => {
+            fs::write(path, output)?;
+            println!("Output written to {path}");
+        }
+        None => print!("{output}"),
+    }
+    eprintln!("Wrote {} chapters", games.len());
+
+    if let (Some(study), Some(token)) = (&settings.push, &token) {
+        for (name, pgn) in &chapters {
+            lichess::import_chapter(study, token, name, pgn)?;
+        }
+        println!(
+            "Pushed {} chapters to https://lichess.org/study/{study}",
+            chapters.len()
+        );
+    }
+    Ok(())
+}
+
+fn fens(settings: &FenSettings) -> io::Result<()> {
+    let input = read_pgn(Some(&settings.input))?;
+    let mut engine = settings.engine.as_deref().map(Engine::start).transpose()?;
+    if let (Some(engine), Some(path)) = (&mut engine, &settings.tablebase) {
+        engine.set_option("SyzygyPath", path)?;
+    }
+
+    let mut columns = vec!["fen"];
+    if settings.eval {
+        columns.extend(["eval", "best_move"]);
+    }
+    if settings.tablebase.is_some() {
+        columns.push("wdl");
+    }
+    if settings.classify {
+        columns.extend(["phase", "endgame", "static_eval", "quiescence"]);
+    }
+    let mut output: Box<dyn Write> = match &settings.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout()),
+    };
+    writeln!(output, "{}", columns.join("\t"))?;
+
+    let (mut analysed, mut skipped) = (0, 0);
+    let go = format!("movetime {}", settings.movetime);
+    for (line_number, position) in fen_lines(&input) {
+        let position = match position {
+            Ok(position) => position,
+            Err(error) => {
+                eprintln!("Warning: line {line_number}, {error}");
+                skipped += 1;
+                continue;
+            }
+        };
+        let fen = position.to_fen();
+        let mut row = vec![fen.clone()];
+
+        if let Some(engine) = &mut engine {
+            engine.new_game()?;
+            let search = engine.search(Some(&fen), &[], &go)?;
+            if settings.eval {
+                let eval = search.score.map(|score| white_centipawns(&position, score));
+                row.push(eval.map_or("-".to_string(), |eval| eval.to_string()));
+                row.push(search.best_move.clone().unwrap_or_else(|| "-".to_string()));
+            }
+            if settings.tablebase.is_some() {
+                let wdl = Wdl::of(&position, &search);
+                row.push(wdl.map_or("-", Wdl::name).to_string());
+            }
+        }
+        if settings.classify {
+            let class = PositionClass::of(&position);
+            row.push(class.phase.name().to_string());
+            row.push(class.endgame.unwrap_or_else(|| "-".to_string()));
+            row.push(class.static_eval.to_string());
+            row.push(class.quiescence.name().to_string());
+        }
+
+        writeln!(output, "{}", row.join("\t"))?;
+        analysed += 1;
+    }
+    output.flush()?;
+
+    eprintln!("Analysed {analysed} positions, skipped {skipped} invalid lines");
+    if let Some(output_path) = &settings.output {
+        println!("Output written to {output_path}");
+    }
+
+    Ok(())
+}
+
+fn gen(settings: &GenSettings, backend: BackendKind) -> io::Result<()> {
+    let mut book = None;
+    if let Some(path) = &settings.book {
+        let mut processor = processor_on(backend);
+        let mut tree = OpeningTree::new(settings.book_depth);
+        for game in split_games(&read_pgn(Some(path))?) {
+            if game.tag("FEN").is_some() {
+                continue;
+            }
+            let moves = processor.process_game(&game);
+            processor.take_warnings();
+            if !processor.is_corrupt() {
+                tree.add_game(&moves, game.tag("Result").unwrap_or("*"));
+            }
+        }
+        book = Some(tree);
+    }
+
+    let seed = settings.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    });
+    eprintln!("Generating {} games with seed {seed}", settings.games);
+    let mut rng = Rng::new(seed);
+
+    let mut output: Box<dyn Write> = match &settings.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout()),
+    };
+    for round in 1..=settings.games {
+        let game = random_game(&mut rng, book.as_ref(), settings.max_plies);
+        let tags: Vec<(String, String)> = [
+            ("Event", "Random game".to_string()),
+            ("Site", "?".to_string()),
+            ("Date", "????.??.??".to_string()),
+            ("Round", round.to_string()),
+            ("White", "Random".to_string()),
+            ("Black", "Random".to_string()),
+            ("Result", game.result.to_string()),
+            ("Termination", game.termination.to_string()),
+        ]
+        .map(|(tag, value)| (tag.to_string(), value))
+        .to_vec();
+
         let mut pgn = String::new();
-        for line in reader.lines() {
-            pgn.push_str(&line?);
-            pgn.push(' ');
+        writer::write_game(
+            &tags,
+            &format!("{} {}", san_movetext(&game.moves), game.result),
+            &mut pgn,
+        );
+        output.write_all(pgn.as_bytes())?;
+    }
+    output.flush()?;
+
+    if let Some(output_path) = &settings.output {
+        println!("Output written to {output_path}");
+    }
+
+    Ok(())
+}
+
+fn perft(
+    depth: usize,
+    fen: Option<&str>,
+    divide: bool,
+    check_crate: bool,
+    backend: BackendKind,
+) -> io::Result<()> {
+    let position = match fen {
+        Some(fen) => Position::from_fen(fen).map_err(|message| invalid_input(&message))?,
+        None => Position::initial(),
+    };
+    // The chess crate's board only starts from the initial position
+    if check_crate && position != Position::initial() {
+        return Err(invalid_input(
+            "--check-crate only applies to the initial position",
+        ));
+    }
+
+    let started = Instant::now();
+    let nodes = if divide {
+        let moves = perft::backend_divide(backend, &position, depth)
+            .map_err(|message| invalid_input(&message))?;
+        for (mv, nodes) in &moves {
+            println!("{mv}: {nodes}");
         }
-        pgn
+        moves.iter().map(|(_, nodes)| nodes).sum()
     } else {
-        // Read from stdin
-        println!("Enter PGN (press Ctrl+D when done):");
-        let stdin = io::stdin();
+        perft::backend_perft(backend, &position, depth)
+            .map_err(|message| invalid_input(&message))?
+    };
+    println!(
+        "Perft {depth}: {nodes} nodes on the {} board in {:.2}s",
+        backend.create().name(),
+        started.elapsed().as_secs_f64()
+    );
+
+    let mut failed = false;
+    match perft::known_nodes(&position, depth) {
+        Some((name, expected)) if expected == nodes => {
+            println!("Matches the published value for {name}")
+        }
+        Some((name, expected)) => {
+            println!("MISMATCH: the published value for {name} is {expected}");
+            failed = true;
+        }
+        None => {}
+    }
+
+    if check_crate {
+        let check = perft::check_crate(depth);
+        for mismatch in &check.mismatches {
+            println!("Crate mismatch {mismatch}");
+        }
+        println!(
+            "Compared the chess crate on {} positions: {}",
+            check.positions,
+            if check.mismatches.is_empty() {
+                "no mismatches"
+            } else {
+                "mismatches found"
+            }
+        );
+        failed |= !check.mismatches.is_empty();
+    }
+
+    if failed {
+        return Err(io::Error::other("perft verification failed"));
+    }
+    Ok(())
+}
+
+fn prep(settings: &PrepSettings, backend: BackendKind) -> io::Result<()> {
+    let games = split_games(&read_pgn(Some(&settings.input))?);
+    let opponent_white = !settings.my_color_white;
+    let opponent_tag = if opponent_white { "White" } else { "Black" };
+
+    let mut recent: Vec<&RawGame> = games
+        .iter()
+        .filter(|game| {
+            game.tag(opponent_tag)
+                .is_some_and(|name| name_matches(&settings.opponent, name))
+        })
+        .collect();
+    // Most recent first, undated games last
+    recent.sort_by_key(|game| Reverse(game_date(game).map(|date| date.earliest())));
+    recent.truncate(settings.recent);
+
+    let mut processor = processor_on(backend);
+    let mut tree = OpeningTree::new(settings.depth);
+    let mut studied: Vec<(&RawGame, Vec<String>)> = Vec::new();
+    for game in recent {
+        if game.tag("FEN").is_some() {
+            continue;
+        }
+        let moves = processor.process_game(game);
+        processor.take_warnings();
+        if processor.is_corrupt() {
+            continue;
+        }
+        let result = game
+            .tag("Result")
+            .or_else(|| game.termination())
+            .unwrap_or("*");
+        tree.add_game(&moves, result);
+        studied.push((game, moves));
+    }
+
+    let dates = match (studied.last(), studied.first()) {
+        (Some((first, _)), Some((last, _))) => first.tag("Date").zip(last.tag("Date")),
+        _ => None,
+    };
+    let mut report = report_heading(&settings.opponent, opponent_white, &tree, dates);
+    report.push_str(&tree_report(&tree, opponent_white, settings.min_games));
+
+    // The most recent game of each main line
+    let mut output = String::new();
+    for line in main_lines(&tree, settings.min_games) {
+        let line: Vec<String> = line.split(' ').map(str::to_string).collect();
+        if let Some((game, _)) = studied.iter().find(|(_, moves)| moves.starts_with(&line)) {
+            writer::write_parsed_game(&Game::parse(game), &mut output);
+        }
+    }
+
+    match &settings.output {
+        Some(path) => fs::write(path, output)?,
+        None => print!("{output}"),
+    }
+    eprint!("{report}");
+    Ok(())
+}
+
+fn screen(settings: &ScreenSettings, backend: BackendKind) -> io::Result<()> {
+    let games = split_games(&read_pgn(Some(&settings.input))?);
+    let mut processor = processor_on(backend);
+    let mut engine = settings.engine.as_deref().map(Engine::start).transpose()?;
+    let mut screen = CheatScreen::new();
+
+    for game in &games {
+        let moves = processor.process_game(game);
+        processor.take_warnings();
+        if processor.is_corrupt() {
+            continue;
+        }
+        let parsed = Game::parse(game);
+
+        let losses = centipawn_losses(&mainline_evals(&parsed.moves));
+        let clocks: Vec<Option<f64>> = parsed
+            .moves
+            .moves
+            .iter()
+            .map(|node| {
+                node.comments
+                    .iter()
+                    .find_map(|comment| clock_annotation(comment))
+            })
+            .collect();
+        let times = game
+            .tag("TimeControl")
+            .and_then(base_and_increment)
+            .map(|(base, increment)| move_times(&clocks, base, increment))
+            .unwrap_or_default();
+
+        // Moves compared with the engine and matches, for each side
+        let mut agreement = [(0, 0); 2];
+        if let Some(engine) = &mut engine {
+            engine.new_game()?;
+            let go = format!("movetime {}", settings.movetime);
+            for ply in settings.skip_plies..moves.len() {
+                let search = engine.search(game.tag("FEN"), &moves[..ply], &go)?;
+                agreement[ply % 2].0 += 1;
+                if search.best_move.as_ref() == Some(&moves[ply]) {
+                    agreement[ply % 2].1 += 1;
+                }
+            }
+        }
+
+        for (side, tag) in ["White", "Black"].into_iter().enumerate() {
+            let player = screen.player(game.tag(tag).unwrap_or("?"));
+            player.games += 1;
+            player.engine_moves += agreement[side].0;
+            player.engine_matches += agreement[side].1;
+            player.losses.extend(&losses[side]);
+            player.times.extend(&times[side]);
+        }
+    }
+
+    let percent = |value: Option<f64>, scale: f64| {
+        value.map_or("-".to_string(), |value| format!("{:.1}", value * scale))
+    };
+    println!("player\tgames\tmoves\tengine_match\tavg_loss\ttime_variation\tflags");
+    let results = screen.results(settings.min_moves);
+    for result in &results {
+        let flags: Vec<&str> = result.flags.iter().map(|flag| flag.name()).collect();
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            result.name,
+            result.player.games,
+            result.player.moves(),
+            percent(result.player.match_rate(), 100.0),
+            percent(result.player.average_loss(), 1.0),
+            result
+                .player
+                .time_variation()
+                .map_or("-".to_string(), |variation| format!("{variation:.2}")),
+            flags.join(", "),
+        );
+    }
+    let flagged = results
+        .iter()
+        .filter(|result| !result.flags.is_empty())
+        .count();
+    eprintln!(
+        "{flagged} of {} players flagged for review; flags are statistical outliers, not proof",
+        results.len()
+    );
+
+    Ok(())
+}
+
+fn test_suite(engine_command: &str, suite_path: &str, movetime: u32) -> io::Result<()> {
+    let mut engine = Engine::start(engine_command)?;
+    let suite = read_pgn(Some(suite_path))?;
+    let (mut solved, mut total) = (0, 0);
+
+    for (line_index, line) in suite.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = match EpdRecord::parse(line) {
+            Ok(record) => record,
+            Err(error) => {
+                eprintln!("Warning: line {}, {error}", line_index + 1);
+                continue;
+            }
+        };
+        let id = record
+            .id()
+            .map_or_else(|| format!("line {}", line_index + 1), str::to_string);
+
+        engine.new_game()?;
+        let search = engine.search(
+            Some(&record.position.to_fen()),
+            &[],
+            &format!("movetime {movetime}"),
+        )?;
+        let mv = search.best_move.unwrap_or_else(|| "(none)".to_string());
+        let Some(is_solved) = record.solved_by(&mv) else {
+            eprintln!("Warning: {id} has no bm or am operation");
+            continue;
+        };
+
+        total += 1;
+        if is_solved {
+            solved += 1;
+        }
+        println!(
+            "{id}: {} ({mv})",
+            if is_solved { "solved" } else { "failed" }
+        );
+    }
+
+    println!("Solved {solved}/{total} positions with {}", engine.name);
+    Ok(())
+}
+
+fn selfplay(settings: &SelfplaySettings) -> io::Result<()> {
+    let mut first = Engine::start(&settings.engines[0])?;
+    let mut second = Engine::start(&settings.engines[1])?;
+    let mut output: Box<dyn Write> = match &settings.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout()),
+    };
+
+    for round in 1..=settings.games {
+        // Engines swap colors every game
+        let (white, black, time_controls) = if round % 2 == 1 {
+            (&mut first, &mut second, settings.time_controls)
+        } else {
+            let [first_control, second_control] = settings.time_controls;
+            (&mut second, &mut first, [second_control, first_control])
+        };
+        let game = play_game(white, black, time_controls, settings.max_plies)?;
+        eprintln!(
+            "Game {round}: {} - {} {} ({})",
+            game.white, game.black, game.result, game.termination
+        );
+
+        let mut tags = vec![
+            ("Event".to_string(), "Selfplay".to_string()),
+            ("Site".to_string(), "?".to_string()),
+            ("Date".to_string(), "????.??.??".to_string()),
+            ("Round".to_string(), round.to_string()),
+            ("White".to_string(), game.white),
+            ("Black".to_string(), game.black),
+            ("Result".to_string(), game.result.to_string()),
+        ];
+        if time_controls[0] == time_controls[1] {
+            tags.push(("TimeControl".to_string(), time_controls[0].tag()));
+        } else {
+            tags.push(("WhiteTimeControl".to_string(), time_controls[0].tag()));
+            tags.push(("BlackTimeControl".to_string(), time_controls[1].tag()));
+        }
+        tags.push(("Termination".to_string(), game.termination.to_string()));
+
         let mut pgn = String::new();
-        for line in stdin.lock().lines() {
-            pgn.push_str(&line?);
-            pgn.push(' ');
+        writer::write_game(
+            &tags,
+            &format!("{} {}", game.moves.join(" "), game.result),
+            &mut pgn,
+        );
+        output.write_all(pgn.as_bytes())?;
+    }
+    output.flush()?;
+
+    if let Some(output_path) = &settings.output {
+        println!("Output written to {output_path}");
+    }
+
+    Ok(())
+}
+
+fn clean(settings: &CleanSettings) -> io::Result<()> {
+    let input = read_pgn(settings.input.as_deref())?;
+    let mut output: Box<dyn Write> = match &settings.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout()),
+    };
+
+    let mut games = split_games(&input);
+    let (mut engine_comments, mut foreign_comments, mut names) = (0, 0, 0);
+    let mut disambiguated = 0;
+    if settings.fix_disambiguation {
+        for raw in &mut games {
+            disambiguated += disambiguation::normalize(raw, &disambiguation::lint_game(raw));
+        }
+    }
+    let mut deduplicator = settings.dedupe.map(Deduplicator::new);
+    let (mut pv_normalized, mut pv_illegal) = (0, 0);
+    for (index, raw) in games.iter().enumerate() {
+        let mut game = Game::parse(raw);
+        let key = duplicate_key(&game);
+        if settings.verify_pv {
+            let start = match game.tag("FEN") {
+                Some(fen) => Position::from_fen(fen),
+                None => Ok(Position::initial()),
+            };
+            if let Ok(start) = start {
+                let mut issues = Vec::new();
+                pv_normalized += verify_pvs(&mut game.moves, start, 0, &mut issues);
+                pv_illegal += issues.len();
+                for issue in issues {
+                    eprintln!(
+                        "Warning: game {}, ply {}, illegal engine line {{{}}}: {}",
+                        index + 1,
+                        issue.ply,
+                        issue.comment,
+                        issue.error
+                    );
+                }
+            }
+        }
+        if settings.strip_engine_comments {
+            engine_comments += strip_engine_comments(&mut game.moves);
         }
-        pgn
+        if let Some(language) = &settings.comment_language {
+            foreign_comments += retain_language(&mut game.moves, language);
+        }
+        if settings.transliterate_names {
+            names += transliterate_players(&mut game);
+        }
+        if settings.accuracy {
+            let accuracy = GameAccuracy::of(&mainline_evals(&game.moves));
+            game.tags
+                .retain(|tag| tag.name != "WhiteAccuracy" && tag.name != "BlackAccuracy");
+            game.tags
+                .extend(accuracy.tags().into_iter().map(|(name, value)| TagPair {
+                    name,
+                    value: value.into(),
+                }));
+        }
+        if let Some(redaction) = &settings.redact {
+            redaction.redact_game(&mut game);
+        }
+        settings.tag_selection.select_game(&mut game);
+
+        match &mut deduplicator {
+            Some(deduplicator) => deduplicator.add(key, game),
+            None => {
+                let mut pgn = String::new();
+                writer::write_parsed_game(&game, &mut pgn);
+                output.write_all(pgn.as_bytes())?;
+            }
+        }
+    }
+    // Duplicates are written once all copies have been seen
+    let (mut duplicates, mut merged_comments) = (0, 0);
+    if let Some(deduplicator) = deduplicator {
+        (duplicates, merged_comments) = (deduplicator.duplicates, deduplicator.merged_comments);
+        for game in deduplicator.into_games() {
+            let mut pgn = String::new();
+            writer::write_parsed_game(&game, &mut pgn);
+            output.write_all(pgn.as_bytes())?;
+        }
+    }
+    output.flush()?;
+
+    if settings.strip_engine_comments {
+        eprintln!("Removed engine output from {engine_comments} comments");
+    }
+    if let Some(language) = &settings.comment_language {
+        eprintln!("Removed {foreign_comments} comments not in {language}");
+    }
+    if settings.transliterate_names {
+        eprintln!("Transliterated {names} player names");
+    }
+    if settings.fix_disambiguation {
+        eprintln!("Rewrote {disambiguated} over-disambiguated moves");
+    }
+    if settings.verify_pv {
+        eprintln!("Rewrote {pv_normalized} engine lines, {pv_illegal} illegal");
+    }
+    if let Some(policy) = settings.dedupe {
+        eprintln!("Removed {duplicates} duplicate games");
+        if policy == ConflictPolicy::MergeComments {
+            eprintln!("Merged {merged_comments} comments from duplicates");
+        }
+    }
+    eprintln!("Cleaned {} games", games.len());
+
+    Ok(())
+}
+
+fn diff(left_path: &str, right_path: &str) -> io::Result<()> {
+    let left_games = split_games(&read_pgn(Some(left_path))?);
+    let right_games = split_games(&read_pgn(Some(right_path))?);
+
+    for difference in diff_databases(&left_games, &right_games) {
+        println!("{difference}");
+    }
+
+    Ok(())
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// A processed game or side line, as it is rendered.
+struct RenderedLine<'a> {
+    tags: &'a [(String, String)],
+    /// The position before each move followed by the final one.
+    positions: &'a [Position],
+    moves: &'a [String],
+    result: &'a str,
+    /// Leading moves found in the Polyglot book, when one is probed.
+    plies_in_book: Option<usize>,
+}
+
+/// Appends a game to the output, or its new positions in unique-positions mode.
+fn render_game(
+    options: &cli::Options,
+    line: RenderedLine,
+    unique_positions: &mut UniquePositions,
+    output: &mut String,
+) {
+    let RenderedLine {
+        tags,
+        positions,
+        moves,
+        result,
+        plies_in_book,
+    } = line;
+    let mirrored;
+    let (tags, positions, moves, result) = if options.mirror {
+        let Some(start) = positions.first() else {
+            return;
+        };
+        mirrored = (
+            mirror_tags(tags, start),
+            positions.iter().map(mirror_position).collect::<Vec<_>>(),
+            moves
+                .iter()
+                .map(|mv| mirror_move(mv).unwrap_or_else(|| mv.clone()))
+                .collect::<Vec<_>>(),
+        );
+        (
+            &mirrored.0[..],
+            &mirrored.1[..],
+            &mirrored.2[..],
+            mirror_result(result),
+        )
+    } else {
+        (tags, positions, moves, result)
     };
 
-    let mut processor = PgnProcessor::new();
-    let processed_moves = processor.process_pgn(&input);
+    if options.unique_positions {
+        unique_positions.collect(positions, moves, result, output);
+        return;
+    }
+
+    let tags = options.tag_selection.select(tags);
+    let notation = options.format.unwrap_or(&Uci);
+    let movetext = if options.fen_injection.is_enabled() {
+        options.fen_injection.render(positions, moves, notation)
+    } else {
+        let mut written = write_moves(notation, positions, moves);
+        if let Some(plies) = plies_in_book {
+            annotate_book_moves(&mut written, plies);
+        }
+        written.join(notation.separator())
+    };
+    writer::write_game(&tags, &movetext, output);
+}
+
+/// Where the output is written locally: outputs meant for S3 are staged in a temporary file
+/// and uploaded once the run completes.
+fn local_output(options: &cli::Options) -> io::Result<Option<String>> {
+    let Some(output) = options.output.as_deref() else {
+        return Ok(None);
+    };
+    if !output.starts_with("s3://") {
+        return Ok(Some(output.to_string()));
+    }
+
+    #[cfg(feature = "s3")]
+    {
+        if options.resume || options.checkpoint_every.is_some() || options.shard_by.is_some() {
+            return Err(invalid_input(
+                "s3:// outputs cannot be combined with --resume, --checkpoint-every or --shard-by",
+            ));
+        }
+        let staged = pgn_crunker::s3::staging_path(output);
+        Ok(Some(staged.to_string_lossy().into_owned()))
+    }
+    #[cfg(not(feature = "s3"))]
+    Err(invalid_input("s3:// outputs need the s3 feature"))
+}
+
+fn crunch(options: &cli::Options) -> io::Result<()> {
+    interrupt::install();
+    if options.files_from.is_some() && options.input.is_some() {
+        return Err(invalid_input(
+            "--files-from cannot be combined with an input argument",
+        ));
+    }
+    let file_list = options
+        .files_from
+        .as_deref()
+        .map(source::read_file_list)
+        .transpose()?;
+    let input_source = || -> io::Result<Box<dyn source::Source>> {
+        match &file_list {
+            Some(paths) => Ok(Box::new(source::FileList(paths.clone()))),
+            None => source::open_source(options.input.as_deref()),
+        }
+    };
+    let from_stdin =
+        file_list.is_none() && options.input.as_deref().is_none_or(|input| input == "-");
+
+    let output = local_output(options)?;
+    let checkpoint_path = output.as_deref().map(Checkpoint::path_for);
+    let resume_from = if options.resume {
+        if from_stdin || options.sample.is_some() || options.unique_positions {
+            return Err(invalid_input(
+                "--resume needs a file input and cannot be combined with --sample or --unique-positions",
+            ));
+        }
+        let path = checkpoint_path
+            .as_deref()
+            .ok_or_else(|| invalid_input("--resume needs an output file"))?;
+        Some(Checkpoint::load(path)?)
+    } else {
+        None
+    };
+    let mut known_games = if options.update {
+        let writes_moves = matches!(
+            options.format.map(|format| format.name()),
+            None | Some("uci") | Some("san")
+        );
+        if options.resume
+            || options.shard_by.is_some()
+            || options.unique_positions
+            || options.mirror
+            || options.redact.is_some()
+            || options.line == LineSelection::Tree
+            || !writes_moves
+        {
+            return Err(invalid_input(
+                "--update cannot be combined with --resume, --shard-by, --unique-positions, --mirror, --redact, --line tree or a --format other than uci and san",
+            ));
+        }
+        let path = options
+            .output
+            .as_deref()
+            .filter(|path| !path.starts_with("s3://"))
+            .ok_or_else(|| invalid_input("--update needs a local output file"))?;
+        match fs::read(path) {
+            Ok(bytes) => Some(
+                KnownGames::load(&bytes, options.backend)
+                    .map_err(|message| invalid_input(&message))?,
+            ),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Some(KnownGames::new()),
+            Err(error) => return Err(error),
+        }
+    } else {
+        None
+    };
+    let mut already_present = 0;
+
+    let base_offset = resume_from.map_or(0, |checkpoint| checkpoint.input_offset);
+    let base_games = resume_from.map_or(0, |checkpoint| checkpoint.games);
+
+    if options.two_pass {
+        let conflicting = options.resume
+            || options.checkpoint_every.is_some()
+            || options.source_tags
+            || options
+                .input_format
+                .is_some_and(|format| format != InputFormat::Pgn);
+        if from_stdin || conflicting {
+            return Err(invalid_input(
+                "--two-pass needs a PGN or SAN file input and cannot be combined with checkpoints or --source-tags",
+            ));
+        }
+    }
+    // The second pass of --two-pass reads the games it needs on its own, and other PGN inputs
+    // are split as they are read, unless sampling has to see all their games first
+    let mut stream = None;
+    let mut detected_format = None;
+    let (input, source_map) = if options.two_pass {
+        (String::new(), None)
+    } else if options.source_tags {
+        let (input, map) = match &file_list {
+            Some(paths) => source::read_files_with_map(paths, base_offset)?,
+            None => source::read_with_map(options.input.as_deref(), base_offset)?,
+        };
+        (input, Some(map))
+    } else {
+        let source = input_source()?;
+        let mut reader = BufReader::new(source.open(base_offset as u64)?);
+        detected_format = match options.input_format {
+            Some(format) => Some(format),
+            None if from_stdin => InputFormat::detect_prefix(reader.fill_buf()?),
+            None => Some(InputFormat::Pgn),
+        };
+        let pgn = matches!(detected_format, Some(InputFormat::Pgn | InputFormat::San));
+        let mut input = String::new();
+        if pgn && options.sample.is_none() {
+            stream = Some(GameReader::new(reader, false));
+        } else {
+            reader.read_to_string(&mut input)?;
+        }
+        (input, None)
+    };
+    let input_format = options
+        .input_format
+        .or(detected_format)
+        .or_else(|| from_stdin.then(|| InputFormat::detect(&input)))
+        .unwrap_or(InputFormat::Pgn);
+    if resume_from.is_some() && matches!(input_format, InputFormat::Uci | InputFormat::FenMoves) {
+        return Err(invalid_input("--resume needs a PGN or SAN input"));
+    }
+    if options.source_tags && matches!(input_format, InputFormat::Uci | InputFormat::FenMoves) {
+        return Err(invalid_input("--source-tags needs a PGN or SAN input"));
+    }
+    let start_position = options
+        .start_fen
+        .as_deref()
+        .map(Position::from_fen)
+        .transpose()
+        .map_err(|message| invalid_input(&format!("Invalid --start-fen: {message}")))?;
+    let start_fen = start_position.map(|position| position.to_fen());
+    let input = input_format
+        .to_pgn_from(input, &start_position.unwrap_or_else(Position::initial))
+        .map_err(|message| invalid_input(&message))?;
+
+    if options.line == LineSelection::Tree
+        && (options.unique_positions || options.fen_injection.is_enabled())
+    {
+        return Err(invalid_input(
+            "--line tree cannot be combined with --unique-positions or FEN comments",
+        ));
+    }
+    if options.quiet_positions && !options.unique_positions && options.features.is_none() {
+        return Err(invalid_input(
+            "--quiet-positions needs --unique-positions or --features",
+        ));
+    }
+
+    // Input numbers of the games kept by the first pass of --two-pass
+    let mut game_numbers = Vec::new();
+    let mut games = if options.two_pass {
+        let source = input_source()?;
+        let (mut games, numbers, scanned) =
+            two_pass::scan_matching(source.as_ref(), |game| options.filter.matches(game))?;
+        game_numbers = numbers;
+        eprintln!(
+            "First pass: {} of {scanned} games pass the header filters",
+            games.len()
+        );
+        two_pass::read_games(source.as_ref(), &mut games)?;
+        games
+    } else if stream.is_some() {
+        Vec::new()
+    } else if options.header_scan {
+        scan_headers(&input)
+    } else {
+        split_games(&input)
+    };
+    let mut processor = processor_on(options.backend);
+    if options.trace {
+        processor.set_trace_hook(|line| eprintln!("{line}"));
+    }
+    processor.set_check_invariants(options.check_invariants);
+    processor.set_repair(options.repair);
+    processor.set_recover(options.recover);
+    if let Some(ply_limit) = options.ply_limit {
+        processor.set_ply_limit(ply_limit);
+    }
+    processor.set_lenient_captures(options.lenient_captures);
+    processor.set_input_notation(options.input_notation);
+    let mut unique_positions = UniquePositions::new();
+    unique_positions.set_quiet_only(options.quiet_positions);
+    let mut heatmaps = Heatmaps::new();
+    let mut capture_stats = CaptureStats::new();
+    let mut castling_stats = CastlingStats::new();
+    let mut vocabulary = Vocabulary::new();
+    let mut accuracy_report = AccuracyReport::new();
+    let mut rating_estimates = RatingEstimates::new();
+    let mut structure_groups = StructureGroups::new();
+    let eco_classifier = EcoClassifier::new();
+
+    let mut shards = match options.shard_by {
+        Some(_) => {
+            if options.resume || options.checkpoint_every.is_some() {
+                return Err(invalid_input(
+                    "--shard-by cannot be combined with checkpoints",
+                ));
+            }
+            let directory = options
+                .output
+                .as_deref()
+                .ok_or_else(|| invalid_input("--shard-by needs an output directory"))?;
+            Some(ShardWriter::new(directory)?)
+        }
+        None => None,
+    };
+
+    let output_file = match &output {
+        Some(_) if shards.is_some() => None,
+        Some(output_path) => match resume_from {
+            Some(checkpoint) => {
+                // Drop whatever was written after the checkpoint
+                let mut file = OpenOptions::new().write(true).open(output_path)?;
+                file.set_len(checkpoint.output_len)?;
+                file.seek(SeekFrom::End(0))?;
+                Some(BufWriter::new(file))
+            }
+            None if options.update => Some(BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(output_path)?,
+            )),
+            None => Some(BufWriter::new(File::create(output_path)?)),
+        },
+        None => None,
+    };
+    let queue_cap = options
+        .max_queued_mb
+        .map_or(output_queue::DEFAULT_CAP, |megabytes| megabytes << 20);
+    let mut output_file = output_file.map(|file| OutputQueue::spawn(file, queue_cap));
+    let mut last_checkpoint = 0;
+
+    let mut feature_output = match &options.features {
+        Some(path) => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            features::write_header(&mut writer)?;
+            Some(writer)
+        }
+        None => None,
+    };
+
+    let mut token_output = match &options.san_tokens {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+
+    let mut lifetime_stats = LifetimeStats::new();
+    let mut lifetime_output = match &options.piece_lifetimes {
+        Some(path) if path.ends_with(".jsonl") => Some(BufWriter::new(File::create(path)?)),
+        _ => None,
+    };
+
+    let mut material_output = match &options.material {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+
+    let mut color_split_output = match &options.color_split {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+
+    let mut move_table_output = match &options.move_table {
+        Some(path) => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            move_table::write_header(&mut writer, path.ends_with(".txt"))?;
+            Some(writer)
+        }
+        None => None,
+    };
+
+    #[cfg(feature = "tensors")]
+    let mut tensor_output = match &options.tensor_output {
+        Some(path) => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            pgn_crunker::tensor_export::write_header(&mut writer)?;
+            Some(writer)
+        }
+        None => None,
+    };
+
+    let candidates: Vec<usize> = (0..games.len())
+        .filter(|index| options.filter.matches(&games[*index]))
+        .collect();
+    let selected = match options.sample {
+        Some(count) => {
+            let (seed, origin) = match (options.seed, options.canonical) {
+                (Some(seed), _) => (seed, ""),
+                (None, true) => (0, ", the --canonical default"),
+                (None, false) => (
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |elapsed| elapsed.as_nanos() as u64),
+                    ", from the clock",
+                ),
+            };
+            eprintln!("Sampling {count} games with seed {seed}{origin}");
+            sample_indices(&candidates, count, &mut Rng::new(seed))
+        }
+        None => candidates,
+    };
+
+    // Games split as they are read are only counted once they all are
+    let total_selected = stream.is_none().then_some(selected.len());
+    let mut selected = selected.into_iter();
+    // The first game left out by Ctrl-C, with its offset and the number of selected games done
+    // before it
+    let mut stopped_at = None;
+
+    let mut profiler = options.profile_games.map(GameProfiler::new);
+
+    let polyglot = match (&options.polyglot_book, &options.polyglot_keys) {
+        (Some(book), Some(keys)) => Some((
+            PolyglotBook::from_bytes(&fs::read(book)?)
+                .map_err(|message| invalid_input(&message))?,
+            PolyglotKeys::parse(&fs::read_to_string(keys)?)
+                .map_err(|message| invalid_input(&message))?,
+        )),
+        (None, None) => None,
+        _ => {
+            return Err(invalid_input(
+                "--polyglot-book and --polyglot-keys must be given together",
+            ))
+        }
+    };
+    if polyglot.is_some() && options.fen_injection.is_enabled() {
+        return Err(invalid_input(
+            "--polyglot-book cannot be combined with FEN comments",
+        ));
+    }
+    // The 1-based ply of the first move out of the book of each game leaving it
+    let mut book_exits = Vec::new();
 
     println!("Processed moves:");
-    for (i, mv) in processed_moves.iter().enumerate() {
-        if i % 2 == 0 {
-            print!("{}. ", i / 2 + 1);
+    for done in 0.. {
+        let (game_index, mut game) = match &mut stream {
+            Some(stream) => {
+                let next = stream.find(|game| {
+                    game.as_ref()
+                        .map_or(true, |game| options.filter.matches(game))
+                });
+                match next {
+                    Some(game) => (stream.games() - 1, game?),
+                    None => break,
+                }
+            }
+            None => match selected.next() {
+                Some(game_index) => (
+                    game_index,
+                    std::mem::replace(&mut games[game_index], RawGame::new(0)),
+                ),
+                None => break,
+            },
+        };
+        if interrupt::interrupted() {
+            stopped_at = Some((game_index, game.offset, done));
+            break;
         }
-        print!("{mv} ");
-        if i % 2 == 1 {
-            println!();
+
+        // All selected games before this one are done
+        if let (Some(every), Some(path), Some(queue)) =
+            (options.checkpoint_every, &checkpoint_path, &mut output_file)
+        {
+            if game_index - last_checkpoint >= every {
+                queue.checkpoint(path, base_offset + game.offset, base_games + game_index)?;
+                last_checkpoint = game_index;
+            }
+        }
+
+        let game = &mut game;
+        let game_number = game_numbers
+            .get(game_index)
+            .map_or(base_games + game_index + 1, |number| *number);
+        if options.header_scan && !options.two_pass && stream.is_none() {
+            load_movetext(&input, game);
+        }
+        let mut output = String::new();
+
+        if let Some(source_map) = &source_map {
+            let start = base_offset + game.offset;
+            if let Some((file, file_start)) = source_map.locate(start) {
+                let file_end = file_start + (game.end - game.offset);
+                game.tags
+                    .retain(|(tag, _)| tag != "SourceFile" && tag != "SourceBytes");
+                game.tags.push(("SourceFile".to_string(), file.to_string()));
+                game.tags.push((
+                    "SourceBytes".to_string(),
+                    format!("{file_start}-{file_end}"),
+                ));
+            }
+        }
+
+        if let Some(fen) = &start_fen {
+            if game.tag("FEN").is_none() {
+                game.tags.retain(|(tag, _)| tag != "SetUp");
+                game.tags.push(("SetUp".to_string(), "1".to_string()));
+                game.tags.push(("FEN".to_string(), fen.clone()));
+            }
+        }
+
+        if options.check_roster {
+            let missing = roster::missing_tags(game);
+            if !missing.is_empty() {
+                eprintln!(
+                    "Game {game_number} is missing roster tags: {}",
+                    missing.join(", ")
+                );
+            }
+        }
+        if options.fix_roster {
+            roster::complete_roster(game);
+        }
+        if options.check_disambiguation || options.fix_disambiguation {
+            let issues = disambiguation::lint_game(game);
+            if options.check_disambiguation {
+                for issue in &issues {
+                    match game.location(issue.offset) {
+                        Some((line, column)) => eprintln!(
+                            "Warning: game {game_number}, line {line}, column {column}, {}",
+                            issue.describe()
+                        ),
+                        None => eprintln!("Warning: game {game_number}, {}", issue.describe()),
+                    }
+                }
+            }
+            if options.fix_disambiguation {
+                disambiguation::normalize(game, &issues);
+            }
+        }
+
+        let started = Instant::now();
+        let processed_moves = match options.line {
+            LineSelection::Longest => match game.tag("FEN") {
+                Some(fen) => processor.process_line_from(fen, &longest_line(&game.movetext)),
+                None => processor.process_line(&longest_line(&game.movetext)),
+            },
+            _ => processor.process_game(game),
+        };
+        if let Some(profiler) = &mut profiler {
+            profiler.record(GameTiming {
+                game_number,
+                plies: processed_moves.len(),
+                movetext_bytes: game.movetext.len(),
+                elapsed: started.elapsed(),
+            });
+        }
+        let warnings = processor.take_warnings();
+        // Tokens of the longest line may come from side lines, which are not located
+        let spans = match options.line {
+            LineSelection::Longest => Vec::new(),
+            _ if warnings.is_empty() => Vec::new(),
+            _ => mainline_spans(&game.movetext),
+        };
+        for warning in warnings {
+            let location = spans
+                .get(warning.token_index)
+                .and_then(|(offset, _)| game.location(*offset));
+            match location {
+                Some((line, column)) => eprintln!(
+                    "Warning: game {game_number}, line {line}, column {column}, {warning}"
+                ),
+                None => eprintln!("Warning: game {game_number}, {warning}"),
+            }
+        }
+        if options.skip_empty_games && processed_moves.is_empty() {
+            continue;
+        }
+        if processor.ply_limit_exceeded() {
+            eprintln!(
+                "Game {game_number} rejected: longer than {} plies",
+                processor.ply_limit()
+            );
+            continue;
+        }
+        if processor.is_corrupt() {
+            eprintln!("Game {game_number} rejected as corrupt");
+            continue;
+        }
+        if !options.filter.matches_plies(processed_moves.len()) {
+            continue;
+        }
+        if let Some(known_games) = &mut known_games {
+            if !known_games.insert(game_hash(&game.tags, &processed_moves)) {
+                already_present += 1;
+                continue;
+            }
+        }
+
+        if options.check_openings || options.fix_openings {
+            let entry = match game.tag("FEN") {
+                Some(_) => None,
+                None => eco_classifier.classify(processor.positions()),
+            };
+            if let Some(entry) = entry {
+                let mismatched = eco::mismatched_tags(game, entry);
+                if options.check_openings && !mismatched.is_empty() {
+                    let found: Vec<String> = mismatched
+                        .iter()
+                        .map(|tag| format!("{tag} \"{}\"", game.tag(tag).unwrap_or_default()))
+                        .collect();
+                    eprintln!(
+                        "Game {game_number} has {} but its moves are {} ({}-{})",
+                        found.join(" and "),
+                        entry.name,
+                        entry.code,
+                        entry.last
+                    );
+                }
+                if options.fix_openings {
+                    eco::fix_tags(game, entry, &mismatched);
+                }
+            }
+        }
+
+        if options.classify_endgames || options.endgame.is_some() {
+            let class = endgame_class(processor.positions());
+            if let Some(wanted) = &options.endgame {
+                let matches = class
+                    .as_ref()
+                    .is_some_and(|class| class.to_lowercase().contains(&wanted.to_lowercase()));
+                if !matches {
+                    continue;
+                }
+            }
+            if let (true, Some(class)) = (options.classify_endgames, class) {
+                game.tags.retain(|(tag, _)| tag != "Endgame");
+                game.tags.push(("Endgame".to_string(), class));
+            }
+        }
+
+        if options.patterns {
+            let motifs = patterns::detect(processor.positions(), &processed_moves);
+            game.tags.retain(|(tag, _)| tag != "Motifs");
+            if !motifs.is_empty() {
+                let names: Vec<&str> = motifs.iter().map(|motif| motif.name()).collect();
+                game.tags.push(("Motifs".to_string(), names.join(", ")));
+            }
+        }
+
+        if options.tag_sacrifices || options.sacrifice.is_some() {
+            let sacrifices = sacrifices(processor.positions(), &processed_moves);
+            if let Some(filter) = &options.sacrifice {
+                if !filter.matches(&sacrifices) {
+                    continue;
+                }
+            }
+            if options.tag_sacrifices {
+                game.tags.retain(|(tag, _)| tag != "Sacrifices");
+                if !sacrifices.is_empty() {
+                    let labels: Vec<String> = sacrifices.iter().map(Sacrifice::label).collect();
+                    game.tags
+                        .push(("Sacrifices".to_string(), labels.join(", ")));
+                }
+            }
+        }
+
+        if let Some(redaction) = &options.redact {
+            redaction.redact_tags(&mut game.tags);
+        }
+
+        let wants_accuracy = options.accuracy || options.accuracy_report.is_some();
+        let evals = if wants_accuracy || options.estimate_ratings.is_some() {
+            mainline_evals(&Game::parse(game).moves)
+        } else {
+            Vec::new()
+        };
+        if options.estimate_ratings.is_some() {
+            let player = |tag| game.tag(tag).unwrap_or("?");
+            let rating = |tag| game.tag(tag).and_then(|elo| elo.parse().ok());
+            rating_estimates.add_game(
+                game_number,
+                [player("White"), player("Black")],
+                [rating("WhiteElo"), rating("BlackElo")],
+                &evals,
+            );
+        }
+        if wants_accuracy {
+            let accuracy = GameAccuracy::of(&evals);
+            if options.accuracy {
+                game.tags
+                    .retain(|(tag, _)| tag != "WhiteAccuracy" && tag != "BlackAccuracy");
+                game.tags.extend(
+                    accuracy
+                        .tags()
+                        .into_iter()
+                        .map(|(tag, value)| (tag.to_string(), value)),
+                );
+            }
+            let player = |tag| game.tag(tag).unwrap_or("?");
+            accuracy_report.add_game(game_number, player("White"), player("Black"), accuracy);
+        }
+
+        let structure = match options.structure_ply {
+            Some(ply) => processor.position_at(ply),
+            None => processor.positions().last(),
+        }
+        .map(PawnStructure::of);
+        if !options.structures.is_empty() {
+            let matches = structure.is_some_and(|structure| {
+                options
+                    .structures
+                    .iter()
+                    .any(|template| template.matches(&structure))
+            });
+            if !matches {
+                continue;
+            }
+        }
+        if let (Some(_), Some(structure)) = (&options.group_structures, structure) {
+            structure_groups.add(structure);
+        }
+
+        println!("{}", writer::numbered_moves(&processed_moves));
+
+        if options.infer_results
+            && result_inference::is_missing(game.tag("Result"))
+            && result_inference::is_missing(game.termination())
+        {
+            let mainline = mainline_moves(&game.movetext);
+            // Only a game played through to its last move has a final position to go by
+            let inferred = processor
+                .positions()
+                .last()
+                .filter(|_| {
+                    options.line == LineSelection::Mainline
+                        && processed_moves.len() == mainline.len()
+                })
+                .and_then(|last| infer_result(last, mainline.last().copied()));
+            if let Some((inferred, inference)) = inferred {
+                let marker = inferred.marker().to_string();
+                match game.tags.iter_mut().find(|(tag, _)| tag == "Result") {
+                    Some((_, value)) => *value = marker,
+                    None => game.tags.push(("Result".to_string(), marker)),
+                }
+                game.tags.retain(|(tag, _)| tag != INFERRED_TAG);
+                game.tags
+                    .push((INFERRED_TAG.to_string(), inference.name().to_string()));
+            }
+        }
+
+        let plies_in_book = polyglot
+            .as_ref()
+            .map(|(book, keys)| book.plies_in_book(keys, processor.positions(), &processed_moves));
+        if let Some(plies) = plies_in_book {
+            game.tags.retain(|(tag, _)| tag != "OutOfBookPly");
+            if plies < processed_moves.len() {
+                game.tags
+                    .push(("OutOfBookPly".to_string(), (plies + 1).to_string()));
+                book_exits.push(plies + 1);
+            }
+        }
+
+        let result = game
+            .tag("Result")
+            .or_else(|| game.termination())
+            .unwrap_or("*");
+
+        if options.heatmaps.is_some() {
+            heatmaps.add_game(processor.positions(), &processed_moves);
+        }
+        if options.capture_stats.is_some() {
+            let captures = GameCaptures::of(processor.positions(), &processed_moves);
+            capture_stats.add_game(game_number, captures);
+        }
+        if options.castling_stats.is_some() {
+            castling_stats.add_game(&GameCastling::of(processor.positions(), &processed_moves));
+        }
+
+        if let Some(writer) = &mut feature_output {
+            features::write_game(
+                writer,
+                game_number,
+                processor.positions(),
+                result,
+                options.quiet_positions,
+            )?;
+        }
+
+        if token_output.is_some() || options.san_vocabulary.is_some() {
+            let tokens = san_tokens::game_tokens(processor.positions(), &processed_moves);
+            if let (Some(writer), Some(path)) = (&mut token_output, &options.san_tokens) {
+                san_tokens::write_sequence(writer, game_number, &tokens, path.ends_with(".jsonl"))?;
+            }
+            vocabulary.add(&tokens);
+        }
+
+        if options.piece_lifetimes.is_some() {
+            let lives = piece_lives(processor.positions(), &processed_moves);
+            match &mut lifetime_output {
+                Some(writer) => {
+                    let pieces = json::array(lives.iter().map(PieceLife::to_json));
+                    let line =
+                        json::object([("game", game_number.to_string()), ("pieces", pieces)]);
+                    writeln!(writer, "{line}")?;
+                }
+                None => lifetime_stats.add(&lives),
+            }
+        }
+
+        if let Some(writer) = &mut material_output {
+            let trajectory = MaterialTrajectory::of(processor.positions());
+            writeln!(writer, "{}", trajectory.to_json(game_number))?;
+        }
+
+        if let (Some(writer), Some(path)) = (&mut color_split_output, &options.color_split) {
+            let notation = options.format.unwrap_or(&Uci);
+            let written = write_moves(notation, processor.positions(), &processed_moves);
+            ColorSplit::of(processor.positions(), &written).write(
+                writer,
+                game_number,
+                path.ends_with(".jsonl"),
+            )?;
+        }
+
+        if let (Some(writer), Some(path)) = (&mut move_table_output, &options.move_table) {
+            let rows = move_table::game_rows(processor.positions(), &processed_moves);
+            move_table::write_game(writer, game_number, &rows, path.ends_with(".txt"))?;
+        }
+
+        #[cfg(feature = "tensors")]
+        if let Some(writer) = &mut tensor_output {
+            pgn_crunker::tensor_export::write_game(
+                writer,
+                processor.positions(),
+                &processed_moves,
+                result,
+            )?;
+        }
+
+        if options.line == LineSelection::Tree {
+            let mut parsed = Game::parse(game);
+            options.tag_selection.select_game(&mut parsed);
+            let tree = game_json(&parsed, result, &mut processor);
+            for warning in processor.take_warnings() {
+                eprintln!("Warning: game {game_number}, variation {warning}");
+            }
+            output.push_str(&tree);
+            output.push('\n');
+        } else {
+            render_game(
+                options,
+                RenderedLine {
+                    tags: &game.tags,
+                    positions: processor.positions(),
+                    moves: &processed_moves,
+                    result,
+                    plies_in_book,
+                },
+                &mut unique_positions,
+                &mut output,
+            );
+        }
+
+        if options.expand_variations {
+            for variation in variations(&game.movetext) {
+                let line = match game.tag("FEN") {
+                    Some(fen) => processor.process_line_from(fen, &variation.moves),
+                    None => processor.process_line(&variation.moves),
+                };
+                for warning in processor.take_warnings() {
+                    eprintln!("Warning: game {game_number}, variation {warning}");
+                }
+                if line.len() < variation.moves.len() || processor.is_corrupt() {
+                    continue;
+                }
+
+                let branch = variation.branch_ply;
+                let mut tags: Vec<(String, String)> = game
+                    .tags
+                    .iter()
+                    .filter(|(tag, _)| !["Result", "SetUp", "FEN"].contains(&tag.as_str()))
+                    .cloned()
+                    .collect();
+                tags.push(("Result".to_string(), "*".to_string()));
+                // Lines branching off at the start of a set-up game keep its position
+                let set_up = game.tag("FEN").is_some();
+                if let Some(fen) = processor.fen_at(branch).filter(|_| branch > 0 || set_up) {
+                    tags.push(("SetUp".to_string(), "1".to_string()));
+                    tags.push(("FEN".to_string(), fen));
+                }
+                render_game(
+                    options,
+                    RenderedLine {
+                        tags: &tags,
+                        positions: &processor.positions()[branch..],
+                        moves: &line[branch..],
+                        result: "*",
+                        plies_in_book: None,
+                    },
+                    &mut unique_positions,
+                    &mut output,
+                );
+            }
+        }
+
+        if let (Some(shards), Some(shard_by)) = (&mut shards, options.shard_by) {
+            for key in shard_by.keys(game) {
+                shards.write(&key, &output)?;
+            }
         }
+        if let Some(queue) = &mut output_file {
+            queue.push(output)?;
+        }
+    }
+
+    if let Some(profiler) = &mut profiler {
+        eprint!("{}", profiler.report());
+    }
+    if let (Some(_), Some(path)) = (&known_games, &options.output) {
+        eprintln!("Skipped {already_present} games already in {path}");
+    }
+    if polyglot.is_some() && !book_exits.is_empty() {
+        eprintln!(
+            "{} games left the book, at ply {} on average",
+            book_exits.len(),
+            json::decimal(
+                book_exits.iter().sum::<usize>() as f64 / book_exits.len() as f64,
+                1
+            )
+        );
+    }
+
+    if let Some(path) = &options.heatmaps {
+        let rendered = if path.ends_with(".svg") {
+            heatmaps.to_svg()
+        } else {
+            heatmaps.to_json()
+        };
+        fs::write(path, rendered)?;
+        println!("Heatmaps written to {path}");
+    }
+
+    if let Some(path) = &options.capture_stats {
+        fs::write(path, capture_stats.to_json())?;
+        println!("Capture statistics written to {path}");
+    }
+
+    if let Some(path) = &options.accuracy_report {
+        fs::write(path, accuracy_report.to_json())?;
+        println!("Accuracy report written to {path}");
+    }
+
+    if let Some(path) = &options.estimate_ratings {
+        fs::write(path, rating_estimates.to_json())?;
+        println!("Rating estimates written to {path}");
     }
-    if processed_moves.len() % 2 == 1 {
-        println!();
+
+    if let Some(path) = &options.castling_stats {
+        fs::write(path, castling_stats.to_json())?;
+        println!("Castling statistics written to {path}");
+    }
+
+    if let (Some(path), Some(mut writer)) = (&options.features, feature_output) {
+        writer.flush()?;
+        println!("Features written to {path}");
+    }
+
+    if let (Some(path), Some(mut writer)) = (&options.san_tokens, token_output) {
+        writer.flush()?;
+        println!("SAN tokens written to {path}");
+    }
+
+    if let Some(path) = &options.piece_lifetimes {
+        match lifetime_output {
+            Some(mut writer) => writer.flush()?,
+            None => fs::write(path, lifetime_stats.to_tsv())?,
+        }
+        println!("Piece lifetimes written to {path}");
+    }
+
+    if let (Some(path), Some(mut writer)) = (&options.material, material_output) {
+        writer.flush()?;
+        println!("Material written to {path}");
+    }
+
+    if let (Some(path), Some(mut writer)) = (&options.color_split, color_split_output) {
+        writer.flush()?;
+        println!("Moves split by color written to {path}");
+    }
+
+    if let (Some(path), Some(mut writer)) = (&options.move_table, move_table_output) {
+        writer.flush()?;
+        println!("Move table written to {path}");
+    }
+
+    if let Some(path) = &options.san_vocabulary {
+        let rendered = if path.ends_with(".json") {
+            vocabulary.to_json()
+        } else {
+            vocabulary.to_text()
+        };
+        fs::write(path, rendered)?;
+        println!(
+            "Vocabulary of {} SAN tokens written to {path}",
+            vocabulary.len()
+        );
+    }
+
+    if let Some(path) = &options.group_structures {
+        fs::write(path, structure_groups.to_tsv())?;
+        println!("Pawn structures written to {path}");
+    }
+
+    if let Some(mut shards) = shards {
+        shards.flush()?;
+        if let Some(output_path) = &options.output {
+            println!("Output written to {output_path}");
+        }
+    }
+
+    if let Some(mut queue) = output_file {
+        if let (Some(_), Some(path)) = (options.checkpoint_every, &checkpoint_path) {
+            let (input_offset, games_done) = match (stopped_at, &stream) {
+                (Some((game_index, offset, _)), _) => (offset, game_index),
+                (None, Some(stream)) => (stream.consumed(), stream.games()),
+                (None, None) => (input.len(), games.len()),
+            };
+            queue.checkpoint(path, base_offset + input_offset, base_games + games_done)?;
+        }
+        queue.finish()?;
+        #[cfg(feature = "s3")]
+        if let (Some(uri), Some(staged)) = (&options.output, &output) {
+            if pgn_crunker::s3::is_s3_uri(uri) {
+                pgn_crunker::s3::upload(std::path::Path::new(staged), uri)?;
+            }
+        }
+        if let Some(output_path) = &options.output {
+            println!("Output written to {output_path}");
+        }
     }
 
-    // Write output to file if desired
-    if args.len() > 2 {
-        let mut output_file = File::create(&args[2])?;
-        writeln!(output_file, "{}", processed_moves.join(" "))?;
-        println!("Output written to {}", &args[2]);
+    if let Some((game_index, _, done)) = stopped_at {
+        let total = total_selected.map_or(String::new(), |total| format!(" of {total}"));
+        eprintln!(
+            "Interrupted before game {}: {done}{total} selected games processed, output flushed",
+            game_numbers
+                .get(game_index)
+                .map_or(base_games + game_index + 1, |number| *number)
+        );
+        process::exit(interrupt::EXIT_CODE);
     }
 
     Ok(())
@@ -0,0 +1,80 @@
+//! The material of each side after every ply of a game, for studying imbalances over time
+//! without replaying the games.
+
+use chess::legal_moves::misc::Color;
+
+use crate::json;
+use crate::position::{Piece, Position};
+
+/// The pieces counted, kings being always on the board.
+pub const COUNTED_PIECES: [Piece; 5] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+];
+
+/// The material of one side over a game, one entry per position.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SideMaterial {
+    /// Counts of each of [`COUNTED_PIECES`], in that order.
+    pub counts: [Vec<usize>; 5],
+    /// Material value in pawns, as [`Position::material`] counts it.
+    pub points: Vec<u32>,
+}
+
+impl SideMaterial {
+    fn of(positions: &[Position], color: Color) -> Self {
+        let mut side = SideMaterial::default();
+        for position in positions {
+            for (counts, piece) in side.counts.iter_mut().zip(COUNTED_PIECES) {
+                counts.push(position.count(color, piece));
+            }
+            side.points.push(position.material(color));
+        }
+        side
+    }
+
+    fn to_json(&self) -> String {
+        let fields = COUNTED_PIECES
+            .iter()
+            .zip(&self.counts)
+            .map(|(piece, counts)| {
+                let values = json::array(counts.iter().map(usize::to_string));
+                (piece.letter().to_string(), values)
+            })
+            .chain([(
+                "points".to_string(),
+                json::array(self.points.iter().map(u32::to_string)),
+            )]);
+        json::object(fields)
+    }
+}
+
+/// Material of both sides at the start and after each ply.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct MaterialTrajectory {
+    pub white: SideMaterial,
+    pub black: SideMaterial,
+}
+
+impl MaterialTrajectory {
+    /// The trajectory over positions holding the start and the position after each ply.
+    pub fn of(positions: &[Position]) -> Self {
+        MaterialTrajectory {
+            white: SideMaterial::of(positions, Color::White),
+            black: SideMaterial::of(positions, Color::Black),
+        }
+    }
+
+    /// A JSON line of the trajectory, the arrays of each side keyed by piece letter and
+    /// their index being the ply.
+    pub fn to_json(&self, game_number: usize) -> String {
+        json::object([
+            ("game", game_number.to_string()),
+            ("white", self.white.to_json()),
+            ("black", self.black.to_json()),
+        ])
+    }
+}
@@ -0,0 +1,70 @@
+use chess::legal_moves::misc::Square;
+
+use crate::position::{coordinate_move, parse_coordinate_move, CastlingRights, Position};
+
+/// Tags describing the opening, which a mirrored game no longer plays.
+const OPENING_TAGS: [&str; 3] = ["ECO", "Opening", "Variation"];
+
+/// The square on the same file of the opposite rank, a2 for a7.
+pub fn mirror_square(square: Square) -> Square {
+    square ^ 56
+}
+
+/// A coordinate move played by the other color on the mirrored board, `e7e5` for `e2e4`.
+pub fn mirror_move(mv: &str) -> Option<String> {
+    let (from, to, promotion) = parse_coordinate_move(mv)?;
+    Some(coordinate_move(
+        mirror_square(from),
+        mirror_square(to),
+        promotion,
+    ))
+}
+
+/// The position with the board flipped and the colors swapped, the other side to move.
+pub fn mirror_position(position: &Position) -> Position {
+    let mut mirrored = *position;
+    for (square, occupant) in position.squares.iter().enumerate() {
+        mirrored.squares[mirror_square(square as Square) as usize] =
+            occupant.map(|(color, piece)| (!color, piece));
+    }
+    mirrored.side_to_move = !position.side_to_move;
+    let rights = position.castling.0;
+    mirrored.castling = CastlingRights((rights & 0b0011) << 2 | (rights & 0b1100) >> 2);
+    mirrored.en_passant = position.en_passant.map(mirror_square);
+    mirrored
+}
+
+pub fn mirror_result(result: &str) -> &str {
+    match result {
+        "1-0" => "0-1",
+        "0-1" => "1-0",
+        result => result,
+    }
+}
+
+/// Tags of the mirrored game: player tags change color, the result is reversed and the
+/// mirrored starting position is set up, since black now moves first. Opening tags are dropped.
+pub fn mirror_tags(tags: &[(String, String)], start: &Position) -> Vec<(String, String)> {
+    let mut mirrored: Vec<(String, String)> = tags
+        .iter()
+        .filter(|(tag, _)| !OPENING_TAGS.contains(&tag.as_str()) && tag != "SetUp" && tag != "FEN")
+        .map(|(tag, value)| {
+            let tag = if let Some(rest) = tag.strip_prefix("White") {
+                format!("Black{rest}")
+            } else if let Some(rest) = tag.strip_prefix("Black") {
+                format!("White{rest}")
+            } else {
+                tag.clone()
+            };
+            let value = match tag.as_str() {
+                "Result" => mirror_result(value).to_string(),
+                _ => value.clone(),
+            };
+            (tag, value)
+        })
+        .collect();
+
+    mirrored.push(("SetUp".to_string(), "1".to_string()));
+    mirrored.push(("FEN".to_string(), mirror_position(start).to_fen()));
+    mirrored
+}
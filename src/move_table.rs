@@ -0,0 +1,93 @@
+//! A row per ply giving its move in SAN and UCI side by side, for GUIs that take moves as
+//! clicks on squares and show them in SAN. The table is tab-separated, or in fixed-width
+//! columns for readers that slice lines by position.
+
+use std::io::{self, Write};
+
+use chess::legal_moves::misc::Color;
+
+use crate::notation::{write_moves, San, Uci};
+use crate::position::Position;
+
+/// Width of each fixed-width column, the last one included, which the widest SAN and UCI
+/// moves fit.
+const WIDTHS: [usize; 6] = [8, 5, 5, 5, 7, 5];
+
+const COLUMNS: [&str; 6] = ["game", "ply", "move", "color", "san", "uci"];
+
+/// A ply of a game.
+#[derive(Clone, PartialEq)]
+pub struct MoveRow {
+    /// 1-based index of the ply in the game.
+    pub ply: usize,
+    /// The fullmove number, as printed before white's moves.
+    pub move_number: u32,
+    pub color: Color,
+    pub san: String,
+    pub uci: String,
+}
+
+impl MoveRow {
+    fn color_name(&self) -> &'static str {
+        match self.color {
+            Color::White => "white",
+            Color::Black => "black",
+        }
+    }
+}
+
+/// The rows of a game. `positions` holds the position before each move.
+pub fn game_rows(positions: &[Position], moves: &[String]) -> Vec<MoveRow> {
+    let san = write_moves(&San, positions, moves);
+    let uci = write_moves(&Uci, positions, moves);
+    positions
+        .iter()
+        .zip(san.into_iter().zip(uci))
+        .enumerate()
+        .map(|(index, (position, (san, uci)))| MoveRow {
+            ply: index + 1,
+            move_number: position.fullmove_number,
+            color: position.side_to_move,
+            san,
+            uci,
+        })
+        .collect()
+}
+
+fn write_line<W: Write>(writer: &mut W, fields: [&str; 6], fixed_width: bool) -> io::Result<()> {
+    if !fixed_width {
+        return writeln!(writer, "{}", fields.join("\t"));
+    }
+    let padded: Vec<String> = fields
+        .iter()
+        .zip(WIDTHS)
+        .map(|(field, width)| format!("{field:<width$}"))
+        .collect();
+    writeln!(writer, "{}", padded.join(" ").trim_end())
+}
+
+pub fn write_header<W: Write>(writer: &mut W, fixed_width: bool) -> io::Result<()> {
+    write_line(writer, COLUMNS, fixed_width)
+}
+
+pub fn write_game<W: Write>(
+    writer: &mut W,
+    game_number: usize,
+    rows: &[MoveRow],
+    fixed_width: bool,
+) -> io::Result<()> {
+    let game = game_number.to_string();
+    for row in rows {
+        let (ply, number) = (row.ply.to_string(), row.move_number.to_string());
+        let fields = [
+            game.as_str(),
+            &ply,
+            &number,
+            row.color_name(),
+            &row.san,
+            &row.uci,
+        ];
+        write_line(writer, fields, fixed_width)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,546 @@
+use chess::legal_moves::misc::{Color, Square};
+use chess::utils::square_to_string;
+
+use crate::position::{coordinate_move, parse_coordinate_move, Piece, Position};
+use crate::san::{to_san, SanMove};
+
+/// The notation of the moves in the input movetext, besides SAN.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum InputNotation {
+    /// SAN, also accepting long algebraic moves such as `Ng1-f3`.
+    #[default]
+    Algebraic,
+    /// ICCF numeric notation used in correspondence chess, such as `5254` for e2-e4.
+    Iccf,
+    /// English descriptive notation of historic games, such as `P-K4`, `N-KB3` or `QxP`.
+    Descriptive,
+}
+
+impl InputNotation {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "san" | "algebraic" => Some(InputNotation::Algebraic),
+            "iccf" => Some(InputNotation::Iccf),
+            "descriptive" => Some(InputNotation::Descriptive),
+            _ => None,
+        }
+    }
+
+    /// Rewrites a move token of this notation as SAN, or as long algebraic notation when it is
+    /// not legal in `position` so that the error reports the squares. Tokens that are not in
+    /// this notation are returned as `None` and read as SAN.
+    pub fn to_algebraic(self, position: &Position, token: &str) -> Option<String> {
+        let (from, to, promotion) = match self {
+            InputNotation::Algebraic => return None,
+            InputNotation::Iccf => parse_iccf(token)?,
+            InputNotation::Descriptive => parse_descriptive(position, token)?,
+        };
+
+        if position.legal_moves().contains(&(from, to, promotion)) {
+            return Some(to_san(position, from, to, promotion));
+        }
+        let piece = match position.piece_at(from) {
+            Some((_, Piece::Pawn)) | None => String::new(),
+            Some((_, piece)) => piece.letter().to_string(),
+        };
+        Some(format!(
+            "{piece}{}-{}",
+            square_to_string(from),
+            square_to_string(to)
+        ))
+    }
+}
+
+/// Parses an ICCF numeric move: origin and target as file and rank digits, then an optional
+/// promotion digit (1 queen, 2 rook, 3 bishop, 4 knight).
+pub fn parse_iccf(token: &str) -> Option<(Square, Square, Option<Piece>)> {
+    let digits = token
+        .bytes()
+        .map(|byte| byte.wrapping_sub(b'0'))
+        .collect::<Vec<u8>>();
+    if !(4..=5).contains(&digits.len()) || digits[..4].iter().any(|digit| !(1..=8).contains(digit))
+    {
+        return None;
+    }
+
+    let promotion = match digits.get(4) {
+        None => None,
+        Some(1) => Some(Piece::Queen),
+        Some(2) => Some(Piece::Rook),
+        Some(3) => Some(Piece::Bishop),
+        Some(4) => Some(Piece::Knight),
+        Some(_) => return None,
+    };
+    let square = |file: u8, rank: u8| (rank - 1) * 8 + file - 1;
+
+    Some((
+        square(digits[0], digits[1]),
+        square(digits[2], digits[3]),
+        promotion,
+    ))
+}
+
+/// A piece or square of descriptive notation, such as `KBP`, `QR` or `KB3`. Ranks are
+/// counted from the side to move, whichever side owns the piece.
+struct Designation {
+    piece: Option<Piece>,
+    files: Vec<u8>,
+    rank: Option<u8>,
+}
+
+impl Designation {
+    fn matches(&self, position: &Position, square: Square, color: Color) -> bool {
+        let piece = position.piece_at(square).map(|(_, piece)| piece);
+        (self.piece.is_none() || self.piece == piece)
+            && self.files.contains(&(square % 8))
+            && self
+                .rank
+                .is_none_or(|rank| relative_rank(square, color) == rank)
+    }
+}
+
+/// The rank of a square as counted from the side of `color`, from 1 to 8.
+fn relative_rank(square: Square, color: Color) -> u8 {
+    if color == Color::White {
+        square / 8 + 1
+    } else {
+        8 - square / 8
+    }
+}
+
+/// Files of a file designator: the piece standing there at the start, prefixed by `K` or `Q`
+/// for the side of the board; a bare `R`, `N` or `B` names both files.
+fn designated_files(designator: &str) -> Option<Vec<u8>> {
+    let files = match designator {
+        "QR" => vec![0],
+        "QN" | "QKt" => vec![1],
+        "QB" => vec![2],
+        "Q" => vec![3],
+        "K" => vec![4],
+        "KB" => vec![5],
+        "KN" | "KKt" => vec![6],
+        "KR" => vec![7],
+        "R" => vec![0, 7],
+        "N" | "Kt" => vec![1, 6],
+        "B" => vec![2, 5],
+        _ => return None,
+    };
+    Some(files)
+}
+
+fn piece_named(name: &str) -> Option<Piece> {
+    match name {
+        "Kt" => Some(Piece::Knight),
+        _ if name.len() == 1 => Piece::ALL
+            .into_iter()
+            .find(|piece| name.starts_with(piece.letter())),
+        _ => None,
+    }
+}
+
+/// Splits a trailing rank qualifier, written as in `R1`, `R(1)` or `R/1`.
+fn split_rank(text: &str) -> (&str, Option<u8>) {
+    let trimmed = text.trim_end_matches(')');
+    match trimmed.bytes().last() {
+        Some(digit @ b'1'..=b'8') => {
+            let rest = trimmed[..trimmed.len() - 1].trim_end_matches(['(', '/']);
+            (rest, Some(digit - b'0'))
+        }
+        _ => (text, None),
+    }
+}
+
+/// A piece such as `P`, `KBP`, `QR`, `Kt` or `R(1)`. A `K` or `Q` prefix before a piece names
+/// its wing, a file designator before `P` names the file of a pawn.
+fn piece_designation(text: &str) -> Option<Designation> {
+    let (text, rank) = split_rank(text);
+    let (prefix, piece) = match text.strip_suffix("Kt") {
+        Some(prefix) => (prefix, Piece::Knight),
+        None => {
+            let split = text.len().checked_sub(1)?;
+            (&text[..split], piece_named(&text[split..])?)
+        }
+    };
+
+    let files = match (prefix, piece) {
+        ("", _) => (0..8).collect(),
+        (prefix, Piece::Pawn) => designated_files(prefix)?,
+        ("K", _) => (4..8).collect(),
+        ("Q", _) => (0..4).collect(),
+        _ => return None,
+    };
+    Some(Designation {
+        piece: Some(piece),
+        files,
+        rank,
+    })
+}
+
+/// A square such as `K4` or `QB3`.
+fn square_designation(text: &str) -> Option<Designation> {
+    let (files, rank) = split_rank(text);
+    Some(Designation {
+        piece: None,
+        files: designated_files(files)?,
+        rank: Some(rank?),
+    })
+}
+
+/// Resolves an English descriptive move against the legal moves of `position`. Castling is
+/// left to SAN, the move must match exactly one legal move.
+pub fn parse_descriptive(
+    position: &Position,
+    token: &str,
+) -> Option<(Square, Square, Option<Piece>)> {
+    let stripped = token
+        .trim_end_matches(['+', '#', '!', '?'])
+        .trim_end_matches("e.p.")
+        .trim_end_matches("ch")
+        .trim_end_matches("mate");
+    if stripped.starts_with("O-O") {
+        return None;
+    }
+    let (mover, target) = stripped.split_once(['-', 'x'])?;
+    let is_capture = stripped.as_bytes()[mover.len()] == b'x';
+
+    let (target, promotion) = match target.rfind(['=', '(']) {
+        Some(index) if !target[index + 1..].starts_with(|c: char| c.is_ascii_digit()) => {
+            let name = target[index + 1..].trim_end_matches(')');
+            (&target[..index], Some(piece_named(name)?))
+        }
+        // Promotions written without a separator, as in P-K8Q
+        _ => match target.find(|c: char| c.is_ascii_digit()) {
+            Some(index) if !target[index + 1..].trim_start_matches(')').is_empty() => {
+                let name = target[index + 1..].trim_start_matches('/');
+                (&target[..=index], Some(piece_named(name)?))
+            }
+            _ => (target, None),
+        },
+    };
+
+    let us = position.side_to_move;
+    let mover = piece_designation(mover)?;
+    let target = if is_capture {
+        piece_designation(target)?
+    } else {
+        square_designation(target)?
+    };
+
+    let mut candidates = position
+        .legal_moves()
+        .into_iter()
+        .filter(|&(from, to, promoted)| {
+            if !mover.matches(position, from, us) {
+                return false;
+            }
+            let matches_promotion = match promotion {
+                Some(piece) => promoted == Some(piece),
+                None => promoted.is_none() || promoted == Some(Piece::Queen),
+            };
+            if !matches_promotion || position.is_capture(from, to) != is_capture {
+                return false;
+            }
+
+            // An en passant capture takes the pawn beside the target square
+            let captured = match position.piece_at(to) {
+                None if is_capture => from / 8 * 8 + to % 8,
+                _ => to,
+            };
+            target.matches(position, if is_capture { captured } else { to }, us)
+        });
+
+    let candidate = candidates.next()?;
+    candidates.next().is_none().then_some(candidate)
+}
+
+/// A way to write the moves of the output movetext. New encodings implement it and are listed
+/// in `OUTPUT_NOTATIONS` to become available to `--format`.
+pub trait OutputNotation {
+    /// The name selecting this notation.
+    fn name(&self) -> &'static str;
+
+    /// What goes between consecutive moves.
+    fn separator(&self) -> &'static str {
+        " "
+    }
+
+    /// Writes a move that is legal in `position`.
+    fn write(
+        &self,
+        position: &Position,
+        from: Square,
+        to: Square,
+        promotion: Option<Piece>,
+    ) -> String;
+}
+
+/// Coordinate moves as in UCI, such as `e2e4`.
+pub struct Uci;
+/// Standard algebraic notation, such as `Nf3`.
+pub struct San;
+/// Long algebraic notation, such as `Ng1-f3` or `e4xd5`.
+pub struct Lan;
+/// ICCF numeric notation, such as `5254`.
+pub struct Iccf;
+/// SAN with figurines in place of piece letters, such as `♘f3`.
+pub struct Figurine;
+/// Moves as read aloud for audio and blindfold training, such as `knight f3`,
+/// `bishop takes e5` or `short castle`, separated by commas.
+pub struct Spoken;
+/// The squares each ply changes, one ply per line, for driving electronic boards: `-e2`
+/// for an emptied square, `+e4P` for a filled one and `xd5p` for a capture, pieces given by
+/// their FEN letter. Castling moves the king, then the rook: `-e1 +g1K -h1 +f1R`.
+pub struct Diff;
+
+pub const OUTPUT_NOTATIONS: [&dyn OutputNotation; 7] =
+    [&Uci, &San, &Lan, &Iccf, &Figurine, &Spoken, &Diff];
+
+/// The output notation called `name`.
+pub fn output_notation(name: &str) -> Option<&'static dyn OutputNotation> {
+    OUTPUT_NOTATIONS
+        .into_iter()
+        .find(|notation| notation.name() == name)
+}
+
+impl OutputNotation for Uci {
+    fn name(&self) -> &'static str {
+        "uci"
+    }
+
+    fn write(&self, _: &Position, from: Square, to: Square, promotion: Option<Piece>) -> String {
+        coordinate_move(from, to, promotion)
+    }
+}
+
+impl OutputNotation for San {
+    fn name(&self) -> &'static str {
+        "san"
+    }
+
+    fn write(
+        &self,
+        position: &Position,
+        from: Square,
+        to: Square,
+        promotion: Option<Piece>,
+    ) -> String {
+        to_san(position, from, to, promotion)
+    }
+}
+
+impl OutputNotation for Lan {
+    fn name(&self) -> &'static str {
+        "lan"
+    }
+
+    fn write(
+        &self,
+        position: &Position,
+        from: Square,
+        to: Square,
+        promotion: Option<Piece>,
+    ) -> String {
+        let san = to_san(position, from, to, promotion);
+        if san.starts_with("O-O") {
+            return san;
+        }
+        let suffix = san.trim_start_matches(|c: char| c != '+' && c != '#');
+
+        let mut lan = match position.piece_at(from) {
+            Some((_, Piece::Pawn)) | None => String::new(),
+            Some((_, piece)) => piece.letter().to_string(),
+        };
+        lan.push_str(&square_to_string(from));
+        lan.push(if position.is_capture(from, to) {
+            'x'
+        } else {
+            '-'
+        });
+        lan.push_str(&square_to_string(to));
+        if let Some(piece) = promotion {
+            lan.push('=');
+            lan.push(piece.letter());
+        }
+        lan + suffix
+    }
+}
+
+impl OutputNotation for Iccf {
+    fn name(&self) -> &'static str {
+        "iccf"
+    }
+
+    fn write(&self, _: &Position, from: Square, to: Square, promotion: Option<Piece>) -> String {
+        let mut iccf = [from, to]
+            .iter()
+            .map(|square| format!("{}{}", square % 8 + 1, square / 8 + 1))
+            .collect::<String>();
+        match promotion {
+            Some(Piece::Queen) => iccf.push('1'),
+            Some(Piece::Rook) => iccf.push('2'),
+            Some(Piece::Bishop) => iccf.push('3'),
+            Some(Piece::Knight) => iccf.push('4'),
+            _ => {}
+        }
+        iccf
+    }
+}
+
+impl OutputNotation for Figurine {
+    fn name(&self) -> &'static str {
+        "figurine"
+    }
+
+    fn write(
+        &self,
+        position: &Position,
+        from: Square,
+        to: Square,
+        promotion: Option<Piece>,
+    ) -> String {
+        to_san(position, from, to, promotion)
+            .chars()
+            .map(|c| match c {
+                'K' => '♔',
+                'Q' => '♕',
+                'R' => '♖',
+                'B' => '♗',
+                'N' => '♘',
+                c => c,
+            })
+            .collect()
+    }
+}
+
+impl OutputNotation for Spoken {
+    fn name(&self) -> &'static str {
+        "spoken"
+    }
+
+    fn separator(&self) -> &'static str {
+        ", "
+    }
+
+    fn write(
+        &self,
+        position: &Position,
+        from: Square,
+        to: Square,
+        promotion: Option<Piece>,
+    ) -> String {
+        let san = to_san(position, from, to, promotion);
+        let mut words: Vec<&str> = Vec::new();
+        let target = square_to_string(to);
+
+        match SanMove::parse(&san) {
+            Ok(SanMove::Castling { queen_side: false }) => words.push("short castle"),
+            Ok(SanMove::Castling { queen_side: true }) => words.push("long castle"),
+            Ok(SanMove::Normal {
+                piece,
+                origin,
+                promotion,
+                ..
+            }) => {
+                if piece != Piece::Pawn {
+                    words.push(piece.name());
+                }
+                // The disambiguation, or the file of a capturing pawn
+                if !origin.is_empty() {
+                    words.push(origin);
+                }
+                if position.is_capture(from, to) {
+                    words.push("takes");
+                }
+                words.push(&target);
+                if piece == Piece::Pawn && position.en_passant == Some(to) {
+                    words.push("en passant");
+                }
+                if let Some(promoted) = promotion {
+                    words.extend(["promotes to", promoted.name()]);
+                }
+            }
+            Err(_) => return san,
+        }
+
+        if san.ends_with('#') {
+            words.push("checkmate");
+        } else if san.ends_with('+') {
+            words.push("check");
+        }
+        words.join(" ")
+    }
+}
+
+impl OutputNotation for Diff {
+    fn name(&self) -> &'static str {
+        "diff"
+    }
+
+    fn separator(&self) -> &'static str {
+        "\n"
+    }
+
+    fn write(
+        &self,
+        position: &Position,
+        from: Square,
+        to: Square,
+        promotion: Option<Piece>,
+    ) -> String {
+        let Some((color, piece)) = position.piece_at(from) else {
+            return coordinate_move(from, to, promotion);
+        };
+        let after = position.play(from, to, promotion);
+        let placed = |square: Square| match after.piece_at(square) {
+            Some((color, piece)) => {
+                format!("+{}{}", square_to_string(square), piece.fen_char(color))
+            }
+            None => format!("+{}", square_to_string(square)),
+        };
+
+        let mut changes = vec![format!("-{}", square_to_string(from))];
+        // En passant takes the pawn beside the origin, not on the target square
+        let captured = match (piece, position.piece_at(to)) {
+            (_, Some((owner, captured))) if owner != color => Some((to, captured)),
+            (Piece::Pawn, None) if from % 8 != to % 8 => {
+                Some((from - from % 8 + to % 8, Piece::Pawn))
+            }
+            _ => None,
+        };
+        if let Some((captured_square, captured)) = captured {
+            changes.push(format!(
+                "x{}{}",
+                square_to_string(captured_square),
+                captured.fen_char(!color)
+            ));
+        }
+        changes.push(placed(to));
+
+        if piece == Piece::King && from.abs_diff(to) == 2 {
+            let (rook_from, rook_to) = if to > from {
+                (from + 3, from + 1)
+            } else {
+                (from - 4, from - 1)
+            };
+            changes.push(format!("-{}", square_to_string(rook_from)));
+            changes.push(placed(rook_to));
+        }
+        changes.join(" ")
+    }
+}
+
+/// Writes coordinate moves in `notation`. `positions` holds the position before each move.
+pub fn write_moves(
+    notation: &dyn OutputNotation,
+    positions: &[Position],
+    moves: &[String],
+) -> Vec<String> {
+    moves
+        .iter()
+        .zip(positions)
+        .map(|(mv, position)| match parse_coordinate_move(mv) {
+            Some((from, to, promotion)) => notation.write(position, from, to, promotion),
+            None => mv.clone(),
+        })
+        .collect()
+}
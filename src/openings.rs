@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+/// Results of the games reaching an opening node, and the engine's view of it.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct OpeningStats {
+    pub games: usize,
+    pub white_wins: usize,
+    pub draws: usize,
+    pub black_wins: usize,
+    /// Engine evaluation in centipawns from white's point of view.
+    pub eval: Option<i32>,
+}
+
+impl OpeningStats {
+    /// White's score per game, counting draws as half a point. Unfinished games count as played
+    /// but score nothing for either side.
+    pub fn practical_score(&self) -> f64 {
+        (self.white_wins as f64 + self.draws as f64 / 2.0) / self.games.max(1) as f64
+    }
+
+    /// White's expected score given the engine evaluation, on the usual logistic curve.
+    pub fn expected_score(&self) -> Option<f64> {
+        self.eval
+            .map(|eval| 1.0 / (1.0 + 10f64.powf(-eval as f64 / 400.0)))
+    }
+
+    /// How much better white scored in practice than the evaluation predicts.
+    pub fn divergence(&self) -> Option<f64> {
+        self.expected_score()
+            .map(|expected| self.practical_score() - expected)
+    }
+}
+
+/// Game counts and results for every move sequence of the first plies, keyed by the
+/// space-separated coordinate moves. The empty key is the starting position.
+pub struct OpeningTree {
+    pub depth: usize,
+    pub nodes: BTreeMap<String, OpeningStats>,
+}
+
+impl OpeningTree {
+    pub fn new(depth: usize) -> Self {
+        OpeningTree {
+            depth,
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_game(&mut self, moves: &[String], result: &str) {
+        for ply in 0..=moves.len().min(self.depth) {
+            let stats = self.nodes.entry(moves[..ply].join(" ")).or_default();
+            stats.games += 1;
+            match result {
+                "1-0" => stats.white_wins += 1,
+                "0-1" => stats.black_wins += 1,
+                "1/2-1/2" => stats.draws += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Evaluated nodes reached by at least `min_games` games, largest divergence first.
+    pub fn divergent(&self, min_games: usize) -> Vec<(&str, &OpeningStats)> {
+        let mut nodes: Vec<(&str, &OpeningStats, f64)> = self
+            .nodes
+            .iter()
+            .filter(|(_, stats)| stats.games >= min_games)
+            .filter_map(|(line, stats)| Some((line.as_str(), stats, stats.divergence()?)))
+            .collect();
+        nodes.sort_by(|a, b| b.2.abs().total_cmp(&a.2.abs()));
+
+        nodes
+            .into_iter()
+            .map(|(line, stats, _)| (line, stats))
+            .collect()
+    }
+}
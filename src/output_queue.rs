@@ -0,0 +1,145 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::checkpoint::Checkpoint;
+
+/// Default cap on the output waiting to be written, in bytes.
+pub const DEFAULT_CAP: usize = 64 << 20;
+
+enum Item {
+    Game(String),
+    /// Saves a checkpoint once everything queued before it is written.
+    Checkpoint {
+        path: String,
+        input_offset: usize,
+        games: usize,
+    },
+}
+
+/// Bytes queued and not yet written, and whether the writer is gone.
+#[derive(Default)]
+struct Backlog {
+    bytes: usize,
+    stopped: bool,
+}
+
+/// Writes games to the output file on a separate thread, so that processing goes on while a
+/// slow sink catches up. At most `cap` bytes wait in the queue: pushing more blocks until the
+/// writer drains it. The cap covers pending output alone; the input is bounded by crunch
+/// splitting games as they are read, which it does unless it needs all of them first, as for
+/// `--sample`, `--source-tags` and UCI or FEN inputs.
+pub struct OutputQueue {
+    sender: Option<Sender<Item>>,
+    backlog: Arc<(Mutex<Backlog>, Condvar)>,
+    cap: usize,
+    writer: Option<JoinHandle<io::Result<u64>>>,
+}
+
+impl OutputQueue {
+    pub fn spawn(file: BufWriter<File>, cap: usize) -> OutputQueue {
+        let (sender, receiver) = mpsc::channel();
+        let backlog = Arc::new((Mutex::new(Backlog::default()), Condvar::new()));
+        let writer_backlog = Arc::clone(&backlog);
+        let writer = thread::spawn(move || {
+            let result = write_items(file, receiver, &writer_backlog);
+            // Release a producer blocked on a queue that will never drain
+            let (state, drained) = &*writer_backlog;
+            state.lock().unwrap().stopped = true;
+            drained.notify_all();
+            result
+        });
+
+        OutputQueue {
+            sender: Some(sender),
+            backlog,
+            cap,
+            writer: Some(writer),
+        }
+    }
+
+    /// Queues a rendered game, waiting while the queue is full.
+    pub fn push(&mut self, game: String) -> io::Result<()> {
+        let (state, drained) = &*self.backlog;
+        let mut backlog = state.lock().unwrap();
+        // A game larger than the cap still goes through once the queue is empty
+        while !backlog.stopped && backlog.bytes > 0 && backlog.bytes + game.len() > self.cap {
+            backlog = drained.wait(backlog).unwrap();
+        }
+        backlog.bytes += game.len();
+        drop(backlog);
+
+        self.send(Item::Game(game))
+    }
+
+    /// Saves a checkpoint covering the games queued so far, once they are written.
+    pub fn checkpoint(&mut self, path: &str, input_offset: usize, games: usize) -> io::Result<()> {
+        self.send(Item::Checkpoint {
+            path: path.to_string(),
+            input_offset,
+            games,
+        })
+    }
+
+    fn send(&mut self, item: Item) -> io::Result<()> {
+        let sent = self
+            .sender
+            .as_ref()
+            .is_some_and(|sender| sender.send(item).is_ok());
+        if sent {
+            return Ok(());
+        }
+        // The writer stopped: report why
+        self.join()?;
+        Err(io::Error::other("Output writer stopped"))
+    }
+
+    fn join(&mut self) -> io::Result<u64> {
+        self.sender = None;
+        match self.writer.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(io::Error::other("Output writer panicked")),
+            None => Err(io::Error::other("Output writer already stopped")),
+        }
+    }
+
+    /// Writes out everything queued and returns the length of the output file.
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.join()
+    }
+}
+
+fn write_items(
+    mut file: BufWriter<File>,
+    receiver: Receiver<Item>,
+    backlog: &(Mutex<Backlog>, Condvar),
+) -> io::Result<u64> {
+    for item in receiver {
+        match item {
+            Item::Game(game) => {
+                file.write_all(game.as_bytes())?;
+                let (state, drained) = backlog;
+                state.lock().unwrap().bytes -= game.len();
+                drained.notify_all();
+            }
+            Item::Checkpoint {
+                path,
+                input_offset,
+                games,
+            } => {
+                file.flush()?;
+                Checkpoint {
+                    input_offset,
+                    games,
+                    output_len: file.get_ref().metadata()?.len(),
+                }
+                .save(&path)?;
+            }
+        }
+    }
+
+    file.flush()?;
+    file.get_ref().metadata().map(|metadata| metadata.len())
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use chess::legal_moves::misc::{Color, Square};
+use chess::utils::string_to_square;
+
+use crate::position::{color_index, offset_square, Piece, Position, KING_OFFSETS};
+
+/// Tactical motifs recognized in played games.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Motif {
+    BackRankMate,
+    SmotheredMate,
+    /// A piece giving repeated discovered checks while the king shuttles.
+    Windmill,
+    /// Both sides promoting within a few moves of each other.
+    PromotionRace,
+}
+
+impl Motif {
+    pub fn name(self) -> &'static str {
+        match self {
+            Motif::BackRankMate => "back-rank mate",
+            Motif::SmotheredMate => "smothered mate",
+            Motif::Windmill => "windmill",
+            Motif::PromotionRace => "promotion race",
+        }
+    }
+}
+
+/// Plies within which both promotions must happen to count as a race.
+const PROMOTION_RACE_PLIES: usize = 6;
+
+/// Motifs occurring in a game. `positions` holds the position before each move followed by
+/// the final one.
+pub fn detect(positions: &[Position], moves: &[String]) -> Vec<Motif> {
+    let mut motifs = Vec::new();
+
+    if let Some(last) = positions.last().filter(|last| last.is_checkmate()) {
+        if is_smothered_mate(last) {
+            motifs.push(Motif::SmotheredMate);
+        }
+        if is_back_rank_mate(last) {
+            motifs.push(Motif::BackRankMate);
+        }
+    }
+    if has_windmill(positions, moves) {
+        motifs.push(Motif::Windmill);
+    }
+    if has_promotion_race(positions, moves) {
+        motifs.push(Motif::PromotionRace);
+    }
+
+    motifs
+}
+
+/// Tactical facts of a single move, read from the attack maps of the position it leads to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MoveTag {
+    Check,
+    /// Two pieces checking at once.
+    DoubleCheck,
+    /// A check given by a piece the move uncovers rather than by the moving piece.
+    DiscoveredCheck,
+    /// The moving piece attacking two or more enemy pieces of higher value, the king included.
+    Fork,
+}
+
+impl MoveTag {
+    pub fn name(self) -> &'static str {
+        match self {
+            MoveTag::Check => "check",
+            MoveTag::DoubleCheck => "double check",
+            MoveTag::DiscoveredCheck => "discovered check",
+            MoveTag::Fork => "fork",
+        }
+    }
+}
+
+/// Worth of a piece as the target of a fork, the king above all others.
+fn target_value(piece: Piece) -> u32 {
+    match piece {
+        Piece::King => u32::MAX,
+        piece => piece.value(),
+    }
+}
+
+/// Tags of the move from `from` to `to` that leads to `after`.
+pub fn move_tags(after: &Position, from: Square, to: Square) -> Vec<MoveTag> {
+    let mut tags = Vec::new();
+    let Some((mover, piece)) = after.piece_at(to) else {
+        return tags;
+    };
+
+    if let Some(king) = after.king_square(!mover) {
+        let checkers = after.attackers(king, mover);
+        // The rook moves along with the king when castling
+        let castled_rook = (piece == Piece::King && from.abs_diff(to) == 2).then(|| {
+            if to > from {
+                to - 1
+            } else {
+                to + 1
+            }
+        });
+        if !checkers.is_empty() {
+            tags.push(MoveTag::Check);
+        }
+        if checkers.len() >= 2 {
+            tags.push(MoveTag::DoubleCheck);
+        }
+        if checkers
+            .iter()
+            .any(|checker| *checker != to && Some(*checker) != castled_rook)
+        {
+            tags.push(MoveTag::DiscoveredCheck);
+        }
+    }
+
+    if piece != Piece::King {
+        let targets = after
+            .attacked_squares(to)
+            .into_iter()
+            .filter(|square| {
+                matches!(after.piece_at(*square), Some((owner, target))
+                    if owner != mover && target_value(target) > piece.value())
+            })
+            .count();
+        if targets >= 2 {
+            tags.push(MoveTag::Fork);
+        }
+    }
+
+    tags
+}
+
+fn squares(mv: &str) -> (Square, Square) {
+    (string_to_square(&mv[0..2]), string_to_square(&mv[2..4]))
+}
+
+/// Squares around the king, on the board.
+fn king_neighbours(king: Square) -> Vec<Square> {
+    KING_OFFSETS
+        .iter()
+        .filter_map(|offset| offset_square(king, *offset))
+        .collect()
+}
+
+fn is_smothered_mate(position: &Position) -> bool {
+    let mated = position.side_to_move;
+    let Some(king) = position.king_square(mated) else {
+        return false;
+    };
+    let checkers = position.attackers(king, !mated);
+
+    checkers
+        .iter()
+        .all(|square| matches!(position.piece_at(*square), Some((_, Piece::Knight))))
+        && king_neighbours(king)
+            .iter()
+            .all(|square| matches!(position.piece_at(*square), Some((owner, _)) if owner == mated))
+}
+
+fn is_back_rank_mate(position: &Position) -> bool {
+    let mated = position.side_to_move;
+    let Some(king) = position.king_square(mated) else {
+        return false;
+    };
+    let (back_rank, forward) = if mated == Color::White {
+        (0, 1)
+    } else {
+        (7, -1)
+    };
+    if king / 8 != back_rank {
+        return false;
+    }
+
+    // Mated along the back rank by a rook or queen, with the escape squares blocked by own pieces
+    let checked_on_rank = position.attackers(king, !mated).iter().all(|square| {
+        square / 8 == back_rank
+            && matches!(
+                position.piece_at(*square),
+                Some((_, Piece::Rook | Piece::Queen))
+            )
+    });
+    let escapes_blocked = [-1, 0, 1]
+        .iter()
+        .filter_map(|file| offset_square(king, (*file, forward)))
+        .all(|square| matches!(position.piece_at(square), Some((owner, _)) if owner == mated));
+
+    checked_on_rank && escapes_blocked
+}
+
+fn has_windmill(positions: &[Position], moves: &[String]) -> bool {
+    // Discovered checks of each piece, keyed by its square, during each side's run of checks
+    let mut runs: [HashMap<Square, usize>; 2] = [HashMap::new(), HashMap::new()];
+
+    for (position, mv) in positions.iter().zip(moves) {
+        let mover = position.side_to_move;
+        let run = &mut runs[color_index(mover)];
+        let (from, to) = squares(mv);
+        let after = position.play(from, to, None);
+
+        let Some(king) = after.king_square(!mover).filter(|_| after.in_check(!mover)) else {
+            run.clear();
+            continue;
+        };
+        let discovered = after
+            .attackers(king, mover)
+            .iter()
+            .any(|checker| *checker != to);
+
+        let count = run.remove(&from).unwrap_or(0) + usize::from(discovered);
+        if count >= 2 {
+            return true;
+        }
+        run.insert(to, count);
+    }
+
+    false
+}
+
+fn has_promotion_race(positions: &[Position], moves: &[String]) -> bool {
+    let mut promotions: [Option<usize>; 2] = [None, None];
+
+    for (ply, (position, mv)) in positions.iter().zip(moves).enumerate() {
+        let (from, to) = squares(mv);
+        if let Some((color, Piece::Pawn)) = position.piece_at(from) {
+            if to / 8 == 0 || to / 8 == 7 {
+                promotions[color_index(color)].get_or_insert(ply);
+            }
+        }
+    }
+
+    matches!(promotions, [Some(white), Some(black)] if white.abs_diff(black) <= PROMOTION_RACE_PLIES)
+}
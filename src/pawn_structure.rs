@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use chess::legal_moves::misc::Color;
+
+use crate::position::{Piece, Position};
+use crate::sampling::splitmix64;
+
+/// The pawns of a position, ignoring every other piece.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PawnStructure {
+    pub white: u64,
+    pub black: u64,
+}
+
+impl PawnStructure {
+    pub fn of(position: &Position) -> Self {
+        PawnStructure {
+            white: position.bitboard(Color::White, Piece::Pawn),
+            black: position.bitboard(Color::Black, Piece::Pawn),
+        }
+    }
+
+    /// Stable hash of the structure, for grouping games.
+    pub fn fingerprint(&self) -> u64 {
+        splitmix64(splitmix64(self.white) ^ self.black)
+    }
+
+    /// FEN piece placement showing only the pawns.
+    pub fn placement(&self) -> String {
+        let mut position = Position::initial();
+        for square in 0..64 {
+            let bit = 1u64 << square;
+            position.squares[square] = if self.white & bit != 0 {
+                Some((Color::White, Piece::Pawn))
+            } else if self.black & bit != 0 {
+                Some((Color::Black, Piece::Pawn))
+            } else {
+                None
+            };
+        }
+        position.placement()
+    }
+}
+
+/// A structure to look for, written like a FEN placement: `P`/`p` squares need a white/black
+/// pawn, `X`/`x` squares must not hold one, digits skip squares that do not matter.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StructureTemplate {
+    pub name: String,
+    required: PawnStructure,
+    forbidden: PawnStructure,
+}
+
+const BUILT_IN_TEMPLATES: [(&str, &str); 3] = [
+    // White d-pawn without white c- or e-pawns
+    ("iqp", "8/2X1X3/2X1X3/2X1X3/2XPX3/2X1X3/2X1X3/8"),
+    // Exchange Queen's Gambit: white d4 without c-pawn against black d5 without e-pawn
+    ("carlsbad", "8/2X1x3/2X1x3/2Xpx3/2XPx3/2X1x3/2X1x3/8"),
+    // Black pawns a6, b6, d6, e6 without c-pawn against white c4 and e4
+    ("hedgehog", "8/2x5/ppxpp3/2x5/2P1P3/8/8/8"),
+];
+
+impl StructureTemplate {
+    /// A built-in template name (`iqp`, `carlsbad`, `hedgehog`) or a mask.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        match BUILT_IN_TEMPLATES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(template))
+        {
+            Some((name, mask)) => Self::from_mask(name, mask),
+            None => Self::from_mask(template, template),
+        }
+    }
+
+    fn from_mask(name: &str, mask: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid pawn structure mask: {mask}");
+        let ranks: Vec<&str> = mask.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(invalid());
+        }
+
+        let empty = PawnStructure { white: 0, black: 0 };
+        let (mut required, mut forbidden) = (empty, empty);
+        for (row, rank) in ranks.iter().enumerate() {
+            let mut file = 0;
+            for c in rank.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    file += skip as usize;
+                    continue;
+                }
+                if file >= 8 {
+                    return Err(invalid());
+                }
+                let bit = 1u64 << ((7 - row) * 8 + file);
+                match c {
+                    'P' => required.white |= bit,
+                    'p' => required.black |= bit,
+                    'X' => forbidden.white |= bit,
+                    'x' => forbidden.black |= bit,
+                    _ => return Err(invalid()),
+                }
+                file += 1;
+            }
+            if file != 8 {
+                return Err(invalid());
+            }
+        }
+
+        Ok(StructureTemplate {
+            name: name.to_string(),
+            required,
+            forbidden,
+        })
+    }
+
+    pub fn matches(&self, structure: &PawnStructure) -> bool {
+        structure.white & self.required.white == self.required.white
+            && structure.black & self.required.black == self.required.black
+            && structure.white & self.forbidden.white == 0
+            && structure.black & self.forbidden.black == 0
+    }
+}
+
+/// Number of games per pawn structure.
+#[derive(Default)]
+pub struct StructureGroups {
+    counts: HashMap<PawnStructure, usize>,
+}
+
+impl StructureGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, structure: PawnStructure) {
+        *self.counts.entry(structure).or_default() += 1;
+    }
+
+    /// `fingerprint<TAB>games<TAB>pawn placement` lines, most common structure first.
+    pub fn to_tsv(&self) -> String {
+        let mut groups: Vec<(&PawnStructure, &usize)> = self.counts.iter().collect();
+        groups.sort_by(|a, b| {
+            b.1.cmp(a.1)
+                .then_with(|| a.0.fingerprint().cmp(&b.0.fingerprint()))
+        });
+
+        groups
+            .into_iter()
+            .map(|(structure, games)| {
+                format!(
+                    "{:016x}\t{games}\t{}\n",
+                    structure.fingerprint(),
+                    structure.placement()
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,324 @@
+//! Move generation node counts, to check the move generator against published perft values
+//! and the chess crate's `is_possible`, which resolves SAN while processing, against both.
+//! Counts are also taken on the board backends themselves, moves being those their
+//! `is_possible` accepts.
+
+use chess::board::Board;
+use chess::legal_moves::is_move_possible::is_possible;
+use chess::legal_moves::misc::Square;
+
+use crate::backend::{BackendKind, BoardBackend};
+use crate::position::{coordinate_move, Piece, Position};
+
+type Move = (Square, Square, Option<Piece>);
+
+/// A reference position with its published node counts from depth 1.
+pub struct KnownPerft {
+    pub name: &'static str,
+    pub fen: &'static str,
+    pub nodes: &'static [u64],
+}
+
+/// The usual perft test positions, from the Chess Programming Wiki.
+pub const KNOWN_PERFT: [KnownPerft; 5] = [
+    KnownPerft {
+        name: "initial",
+        fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        nodes: &[20, 400, 8902, 197_281, 4_865_609, 119_060_324],
+    },
+    KnownPerft {
+        name: "kiwipete",
+        fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        nodes: &[48, 2039, 97_862, 4_085_603, 193_690_690],
+    },
+    KnownPerft {
+        name: "position 3",
+        fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        nodes: &[14, 191, 2812, 43_238, 674_624, 11_030_083],
+    },
+    KnownPerft {
+        name: "position 4",
+        fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        nodes: &[6, 264, 9467, 422_333, 15_833_292],
+    },
+    KnownPerft {
+        name: "position 5",
+        fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        nodes: &[44, 1486, 62_379, 2_103_487, 89_941_194],
+    },
+];
+
+/// The published count for a position and depth, comparing placement, side to move, castling
+/// and en passant but not the clocks.
+pub fn known_nodes(position: &Position, depth: usize) -> Option<(&'static str, u64)> {
+    let key = |fen: &str| fen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+    let fen = key(&position.to_fen());
+    KNOWN_PERFT
+        .iter()
+        .find(|known| key(known.fen) == fen)
+        .and_then(|known| Some((known.name, *known.nodes.get(depth.checked_sub(1)?)?)))
+}
+
+/// Number of leaf nodes of the legal move tree `depth` plies deep.
+pub fn perft(position: &Position, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = position.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|(from, to, promotion)| perft(&position.play(from, to, promotion), depth - 1))
+        .sum()
+}
+
+/// The node count below each legal move, in coordinate notation.
+pub fn divide(position: &Position, depth: usize) -> Vec<(String, u64)> {
+    position
+        .legal_moves()
+        .into_iter()
+        .map(|(from, to, promotion)| {
+            let nodes = perft(&position.play(from, to, promotion), depth.saturating_sub(1));
+            (coordinate_move(from, to, promotion), nodes)
+        })
+        .collect()
+}
+
+/// Number of leaf nodes of the move tree `depth` plies deep, as a board backend plays it. The
+/// backend decides which moves are possible; castling, the side to move and castling rights
+/// are tracked beside it, as the processor tracks them. Fails when the backend cannot start
+/// from `position`, or cannot play a promotion the tree goes through.
+pub fn backend_perft(
+    backend: BackendKind,
+    position: &Position,
+    depth: usize,
+) -> Result<u64, String> {
+    if depth == 0 {
+        return Ok(1);
+    }
+    walk_backend(backend, position, &mut Vec::new(), depth)
+}
+
+/// The node count below each move of a backend, in coordinate notation, as [`divide`] counts
+/// them on the move generator.
+pub fn backend_divide(
+    backend: BackendKind,
+    position: &Position,
+    depth: usize,
+) -> Result<Vec<(String, u64)>, String> {
+    let board = replay_backend(backend, position, &[])?;
+    let mut history = Vec::new();
+    backend_moves(board.as_ref(), position)
+        .into_iter()
+        .map(|mv| {
+            let (from, to, promotion) = mv;
+            let nodes = match depth {
+                0 | 1 => 1,
+                _ => {
+                    check_promotion(board.as_ref(), mv)?;
+                    history.push(mv);
+                    let nodes = walk_backend(backend, position, &mut history, depth - 1);
+                    history.pop();
+                    nodes?
+                }
+            };
+            Ok((coordinate_move(from, to, promotion), nodes))
+        })
+        .collect()
+}
+
+/// Counts the leaves `depth` plies below the position reached by `history` from `root`.
+fn walk_backend(
+    backend: BackendKind,
+    root: &Position,
+    history: &mut Vec<Move>,
+    depth: usize,
+) -> Result<u64, String> {
+    let board = replay_backend(backend, root, history)?;
+    let position = history
+        .iter()
+        .fold(*root, |position, (from, to, promotion)| {
+            position.play(*from, *to, *promotion)
+        });
+    let moves = backend_moves(board.as_ref(), &position);
+    if depth == 1 {
+        return Ok(moves.len() as u64);
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        check_promotion(board.as_ref(), mv)?;
+        history.push(mv);
+        let below = walk_backend(backend, root, history, depth - 1);
+        history.pop();
+        nodes += below?;
+    }
+    Ok(nodes)
+}
+
+fn check_promotion(board: &dyn BoardBackend, (_, _, promotion): Move) -> Result<(), String> {
+    match promotion {
+        Some(_) if !board.supports_promotion() => Err(format!(
+            "The {} backend cannot play the promotions the tree goes through",
+            board.name()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A backend set up on `root` with the moves of `history` played, backends not being able to
+/// take moves back.
+fn replay_backend(
+    backend: BackendKind,
+    root: &Position,
+    history: &[Move],
+) -> Result<Box<dyn BoardBackend>, String> {
+    let mut board = backend.create();
+    if *root != Position::initial() && !board.set_position(root) {
+        return Err(format!(
+            "The {} backend cannot start from a set-up position, use --backend mailbox",
+            board.name()
+        ));
+    }
+    let mut position = *root;
+    for &(from, to, promotion) in history {
+        match position.piece_at(from) {
+            Some((color, Piece::King)) if from.abs_diff(to) == 2 => {
+                board.castle(if to > from { "O-O" } else { "O-O-O" }, color)
+            }
+            _ => board.play_move(from, to, promotion),
+        }
+        position = position.play(from, to, promotion);
+    }
+    Ok(board)
+}
+
+/// The moves a backend finds possible for the side to move of `position`, with the castling
+/// moves of `position`, which the processor plays without asking the backend.
+fn backend_moves(board: &dyn BoardBackend, position: &Position) -> Vec<Move> {
+    let color = position.side_to_move;
+    let mut moves = Vec::new();
+    for piece in Piece::ALL {
+        for from in board.pieces(color, piece) {
+            for to in 0..64 {
+                let castling = piece == Piece::King && from.abs_diff(to) == 2;
+                if from == to || castling || !board.is_possible(from, to) {
+                    continue;
+                }
+                if piece == Piece::Pawn && matches!(to / 8, 0 | 7) {
+                    for promotion in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+                        moves.push((from, to, Some(promotion)));
+                    }
+                } else {
+                    moves.push((from, to, None));
+                }
+            }
+        }
+    }
+    moves.extend(
+        position
+            .castling_moves()
+            .into_iter()
+            .map(|(from, to)| (from, to, None)),
+    );
+    moves
+}
+
+/// Moves the chess crate and the move generator disagree on, found while walking the tree.
+#[derive(Default)]
+pub struct CrateCheck {
+    /// Positions compared.
+    pub positions: u64,
+    /// Each disagreement as the moves leading to it and the move in question.
+    pub mismatches: Vec<String>,
+}
+
+/// Stops collecting mismatches past this many, the walk still counting positions.
+const MAX_MISMATCHES: usize = 20;
+
+/// Replays moves from the initial position on a board of the chess crate, castling being
+/// played through `Board::castle` as the processor does.
+fn replay_board(history: &[(Position, Square, Square)]) -> Board {
+    let mut board = Board::init();
+    for (position, from, to) in history {
+        match position.piece_at(*from) {
+            Some((color, Piece::King)) if from.abs_diff(*to) == 2 => {
+                let castle = if to > from { "O-O" } else { "O-O-O" };
+                board.castle(castle, &color);
+            }
+            _ => board.play_move(&(*from, *to)),
+        }
+    }
+    board
+}
+
+/// Compares `is_possible` with the legal moves of every position of the tree from the initial
+/// position, `depth` plies deep. Castling is left out, the processor handling it itself, and
+/// so are the subtrees of promotions, which the crate's board does not play.
+pub fn check_crate(depth: usize) -> CrateCheck {
+    fn walk(
+        position: &Position,
+        history: &mut Vec<(Position, Square, Square)>,
+        depth: usize,
+        check: &mut CrateCheck,
+    ) {
+        let board = replay_board(history);
+        let legal = position.legal_moves();
+        check.positions += 1;
+
+        let mut report = |mv: String, verdict: &str| {
+            if check.mismatches.len() < MAX_MISMATCHES {
+                let line: Vec<String> = history
+                    .iter()
+                    .map(|(_, from, to)| coordinate_move(*from, *to, None))
+                    .collect();
+                check
+                    .mismatches
+                    .push(format!("after [{}]: {mv} {verdict}", line.join(" ")));
+            }
+        };
+        for from in 0..64 {
+            if !matches!(position.piece_at(from), Some((color, _)) if color == position.side_to_move)
+            {
+                continue;
+            }
+            for to in 0..64 {
+                let castling = matches!(position.piece_at(from), Some((_, Piece::King)))
+                    && from.abs_diff(to) == 2;
+                if from == to || castling {
+                    continue;
+                }
+                let legal_here = legal.iter().any(|mv| mv.0 == from && mv.1 == to);
+                match (is_possible(&board, &(from, to)), legal_here) {
+                    (true, false) => {
+                        report(coordinate_move(from, to, None), "accepted by the crate")
+                    }
+                    (false, true) => {
+                        report(coordinate_move(from, to, None), "rejected by the crate")
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if depth <= 1 {
+            return;
+        }
+        for (from, to, promotion) in legal {
+            if promotion.is_some() {
+                continue;
+            }
+            history.push((*position, from, to));
+            walk(&position.play(from, to, None), history, depth - 1, check);
+            history.pop();
+        }
+    }
+
+    let mut check = CrateCheck::default();
+    if depth > 0 {
+        walk(&Position::initial(), &mut Vec::new(), depth, &mut check);
+    }
+    check
+}
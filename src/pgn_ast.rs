@@ -0,0 +1,206 @@
+use std::borrow::Cow;
+
+use crate::pgn_cleaner::{normalize_result, strip_suffixes, RawGame, Token, Tokens};
+
+/// A game parsed into its tags and move tree. Text borrows from the raw game.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Game<'a> {
+    pub tags: Vec<TagPair<'a>>,
+    pub moves: Line<'a>,
+    /// The termination marker ending the movetext, if any.
+    pub result: Option<GameResult>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TagPair<'a> {
+    pub name: &'a str,
+    pub value: Cow<'a, str>,
+}
+
+/// A sequence of moves: the mainline, or a side line replacing the move it is attached to.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Line<'a> {
+    /// Comments before the first move of the line.
+    pub comments: Vec<Cow<'a, str>>,
+    pub moves: Vec<MoveNode<'a>>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct MoveNode<'a> {
+    /// The SAN move without check or annotation suffixes.
+    pub san: &'a str,
+    /// Check and annotation suffixes as written, such as `+!?`.
+    pub suffix: &'a str,
+    /// Numeric annotation glyphs such as `$1`.
+    pub nags: Vec<&'a str>,
+    /// Comments following the move.
+    pub comments: Vec<Cow<'a, str>>,
+    /// Side lines replacing this move.
+    pub variations: Vec<Line<'a>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Unknown,
+}
+
+impl GameResult {
+    /// Reads a result, tolerating the near misses of `normalize_result`.
+    pub fn parse(marker: &str) -> Option<Self> {
+        match normalize_result(marker)? {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn marker(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+}
+
+impl MoveNode<'_> {
+    /// The move as written, suffixes included.
+    pub fn written(&self) -> String {
+        format!("{}{}", self.san, self.suffix)
+    }
+}
+
+impl<'a> Line<'a> {
+    /// SAN moves of the line, without suffixes.
+    pub fn sans(&self) -> Vec<&'a str> {
+        self.moves.iter().map(|node| node.san).collect()
+    }
+
+    /// Writes the line in export form, starting at `ply` (0 for White's first move).
+    fn write(&self, ply: usize, out: &mut Vec<String>) {
+        for comment in &self.comments {
+            out.push(format!("{{{comment}}}"));
+        }
+        // A move number is needed for Black after anything interrupting the moves
+        let mut needs_number = true;
+        for (i, node) in self.moves.iter().enumerate() {
+            let ply = ply + i;
+            if ply.is_multiple_of(2) {
+                out.push(format!("{}.", ply / 2 + 1));
+            } else if needs_number {
+                out.push(format!("{}...", ply / 2 + 1));
+            }
+            out.push(node.written());
+            out.extend(node.nags.iter().map(|nag| nag.to_string()));
+            for comment in &node.comments {
+                out.push(format!("{{{comment}}}"));
+            }
+            for variation in &node.variations {
+                let mut side = Vec::new();
+                variation.write(ply, &mut side);
+                out.push(format!("({})", side.join(" ")));
+            }
+            needs_number = !node.comments.is_empty() || !node.variations.is_empty();
+        }
+    }
+}
+
+/// Parses movetext into its mainline and result. Unbalanced parentheses are tolerated: a
+/// stray `)` is ignored and side lines left open are closed at the end.
+pub fn parse_movetext(movetext: &str) -> (Line<'_>, Option<GameResult>) {
+    // The mainline, then each side line currently open
+    let mut lines: Vec<Line> = vec![Line::default()];
+    let mut result = None;
+
+    let close = |lines: &mut Vec<Line<'_>>| {
+        let line = lines.pop().unwrap_or_default();
+        // A side line before any move of its parent has nothing to replace
+        if let Some(node) = lines.last_mut().and_then(|parent| parent.moves.last_mut()) {
+            node.variations.push(line);
+        }
+    };
+
+    for (_, token) in Tokens::new(movetext) {
+        match token {
+            Token::VariationStart => lines.push(Line::default()),
+            Token::VariationEnd if lines.len() > 1 => close(&mut lines),
+            Token::Termination(marker) if lines.len() == 1 => result = GameResult::parse(marker),
+            token => {
+                let Some(line) = lines.last_mut() else {
+                    break;
+                };
+                match token {
+                    Token::Move(text) => {
+                        let san = strip_suffixes(text);
+                        if !san.is_empty() {
+                            line.moves.push(MoveNode {
+                                san,
+                                suffix: &text[san.len()..],
+                                nags: Vec::new(),
+                                comments: Vec::new(),
+                                variations: Vec::new(),
+                            });
+                        }
+                    }
+                    Token::Nag(nag) => {
+                        if let Some(node) = line.moves.last_mut() {
+                            node.nags.push(nag);
+                        }
+                    }
+                    Token::Comment(comment) => match line.moves.last_mut() {
+                        Some(node) => node.comments.push(comment.into()),
+                        None => line.comments.push(comment.into()),
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+    while lines.len() > 1 {
+        close(&mut lines);
+    }
+
+    (lines.pop().unwrap_or_default(), result)
+}
+
+impl<'a> Game<'a> {
+    pub fn parse(raw: &'a RawGame) -> Game<'a> {
+        let (moves, result) = parse_movetext(&raw.movetext);
+        Game {
+            tags: raw
+                .tags
+                .iter()
+                .map(|(name, value)| TagPair {
+                    name,
+                    value: value.into(),
+                })
+                .collect(),
+            moves,
+            result,
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|tag| tag.name == name)
+            .map(|tag| tag.value.as_ref())
+    }
+
+    /// The movetext in export form, with move numbers, comments, NAGs, side lines and the
+    /// result.
+    pub fn movetext(&self) -> String {
+        let mut out = Vec::new();
+        self.moves.write(0, &mut out);
+        if let Some(result) = self.result {
+            out.push(result.marker().to_string());
+        }
+        out.join(" ")
+    }
+}
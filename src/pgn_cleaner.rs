@@ -0,0 +1,572 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+
+/// A single game as found in the input: its tag pairs and raw movetext.
+pub struct RawGame {
+    pub tags: Vec<(String, String)>,
+    /// Trimmed movetext lines, each ending with a newline.
+    pub movetext: String,
+    /// Byte offset of the game's first line in the input.
+    pub offset: usize,
+    /// Byte offset just past the game's last line in the input.
+    pub end: usize,
+    /// 1-based input line of the game's first line.
+    pub line: usize,
+    /// Where each movetext line comes from in the input.
+    pub movetext_lines: Vec<LineOrigin>,
+}
+
+/// The input line a movetext line was read from.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LineOrigin {
+    /// 1-based line number in the input.
+    pub line: usize,
+    /// Bytes of leading whitespace trimmed from the input line.
+    pub indent: usize,
+}
+
+impl RawGame {
+    pub fn new(offset: usize) -> Self {
+        RawGame {
+            tags: Vec::new(),
+            movetext: String::new(),
+            offset,
+            end: offset,
+            line: 1,
+            movetext_lines: Vec::new(),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 1-based line and column in the input of a byte offset into the movetext.
+    pub fn location(&self, offset: usize) -> Option<(usize, usize)> {
+        let mut line_start = 0;
+        for (line, origin) in self
+            .movetext
+            .split_inclusive('\n')
+            .zip(&self.movetext_lines)
+        {
+            if offset < line_start + line.len() {
+                let column = self.movetext[line_start..offset].chars().count();
+                return Some((origin.line, origin.indent + column + 1));
+            }
+            line_start += line.len();
+        }
+        None
+    }
+
+    /// The termination marker ending the movetext, normalized as by `normalize_result`.
+    pub fn termination(&self) -> Option<&str> {
+        match tokenize(&self.movetext).last() {
+            Some(Token::Termination(termination)) => normalize_result(termination),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a tag pair line such as `[White "Carlsen, Magnus"]`.
+fn parse_tag_pair(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let value = value.replace("\\\"", "\"");
+    let value = match name {
+        "Result" => normalize_result(&value).map_or(value, str::to_string),
+        _ => value,
+    };
+    Some((name.to_string(), value))
+}
+
+/// Follows brace comments through a movetext line. Returns whether a brace comment is open at
+/// the end of the line, and where the text after the last closed comment starts.
+fn scan_comments(line: &str, mut in_comment: bool) -> (bool, usize) {
+    let mut code_start = 0;
+
+    for (i, byte) in line.bytes().enumerate() {
+        match byte {
+            b'}' if in_comment => {
+                in_comment = false;
+                code_start = i + 1;
+            }
+            b'{' if !in_comment => in_comment = true,
+            // A rest-of-line comment hides any brace that follows it
+            b';' if !in_comment => break,
+            _ => {}
+        }
+    }
+
+    (in_comment, code_start)
+}
+
+/// Maps the Unicode punctuation of PGNs copied from web pages to ASCII: dashes and minus signs
+/// (`O–O`), Unicode spaces, curly quotes, the multiplication sign used for captures and the
+/// ellipsis of `1… e5`. Each character maps to a single one, so columns stay valid.
+pub fn normalize_unicode(line: &str) -> Cow<'_, str> {
+    if line.is_ascii() {
+        return Cow::Borrowed(line);
+    }
+    Cow::Owned(
+        line.chars()
+            .map(|c| match c {
+                '\u{2010}'..='\u{2015}' | '\u{2212}' => '-',
+                '\u{a0}'
+                | '\u{2000}'..='\u{200b}'
+                | '\u{202f}'
+                | '\u{205f}'
+                | '\u{3000}'
+                | '\u{feff}' => ' ',
+                '\u{2018}'..='\u{201b}' => '\'',
+                '\u{201c}'..='\u{201f}' | '\u{ab}' | '\u{bb}' => '"',
+                '\u{d7}' => 'x',
+                '\u{2026}' => '.',
+                c => c,
+            })
+            .collect(),
+    )
+}
+
+/// Splits a PGN database into games. A game ends at its termination marker or where a new tag section starts.
+pub fn split_games(input: &str) -> Vec<RawGame> {
+    split(input, false)
+}
+
+/// Splits a PGN database into games like [`split_games`], reading their tags but not their
+/// movetext, which is only followed for brace comments and termination markers. Fill in the
+/// movetext of the games kept with [`load_movetext`].
+pub fn scan_headers(input: &str) -> Vec<RawGame> {
+    split(input, true)
+}
+
+/// Reads the movetext of a game found by [`scan_headers`] from the same input.
+pub fn load_movetext(input: &str, game: &mut RawGame) {
+    fill_movetext(&input[game.offset..game.end], game);
+}
+
+/// Reads the movetext of a game found by a header scan from the text of the game alone.
+pub fn fill_movetext(text: &str, game: &mut RawGame) {
+    let Some(full) = split(text, false).into_iter().next() else {
+        return;
+    };
+    game.movetext = full.movetext;
+    game.movetext_lines = full.movetext_lines;
+    for origin in &mut game.movetext_lines {
+        origin.line += game.line - 1;
+    }
+}
+
+fn split(input: &str, headers_only: bool) -> Vec<RawGame> {
+    let mut splitter = Splitter::new(headers_only);
+    let mut games: Vec<RawGame> = input
+        .split_inclusive('\n')
+        .filter_map(|line| splitter.push_line(line))
+        .collect();
+    games.extend(splitter.finish());
+    games
+}
+
+/// Splits input fed line by line into games, as [`split_games`] and [`scan_headers`] do, so
+/// that inputs can be split without being held in memory.
+pub struct Splitter {
+    headers_only: bool,
+    current: RawGame,
+    /// Bytes and lines read so far.
+    offset: usize,
+    lines: usize,
+    /// Whether a brace comment is still open at the end of the previous line.
+    in_comment: bool,
+    /// Whether the current game has movetext, which `headers_only` leaves out.
+    has_movetext: bool,
+}
+
+impl Splitter {
+    pub fn new(headers_only: bool) -> Self {
+        Splitter {
+            headers_only,
+            current: RawGame::new(0),
+            offset: 0,
+            lines: 0,
+            in_comment: false,
+            has_movetext: false,
+        }
+    }
+
+    /// Reads the next line of the input, line ending included, returning the game it ends.
+    pub fn push_line(&mut self, raw_line: &str) -> Option<RawGame> {
+        let line_start = self.offset;
+        self.offset += raw_line.len();
+        self.lines += 1;
+        let normalized = normalize_unicode(raw_line);
+        let line = normalized.trim();
+
+        if self.current.tags.is_empty() && !self.has_movetext {
+            if line.is_empty() {
+                return None;
+            }
+            self.current.offset = line_start;
+            self.current.line = self.lines;
+        }
+
+        if !self.in_comment && line.starts_with('[') {
+            let mut finished = None;
+            if self.has_movetext {
+                let mut next = RawGame::new(line_start);
+                next.line = self.lines;
+                finished = Some(std::mem::replace(&mut self.current, next));
+                self.has_movetext = false;
+            }
+            if let Some(tag) = parse_tag_pair(line) {
+                self.current.tags.push(tag);
+            }
+            self.current.end = self.offset;
+            return finished;
+        }
+
+        if !line.is_empty() {
+            self.current.end = self.offset;
+            self.has_movetext = true;
+        }
+        if !self.headers_only {
+            self.current.movetext.push_str(line);
+            self.current.movetext.push('\n');
+            self.current.movetext_lines.push(LineOrigin {
+                line: self.lines,
+                indent: raw_line.len() - raw_line.trim_start().len(),
+            });
+        }
+
+        let code_start;
+        (self.in_comment, code_start) = scan_comments(line, self.in_comment);
+
+        // A termination marker also ends games that are not separated by tag sections. Tokens
+        // other than comments and escaped lines do not span whitespace, so without those the
+        // last word holds the last token.
+        let code = &line[code_start..];
+        let last_code = if self.headers_only && !code.contains(';') && !code.starts_with('%') {
+            code.split_whitespace().next_back().unwrap_or_default()
+        } else {
+            code
+        };
+        let ends_game =
+            !self.in_comment && matches!(tokenize(last_code).last(), Some(Token::Termination(_)));
+        if !ends_game {
+            return None;
+        }
+        self.has_movetext = false;
+        Some(std::mem::replace(
+            &mut self.current,
+            RawGame::new(self.offset),
+        ))
+    }
+
+    /// The last game, left open at the end of the input.
+    pub fn finish(self) -> Option<RawGame> {
+        (!self.current.tags.is_empty() || self.has_movetext).then_some(self.current)
+    }
+}
+
+/// The games of a reader, split by a [`Splitter`] as lines are read, so that no more than the
+/// game being read is held in memory. Offsets count from where the reader starts.
+pub struct GameReader<R> {
+    reader: R,
+    /// Taken once the input is exhausted or fails.
+    splitter: Option<Splitter>,
+    line: String,
+    consumed: usize,
+    games: usize,
+}
+
+impl<R: BufRead> GameReader<R> {
+    pub fn new(reader: R, headers_only: bool) -> Self {
+        GameReader {
+            reader,
+            splitter: Some(Splitter::new(headers_only)),
+            line: String::new(),
+            consumed: 0,
+            games: 0,
+        }
+    }
+
+    /// Bytes read so far, the whole input once the games are exhausted.
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// Games returned so far.
+    pub fn games(&self) -> usize {
+        self.games
+    }
+}
+
+impl<R: BufRead> Iterator for GameReader<R> {
+    type Item = io::Result<RawGame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            let read = self.reader.read_line(&mut self.line);
+            let game = match read {
+                Ok(0) => self.splitter.take()?.finish(),
+                Ok(length) => {
+                    self.consumed += length;
+                    self.splitter.as_mut()?.push_line(&self.line)
+                }
+                Err(error) => {
+                    self.splitter.take()?;
+                    return Some(Err(error));
+                }
+            };
+            if game.is_some() || self.splitter.is_none() {
+                self.games += game.is_some() as usize;
+                return game.map(Ok);
+            }
+        }
+    }
+}
+
+/// A lexical element of movetext. Slices borrow from the movetext.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Token<'a> {
+    /// Move number indication such as `12.` or `12...`.
+    MoveNumber(&'a str),
+    /// SAN move, including any check or annotation suffix.
+    Move(&'a str),
+    /// Brace or rest-of-line comment, without delimiters.
+    Comment(&'a str),
+    /// Numeric annotation glyph such as `$1`.
+    Nag(&'a str),
+    VariationStart,
+    VariationEnd,
+    Termination(&'a str),
+}
+
+/// The standard result of a near-miss result token from scraped PGNs, such as `1–0` with an
+/// en dash, `1-O` with a letter O, `½-½`, `1:0` or `0-1.` with trailing punctuation. Castling
+/// written with zeros is not mistaken for a result.
+pub fn normalize_result(token: &str) -> Option<&'static str> {
+    let token = token.trim().trim_end_matches(['.', ',', ';', ':', '!']);
+    let mut normalized = String::with_capacity(token.len());
+    for c in token.chars() {
+        match c {
+            '\u{2010}'..='\u{2015}' | '\u{2212}' | ':' => normalized.push('-'),
+            'O' | 'o' => normalized.push('0'),
+            'l' | 'I' => normalized.push('1'),
+            '\u{bd}' => normalized.push_str("1/2"),
+            c => normalized.push(c),
+        }
+    }
+    match normalized.as_str() {
+        "*" => Some("*"),
+        "1-0" => Some("1-0"),
+        "0-1" => Some("0-1"),
+        "1/2-1/2" | "0.5-0.5" | "1/2" => Some("1/2-1/2"),
+        _ => None,
+    }
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || matches!(byte, b'{' | b'}' | b'(' | b')' | b';')
+}
+
+/// Tokens of a movetext, borrowed from it.
+pub fn tokenize(movetext: &str) -> impl Iterator<Item = Token<'_>> {
+    Tokens::new(movetext).map(|(_, token)| token)
+}
+
+/// Lazy tokenizer scanning the bytes of a movetext. Yields each token with the byte offset
+/// where it starts; token slices borrow from the movetext, so nothing is copied.
+pub struct Tokens<'a> {
+    movetext: &'a str,
+    position: usize,
+    /// Second token of a symbol such as `12.e4`.
+    pending: Option<(usize, Token<'a>)>,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(movetext: &'a str) -> Self {
+        Tokens {
+            movetext,
+            position: 0,
+            pending: None,
+        }
+    }
+
+    /// Offset of the next `byte` at or after `from`, or the end of the movetext.
+    fn find_byte(&self, from: usize, byte: u8) -> usize {
+        self.movetext.as_bytes()[from..]
+            .iter()
+            .position(|&b| b == byte)
+            .map_or(self.movetext.len(), |end| from + end)
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = (usize, Token<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = self.pending.take() {
+            return Some(pending);
+        }
+
+        let movetext = self.movetext;
+        let bytes = movetext.as_bytes();
+        while self.position < bytes.len() {
+            let start = self.position;
+            let token = match bytes[start] {
+                byte if byte.is_ascii_whitespace() => {
+                    self.position += 1;
+                    continue;
+                }
+                b'{' => {
+                    let end = self.find_byte(start, b'}');
+                    self.position = end + 1;
+                    Token::Comment(movetext[start + 1..end].trim())
+                }
+                b';' => {
+                    let end = self.find_byte(start, b'\n');
+                    self.position = end;
+                    Token::Comment(movetext[start + 1..end].trim())
+                }
+                // Escaped lines are ignored
+                b'%' if start == 0 || bytes[start - 1] == b'\n' => {
+                    self.position = self.find_byte(start, b'\n');
+                    continue;
+                }
+                b'(' => {
+                    self.position += 1;
+                    Token::VariationStart
+                }
+                b')' => {
+                    self.position += 1;
+                    Token::VariationEnd
+                }
+                b'}' => {
+                    self.position += 1;
+                    continue;
+                }
+                _ => {
+                    let length = bytes[start..]
+                        .iter()
+                        .position(|&byte| is_delimiter(byte))
+                        .unwrap_or(bytes.len() - start);
+                    self.position = start + length;
+                    let (token, second) = symbol_tokens(&movetext[start..self.position]);
+                    self.pending = second.map(|(offset, second)| (start + offset, second));
+                    token
+                }
+            };
+            return Some((start, token));
+        }
+
+        None
+    }
+}
+
+/// The token of a symbol, and the second one with its offset in the symbol when a move
+/// number is glued to its move.
+fn symbol_tokens(symbol: &str) -> (Token<'_>, Option<(usize, Token<'_>)>) {
+    if normalize_result(symbol).is_some() {
+        (Token::Termination(symbol), None)
+    } else if symbol.starts_with('$') {
+        (Token::Nag(symbol), None)
+    } else if symbol.starts_with(|c: char| c.is_ascii_digit()) {
+        // Move number, possibly glued to the move as in "12.e4"
+        let digits = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+        let san = digits.trim_start_matches('.');
+        let number_end = symbol.len() - san.len();
+        if number_end > symbol.len() - digits.len() {
+            let san = (!san.is_empty()).then_some((number_end, Token::Move(san)));
+            (Token::MoveNumber(&symbol[..number_end]), san)
+        } else {
+            (Token::Move(symbol), None)
+        }
+    } else {
+        (Token::Move(symbol), None)
+    }
+}
+
+/// SAN moves of the mainline, with check and annotation suffixes removed. Comments,
+/// variations, NAGs, move numbers and the termination marker are skipped.
+pub fn mainline_moves(movetext: &str) -> Vec<&str> {
+    mainline_spans(movetext)
+        .into_iter()
+        .map(|(_, san)| san)
+        .collect()
+}
+
+/// The mainline moves of `mainline_moves`, each with its byte offset in the movetext.
+pub fn mainline_spans(movetext: &str) -> Vec<(usize, &str)> {
+    let mut moves = Vec::new();
+    let mut depth = 0usize;
+
+    for (offset, token) in Tokens::new(movetext) {
+        match token {
+            Token::VariationStart => depth += 1,
+            Token::VariationEnd => depth = depth.saturating_sub(1),
+            Token::Move(san) if depth == 0 => {
+                let san = strip_suffixes(san);
+                if !san.is_empty() {
+                    moves.push((offset, san));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    moves
+}
+
+/// A SAN move without its check and annotation suffixes.
+pub fn strip_suffixes(san: &str) -> &str {
+    san.trim_end_matches(['+', '#', '!', '?'])
+}
+
+/// A side line (RAV) of the movetext.
+pub struct Variation<'a> {
+    /// SAN moves from the start of the game: the path to the branch point, then the side line.
+    pub moves: Vec<&'a str>,
+    /// Number of moves played before the side line diverges.
+    pub branch_ply: usize,
+}
+
+/// Side lines at any depth, in the order they start. A side line replaces the last move
+/// played before it on its parent line.
+pub fn variations(movetext: &str) -> Vec<Variation<'_>> {
+    let mut mainline = Vec::new();
+    let mut variations: Vec<Variation> = Vec::new();
+    // Indices in `variations` of the side lines currently open
+    let mut open: Vec<usize> = Vec::new();
+
+    for token in tokenize(movetext) {
+        let line = match open.last() {
+            Some(&index) => &mut variations[index].moves,
+            None => &mut mainline,
+        };
+        match token {
+            Token::VariationStart => {
+                let branch_ply = line.len().saturating_sub(1);
+                let moves = line[..branch_ply].to_vec();
+                open.push(variations.len());
+                variations.push(Variation { moves, branch_ply });
+            }
+            Token::VariationEnd => {
+                open.pop();
+            }
+            Token::Move(san) => {
+                let san = strip_suffixes(san);
+                if !san.is_empty() {
+                    line.push(san);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    variations
+}
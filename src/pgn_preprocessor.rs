@@ -1,31 +1,274 @@
-use chess::bitboard::BitBoardGetter;
+use std::fmt;
+
 use chess::board::Board;
-use chess::legal_moves::is_move_possible::is_possible;
-use chess::legal_moves::misc::{Color, Square, Type};
+use chess::legal_moves::misc::{Color, Square};
 use chess::utils::{square_to_string, string_to_square};
 
+use crate::backend::{BackendKind, BoardBackend};
+use crate::legality::{explain_illegal, find_recovery, suggest_move, Recovery};
+use crate::notation::InputNotation;
+use crate::pgn_cleaner::{mainline_moves, split_games, RawGame};
+use crate::position::{coordinate_move, is_square_name, CastlingRights, Piece, Position};
+use crate::san::to_san;
+
+/// A problem found while processing a game. Processing of the game stops at the offending move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    /// 1-based ply of the offending move.
+    pub ply: usize,
+    /// Index of the offending token in the processed line.
+    pub token_index: usize,
+    pub token: String,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ply {} ('{}'): {}", self.ply, self.token, self.message)
+    }
+}
+
+pub type WarningHook = Box<dyn FnMut(&Warning)>;
+
+/// Receives the lines tracing how each SAN token is resolved.
+pub type TraceHook = Box<dyn FnMut(&str)>;
+
+/// What to do about an illegal SAN token.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum RepairMode {
+    /// Stop processing the game at the token.
+    #[default]
+    Off,
+    /// Stop, and name the legal move most likely intended in the warning.
+    Suggest,
+    /// Play the most likely intended move instead and go on.
+    Apply,
+}
+
+/// Plies played of a game before it is given up on; generous, real games stay far below it.
+pub const DEFAULT_PLY_LIMIT: usize = 1024;
+
 pub struct PgnProcessor {
-    board: Board,
+    board: Box<dyn BoardBackend>,
     current_turn: Color,
+    castling: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    /// Where lines tracing how each SAN token is resolved go, when tracing.
+    trace_hook: Option<TraceHook>,
+    /// Verify after every ply that the tracked state survives a FEN round trip and a replay.
+    check_invariants: bool,
+    warnings: Vec<Warning>,
+    /// Set when the current game reached an impossible position.
+    corrupt: bool,
+    /// Plies a game may have, a safety limit against runaway inputs.
+    ply_limit: usize,
+    /// Set when the current game was longer than the ply limit.
+    ply_limit_exceeded: bool,
+    warning_hook: Option<WarningHook>,
+    repair: RepairMode,
+    /// Index in the processed line of the token being processed, for warnings.
+    token_index: usize,
+    /// The notation of the move tokens, translated to SAN before resolving them.
+    input_notation: InputNotation,
+    /// Resolve moves whatever their capture marker says, warning on mismatches.
+    lenient_captures: bool,
+    /// Skip or replace a failing token when the rest of the game then plays on.
+    recover: bool,
+    /// Snapshot before each processed ply, followed by the current position.
+    positions: Vec<Position>,
+    /// From and to squares of each processed ply; castling is recorded as the king move.
+    history: Vec<(Square, Square)>,
+    /// Set when the current game started from a set-up position.
+    set_up: bool,
+}
+
+impl Default for PgnProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PgnProcessor {
     pub fn new() -> Self {
-        PgnProcessor {
-            board: Board::init(),
+        let mut processor = PgnProcessor {
+            board: BackendKind::default().create(),
             current_turn: Color::White,
-        }
+            castling: CastlingRights::ALL,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            trace_hook: None,
+            check_invariants: false,
+            warnings: Vec::new(),
+            corrupt: false,
+            ply_limit: DEFAULT_PLY_LIMIT,
+            ply_limit_exceeded: false,
+            warning_hook: None,
+            repair: RepairMode::Off,
+            token_index: 0,
+            input_notation: InputNotation::Algebraic,
+            lenient_captures: false,
+            recover: false,
+            positions: Vec::new(),
+            history: Vec::new(),
+            set_up: false,
+        };
+        processor.positions.push(processor.snapshot());
+        processor
     }
 
     pub fn reset(&mut self) {
-        self.board = Board::init();
+        self.board.reset();
         self.current_turn = Color::White;
+        self.castling = CastlingRights::ALL;
+        self.en_passant = None;
+        self.halfmove_clock = 0;
+        self.fullmove_number = 1;
+        self.positions = vec![self.snapshot()];
+        self.history.clear();
+        self.set_up = false;
+        self.corrupt = false;
+        self.ply_limit_exceeded = false;
+    }
+
+    /// Whether the current game led to an impossible position and should not be trusted.
+    pub fn is_corrupt(&self) -> bool {
+        self.corrupt
+    }
+
+    /// Whether the current game was longer than the ply limit. Such games are also corrupt.
+    pub fn ply_limit_exceeded(&self) -> bool {
+        self.ply_limit_exceeded
+    }
+
+    pub fn ply_limit(&self) -> usize {
+        self.ply_limit
+    }
+
+    pub fn set_ply_limit(&mut self, ply_limit: usize) {
+        self.ply_limit = ply_limit;
+    }
+
+    /// Plays games on another board, starting over from the initial position.
+    pub fn set_backend(&mut self, backend: BackendKind) {
+        self.board = backend.create();
+        self.reset();
+    }
+
+    /// Traces how each SAN token is resolved, handing every line of the trace to `hook`.
+    pub fn set_trace_hook<F: FnMut(&str) + 'static>(&mut self, hook: F) {
+        self.trace_hook = Some(Box::new(hook));
+    }
+
+    pub fn set_check_invariants(&mut self, check_invariants: bool) {
+        self.check_invariants = check_invariants;
+    }
+
+    pub fn set_repair(&mut self, repair: RepairMode) {
+        self.repair = repair;
+    }
+
+    pub fn set_input_notation(&mut self, input_notation: InputNotation) {
+        self.input_notation = input_notation;
+    }
+
+    pub fn set_lenient_captures(&mut self, lenient_captures: bool) {
+        self.lenient_captures = lenient_captures;
+    }
+
+    pub fn set_recover(&mut self, recover: bool) {
+        self.recover = recover;
+    }
+
+    /// Registers a callback invoked for every warning as it is raised.
+    pub fn set_warning_hook<F: FnMut(&Warning) + 'static>(&mut self, hook: F) {
+        self.warning_hook = Some(Box::new(hook));
     }
 
-    fn process_move(&mut self, move_str: &str, line_index: usize) -> Option<String> {
+    /// Warnings collected since the last call.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn warn(&mut self, warning: Warning) {
+        if let Some(hook) = &mut self.warning_hook {
+            hook(&warning);
+        }
+        self.warnings.push(warning);
+    }
+
+    /// The current position, including the state the board itself does not report.
+    pub fn snapshot(&self) -> Position {
+        let mut squares = [None; 64];
+        for color in [Color::White, Color::Black] {
+            for piece in Piece::ALL {
+                for square in self.board.pieces(color, piece) {
+                    squares[square as usize] = Some((color, piece));
+                }
+            }
+        }
+
+        Position {
+            squares,
+            side_to_move: self.current_turn,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+    }
+
+    /// Positions of the current game: the one before each processed ply, then the current one.
+    pub fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+
+    /// From and to squares of each processed ply of the current game.
+    pub fn history(&self) -> &[(Square, Square)] {
+        &self.history
+    }
+
+    /// Position of the current game after `ply` plies; ply 0 is the starting position.
+    pub fn position_at(&self, ply: usize) -> Option<&Position> {
+        self.positions.get(ply)
+    }
+
+    pub fn fen_at(&self, ply: usize) -> Option<String> {
+        self.position_at(ply).map(Position::to_fen)
+    }
+
+    /// A board of the `chess` crate for the current game after `ply` plies, replayed from the
+    /// initial position whatever the backend. Games from a set-up position have none.
+    pub fn board_at(&self, ply: usize) -> Option<Board> {
+        if ply > self.history.len() || self.set_up {
+            return None;
+        }
+
+        let mut board = Board::init();
+        for (index, (start, end)) in self.history[..ply].iter().enumerate() {
+            let position = &self.positions[index];
+            match position.piece_at(*start) {
+                Some((color, Piece::King)) if start.abs_diff(*end) == 2 => {
+                    let castle = if end > start { "O-O" } else { "O-O-O" };
+                    board.castle(castle, &color);
+                }
+                _ => board.play_move(&(*start, *end)),
+            }
+        }
+
+        Some(board)
+    }
+
+    fn process_move(&mut self, move_str: &str) -> Result<String, String> {
+        self.trace(|| format!("{move_str}:"));
+
         // Handle castling
         if move_str == "O-O" || move_str == "O-O-O" {
-            self.board.castle(move_str, &self.current_turn);
+            self.board.castle(move_str, self.current_turn);
+            self.castling.remove_color(self.current_turn);
+            self.en_passant = None;
 
             let rank = if self.current_turn == Color::White {
                 "1"
@@ -35,46 +278,171 @@ impl PgnProcessor {
             let starting_square = format!("e{rank}");
             let ending_file = if move_str == "O-O" { "g" } else { "c" };
             let ending_square = format!("{ending_file}{rank}");
+            self.history.push((
+                string_to_square(&starting_square),
+                string_to_square(&ending_square),
+            ));
 
+            self.advance_clocks(false);
             self.current_turn = !self.current_turn;
-            return Some(format!("{starting_square}{ending_square}"));
+            self.trace(|| format!("  castling -> {starting_square}{ending_square}"));
+            return Ok(format!("{starting_square}{ending_square}"));
         }
 
         // Remove check/checkmate symbols
         let cleaned_move = move_str.trim_end_matches('+').trim_end_matches('#');
 
         // Parse the move
-        if let Some((start, end)) = self.parse_move(cleaned_move, line_index) {
-            let move_tuple = (start, end);
-            if is_possible(&self.board, &move_tuple) {
-                let result = format!("{}{}", square_to_string(start), square_to_string(end));
+        let (start, end, promotion) = self.parse_move(cleaned_move)?;
+        if self.board.is_possible(start, end) {
+            let result = coordinate_move(start, end, promotion);
+            let before = self
+                .positions
+                .last()
+                .copied()
+                .unwrap_or_else(|| self.snapshot());
+            let is_pawn = matches!(before.piece_at(start), Some((_, Piece::Pawn)));
+            let is_capture = before.is_capture(start, end);
+
+            // Update board state
+            self.board.play_move(start, end, promotion);
+            self.history.push((start, end));
+            self.castling.update(start, end);
+            self.en_passant = (is_pawn && start.abs_diff(end) == 16).then_some((start + end) / 2);
+            self.advance_clocks(is_pawn || is_capture);
+            self.current_turn = !self.current_turn;
 
-                // Update board state
-                self.board.play_move(&move_tuple);
-                self.current_turn = !self.current_turn;
+            self.trace(|| format!("  -> {result}"));
 
-                return Some(result);
-            }
+            return Ok(result);
         }
 
-        panic!("Invalid move: {move_str}\n at line {line_index}");
+        Err(format!("Invalid move: {move_str}"))
+    }
+
+    /// Updates the move counters for the side to move having played a move.
+    fn advance_clocks(&mut self, irreversible: bool) {
+        self.halfmove_clock = if irreversible {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        if self.current_turn == Color::Black {
+            self.fullmove_number += 1;
+        }
     }
 
-    fn parse_move(&self, move_str: &str, line_index: usize) -> Option<(Square, Square)> {
+    /// Compares the state after the last ply with its FEN round trip and with the previous
+    /// snapshot replayed by the mailbox model.
+    fn invariant_violations(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+        let [.., before, after] = &self.positions[..] else {
+            return violations;
+        };
+        let Some((start, end)) = self.history.last() else {
+            return violations;
+        };
+
+        let fen = after.to_fen();
+        match Position::from_fen(&fen) {
+            Ok(parsed) if parsed == *after && parsed.to_fen() == fen => {}
+            Ok(parsed) => violations.push(format!(
+                "FEN round trip drifted: {fen} became {}",
+                parsed.to_fen()
+            )),
+            Err(message) => violations.push(format!("FEN round trip failed: {message}")),
+        }
+
+        // The promotion piece, if any, is the one standing on the target square
+        let promotion = after.piece_at(*end).map(|(_, piece)| piece);
+        let replayed = before.play(*start, *end, promotion);
+        if replayed != *after {
+            violations.push(format!(
+                "Board and tracked state disagree: expected {}, board has {fen}",
+                replayed.to_fen()
+            ));
+        }
+
+        violations
+    }
+
+    fn trace(&mut self, line: impl FnOnce() -> String) {
+        if let Some(hook) = &mut self.trace_hook {
+            hook(&line());
+        }
+    }
+
+    fn trace_candidate(&mut self, square: Square, verdict: &str) {
+        self.trace(|| format!("  {} {verdict}", square_to_string(square)));
+    }
+
+    /// Why the board rejected a move, worked out on a snapshot of the current position.
+    fn rejection_reason(&self, start: Square, target: Square) -> &'static str {
+        let position = self.snapshot();
+
+        if !position.pseudo_legal_targets(start).contains(&target) {
+            "rejected: cannot reach target"
+        } else if !position
+            .play(start, target, None)
+            .in_check(self.current_turn)
+        {
+            "rejected by board"
+        } else if position.in_check(self.current_turn) {
+            "rejected: king stays in check"
+        } else {
+            "rejected: pinned"
+        }
+    }
+
+    fn parse_move(&mut self, move_str: &str) -> Result<(Square, Square, Option<Piece>), String> {
+        let first = move_str
+            .chars()
+            .next()
+            .ok_or_else(|| "Empty move".to_string())?;
+
+        // Handle long algebraic notation (e.g., e2-e4, Ng1-f3, Rh1xh8)
+        if let Some((start, end)) = self.parse_long_move(move_str) {
+            return Ok((start, end, None));
+        }
+
         // Handle pawn moves (e.g., e4, exd5, e8=Q)
-        if move_str.chars().next()?.is_lowercase() {
+        if first.is_lowercase() {
             return self.parse_pawn_move(move_str);
         }
 
         // Handle piece moves (e.g., Nf3, Raxa1, Qh4e1)
-        if let Some(piece_type) = Self::get_piece_type(move_str.chars().next()?) {
-            return self.parse_piece_move(move_str, piece_type);
+        if let Some(piece_type) = Self::get_piece_type(first) {
+            return self
+                .parse_piece_move(move_str, piece_type)
+                .map(|(start, end)| (start, end, None));
         }
 
-        panic!("Invalid piece type at line {line_index}, move: {move_str}");
+        Err(format!("Invalid piece type, move: {move_str}"))
     }
 
-    fn parse_pawn_move(&self, move_str: &str) -> Option<(Square, Square)> {
+    /// Reads a move spelled out as origin, '-' or 'x', and target, provided the named
+    /// piece of the side to move stands on the origin square.
+    fn parse_long_move(&self, move_str: &str) -> Option<(Square, Square)> {
+        let (piece_type, squares) = match move_str.chars().next()? {
+            first if first.is_uppercase() => (Self::get_piece_type(first)?, &move_str[1..]),
+            _ => (Piece::Pawn, move_str),
+        };
+        let (from, to) = squares.split_once(['-', 'x'])?;
+        if !is_square_name(from) || !is_square_name(to) {
+            return None;
+        }
+
+        let start = string_to_square(from);
+        self.board
+            .pieces(self.current_turn, piece_type)
+            .contains(&start)
+            .then(|| (start, string_to_square(to)))
+    }
+
+    fn parse_pawn_move(
+        &mut self,
+        move_str: &str,
+    ) -> Result<(Square, Square, Option<Piece>), String> {
         let chars: Vec<char> = move_str.chars().collect();
         let mut idx = 0;
 
@@ -86,51 +454,77 @@ impl PgnProcessor {
         };
 
         if is_capture {
+            if !('a'..='h').contains(&chars[0]) {
+                return Err(format!("Invalid file: {move_str}"));
+            }
             idx += 2; // Skip capture notation (e.g., 'x')
         }
 
         // Parse target square
-        if chars.len() - idx < 2 {
-            return None;
+        if chars.len() < idx + 2 {
+            return Err(format!("Missing target square: {move_str}"));
         }
 
         let target_str: String = chars[idx..].iter().take(2).collect();
+        if !is_square_name(&target_str) {
+            return Err(format!("Invalid target square: {move_str}"));
+        }
         let target_square = string_to_square(&target_str);
         idx += 2;
 
         // Check for promotion (e.g., e8=Q)
+        let mut promotion = None;
         if idx < chars.len() && chars[idx] == '=' {
-            todo!("Promotion");
+            if !self.board.supports_promotion() {
+                return Err(format!("Promotion is not supported: {move_str}"));
+            }
+            promotion = chars
+                .get(idx + 1)
+                .copied()
+                .and_then(Self::get_piece_type)
+                .filter(|piece| *piece != Piece::King);
+            if promotion.is_none() || !matches!(target_square / 8, 0 | 7) {
+                return Err(format!("Invalid promotion: {move_str}"));
+            }
         }
 
         // Find the pawn that can make this move
-        let pawns = self.board.get_bitboard(&self.current_turn, &Type::Pawn);
         let mut possible_starts = vec![];
 
-        for start_square in pawns.get_occupied_squares() {
+        for start_square in self.board.pieces(self.current_turn, Piece::Pawn) {
             // Check file if specified (for captures or disambiguation)
             if let Some(file) = file {
                 let start_file = start_square % 8;
                 let expected_file = file as u8 - b'a';
                 if start_file != expected_file {
+                    self.trace_candidate(start_square, "skipped: wrong file");
                     continue;
                 }
             }
 
-            let move_tuple = (start_square, target_square);
-            if is_possible(&self.board, &move_tuple) {
+            if self.board.is_possible(start_square, target_square) {
+                self.trace_candidate(start_square, "possible");
                 possible_starts.push(start_square);
+            } else if self.trace_hook.is_some() {
+                self.trace_candidate(
+                    start_square,
+                    self.rejection_reason(start_square, target_square),
+                );
             }
         }
 
         if possible_starts.len() == 1 {
-            return Some((possible_starts[0], target_square));
+            return Ok((possible_starts[0], target_square, promotion));
         }
 
-        None
+        Err(format!("No unique pawn for move: {move_str}"))
     }
 
-    fn parse_piece_move(&self, move_str: &str, piece_type: Type) -> Option<(Square, Square)> {
+    fn parse_piece_move(
+        &mut self,
+        move_str: &str,
+        piece_type: Piece,
+    ) -> Result<(Square, Square), String> {
         let chars: Vec<char> = move_str.chars().collect();
         let mut idx = 1; // Skip piece character
 
@@ -139,39 +533,41 @@ impl PgnProcessor {
         let mut rank_disambig = None;
 
         // Check for file disambiguation (e.g., Nbd2)
-        if idx + 2 < chars.len() && chars[idx].is_lowercase() && chars[idx] != 'x' {
+        if idx + 2 < chars.len() && ('a'..='h').contains(&chars[idx]) {
             file_disambig = Some(chars[idx]);
             idx += 1;
         }
         // Check for rank disambiguation (e.g., N1d2)
-        if idx + 2 < chars.len() && chars[idx].is_ascii_digit() {
+        if idx + 2 < chars.len() && ('1'..='8').contains(&chars[idx]) {
             rank_disambig = Some(chars[idx]);
             idx += 1;
         }
 
         // Check for capture (e.g., Nxd2)
-        if chars[idx] == 'x' {
+        if chars.get(idx) == Some(&'x') {
             idx += 1;
         }
 
         if idx + 2 > chars.len() {
-            return None;
+            return Err(format!("Missing target square: {move_str}"));
         }
 
         let target_str: String = chars[idx..].iter().take(2).collect();
+        if !is_square_name(&target_str) {
+            return Err(format!("Invalid target square: {move_str}"));
+        }
         let target_square = string_to_square(&target_str);
 
         // Find the piece that can make this move
-        let pieces = self.board.get_bitboard(&self.current_turn, &piece_type);
-
         let mut possible_starts = vec![];
 
-        for start_square in pieces.get_occupied_squares() {
+        for start_square in self.board.pieces(self.current_turn, piece_type) {
             // Check file disambiguation if specified
             if let Some(file) = file_disambig {
                 let start_file = start_square % 8;
                 let expected_file = file as u8 - b'a';
                 if start_file != expected_file {
+                    self.trace_candidate(start_square, "skipped: wrong file");
                     continue;
                 }
             }
@@ -181,74 +577,268 @@ impl PgnProcessor {
                 let start_rank = start_square / 8;
                 let expected_rank = rank as u8 - b'1';
                 if start_rank != expected_rank {
+                    self.trace_candidate(start_square, "skipped: wrong rank");
                     continue;
                 }
             }
 
-            let move_tuple = (start_square, target_square);
-            if is_possible(&self.board, &move_tuple) {
+            if self.board.is_possible(start_square, target_square) {
+                self.trace_candidate(start_square, "possible");
                 possible_starts.push(start_square);
+            } else if self.trace_hook.is_some() {
+                self.trace_candidate(
+                    start_square,
+                    self.rejection_reason(start_square, target_square),
+                );
             }
         }
 
         if possible_starts.len() == 1 {
-            return Some((possible_starts[0], target_square));
+            return Ok((possible_starts[0], target_square));
+        }
+        if possible_starts.is_empty() {
+            return Err(format!("No piece can play move: {move_str}"));
         }
 
-        panic!(
-            "Ambiguous move: {}\n target: {}\n possible_starts: {:?}",
+        Err(format!(
+            "Ambiguous move: {}, target: {}, possible_starts: {:?}",
             move_str,
             target_str,
             possible_starts
                 .iter()
                 .map(|s| square_to_string(*s))
                 .collect::<Vec<String>>(),
-        );
+        ))
     }
 
-    fn get_piece_type(c: char) -> Option<Type> {
+    fn get_piece_type(c: char) -> Option<Piece> {
         match c {
-            'N' => Some(Type::Knight),
-            'B' => Some(Type::Bishop),
-            'R' => Some(Type::Rook),
-            'Q' => Some(Type::Queen),
-            'K' => Some(Type::King),
+            'N' => Some(Piece::Knight),
+            'B' => Some(Piece::Bishop),
+            'R' => Some(Piece::Rook),
+            'Q' => Some(Piece::Queen),
+            'K' => Some(Piece::King),
             _ => None,
         }
     }
 
+    /// Processes every game of a PGN database. Each game's moves are preceded by a "\n" marker.
     pub fn process_pgn(&mut self, pgn: &str) -> Vec<String> {
-        let cleaned_pgn = pgn
-            .replace("\n", " ")
-            .replace("+", "")
-            .replace("#", "")
-            .replace("1/2-1/2", "")
-            .replace("1-0", "")
-            .replace("0-1", "")
-            .lines()
-            .filter(|line| !line.is_empty())
-            .filter(|line| !line.starts_with("["))
-            .collect::<String>();
+        let mut result = Vec::new();
 
-        let mut result: Vec<String> = Vec::new();
+        for game in split_games(pgn) {
+            let moves = self.process_game(&game);
+            result.push("\n".to_string());
+            result.extend(moves);
+        }
 
-        for (line_index, token) in cleaned_pgn.split_whitespace().enumerate() {
-            if token == "1." {
-                self.reset();
-                result.push("\n".to_string());
-            }
+        result
+    }
+
+    /// Processes the mainline of a single game from its FEN tag, or the initial position.
+    pub fn process_game(&mut self, game: &RawGame) -> Vec<String> {
+        let moves = mainline_moves(&game.movetext);
+        match game.tag("FEN") {
+            Some(fen) => self.process_line_from(fen, &moves),
+            None => self.process_line(&moves),
+        }
+    }
 
-            // Skip move numbers and game headers
-            if token.ends_with('.') || token.starts_with('[') {
-                continue;
+    /// Processes a line of SAN moves from a position given as FEN. A FEN that is invalid, or
+    /// that the backend cannot set up, makes the game corrupt.
+    pub fn process_line_from(&mut self, fen: &str, moves: &[&str]) -> Vec<String> {
+        self.reset();
+        let set_up = Position::from_fen(fen).and_then(|position| {
+            if !self.board.set_position(&position) {
+                return Err(format!(
+                    "The {} backend cannot start from a set-up position, use --backend mailbox",
+                    self.board.name()
+                ));
+            }
+            Ok(position)
+        });
+        match set_up {
+            Ok(position) => {
+                self.current_turn = position.side_to_move;
+                self.castling = position.castling;
+                self.en_passant = position.en_passant;
+                self.halfmove_clock = position.halfmove_clock;
+                self.fullmove_number = position.fullmove_number;
+                self.positions = vec![self.snapshot()];
+                self.set_up = true;
+                self.process_moves(moves)
             }
+            Err(message) => {
+                self.corrupt = true;
+                self.warn(Warning {
+                    ply: 0,
+                    // Past the moves, as the FEN is not a movetext token
+                    token_index: moves.len(),
+                    token: fen.to_string(),
+                    message,
+                });
+                Vec::new()
+            }
+        }
+    }
 
-            println!("{token}");
+    /// Processes a line of SAN moves from the initial position.
+    pub fn process_line(&mut self, moves: &[&str]) -> Vec<String> {
+        self.reset();
+        self.process_moves(moves)
+    }
 
-            if let Some(processed_move) = self.process_move(token, line_index) {
-                result.push(processed_move);
-            } else {
-                panic!("Could not process move '{token}' at line {line_index}");
+    /// Reports a token that could not be processed and, depending on the repair mode, plays the
+    /// legal move most likely intended instead. Returns the move played, if any.
+    fn repair_move(&mut self, token: &str, message: &str) -> Option<String> {
+        let ply = self.positions.len();
+        let position = self.snapshot();
+
+        // Explain moves the rules forbid; the rest are limitations of the board
+        if position.parse_san(token).is_ok() {
+            self.warn(Warning {
+                ply,
+                token_index: self.token_index,
+                token: token.to_string(),
+                message: format!("Could not process move: {message}"),
+            });
+            return None;
+        }
+        let explanation = explain_illegal(&position, token);
+        let suggestion = match self.repair {
+            RepairMode::Off => None,
+            RepairMode::Suggest | RepairMode::Apply => suggest_move(&position, token),
+        };
+
+        let replacement = suggestion
+            .as_deref()
+            .filter(|_| self.repair == RepairMode::Apply)
+            .and_then(|suggestion| Some((suggestion, self.process_move(suggestion).ok()?)));
+        let message = match (&replacement, &suggestion) {
+            (Some((suggestion, _)), _) => format!("{explanation}; replaced by {suggestion}"),
+            (None, Some(suggestion)) => format!("{explanation}; did you mean {suggestion}?"),
+            (None, None) => explanation,
+        };
+        self.warn(Warning {
+            ply,
+            token_index: self.token_index,
+            token: token.to_string(),
+            message,
+        });
+
+        replacement.map(|(_, processed_move)| processed_move)
+    }
+
+    /// With lenient captures, a token whose capture marker disagrees with the board is
+    /// reported and returned in normalized form.
+    fn check_capture_marker(&mut self, token: &str) -> Option<String> {
+        if !self.lenient_captures {
+            return None;
+        }
+        let position = self.snapshot();
+        let (from, to, promotion) = position.parse_san(token).ok()?;
+        if position.is_capture(from, to) == token.contains('x') {
+            return None;
+        }
+
+        let normalized = to_san(&position, from, to, promotion);
+        self.warn(Warning {
+            ply: self.positions.len(),
+            token_index: self.token_index,
+            token: token.to_string(),
+            message: format!("Capture marker mismatch, read as {normalized}"),
+        });
+        Some(normalized)
+    }
+
+    /// Looks for a way to resume after the failing `tokens[0]`. Returns the move played in its
+    /// place, or the number of tokens to skip.
+    fn recover_from(&mut self, tokens: &[&str]) -> Option<Result<String, usize>> {
+        let ply = self.positions.len();
+        let recovery = find_recovery(&self.snapshot(), tokens)?;
+        let (outcome, message) = match recovery {
+            Recovery::Skip(count) => (
+                Err(count),
+                format!("Recovered by skipping {}", tokens[..count].join(" ")),
+            ),
+            Recovery::Replace(san) => (
+                Ok(self.process_move(&san).ok()?),
+                format!("Recovered by playing {san} instead"),
+            ),
+        };
+        self.warn(Warning {
+            ply,
+            token_index: self.token_index,
+            token: tokens[0].to_string(),
+            message,
+        });
+
+        Some(outcome)
+    }
+
+    /// Plays SAN moves from the current position. Processing stops at the first failing move,
+    /// unless the repair mode replaces it or recovery finds a way past it.
+    fn process_moves(&mut self, moves: &[&str]) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+
+        let mut index = 0;
+        while let Some(token) = moves.get(index) {
+            self.token_index = index;
+            index += 1;
+            if self.history.len() >= self.ply_limit {
+                self.corrupt = true;
+                self.ply_limit_exceeded = true;
+                self.warn(Warning {
+                    ply: self.positions.len(),
+                    token_index: self.token_index,
+                    token: token.to_string(),
+                    message: format!("Game exceeds the limit of {} plies", self.ply_limit),
+                });
+                break;
+            }
+            let translated = self.input_notation.to_algebraic(&self.snapshot(), token);
+            let token = translated.as_deref().unwrap_or(token);
+            let normalized = self.check_capture_marker(token);
+            let token = normalized.as_deref().unwrap_or(token);
+            let processed_move = match self.process_move(token) {
+                Ok(processed_move) => processed_move,
+                Err(message) => match self.repair_move(token, &message) {
+                    Some(processed_move) => processed_move,
+                    None if self.recover => match self.recover_from(&moves[index - 1..]) {
+                        Some(Ok(processed_move)) => processed_move,
+                        Some(Err(skipped)) => {
+                            index += skipped - 1;
+                            continue;
+                        }
+                        None => break,
+                    },
+                    None => break,
+                },
+            };
+            result.push(processed_move);
+            self.positions.push(self.snapshot());
+
+            let errors = self.positions.last().map(Position::sanity_errors);
+            if let Some(errors) = errors.filter(|errors| !errors.is_empty()) {
+                self.corrupt = true;
+                self.warn(Warning {
+                    ply: self.history.len(),
+                    token_index: self.token_index,
+                    token: token.to_string(),
+                    message: format!("Corrupt position: {}", errors.join(", ")),
+                });
+                break;
+            }
+
+            if self.check_invariants {
+                for message in self.invariant_violations() {
+                    self.warn(Warning {
+                        ply: self.history.len(),
+                        token_index: self.token_index,
+                        token: token.to_string(),
+                        message,
+                    });
+                }
             }
         }
 
@@ -0,0 +1,80 @@
+use chess::legal_moves::misc::Color;
+
+use crate::position::{Piece, Position};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Opening => "opening",
+            Phase::Middlegame => "middlegame",
+            Phase::Endgame => "endgame",
+        }
+    }
+
+    /// Phase suggested by a single position: endgames by reduced material, middlegames by
+    /// development, castling or early trades.
+    pub fn of(position: &Position) -> Phase {
+        let mut material = 0;
+        let mut queens = 0;
+        for (_, piece) in position.squares.iter().flatten() {
+            if *piece != Piece::Pawn {
+                material += piece.value();
+            }
+            if *piece == Piece::Queen {
+                queens += 1;
+            }
+        }
+        if material <= 26 || (queens == 0 && material <= 32) {
+            return Phase::Endgame;
+        }
+
+        let minor_homes = [
+            (1, Color::White, Piece::Knight),
+            (6, Color::White, Piece::Knight),
+            (2, Color::White, Piece::Bishop),
+            (5, Color::White, Piece::Bishop),
+            (57, Color::Black, Piece::Knight),
+            (62, Color::Black, Piece::Knight),
+            (58, Color::Black, Piece::Bishop),
+            (61, Color::Black, Piece::Bishop),
+        ];
+        let undeveloped = minor_homes
+            .iter()
+            .filter(|(square, color, piece)| position.piece_at(*square) == Some((*color, *piece)))
+            .count();
+        let castled = [(Color::White, [2, 6]), (Color::Black, [58, 62])]
+            .iter()
+            .any(|(color, squares)| {
+                squares
+                    .iter()
+                    .any(|square| position.piece_at(*square) == Some((*color, Piece::King)))
+            });
+
+        // 62 is the non-pawn material of the starting position
+        if undeveloped <= 4 || castled || material <= 54 {
+            Phase::Middlegame
+        } else {
+            Phase::Opening
+        }
+    }
+}
+
+/// Phase of each position. Phases never go back, so each game has at most two boundaries.
+pub fn phases(positions: &[Position]) -> Vec<Phase> {
+    let mut current = Phase::Opening;
+
+    positions
+        .iter()
+        .map(|position| {
+            current = current.max(Phase::of(position));
+            current
+        })
+        .collect()
+}
@@ -0,0 +1,205 @@
+//! The life of each piece of a game, followed from the square it starts on: the squares it
+//! moves to, the ply it is captured on and what a pawn promotes to. Pieces keep the name of
+//! their start square, as `Ng1`, through promotions.
+
+use std::collections::BTreeMap;
+
+use chess::legal_moves::misc::{Color, Square};
+use chess::utils::square_to_string;
+
+use crate::json;
+use crate::position::{color_index, color_name, parse_coordinate_move, Piece, Position};
+
+/// A piece over a game.
+#[derive(Clone, PartialEq)]
+pub struct PieceLife {
+    pub color: Color,
+    /// The piece as it starts.
+    pub piece: Piece,
+    pub start: Square,
+    /// The square each of its moves reaches, castling included for the rook.
+    pub path: Vec<Square>,
+    /// Ply it is captured on.
+    pub captured: Option<usize>,
+    /// What a pawn promotes to, and on which ply.
+    pub promoted: Option<(Piece, usize)>,
+}
+
+impl PieceLife {
+    /// The piece letter and start square, such as `Ng1` or `Pe2`.
+    pub fn label(&self) -> String {
+        format!("{}{}", self.piece.letter(), square_to_string(self.start))
+    }
+
+    pub fn to_json(&self) -> String {
+        let path = json::array(
+            self.path
+                .iter()
+                .map(|square| json::string(&square_to_string(*square))),
+        );
+        let promoted = match self.promoted {
+            Some((piece, ply)) => json::object([
+                ("piece", json::string(&piece.letter().to_string())),
+                ("ply", ply.to_string()),
+            ]),
+            None => "null".to_string(),
+        };
+        json::object([
+            ("piece", json::string(&self.label())),
+            ("color", json::string(color_name(self.color))),
+            ("moves", self.path.len().to_string()),
+            ("path", path),
+            (
+                "captured",
+                self.captured
+                    .map_or("null".to_string(), |ply| ply.to_string()),
+            ),
+            ("promoted", promoted),
+        ])
+    }
+}
+
+/// The lives of the pieces of a game, in the order of their start squares. `positions` holds
+/// the position before each move. Pieces are followed up to the first move that cannot be
+/// read.
+pub fn piece_lives(positions: &[Position], moves: &[String]) -> Vec<PieceLife> {
+    let Some(start) = positions.first() else {
+        return Vec::new();
+    };
+    let mut lives = Vec::new();
+    // The life of the piece standing on each square
+    let mut on_square: [Option<usize>; 64] = [None; 64];
+    for (square, occupant) in start.squares.iter().enumerate() {
+        if let Some((color, piece)) = *occupant {
+            on_square[square] = Some(lives.len());
+            lives.push(PieceLife {
+                color,
+                piece,
+                start: square as Square,
+                path: Vec::new(),
+                captured: None,
+                promoted: None,
+            });
+        }
+    }
+
+    for (index, (position, mv)) in positions.iter().zip(moves).enumerate() {
+        let ply = index + 1;
+        let Some((from, to, promotion)) = parse_coordinate_move(mv) else {
+            break;
+        };
+        let (Some(mover), Some((color, piece))) =
+            (on_square[from as usize], position.piece_at(from))
+        else {
+            break;
+        };
+
+        // En passant takes the pawn beside the origin, not on the target square
+        let captured_square = match (piece, position.piece_at(to)) {
+            (Piece::Pawn, None) if from % 8 != to % 8 => from - from % 8 + to % 8,
+            _ => to,
+        };
+        if position
+            .piece_at(captured_square)
+            .is_some_and(|(owner, _)| owner != color)
+        {
+            if let Some(captured) = on_square[captured_square as usize].take() {
+                lives[captured].captured = Some(ply);
+            }
+        }
+
+        on_square[from as usize] = None;
+        on_square[to as usize] = Some(mover);
+        lives[mover].path.push(to);
+        if let Some(promoted) = promotion {
+            lives[mover].promoted = Some((promoted, ply));
+        }
+
+        if piece == Piece::King && from.abs_diff(to) == 2 {
+            let (rook_from, rook_to) = if to > from {
+                (from + 3, from + 1)
+            } else {
+                (from - 4, from - 1)
+            };
+            if let Some(rook) = on_square[rook_from as usize].take() {
+                on_square[rook_to as usize] = Some(rook);
+                lives[rook].path.push(rook_to);
+            }
+        }
+    }
+    lives
+}
+
+/// Totals over the games of the pieces starting on one square.
+#[derive(Clone, Copy, Default)]
+struct Totals {
+    games: usize,
+    captured: usize,
+    capture_plies: usize,
+    moves: usize,
+    promoted: usize,
+}
+
+/// Piece lives over a database, by color, start square and piece.
+#[derive(Default)]
+pub struct LifetimeStats {
+    totals: BTreeMap<(usize, usize, usize), Totals>,
+}
+
+impl LifetimeStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, lives: &[PieceLife]) {
+        for life in lives {
+            let key = (
+                color_index(life.color),
+                life.start as usize,
+                life.piece.index(),
+            );
+            let totals = self.totals.entry(key).or_default();
+            totals.games += 1;
+            totals.moves += life.path.len();
+            if let Some(ply) = life.captured {
+                totals.captured += 1;
+                totals.capture_plies += ply;
+            }
+            totals.promoted += usize::from(life.promoted.is_some());
+        }
+    }
+
+    /// A row per piece, white's first, in start square order: the games it appears in, the
+    /// share of them it is captured in, the mean ply of its capture, its mean number of moves
+    /// and the games it promotes in.
+    pub fn to_tsv(&self) -> String {
+        let mut tsv =
+            String::from("piece\tcolor\tgames\tcaptured\tmean_capture_ply\tmean_moves\tpromoted\n");
+        for (&(color, square, piece), totals) in &self.totals {
+            let color = if color == 0 {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let label = format!(
+                "{}{}",
+                Piece::ALL[piece].letter(),
+                square_to_string(square as Square)
+            );
+            let games = totals.games as f64;
+            let capture_ply = match totals.captured {
+                0 => String::new(),
+                captured => format!("{:.1}", totals.capture_plies as f64 / captured as f64),
+            };
+            tsv.push_str(&format!(
+                "{label}\t{}\t{}\t{:.3}\t{capture_ply}\t{:.1}\t{}\n",
+                color_name(color),
+                totals.games,
+                totals.captured as f64 / games,
+                totals.moves as f64 / games,
+                totals.promoted
+            ));
+        }
+        tsv
+    }
+}
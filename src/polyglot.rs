@@ -0,0 +1,209 @@
+//! Polyglot opening books: sorted 16-byte entries of a position key, a move and a weight.
+//! Keys hash the position with the 781 `Random64` numbers of the Polyglot specification,
+//! which are read from a file rather than bundled.
+
+use chess::legal_moves::misc::{Color, Square};
+
+use crate::position::{coordinate_move, Piece, Position};
+
+/// The size of the Polyglot `Random64` table.
+pub const RANDOM_COUNT: usize = 781;
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+/// The `Random64` numbers keys are computed from.
+pub struct PolyglotKeys(Vec<u64>);
+
+impl PolyglotKeys {
+    /// Reads the numbers as hexadecimal, with or without `0x`, separated by whitespace or
+    /// commas, as they appear in the specification.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let randoms = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let digits = word.trim_start_matches("0x").trim_start_matches("0X");
+                u64::from_str_radix(digits, 16)
+                    .map_err(|_| format!("Invalid Polyglot random number: {word}"))
+            })
+            .collect::<Result<Vec<u64>, String>>()?;
+        if randoms.len() != RANDOM_COUNT {
+            return Err(format!(
+                "Expected {RANDOM_COUNT} Polyglot random numbers, found {}",
+                randoms.len()
+            ));
+        }
+        Ok(PolyglotKeys(randoms))
+    }
+
+    /// The Polyglot key of a position. The en passant file only counts when a pawn of the
+    /// side to move stands ready to take.
+    pub fn key(&self, position: &Position) -> u64 {
+        let mut key = 0;
+        for square in 0..64 {
+            if let Some((color, piece)) = position.piece_at(square) {
+                let kind = 2 * piece_index(piece) + usize::from(color == Color::White);
+                key ^= self.0[64 * kind + usize::from(square)];
+            }
+        }
+        for right in 0..4u8 {
+            // Polyglot orders the rights K, Q, k, q, as the castling bits do
+            if position.castling.has(1 << right) {
+                key ^= self.0[CASTLING_OFFSET + usize::from(right)];
+            }
+        }
+        if let Some(target) = position
+            .en_passant
+            .filter(|target| can_take(position, *target))
+        {
+            key ^= self.0[EN_PASSANT_OFFSET + usize::from(target % 8)];
+        }
+        if position.side_to_move == Color::White {
+            key ^= self.0[TURN_OFFSET];
+        }
+        key
+    }
+}
+
+fn piece_index(piece: Piece) -> usize {
+    match piece {
+        Piece::Pawn => 0,
+        Piece::Knight => 1,
+        Piece::Bishop => 2,
+        Piece::Rook => 3,
+        Piece::Queen => 4,
+        Piece::King => 5,
+    }
+}
+
+/// Whether a pawn of the side to move stands beside the pawn that just passed `target`.
+fn can_take(position: &Position, target: Square) -> bool {
+    let color = position.side_to_move;
+    let passed = if color == Color::White {
+        target - 8
+    } else {
+        target + 8
+    };
+    [passed.checked_sub(1), Some(passed + 1)]
+        .into_iter()
+        .flatten()
+        .filter(|square| square / 8 == passed / 8)
+        .any(|square| position.piece_at(square) == Some((color, Piece::Pawn)))
+}
+
+/// A move of the book with its weight.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+}
+
+impl BookEntry {
+    /// The move in coordinate notation. Polyglot writes castling as the king taking its own
+    /// rook, which becomes the usual two-square king move.
+    pub fn coordinate_move(&self, position: &Position) -> String {
+        let square = |bits: u16| ((bits >> 3 & 7) * 8 + (bits & 7)) as Square;
+        let (from, mut to) = (square(self.mv >> 6), square(self.mv));
+        let promotion = match self.mv >> 12 & 7 {
+            1 => Some(Piece::Knight),
+            2 => Some(Piece::Bishop),
+            3 => Some(Piece::Rook),
+            4 => Some(Piece::Queen),
+            _ => None,
+        };
+        let castling = matches!(
+            (position.piece_at(from), position.piece_at(to)),
+            (Some((color, Piece::King)), Some((owner, Piece::Rook))) if color == owner
+        );
+        if castling {
+            to = if to > from { from + 2 } else { from - 2 };
+        }
+        coordinate_move(from, to, promotion)
+    }
+}
+
+/// A Polyglot book held in memory.
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+}
+
+impl PolyglotBook {
+    /// Reads the big-endian entries of a book file; the learn field is ignored.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(16) {
+            return Err(format!(
+                "A Polyglot book is made of 16-byte entries, found {} bytes",
+                bytes.len()
+            ));
+        }
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(16)
+            .map(|entry| BookEntry {
+                key: u64::from_be_bytes(entry[0..8].try_into().unwrap()),
+                mv: u16::from_be_bytes([entry[8], entry[9]]),
+                weight: u16::from_be_bytes([entry[10], entry[11]]),
+            })
+            .collect();
+        // Books come sorted; sorting again guards the lookups against files that are not
+        entries.sort_by_key(|entry| entry.key);
+        Ok(PolyglotBook { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries for a key, in file order.
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// The book moves of a position in coordinate notation, with their weights.
+    pub fn moves(&self, keys: &PolyglotKeys, position: &Position) -> Vec<(String, u16)> {
+        self.entries(keys.key(position))
+            .iter()
+            .map(|entry| (entry.coordinate_move(position), entry.weight))
+            .collect()
+    }
+
+    /// How many moves from the start of a game are in the book. `positions` holds the
+    /// position before each move.
+    pub fn plies_in_book(
+        &self,
+        keys: &PolyglotKeys,
+        positions: &[Position],
+        moves: &[String],
+    ) -> usize {
+        positions
+            .iter()
+            .zip(moves)
+            .take_while(|(position, mv)| {
+                self.moves(keys, position)
+                    .iter()
+                    .any(|(book_move, _)| book_move == *mv)
+            })
+            .count()
+    }
+}
+
+/// Moves with a `{ book }` comment while in the book and `{ out of book }` on the first move
+/// that leaves it.
+pub fn annotate_book_moves(written: &mut [String], plies_in_book: usize) {
+    for (ply, mv) in written.iter_mut().enumerate() {
+        if ply < plies_in_book {
+            mv.push_str(" { book }");
+        } else {
+            mv.push_str(" { out of book }");
+            break;
+        }
+    }
+}
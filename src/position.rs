@@ -0,0 +1,806 @@
+use chess::bitboard::BitBoardGetter;
+use chess::board::Board;
+use chess::legal_moves::misc::{Color, Square, Type};
+use chess::utils::{square_to_string, string_to_square};
+
+use crate::sampling::splitmix64;
+use crate::san::{matches_origin, SanMove};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Piece {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl Piece {
+    pub const ALL: [Piece; 6] = [
+        Piece::Pawn,
+        Piece::Knight,
+        Piece::Bishop,
+        Piece::Rook,
+        Piece::Queen,
+        Piece::King,
+    ];
+
+    pub fn to_type(self) -> Type {
+        match self {
+            Piece::Pawn => Type::Pawn,
+            Piece::Knight => Type::Knight,
+            Piece::Bishop => Type::Bishop,
+            Piece::Rook => Type::Rook,
+            Piece::Queen => Type::Queen,
+            Piece::King => Type::King,
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Piece::Pawn => "pawn",
+            Piece::Knight => "knight",
+            Piece::Bishop => "bishop",
+            Piece::Rook => "rook",
+            Piece::Queen => "queen",
+            Piece::King => "king",
+        }
+    }
+
+    /// Conventional material value in pawns; the king counts for nothing.
+    pub fn value(self) -> u32 {
+        match self {
+            Piece::Pawn => 1,
+            Piece::Knight | Piece::Bishop => 3,
+            Piece::Rook => 5,
+            Piece::Queen => 9,
+            Piece::King => 0,
+        }
+    }
+
+    /// Upper case SAN letter, 'P' for pawns.
+    pub fn letter(self) -> char {
+        match self {
+            Piece::Pawn => 'P',
+            Piece::Knight => 'N',
+            Piece::Bishop => 'B',
+            Piece::Rook => 'R',
+            Piece::Queen => 'Q',
+            Piece::King => 'K',
+        }
+    }
+
+    pub fn fen_char(self, color: Color) -> char {
+        if color == Color::White {
+            self.letter()
+        } else {
+            self.letter().to_ascii_lowercase()
+        }
+    }
+}
+
+pub fn color_name(color: Color) -> &'static str {
+    if color == Color::White {
+        "white"
+    } else {
+        "black"
+    }
+}
+
+pub fn color_index(color: Color) -> usize {
+    if color == Color::White {
+        0
+    } else {
+        1
+    }
+}
+
+const fn zobrist_keys<const N: usize>() -> [u64; N] {
+    let mut keys = [0; N];
+    let mut i = 0;
+    while i < N {
+        keys[i] = splitmix64((i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        i += 1;
+    }
+    keys
+}
+
+/// One key per (color, piece, square), the side-to-move key, one key per castling right and
+/// one per en passant file.
+const ZOBRIST_KEYS: [u64; 2 * 6 * 64 + 1 + 4 + 8] = zobrist_keys();
+const ZOBRIST_BLACK_TO_MOVE: usize = 2 * 6 * 64;
+const ZOBRIST_CASTLING: usize = ZOBRIST_BLACK_TO_MOVE + 1;
+const ZOBRIST_EN_PASSANT: usize = ZOBRIST_CASTLING + 4;
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+pub const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Whether `name` is a square name such as "e4".
+pub fn is_square_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() == 2 && (b'a'..=b'h').contains(&bytes[0]) && (b'1'..=b'8').contains(&bytes[1])
+}
+
+/// The square reached from `square` by a (file, rank) offset, if it is on the board.
+pub fn offset_square(square: Square, (file_offset, rank_offset): (i8, i8)) -> Option<Square> {
+    let file = (square % 8) as i8 + file_offset;
+    let rank = (square / 8) as i8 + rank_offset;
+
+    ((0..8).contains(&file) && (0..8).contains(&rank)).then_some((rank * 8 + file) as Square)
+}
+
+/// A move in the coordinate notation of UCI, such as `e2e4` or `e7e8q`.
+pub fn coordinate_move(from: Square, to: Square, promotion: Option<Piece>) -> String {
+    let mut mv = format!("{}{}", square_to_string(from), square_to_string(to));
+    if let Some(piece) = promotion {
+        mv.push(piece.letter().to_ascii_lowercase());
+    }
+    mv
+}
+
+/// Reads a coordinate move such as `e2e4` or `e7e8q`.
+pub fn parse_coordinate_move(mv: &str) -> Option<(Square, Square, Option<Piece>)> {
+    if mv.len() < 4 || !is_square_name(&mv[0..2]) || !is_square_name(&mv[2..4]) {
+        return None;
+    }
+    let promotion = match mv.get(4..) {
+        Some("q") => Some(Piece::Queen),
+        Some("r") => Some(Piece::Rook),
+        Some("b") => Some(Piece::Bishop),
+        Some("n") => Some(Piece::Knight),
+        _ => None,
+    };
+
+    Some((
+        string_to_square(&mv[0..2]),
+        string_to_square(&mv[2..4]),
+        promotion,
+    ))
+}
+
+/// Castling rights as KQkq bits (white king side is bit 0).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CastlingRights(pub u8);
+
+impl CastlingRights {
+    pub const NONE: CastlingRights = CastlingRights(0);
+    pub const ALL: CastlingRights = CastlingRights(0b1111);
+
+    pub fn has(&self, right: u8) -> bool {
+        self.0 & right != 0
+    }
+
+    pub fn remove_color(&mut self, color: Color) {
+        self.0 &= if color == Color::White {
+            0b1100
+        } else {
+            0b0011
+        };
+    }
+
+    /// Drops the rights lost by a move touching a king or rook home square.
+    pub fn update(&mut self, from: Square, to: Square) {
+        for square in [from, to] {
+            self.0 &= match square {
+                4 => 0b1100,
+                7 => 0b1110,
+                0 => 0b1101,
+                60 => 0b0011,
+                63 => 0b1011,
+                56 => 0b0111,
+                _ => 0b1111,
+            };
+        }
+    }
+
+    /// The castling field of the FEN.
+    pub fn to_fen(&self) -> String {
+        let fen: String = "KQkq"
+            .chars()
+            .enumerate()
+            .filter(|(bit, _)| self.has(1 << bit))
+            .map(|(_, right)| right)
+            .collect();
+
+        if fen.is_empty() {
+            "-".to_string()
+        } else {
+            fen
+        }
+    }
+}
+
+/// Mailbox snapshot of a board, indexed by square (a1 = 0, h8 = 63).
+#[derive(Clone, Copy, PartialEq)]
+pub struct Position {
+    pub squares: [Option<(Color, Piece)>; 64],
+    pub side_to_move: Color,
+    pub castling: CastlingRights,
+    /// Square skipped by a pawn double push on the previous ply.
+    pub en_passant: Option<Square>,
+    /// Plies since the last capture or pawn move.
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+impl Position {
+    /// Snapshot of the pieces on the board. Castling rights are not known to the board and start empty.
+    pub fn from_board(board: &Board, side_to_move: Color) -> Self {
+        let mut squares = [None; 64];
+
+        for color in [Color::White, Color::Black] {
+            for piece in Piece::ALL {
+                for square in board
+                    .get_bitboard(&color, &piece.to_type())
+                    .get_occupied_squares()
+                {
+                    squares[square as usize] = Some((color, piece));
+                }
+            }
+        }
+
+        Position {
+            squares,
+            side_to_move,
+            castling: CastlingRights::NONE,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    /// The standard starting position.
+    pub fn initial() -> Self {
+        let mut position = Position::from_board(&Board::init(), Color::White);
+        position.castling = CastlingRights::ALL;
+        position
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<(Color, Piece)> {
+        self.squares[square as usize]
+    }
+
+    pub fn bitboard(&self, color: Color, piece: Piece) -> u64 {
+        self.squares
+            .iter()
+            .enumerate()
+            .filter(|(_, occupant)| **occupant == Some((color, piece)))
+            .fold(0, |bitboard, (square, _)| bitboard | 1 << square)
+    }
+
+    pub fn zobrist(&self) -> u64 {
+        let mut hash = 0;
+
+        for (square, occupant) in self.squares.iter().enumerate() {
+            if let Some((color, piece)) = occupant {
+                hash ^= ZOBRIST_KEYS[(color_index(*color) * 6 + piece.index()) * 64 + square];
+            }
+        }
+        if self.side_to_move == Color::Black {
+            hash ^= ZOBRIST_KEYS[ZOBRIST_BLACK_TO_MOVE];
+        }
+        for bit in 0..4 {
+            if self.castling.has(1 << bit) {
+                hash ^= ZOBRIST_KEYS[ZOBRIST_CASTLING + bit];
+            }
+        }
+        if let Some(square) = self.en_passant {
+            hash ^= ZOBRIST_KEYS[ZOBRIST_EN_PASSANT + (square % 8) as usize];
+        }
+
+        hash
+    }
+
+    /// The piece placement field of the FEN.
+    pub fn placement(&self) -> String {
+        let mut placement = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.squares[rank * 8 + file] {
+                    Some((color, piece)) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(piece.fen_char(color));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        placement
+    }
+
+    pub fn to_fen(&self) -> String {
+        let side = if self.side_to_move == Color::White {
+            "w"
+        } else {
+            "b"
+        };
+        let en_passant = self.en_passant.map_or("-".to_string(), square_to_string);
+        format!(
+            "{} {side} {} {en_passant} {} {}",
+            self.placement(),
+            self.castling.to_fen(),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    /// Parses a FEN. Missing move counters default to 0 and 1.
+    pub fn from_fen(fen: &str) -> Result<Position, String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(format!("FEN needs at least 4 fields: {fen}"));
+        }
+
+        let mut squares = [None; 64];
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(format!("FEN placement needs 8 ranks: {}", fields[0]));
+        }
+        for (rank_index, rank) in ranks.iter().enumerate() {
+            let rank_number = 7 - rank_index;
+            let mut file = 0;
+            for c in rank.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    file += empty as usize;
+                    continue;
+                }
+                let piece = Piece::ALL
+                    .into_iter()
+                    .find(|piece| piece.letter() == c.to_ascii_uppercase())
+                    .ok_or_else(|| format!("Invalid piece in FEN: {c}"))?;
+                let color = if c.is_ascii_uppercase() {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                if file >= 8 {
+                    return Err(format!("FEN rank too long: {rank}"));
+                }
+                squares[rank_number * 8 + file] = Some((color, piece));
+                file += 1;
+            }
+            if file != 8 {
+                return Err(format!("FEN rank does not cover 8 files: {rank}"));
+            }
+        }
+
+        let side_to_move = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            side => return Err(format!("Invalid side to move in FEN: {side}")),
+        };
+
+        let mut castling = CastlingRights::NONE;
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let bit = "KQkq"
+                    .find(c)
+                    .ok_or_else(|| format!("Invalid castling rights in FEN: {}", fields[2]))?;
+                castling.0 |= 1 << bit;
+            }
+        }
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            square if is_square_name(square) => Some(string_to_square(square)),
+            square => return Err(format!("Invalid en passant square in FEN: {square}")),
+        };
+
+        let counter = |index: usize, default: u32| {
+            fields.get(index).map_or(Ok(default), |counter| {
+                counter
+                    .parse::<u32>()
+                    .map_err(|_| format!("Invalid move counter in FEN: {counter}"))
+            })
+        };
+
+        Ok(Position {
+            squares,
+            side_to_move,
+            castling,
+            en_passant,
+            halfmove_clock: counter(4, 0)?,
+            fullmove_number: counter(5, 1)?,
+        })
+    }
+
+    /// Whether a move from `from` to `to` captures, including en passant.
+    pub fn is_capture(&self, from: Square, to: Square) -> bool {
+        self.piece_at(to).is_some()
+            || (self.en_passant == Some(to)
+                && matches!(self.piece_at(from), Some((_, Piece::Pawn))))
+    }
+
+    /// Whether either side may claim a draw under the fifty-move rule.
+    pub fn fifty_move_rule(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    /// Material of one side in pawns.
+    pub fn material(&self, color: Color) -> u32 {
+        self.squares
+            .iter()
+            .flatten()
+            .filter(|(owner, _)| *owner == color)
+            .map(|(_, piece)| piece.value())
+            .sum()
+    }
+
+    pub fn count(&self, color: Color, piece: Piece) -> usize {
+        self.squares
+            .iter()
+            .filter(|occupant| **occupant == Some((color, piece)))
+            .count()
+    }
+
+    /// Reasons why the position cannot arise in a legal game; empty for plausible positions.
+    pub fn sanity_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for color in [Color::White, Color::Black] {
+            let name = if color == Color::White {
+                "white"
+            } else {
+                "black"
+            };
+
+            let kings = self.count(color, Piece::King);
+            if kings != 1 {
+                errors.push(format!("{name} has {kings} kings"));
+            }
+
+            let pawns = self.count(color, Piece::Pawn);
+            if pawns > 8 {
+                errors.push(format!("{name} has {pawns} pawns"));
+            }
+
+            // Every piece beyond the initial set must come from a promoted pawn
+            let promoted: usize = [
+                (Piece::Queen, 1),
+                (Piece::Rook, 2),
+                (Piece::Bishop, 2),
+                (Piece::Knight, 2),
+            ]
+            .iter()
+            .map(|(piece, initial)| self.count(color, *piece).saturating_sub(*initial))
+            .sum();
+            if promoted + pawns > 8 {
+                errors.push(format!(
+                    "{name} has {promoted} promoted pieces with {pawns} pawns left"
+                ));
+            }
+        }
+
+        if (0..8)
+            .chain(56..64)
+            .any(|square| matches!(self.squares[square], Some((_, Piece::Pawn))))
+        {
+            errors.push("pawn on the first or last rank".to_string());
+        }
+
+        if self.in_check(!self.side_to_move) {
+            errors.push("the side that just moved is in check".to_string());
+        }
+
+        errors
+    }
+
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        (0..64).find(|square| self.squares[*square as usize] == Some((color, Piece::King)))
+    }
+
+    fn slide(&self, from: Square, directions: &[(i8, i8)], squares: &mut Vec<Square>) {
+        for direction in directions {
+            let mut square = from;
+            while let Some(next) = offset_square(square, *direction) {
+                squares.push(next);
+                if self.squares[next as usize].is_some() {
+                    break;
+                }
+                square = next;
+            }
+        }
+    }
+
+    /// Squares attacked by the piece on `from`, regardless of what occupies them.
+    pub fn attacked_squares(&self, from: Square) -> Vec<Square> {
+        let Some((color, piece)) = self.piece_at(from) else {
+            return Vec::new();
+        };
+        let mut squares = Vec::new();
+
+        match piece {
+            Piece::Pawn => {
+                let forward = if color == Color::White { 1 } else { -1 };
+                squares.extend(
+                    [(-1, forward), (1, forward)]
+                        .iter()
+                        .filter_map(|offset| offset_square(from, *offset)),
+                );
+            }
+            Piece::Knight => squares.extend(
+                KNIGHT_OFFSETS
+                    .iter()
+                    .filter_map(|offset| offset_square(from, *offset)),
+            ),
+            Piece::King => squares.extend(
+                KING_OFFSETS
+                    .iter()
+                    .filter_map(|offset| offset_square(from, *offset)),
+            ),
+            Piece::Bishop => self.slide(from, &BISHOP_DIRECTIONS, &mut squares),
+            Piece::Rook => self.slide(from, &ROOK_DIRECTIONS, &mut squares),
+            Piece::Queen => {
+                self.slide(from, &BISHOP_DIRECTIONS, &mut squares);
+                self.slide(from, &ROOK_DIRECTIONS, &mut squares);
+            }
+        }
+
+        squares
+    }
+
+    /// Pieces of `color` attacking `square`.
+    pub fn attackers(&self, square: Square, color: Color) -> Vec<Square> {
+        (0..64)
+            .filter(|from| {
+                matches!(self.piece_at(*from), Some((owner, _)) if owner == color)
+                    && self.attacked_squares(*from).contains(&square)
+            })
+            .collect()
+    }
+
+    pub fn is_attacked(&self, square: Square, by: Color) -> bool {
+        !self.attackers(square, by).is_empty()
+    }
+
+    pub fn in_check(&self, color: Color) -> bool {
+        self.king_square(color)
+            .is_some_and(|king| self.is_attacked(king, !color))
+    }
+
+    /// Whether the side to move is checkmated.
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check(self.side_to_move) && self.legal_moves().is_empty()
+    }
+
+    /// Destinations of the piece on `from`, ignoring whether the own king is left in check.
+    /// Castling is not included.
+    pub fn pseudo_legal_targets(&self, from: Square) -> Vec<Square> {
+        let Some((color, piece)) = self.piece_at(from) else {
+            return Vec::new();
+        };
+
+        if piece != Piece::Pawn {
+            return self
+                .attacked_squares(from)
+                .into_iter()
+                .filter(|to| !matches!(self.piece_at(*to), Some((owner, _)) if owner == color))
+                .collect();
+        }
+
+        let (forward, start_rank) = if color == Color::White {
+            (1, 1)
+        } else {
+            (-1, 6)
+        };
+        let mut targets = Vec::new();
+
+        if let Some(one) =
+            offset_square(from, (0, forward)).filter(|to| self.piece_at(*to).is_none())
+        {
+            targets.push(one);
+            if from / 8 == start_rank {
+                if let Some(two) =
+                    offset_square(one, (0, forward)).filter(|to| self.piece_at(*to).is_none())
+                {
+                    targets.push(two);
+                }
+            }
+        }
+        targets.extend(self.attacked_squares(from).into_iter().filter(|to| {
+            matches!(self.piece_at(*to), Some((owner, _)) if owner != color)
+                || self.en_passant == Some(*to)
+        }));
+
+        targets
+    }
+
+    /// Plays a move on the snapshot, handling castling (as a two-square king move), en passant
+    /// and promotion (to `promotion`, a queen by default).
+    pub fn play(&self, from: Square, to: Square, promotion: Option<Piece>) -> Position {
+        let mut next = *self;
+        let Some((color, piece)) = self.piece_at(from) else {
+            return next;
+        };
+
+        next.halfmove_clock = if piece == Piece::Pawn || self.is_capture(from, to) {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        if color == Color::Black {
+            next.fullmove_number += 1;
+        }
+
+        next.squares[from as usize] = None;
+        if piece == Piece::Pawn && Some(to) == self.en_passant && self.piece_at(to).is_none() {
+            let captured = if color == Color::White {
+                to - 8
+            } else {
+                to + 8
+            };
+            next.squares[captured as usize] = None;
+        }
+        if piece == Piece::King && from.abs_diff(to) == 2 {
+            let (rook_from, rook_to) = if to > from {
+                (from + 3, from + 1)
+            } else {
+                (from - 4, from - 1)
+            };
+            next.squares[rook_to as usize] = next.squares[rook_from as usize].take();
+        }
+
+        let promotes = piece == Piece::Pawn && (to / 8 == 0 || to / 8 == 7);
+        let placed = if promotes {
+            promotion.unwrap_or(Piece::Queen)
+        } else {
+            piece
+        };
+        next.squares[to as usize] = Some((color, placed));
+
+        next.castling.update(from, to);
+        next.en_passant =
+            (piece == Piece::Pawn && from.abs_diff(to) == 16).then_some((from + to) / 2);
+        next.side_to_move = !color;
+
+        next
+    }
+
+    /// Castling king moves available to the side to move.
+    pub fn castling_moves(&self) -> Vec<(Square, Square)> {
+        let color = self.side_to_move;
+        let (king, rights) = if color == Color::White {
+            (4, [0b0001, 0b0010])
+        } else {
+            (60, [0b0100, 0b1000])
+        };
+        if self.piece_at(king) != Some((color, Piece::King)) || self.in_check(color) {
+            return Vec::new();
+        }
+
+        let mut moves = Vec::new();
+        // King side: f and g files must be empty and safe; queen side: b, c and d empty, c and d safe
+        for (right, empty, safe, to) in [
+            (
+                rights[0],
+                &[king + 1, king + 2][..],
+                &[king + 1, king + 2][..],
+                king + 2,
+            ),
+            (
+                rights[1],
+                &[king - 1, king - 2, king - 3][..],
+                &[king - 1, king - 2][..],
+                king - 2,
+            ),
+        ] {
+            if self.castling.has(right)
+                && empty.iter().all(|square| self.piece_at(*square).is_none())
+                && safe.iter().all(|square| !self.is_attacked(*square, !color))
+            {
+                moves.push((king, to));
+            }
+        }
+
+        moves
+    }
+
+    /// Resolves a SAN move such as `Nbd7`, `exd6`, `e8=Q+` or `O-O` against the legal moves.
+    pub fn parse_san(&self, san: &str) -> Result<(Square, Square, Option<Piece>), String> {
+        let legal = self.legal_moves();
+
+        let candidates: Vec<(Square, Square, Option<Piece>)> = match SanMove::parse(san)? {
+            SanMove::Castling { queen_side } => legal
+                .into_iter()
+                .filter(|(from, to, _)| {
+                    self.piece_at(*from)
+                        .is_some_and(|(_, piece)| piece == Piece::King)
+                        && from.abs_diff(*to) == 2
+                        && (to < from) == queen_side
+                })
+                .collect(),
+            SanMove::Normal {
+                piece,
+                origin,
+                to,
+                promotion,
+            } => legal
+                .into_iter()
+                .filter(|(from, target, promoted)| {
+                    *target == to
+                        && (promotion.is_none() || *promoted == promotion)
+                        && (promotion.is_some() || matches!(promoted, None | Some(Piece::Queen)))
+                        && self.piece_at(*from).map(|(_, moved)| moved) == Some(piece)
+                        && matches_origin(origin, *from)
+                })
+                .collect(),
+        };
+
+        match candidates[..] {
+            [single] => Ok(single),
+            [] => Err(format!("No piece can play move: {san}")),
+            _ => Err(format!("Ambiguous move: {san}")),
+        }
+    }
+
+    /// Legal moves of the side to move. Promotions are listed once per promotion piece.
+    pub fn legal_moves(&self) -> Vec<(Square, Square, Option<Piece>)> {
+        let color = self.side_to_move;
+        let mut moves = Vec::new();
+
+        for from in 0..64 {
+            let Some((owner, piece)) = self.piece_at(from) else {
+                continue;
+            };
+            if owner != color {
+                continue;
+            }
+            for to in self.pseudo_legal_targets(from) {
+                if self.play(from, to, None).in_check(color) {
+                    continue;
+                }
+                if piece == Piece::Pawn && (to / 8 == 0 || to / 8 == 7) {
+                    for promotion in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+                        moves.push((from, to, Some(promotion)));
+                    }
+                } else {
+                    moves.push((from, to, None));
+                }
+            }
+        }
+        moves.extend(
+            self.castling_moves()
+                .into_iter()
+                .map(|(from, to)| (from, to, None)),
+        );
+
+        moves
+    }
+}
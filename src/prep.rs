@@ -0,0 +1,136 @@
+//! Preparation against an opponent: the opening tree of their recent games with the color they
+//! will have, as a compact report, and a game for each of their main lines.
+
+use crate::openings::{OpeningStats, OpeningTree};
+use crate::position::{parse_coordinate_move, Position};
+use crate::san::to_san;
+
+/// Nodes of the tree one move below `line`, most played first.
+pub fn children<'a>(tree: &'a OpeningTree, line: &str) -> Vec<(&'a str, &'a OpeningStats)> {
+    let prefix = if line.is_empty() {
+        String::new()
+    } else {
+        format!("{line} ")
+    };
+    let mut children: Vec<(&str, &OpeningStats)> = tree
+        .nodes
+        .range(prefix.clone()..)
+        .take_while(|(key, _)| key.starts_with(&prefix))
+        .filter(|(key, _)| {
+            let mv = &key[prefix.len()..];
+            !mv.is_empty() && !mv.contains(' ')
+        })
+        .map(|(key, stats)| (key.as_str(), stats))
+        .collect();
+    children.sort_by(|a, b| b.1.games.cmp(&a.1.games).then(a.0.cmp(b.0)));
+    children
+}
+
+/// The main lines of the tree: nodes reached by at least `min_games` games none of whose
+/// continuations is, most played first.
+pub fn main_lines(tree: &OpeningTree, min_games: usize) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut pending = vec![""];
+    while let Some(line) = pending.pop() {
+        let frequent: Vec<&str> = children(tree, line)
+            .into_iter()
+            .filter(|(_, stats)| stats.games >= min_games)
+            .map(|(child, _)| child)
+            .collect();
+        if frequent.is_empty() {
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        } else {
+            pending.extend(frequent.into_iter().rev());
+        }
+    }
+    lines
+}
+
+/// The opponent's score in percent, unfinished games counting as played.
+fn score_percent(stats: &OpeningStats, opponent_white: bool) -> f64 {
+    let wins = if opponent_white {
+        stats.white_wins
+    } else {
+        stats.black_wins
+    };
+    100.0 * (wins as f64 + stats.draws as f64 / 2.0) / stats.games.max(1) as f64
+}
+
+/// The tree as indented moves in SAN, each with its game count, its share of the games of its
+/// parent and the opponent's score, keeping moves played in at least `min_games` games.
+pub fn tree_report(tree: &OpeningTree, opponent_white: bool, min_games: usize) -> String {
+    fn walk(
+        tree: &OpeningTree,
+        line: &str,
+        position: &Position,
+        opponent_white: bool,
+        min_games: usize,
+        report: &mut String,
+    ) {
+        let parent_games = tree.nodes.get(line).map_or(0, |stats| stats.games);
+        let ply = if line.is_empty() {
+            0
+        } else {
+            line.split(' ').count()
+        };
+
+        for (child, stats) in children(tree, line) {
+            if stats.games < min_games {
+                continue;
+            }
+            let Some((from, to, promotion)) =
+                child.rsplit(' ').next().and_then(parse_coordinate_move)
+            else {
+                continue;
+            };
+            let number = if ply % 2 == 0 {
+                format!("{}.", ply / 2 + 1)
+            } else {
+                format!("{}...", ply / 2 + 1)
+            };
+            report.push_str(&format!(
+                "{:indent$}{number}{} {} games ({:.0}%), score {:.0}%\n",
+                "",
+                to_san(position, from, to, promotion),
+                stats.games,
+                100.0 * stats.games as f64 / parent_games.max(1) as f64,
+                score_percent(stats, opponent_white),
+                indent = 2 * ply
+            ));
+            let next = position.play(from, to, promotion);
+            walk(tree, child, &next, opponent_white, min_games, report);
+        }
+    }
+
+    let mut report = String::new();
+    walk(
+        tree,
+        "",
+        &Position::initial(),
+        opponent_white,
+        min_games,
+        &mut report,
+    );
+    report
+}
+
+/// Heading of the prep report: whose games, how many and the opponent's overall score.
+pub fn report_heading(
+    opponent: &str,
+    opponent_white: bool,
+    tree: &OpeningTree,
+    dates: Option<(&str, &str)>,
+) -> String {
+    let color = if opponent_white { "white" } else { "black" };
+    let root = tree.nodes.get("").copied().unwrap_or_default();
+    let period = dates.map_or(String::new(), |(first, last)| {
+        format!(", {first} to {last}")
+    });
+    format!(
+        "{opponent} with {color}: {} games{period}, score {:.0}%\n",
+        root.games,
+        score_percent(&root, opponent_white)
+    )
+}
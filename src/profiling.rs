@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+/// How long processing one game took.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GameTiming {
+    /// 1-based number of the game in the input.
+    pub game_number: usize,
+    pub plies: usize,
+    /// Size of the movetext, comments and variations included.
+    pub movetext_bytes: usize,
+    pub elapsed: Duration,
+}
+
+/// Keeps the slowest games of a run, to find the inputs that dominate its runtime.
+pub struct GameProfiler {
+    limit: usize,
+    slowest: Vec<GameTiming>,
+    games: usize,
+    total: Duration,
+}
+
+impl GameProfiler {
+    pub fn new(limit: usize) -> Self {
+        GameProfiler {
+            limit,
+            slowest: Vec::new(),
+            games: 0,
+            total: Duration::ZERO,
+        }
+    }
+
+    pub fn record(&mut self, timing: GameTiming) {
+        self.games += 1;
+        self.total += timing.elapsed;
+        self.slowest.push(timing);
+        // Trimming only once the list doubles keeps recording cheap
+        if self.slowest.len() >= 2 * self.limit.max(1) {
+            self.trim();
+        }
+    }
+
+    fn trim(&mut self) {
+        self.slowest.sort_by(|a, b| {
+            b.elapsed
+                .cmp(&a.elapsed)
+                .then(a.game_number.cmp(&b.game_number))
+        });
+        self.slowest.truncate(self.limit);
+    }
+
+    /// The slowest games, slowest first.
+    pub fn slowest(&mut self) -> &[GameTiming] {
+        self.trim();
+        &self.slowest
+    }
+
+    /// The total time, then a line per slow game with its share of the total.
+    pub fn report(&mut self) -> String {
+        let (games, total) = (self.games, self.total);
+        let mut report = format!(
+            "Processed {games} games in {:.3}s, slowest {}:\n",
+            total.as_secs_f64(),
+            self.limit.min(games)
+        );
+        for timing in self.slowest() {
+            report.push_str(&format!(
+                "  game {}: {:.3} ms, {} plies, {} bytes of movetext ({:.1}% of the time)\n",
+                timing.game_number,
+                timing.elapsed.as_secs_f64() * 1000.0,
+                timing.plies,
+                timing.movetext_bytes,
+                100.0 * timing.elapsed.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON)
+            ));
+        }
+        report
+    }
+}
@@ -0,0 +1,111 @@
+//! Engine lines in comments, such as `+0.45/18 19. Nf5 Qd7` or `g1f3 d7d5`, checked against
+//! the position they start from: after the move the comment follows, or before the first
+//! move of its line. Legal lines are rewritten as numbered SAN; illegal ones, as manual
+//! editing leaves them, are reported and kept as written.
+
+use std::borrow::Cow;
+
+use crate::comments::{is_engine_word, is_san};
+use crate::input_format::{is_coordinate_move, moves_game};
+use crate::pgn_ast::Line;
+use crate::position::Position;
+
+/// The move of a word of engine output, its move number and punctuation left out.
+fn move_word(word: &str) -> Option<&str> {
+    let word = word.trim_matches(['(', ')', ',']);
+    let unnumbered = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    Some(unnumbered).filter(|mv| !mv.is_empty() && (is_coordinate_move(mv) || is_san(mv)))
+}
+
+/// Whether a word is a move number such as `12.` or `12...`.
+fn is_move_number(word: &str) -> bool {
+    let number = word.trim_end_matches('.');
+    number.len() < word.len() && !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Splits a comment made of engine output into the words before its line, such as a score
+/// and depth, and the moves of the line. None when the comment holds other words or no move.
+pub fn split_pv(comment: &str) -> Option<(Vec<&str>, Vec<&str>)> {
+    let (mut prefix, mut moves) = (Vec::new(), Vec::new());
+    for word in comment.split_whitespace() {
+        match move_word(word) {
+            Some(mv) => moves.push(mv),
+            // Move numbers, and scores within the line, are written anew
+            None if is_engine_word(word) => {
+                if moves.is_empty() && !is_move_number(word) {
+                    prefix.push(word);
+                }
+            }
+            None => return None,
+        }
+    }
+    (!moves.is_empty()).then_some((prefix, moves))
+}
+
+/// An engine line that is not legal from its position.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PvIssue {
+    /// Plies played before the comment.
+    pub ply: usize,
+    pub comment: String,
+    pub error: String,
+}
+
+/// The comment of a legal engine line rewritten as numbered SAN, or the error of an illegal
+/// one. None when the comment holds no engine line.
+pub fn normalize_pv(comment: &str, position: &Position) -> Option<Result<String, String>> {
+    let (prefix, moves) = split_pv(comment)?;
+    Some(moves_game(*position, moves.into_iter()).map(|line| {
+        let mut words = prefix;
+        words.push(&line);
+        words.join(" ")
+    }))
+}
+
+fn verify_comments(
+    comments: &mut [Cow<'_, str>],
+    position: &Position,
+    ply: usize,
+    issues: &mut Vec<PvIssue>,
+) -> usize {
+    let mut normalized = 0;
+    for comment in comments {
+        match normalize_pv(comment, position) {
+            Some(Ok(line)) if line != *comment => {
+                *comment = Cow::Owned(line);
+                normalized += 1;
+            }
+            Some(Err(error)) => issues.push(PvIssue {
+                ply,
+                comment: comment.to_string(),
+                error,
+            }),
+            Some(Ok(_)) | None => {}
+        }
+    }
+    normalized
+}
+
+/// Checks the engine lines in the comments of a line and its side lines, the line starting
+/// from `position` after `ply` plies. Returns the number of comments rewritten; the lines
+/// that are not legal are added to `issues`. Comments past an illegal move of the line itself
+/// are left alone, their position being unknown.
+pub fn verify_pvs(
+    line: &mut Line,
+    mut position: Position,
+    ply: usize,
+    issues: &mut Vec<PvIssue>,
+) -> usize {
+    let mut normalized = verify_comments(&mut line.comments, &position, ply, issues);
+    for (index, node) in line.moves.iter_mut().enumerate() {
+        for variation in &mut node.variations {
+            normalized += verify_pvs(variation, position, ply + index, issues);
+        }
+        let Ok((from, to, promotion)) = position.parse_san(node.san) else {
+            break;
+        };
+        position = position.play(from, to, promotion);
+        normalized += verify_comments(&mut node.comments, &position, ply + index + 1, issues);
+    }
+    normalized
+}
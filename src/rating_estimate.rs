@@ -0,0 +1,212 @@
+//! Experimental estimate of playing strength from move quality, for sanity-checking rating
+//! tags. Each side's centipawn losses, read from `[%eval]` annotations, are compared with
+//! reference curves relating Elo to the average loss and to the share of mistakes. The curves
+//! are rough fits to online games and the estimate is only meaningful over many moves.
+
+use std::collections::BTreeMap;
+
+use crate::accuracy::INITIAL_CENTIPAWNS;
+use crate::json;
+use crate::uci::Score;
+
+/// Fewer evaluated moves than this give no estimate.
+pub const MIN_MOVES: usize = 10;
+
+/// A move losing at least this many centipawns counts as a mistake.
+const MISTAKE_CENTIPAWNS: u32 = 100;
+
+/// Elo by average centipawn loss.
+const ACPL_CURVE: [(f64, f64); 10] = [
+    (5.0, 2800.0),
+    (10.0, 2650.0),
+    (15.0, 2500.0),
+    (20.0, 2350.0),
+    (30.0, 2100.0),
+    (40.0, 1900.0),
+    (55.0, 1650.0),
+    (75.0, 1400.0),
+    (100.0, 1150.0),
+    (150.0, 850.0),
+];
+
+/// Elo by percentage of moves that are mistakes.
+const MISTAKE_CURVE: [(f64, f64); 7] = [
+    (1.0, 2700.0),
+    (3.0, 2400.0),
+    (6.0, 2100.0),
+    (10.0, 1800.0),
+    (15.0, 1500.0),
+    (22.0, 1200.0),
+    (30.0, 900.0),
+];
+
+/// Reads a curve by linear interpolation, holding its end values outside of it.
+fn interpolate(curve: &[(f64, f64)], x: f64) -> f64 {
+    let (first, last) = (curve[0], curve[curve.len() - 1]);
+    if x <= first.0 {
+        return first.1;
+    }
+    for pair in curve.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if x <= x1 {
+            return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+        }
+    }
+    last.1
+}
+
+/// Centipawns lost by each move of each side, white first, from the evaluations for white after
+/// each move. Scores are capped at ten pawns so that mates do not swamp the average; moves next
+/// to a missing evaluation are left out.
+pub fn centipawn_losses(evals: &[Option<Score>]) -> [Vec<u32>; 2] {
+    let scores: Vec<Option<i32>> = [Some(INITIAL_CENTIPAWNS)]
+        .into_iter()
+        .chain(
+            evals
+                .iter()
+                .map(|score| score.map(|score| score.centipawns().clamp(-1000, 1000))),
+        )
+        .collect();
+
+    let mut losses = [Vec::new(), Vec::new()];
+    for (ply, pair) in scores.windows(2).enumerate() {
+        if let (Some(before), Some(after)) = (pair[0], pair[1]) {
+            let sign = if ply % 2 == 0 { 1 } else { -1 };
+            losses[ply % 2].push((sign * (before - after)).max(0) as u32);
+        }
+    }
+    losses
+}
+
+/// Move quality of a player over some moves, with the strength it suggests.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Estimate {
+    pub moves: usize,
+    pub average_loss: f64,
+    /// Percentage of moves losing a pawn or more.
+    pub mistake_rate: f64,
+    /// Estimated Elo, rounded to ten points; None below `MIN_MOVES` moves.
+    pub elo: Option<u32>,
+}
+
+impl Estimate {
+    pub fn of(losses: &[u32]) -> Option<Estimate> {
+        if losses.is_empty() {
+            return None;
+        }
+        let moves = losses.len();
+        let average_loss = losses.iter().sum::<u32>() as f64 / moves as f64;
+        let mistakes = losses
+            .iter()
+            .filter(|loss| **loss >= MISTAKE_CENTIPAWNS)
+            .count();
+        let mistake_rate = 100.0 * mistakes as f64 / moves as f64;
+
+        let elo = (moves >= MIN_MOVES).then(|| {
+            let elo = (interpolate(&ACPL_CURVE, average_loss)
+                + interpolate(&MISTAKE_CURVE, mistake_rate))
+                / 2.0;
+            (elo / 10.0).round() as u32 * 10
+        });
+
+        Some(Estimate {
+            moves,
+            average_loss,
+            mistake_rate,
+            elo,
+        })
+    }
+
+    fn to_json(self) -> String {
+        json::object([
+            ("moves", self.moves.to_string()),
+            ("average_loss", json::decimal(self.average_loss, 1)),
+            ("mistake_rate", json::decimal(self.mistake_rate, 1)),
+            (
+                "estimated_elo",
+                self.elo.map_or("null".to_string(), |elo| elo.to_string()),
+            ),
+        ])
+    }
+}
+
+/// Moves and rating tags of a player across the input.
+#[derive(Default)]
+struct PlayerRecord {
+    games: usize,
+    losses: Vec<u32>,
+    ratings: Vec<u32>,
+}
+
+/// Per-game estimates and per-player aggregates, for the JSON report.
+#[derive(Default)]
+pub struct RatingEstimates {
+    games: Vec<String>,
+    players: BTreeMap<String, PlayerRecord>,
+}
+
+impl RatingEstimates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a game from its players, their `WhiteElo` and `BlackElo` tags, and its
+    /// evaluations for white after each move.
+    pub fn add_game(
+        &mut self,
+        game_number: usize,
+        players: [&str; 2],
+        ratings: [Option<u32>; 2],
+        evals: &[Option<Score>],
+    ) {
+        let losses = centipawn_losses(evals);
+
+        let [white, black] = [0, 1].map(|side| {
+            let record = self.players.entry(players[side].to_string()).or_default();
+            record.games += 1;
+            record.losses.extend(&losses[side]);
+            record.ratings.extend(ratings[side]);
+
+            json::object([
+                ("name", json::string(players[side])),
+                (
+                    "elo",
+                    ratings[side].map_or("null".to_string(), |elo| elo.to_string()),
+                ),
+                (
+                    "estimate",
+                    Estimate::of(&losses[side]).map_or("null".to_string(), Estimate::to_json),
+                ),
+            ])
+        });
+
+        self.games.push(json::object([
+            ("game", game_number.to_string()),
+            ("white", white),
+            ("black", black),
+        ]));
+    }
+
+    pub fn to_json(&self) -> String {
+        let players = self.players.iter().map(|(name, record)| {
+            let average_rating = match record.ratings.len() {
+                0 => "null".to_string(),
+                count => (record.ratings.iter().sum::<u32>() / count as u32).to_string(),
+            };
+            json::object([
+                ("name", json::string(name)),
+                ("games", record.games.to_string()),
+                ("average_elo", average_rating),
+                (
+                    "estimate",
+                    Estimate::of(&record.losses).map_or("null".to_string(), Estimate::to_json),
+                ),
+            ])
+        });
+
+        json::object([
+            ("games", json::array(self.games.iter().cloned())),
+            ("players", json::array(players)),
+        ])
+    }
+}
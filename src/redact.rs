@@ -0,0 +1,116 @@
+use std::borrow::Cow;
+
+use crate::pgn_ast::Game;
+
+/// Tags naming a person.
+const NAME_TAGS: [&str; 3] = ["White", "Black", "Annotator"];
+
+/// Tags holding a player's identifier in a rating system or on a server.
+const ID_TAGS: [&str; 6] = [
+    "WhiteFideId",
+    "BlackFideId",
+    "WhiteUSCF",
+    "BlackUSCF",
+    "WhiteId",
+    "BlackId",
+];
+
+/// How personally identifying tags are hidden when publishing games.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Redaction {
+    /// Player names become `?` and other identifying tags are dropped.
+    Strip,
+    /// Names and identifiers become pseudonyms, the same for every game of a player. The salt
+    /// keeps pseudonyms from being matched against a list of known names.
+    Hash { salt: String },
+}
+
+/// What redaction does to a tag.
+#[derive(PartialEq, Debug)]
+pub enum TagChange {
+    Keep,
+    Replace(String),
+    Drop,
+}
+
+/// FNV-1a, stable across runs and platforms unlike the standard library hasher.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The scheme and host of a URL, dropping paths and queries that may hold user or game IDs.
+fn url_origin(value: &str) -> Option<&str> {
+    let scheme_end = value.find("://")? + 3;
+    let host_end = value[scheme_end..]
+        .find(['/', '?', '#'])
+        .map_or(value.len(), |end| scheme_end + end);
+    Some(&value[..host_end])
+}
+
+impl Redaction {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "strip" => Some(Redaction::Strip),
+            "hash" => Some(Redaction::Hash {
+                salt: String::new(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn pseudonym(&self, value: &str) -> Option<String> {
+        match self {
+            Redaction::Strip => None,
+            Redaction::Hash { salt } => {
+                let hash = fnv1a(format!("{salt}\0{}", value.trim()).as_bytes());
+                Some(format!("anon-{:010x}", hash >> 24))
+            }
+        }
+    }
+
+    pub fn tag_change(&self, tag: &str, value: &str) -> TagChange {
+        // Unknown values identify nobody
+        if matches!(value.trim(), "" | "?" | "-") {
+            return TagChange::Keep;
+        }
+
+        if NAME_TAGS.contains(&tag) || ID_TAGS.contains(&tag) {
+            return match self.pseudonym(value) {
+                Some(pseudonym) => TagChange::Replace(pseudonym),
+                // The seven tag roster keeps its player tags
+                None if tag == "White" || tag == "Black" => TagChange::Replace("?".to_string()),
+                None => TagChange::Drop,
+            };
+        }
+
+        match url_origin(value) {
+            Some(origin) if origin != value => TagChange::Replace(origin.to_string()),
+            _ => TagChange::Keep,
+        }
+    }
+
+    pub fn redact_tags(&self, tags: &mut Vec<(String, String)>) {
+        tags.retain_mut(|(tag, value)| match self.tag_change(tag, value) {
+            TagChange::Keep => true,
+            TagChange::Replace(redacted) => {
+                *value = redacted;
+                true
+            }
+            TagChange::Drop => false,
+        });
+    }
+
+    pub fn redact_game(&self, game: &mut Game) {
+        game.tags
+            .retain_mut(|tag| match self.tag_change(tag.name, &tag.value) {
+                TagChange::Keep => true,
+                TagChange::Replace(redacted) => {
+                    tag.value = Cow::Owned(redacted);
+                    true
+                }
+                TagChange::Drop => false,
+            });
+    }
+}
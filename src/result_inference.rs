@@ -0,0 +1,58 @@
+//! Results of games whose result is missing, read from the final position and the mate
+//! annotation of the last move.
+
+use chess::legal_moves::misc::Color;
+
+use crate::pgn_ast::GameResult;
+use crate::position::Position;
+
+/// The tag marking a Result tag as inferred, holding what it was inferred from.
+pub const INFERRED_TAG: &str = "ResultInferred";
+
+/// Why a result was inferred.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Inference {
+    /// The final position is mate.
+    Checkmate,
+    /// The last move carries '#' but the board does not see mate.
+    MateAnnotation,
+    Stalemate,
+}
+
+impl Inference {
+    pub fn name(self) -> &'static str {
+        match self {
+            Inference::Checkmate => "checkmate",
+            Inference::MateAnnotation => "mate annotation",
+            Inference::Stalemate => "stalemate",
+        }
+    }
+}
+
+/// Whether a result marker leaves the result open: missing, `*` or unreadable.
+pub fn is_missing(result: Option<&str>) -> bool {
+    result
+        .and_then(GameResult::parse)
+        .is_none_or(|result| result == GameResult::Unknown)
+}
+
+/// The result of a game played through to `last`, its final position, if the position or the
+/// last move, given in SAN, settles it.
+pub fn infer_result(last: &Position, last_san: Option<&str>) -> Option<(GameResult, Inference)> {
+    // The side to move is the one mated
+    let loss = if last.side_to_move == Color::White {
+        GameResult::BlackWins
+    } else {
+        GameResult::WhiteWins
+    };
+    if last.is_checkmate() {
+        return Some((loss, Inference::Checkmate));
+    }
+    if last_san.is_some_and(|san| san.ends_with('#')) {
+        return Some((loss, Inference::MateAnnotation));
+    }
+    if !last.in_check(last.side_to_move) && last.legal_moves().is_empty() {
+        return Some((GameResult::Draw, Inference::Stalemate));
+    }
+    None
+}
@@ -0,0 +1,45 @@
+use crate::pgn_cleaner::RawGame;
+
+/// The mandatory tags of the PGN standard, in their required export order.
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Value the PGN standard prescribes for an unknown roster tag.
+pub fn placeholder(tag: &str) -> &'static str {
+    match tag {
+        "Date" => "????.??.??",
+        "Result" => "*",
+        _ => "?",
+    }
+}
+
+pub fn missing_tags(game: &RawGame) -> Vec<&'static str> {
+    SEVEN_TAG_ROSTER
+        .iter()
+        .copied()
+        .filter(|tag| game.tag(tag).is_none())
+        .collect()
+}
+
+/// Inserts placeholders for missing roster tags and moves the roster to the front in standard order.
+pub fn complete_roster(game: &mut RawGame) {
+    let mut tags: Vec<(String, String)> = SEVEN_TAG_ROSTER
+        .iter()
+        .map(|tag| {
+            let value = match (tag, game.tag(tag)) {
+                (_, Some(value)) => value,
+                (&"Result", None) => game.termination().unwrap_or(placeholder(tag)),
+                (_, None) => placeholder(tag),
+            };
+            (tag.to_string(), value.to_string())
+        })
+        .collect();
+
+    tags.extend(
+        game.tags
+            .drain(..)
+            .filter(|(tag, _)| !SEVEN_TAG_ROSTER.contains(&tag.as_str())),
+    );
+
+    game.tags = tags;
+}
@@ -0,0 +1,70 @@
+//! `s3://` inputs and outputs, behind the `s3` feature. Objects are transferred with the AWS
+//! command line client rather than an SDK, so the feature adds no dependency to the build but
+//! needs `aws` on the PATH at run time, set up with credentials and a region as for
+//! `aws s3 cp`. Inputs are streamed from the client; outputs are staged in a temporary file
+//! and uploaded once the run completes.
+
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::source::{decompressor_for, skip, spawn, ProcessOutput, Source};
+
+/// Whether an input or output argument names an object in S3, such as `s3://bucket/games.pgn`.
+pub fn is_s3_uri(name: &str) -> bool {
+    name.starts_with("s3://")
+}
+
+/// An object read from S3 with the AWS command line client, using its usual credentials and
+/// region configuration. Compressed objects are decompressed on the fly.
+pub struct S3Object(pub String);
+
+impl Source for S3Object {
+    fn open(&self, offset: u64) -> io::Result<Box<dyn Read>> {
+        let args: Vec<&OsStr> = ["s3", "cp", &self.0, "-"].map(OsStr::new).to_vec();
+        let mut pipeline = vec![spawn("aws", &args, Stdio::null()).map_err(aws_missing)?];
+
+        if let Some(decompressor) = decompressor_for(Path::new(&self.0)) {
+            let download = pipeline[0]
+                .1
+                .stdout
+                .take()
+                .map_or_else(Stdio::null, Stdio::from);
+            pipeline.push(spawn(decompressor, &["-dc".as_ref()], download)?);
+        }
+        skip(Box::new(ProcessOutput(pipeline)), offset)
+    }
+}
+
+/// The error of an `aws` client that cannot be started, saying what it is needed for.
+fn aws_missing(error: io::Error) -> io::Error {
+    io::Error::new(
+        error.kind(),
+        format!("s3:// URIs need the AWS command line client (aws) on the PATH: {error}"),
+    )
+}
+
+/// The local file collecting output meant for an S3 object until it is uploaded.
+pub fn staging_path(uri: &str) -> PathBuf {
+    let name = uri.rsplit('/').next().unwrap_or("output.pgn");
+    env::temp_dir().join(format!("pgn-crunker-{}-{name}", std::process::id()))
+}
+
+/// Uploads a local file to an S3 object and removes it.
+pub fn upload(path: &Path, uri: &str) -> io::Result<()> {
+    let status = Command::new("aws")
+        .args(["s3", "cp"])
+        .arg(path)
+        .arg(uri)
+        .stdout(Stdio::null())
+        .status()
+        .map_err(aws_missing)?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "Upload to {uri} failed: {status}"
+        )));
+    }
+    std::fs::remove_file(path)
+}
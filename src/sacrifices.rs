@@ -0,0 +1,123 @@
+//! Material sacrifices: moves leaving the moved piece where the opponent wins material by
+//! taking it, as [`see`] plays out the exchanges on its square. A heuristic, as exchanges are
+//! resolved without regard to pins or to the threats the sacrifice makes.
+
+use chess::legal_moves::misc::{Color, Square};
+
+use crate::position::{parse_coordinate_move, Piece, Position};
+use crate::see::see;
+
+/// Least material, in pawns, a move must give up to count as a sacrifice. Pawns offered for a
+/// move or two, as in gambits, fall short of it.
+pub const MIN_SACRIFICE: i32 = 2;
+
+/// A move giving up material.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Sacrifice {
+    /// Ply of the move, counted from 1.
+    pub ply: usize,
+    pub move_number: u32,
+    pub color: Color,
+    /// The piece given up, as it stands after the move.
+    pub piece: Piece,
+    /// Material lost in pawns.
+    pub loss: i32,
+}
+
+impl Sacrifice {
+    /// The move number and piece, as `12. queen` or `12... rook`.
+    pub fn label(&self) -> String {
+        let dots = if self.color == Color::White {
+            "."
+        } else {
+            "..."
+        };
+        format!("{}{dots} {}", self.move_number, self.piece.name())
+    }
+}
+
+/// The piece given up by the move from `from` to `to` leading to `after`, and the material
+/// lost, if it is a sacrifice. Mates are left out, the piece given up being out of reach.
+pub fn sacrificed(
+    before: &Position,
+    after: &Position,
+    from: Square,
+    to: Square,
+) -> Option<(Piece, i32)> {
+    let (_, piece) = after.piece_at(to)?;
+    if after.is_checkmate() {
+        return None;
+    }
+    let promotion = before
+        .piece_at(from)
+        .is_some_and(|(_, moved)| moved != piece)
+        .then_some(piece);
+    let loss = -see(before, from, to, promotion);
+    (loss >= MIN_SACRIFICE).then_some((piece, loss))
+}
+
+/// Sacrifices of a game. `positions` holds the position before each move followed by the
+/// final one.
+pub fn sacrifices(positions: &[Position], moves: &[String]) -> Vec<Sacrifice> {
+    positions
+        .windows(2)
+        .zip(moves)
+        .enumerate()
+        .filter_map(|(index, (pair, mv))| {
+            let (from, to, _) = parse_coordinate_move(mv)?;
+            let (before, after) = (&pair[0], &pair[1]);
+            let (piece, loss) = sacrificed(before, after, from, to)?;
+            Some(Sacrifice {
+                ply: index + 1,
+                move_number: before.fullmove_number,
+                color: before.side_to_move,
+                piece,
+                loss,
+            })
+        })
+        .collect()
+}
+
+/// Selects games by their sacrifices: of a given piece, or any, made before a move number.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SacrificeFilter {
+    pub piece: Option<Piece>,
+    pub before_move: Option<u32>,
+}
+
+impl SacrificeFilter {
+    /// Parses `PIECE[:MOVE]`, such as `queen:25` for queen sacrifices before move 25, with
+    /// `any` for sacrifices of any piece.
+    pub fn parse(filter: &str) -> Result<Self, String> {
+        let (name, before) = match filter.split_once(':') {
+            Some((name, before)) => (name, Some(before)),
+            None => (filter, None),
+        };
+        let piece = match name.to_lowercase().as_str() {
+            "any" => None,
+            name => Some(
+                Piece::ALL
+                    .into_iter()
+                    .find(|piece| piece.name() == name)
+                    .ok_or_else(|| format!("Unknown piece {name}"))?,
+            ),
+        };
+        let before_move = before
+            .map(|before| {
+                before
+                    .parse()
+                    .map_err(|_| format!("Invalid move number {before}"))
+            })
+            .transpose()?;
+        Ok(SacrificeFilter { piece, before_move })
+    }
+
+    pub fn matches(&self, sacrifices: &[Sacrifice]) -> bool {
+        sacrifices.iter().any(|sacrifice| {
+            self.piece.is_none_or(|piece| piece == sacrifice.piece)
+                && self
+                    .before_move
+                    .is_none_or(|before| sacrifice.move_number < before)
+        })
+    }
+}
@@ -0,0 +1,44 @@
+/// One step of the SplitMix64 generator.
+pub const fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Small seeded generator so that sampling is reproducible across runs.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        splitmix64(self.state)
+    }
+
+    /// Uniform value in `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// Picks `count` of the candidates uniformly at random, returned in input order.
+pub fn sample_indices(candidates: &[usize], count: usize, rng: &mut Rng) -> Vec<usize> {
+    let mut pool = candidates.to_vec();
+    let count = count.min(pool.len());
+
+    // Partial Fisher-Yates shuffle
+    for i in 0..count {
+        let j = i + rng.below(pool.len() - i);
+        pool.swap(i, j);
+    }
+
+    let mut sample = pool[..count].to_vec();
+    sample.sort_unstable();
+    sample
+}
@@ -0,0 +1,120 @@
+use chess::legal_moves::misc::Square;
+use chess::utils::{square_to_string, string_to_square};
+
+use crate::position::{is_square_name, Piece, Position};
+
+/// The parts of a SAN move, before resolving it against a position.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SanMove<'a> {
+    Castling {
+        queen_side: bool,
+    },
+    Normal {
+        piece: Piece,
+        /// File and/or rank of the moving piece given for disambiguation, such as `b` in `Nbd7`.
+        origin: &'a str,
+        to: Square,
+        promotion: Option<Piece>,
+    },
+}
+
+impl<'a> SanMove<'a> {
+    /// Parses moves such as `Nbd7`, `exd6`, `e8=Q+` or `O-O`; check and annotation suffixes are ignored.
+    /// Long algebraic moves such as `Ng1-f3` read as fully disambiguated SAN.
+    pub fn parse(san: &'a str) -> Result<SanMove<'a>, String> {
+        let stripped = san.trim_end_matches(['+', '#', '!', '?']);
+        match stripped {
+            "O-O" | "0-0" => return Ok(SanMove::Castling { queen_side: false }),
+            "O-O-O" | "0-0-0" => return Ok(SanMove::Castling { queen_side: true }),
+            _ => {}
+        }
+
+        let (body, promotion) = match stripped.char_indices().last() {
+            Some((index, letter)) if "QRBN".contains(letter) && index >= 2 => {
+                let piece = Piece::ALL
+                    .into_iter()
+                    .find(|piece| piece.letter() == letter);
+                (stripped[..index].trim_end_matches('='), piece)
+            }
+            _ => (stripped, None),
+        };
+        let piece = Piece::ALL
+            .into_iter()
+            .find(|piece| *piece != Piece::Pawn && body.starts_with(piece.letter()));
+        let body = if piece.is_some() { &body[1..] } else { body };
+        if body.len() < 2 || !is_square_name(&body[body.len() - 2..]) {
+            return Err(format!("Invalid SAN: {san}"));
+        }
+
+        Ok(SanMove::Normal {
+            piece: piece.unwrap_or(Piece::Pawn),
+            origin: body[..body.len() - 2].trim_end_matches(['x', '-']),
+            to: string_to_square(&body[body.len() - 2..]),
+            promotion,
+        })
+    }
+}
+
+/// Whether a square agrees with the disambiguation of a SAN move.
+pub fn matches_origin(origin: &str, square: Square) -> bool {
+    let name = square_to_string(square);
+    origin.chars().all(|c| name.contains(c))
+}
+
+/// The SAN of a legal move, with its check or mate suffix.
+pub fn to_san(position: &Position, from: Square, to: Square, promotion: Option<Piece>) -> String {
+    let Some((_, piece)) = position.piece_at(from) else {
+        return format!("{}{}", square_to_string(from), square_to_string(to));
+    };
+    let mut san = String::new();
+
+    if piece == Piece::King && from.abs_diff(to) == 2 {
+        san.push_str(if to > from { "O-O" } else { "O-O-O" });
+    } else {
+        let capture = position.is_capture(from, to);
+        let origin = square_to_string(from);
+        if piece == Piece::Pawn {
+            if capture {
+                san.push_str(&origin[..1]);
+            }
+        } else {
+            san.push(piece.letter());
+            // Other pieces of the same type that could also go there
+            let rivals: Vec<Square> = position
+                .legal_moves()
+                .into_iter()
+                .filter(|(other, target, _)| {
+                    *target == to
+                        && *other != from
+                        && position.piece_at(*other) == position.piece_at(from)
+                })
+                .map(|(other, _, _)| other)
+                .collect();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|other| other % 8 != from % 8) {
+                    san.push_str(&origin[..1]);
+                } else if rivals.iter().all(|other| other / 8 != from / 8) {
+                    san.push_str(&origin[1..]);
+                } else {
+                    san.push_str(&origin);
+                }
+            }
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&square_to_string(to));
+        if let Some(promoted) = promotion {
+            san.push('=');
+            san.push(promoted.letter());
+        }
+    }
+
+    let after = position.play(from, to, promotion);
+    if after.is_checkmate() {
+        san.push('#');
+    } else if after.in_check(after.side_to_move) {
+        san.push('+');
+    }
+    san
+}
@@ -0,0 +1,86 @@
+//! Games as sequences of SAN tokens and the vocabulary they use, for sequence models trained on
+//! moves. Tokens come from the SAN writer, so `Ngf3`, `N1f3` and `Nf3` written by different
+//! sources all become the same token.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::json;
+use crate::notation::{write_moves, San};
+use crate::position::Position;
+
+/// The SAN tokens of a game. `positions` holds the position before each move.
+pub fn game_tokens(positions: &[Position], moves: &[String]) -> Vec<String> {
+    write_moves(&San, positions, moves)
+}
+
+/// Writes the tokens of a game on a line, after its number and a tab, or as a JSON line.
+pub fn write_sequence<W: Write>(
+    writer: &mut W,
+    game_number: usize,
+    tokens: &[String],
+    as_json: bool,
+) -> io::Result<()> {
+    if as_json {
+        let tokens = json::array(tokens.iter().map(|token| json::string(token)));
+        let line = json::object([("game", game_number.to_string()), ("tokens", tokens)]);
+        writeln!(writer, "{line}")
+    } else {
+        writeln!(writer, "{game_number}\t{}", tokens.join(" "))
+    }
+}
+
+/// Token counts over all games.
+#[derive(Default)]
+pub struct Vocabulary {
+    counts: HashMap<String, usize>,
+}
+
+impl Vocabulary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, tokens: &[String]) {
+        for token in tokens {
+            *self.counts.entry(token.clone()).or_default() += 1;
+        }
+    }
+
+    /// Number of distinct tokens.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Tokens with their counts, most frequent first and alphabetically among equals.
+    pub fn sorted(&self) -> Vec<(&str, usize)> {
+        let mut tokens: Vec<(&str, usize)> = self
+            .counts
+            .iter()
+            .map(|(token, count)| (token.as_str(), *count))
+            .collect();
+        tokens.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        tokens
+    }
+
+    /// A token and its count per line, separated by a tab.
+    pub fn to_text(&self) -> String {
+        self.sorted()
+            .iter()
+            .map(|(token, count)| format!("{token}\t{count}\n"))
+            .collect()
+    }
+
+    /// An object of the counts, keys in the order of [`Vocabulary::sorted`].
+    pub fn to_json(&self) -> String {
+        json::object(
+            self.sorted()
+                .into_iter()
+                .map(|(token, count)| (token, count.to_string())),
+        )
+    }
+}
@@ -0,0 +1,65 @@
+//! Static exchange evaluation: the material a move wins or loses once the captures on its
+//! target square are played out, each side taking with its least valuable piece and
+//! stopping when going on would lose. Pins, checks and threats elsewhere are not seen.
+
+use chess::legal_moves::misc::{Color, Square};
+
+use crate::position::{Piece, Position};
+
+/// Value of a piece in an exchange, in pawns, the king being worth more than any gain.
+pub fn exchange_value(piece: Piece) -> i32 {
+    match piece {
+        Piece::King => 100,
+        piece => piece.value() as i32,
+    }
+}
+
+/// Material `side` wins by capturing on `square` and carrying the exchange on while it pays,
+/// 0 when it has better not start.
+pub fn exchange_gain(position: &Position, square: Square, side: Color) -> i32 {
+    let Some((_, target)) = position.piece_at(square) else {
+        return 0;
+    };
+    let attacker = position
+        .attackers(square, side)
+        .into_iter()
+        .filter_map(|from| position.piece_at(from).map(|(_, piece)| (from, piece)))
+        .min_by_key(|(_, piece)| exchange_value(*piece));
+    let Some((from, _)) = attacker else {
+        return 0;
+    };
+
+    // Taking the attacker off its square uncovers the pieces lined up behind it
+    let mut next = *position;
+    next.squares[square as usize] = next.squares[from as usize].take();
+    (exchange_value(target) - exchange_gain(&next, square, !side)).max(0)
+}
+
+/// Exchange value of the move from `from` to `to`, in pawns, for the side making it: what it
+/// captures and gains by promoting, less what the opponent then wins on `to`. Quiet moves are
+/// valued too, as the loss of a piece moved where it can be taken. 0 when `from` is empty.
+pub fn see(position: &Position, from: Square, to: Square, promotion: Option<Piece>) -> i32 {
+    let Some((color, piece)) = position.piece_at(from) else {
+        return 0;
+    };
+    let mut next = *position;
+    let mut gain = 0;
+
+    match position.piece_at(to) {
+        Some((owner, captured)) if owner != color => gain += exchange_value(captured),
+        Some(_) => return 0,
+        // En passant takes the pawn beside the origin
+        None if piece == Piece::Pawn && from % 8 != to % 8 => {
+            next.squares[(from - from % 8 + to % 8) as usize] = None;
+            gain += exchange_value(Piece::Pawn);
+        }
+        None => {}
+    }
+
+    let moved = promotion.unwrap_or(piece);
+    gain += exchange_value(moved) - exchange_value(piece);
+    next.squares[from as usize] = None;
+    next.squares[to as usize] = Some((color, moved));
+
+    gain - exchange_gain(&next, to, !color)
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::io;
+use std::time::Instant;
+
+use chess::legal_moves::misc::Color;
+
+use crate::position::{coordinate_move, parse_coordinate_move, Piece, Position};
+use crate::uci::{Clocks, Engine};
+
+/// Base time and increment of one player, in milliseconds.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimeControl {
+    pub base: u64,
+    pub increment: u64,
+}
+
+impl TimeControl {
+    /// Parses a TimeControl tag style value in seconds, such as `60+1` or `300`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (base, increment) = value.split_once('+').unwrap_or((value, "0"));
+        let seconds = |value: &str| value.parse::<f64>().ok().filter(|s| *s >= 0.0);
+
+        Some(TimeControl {
+            base: (seconds(base)? * 1000.0) as u64,
+            increment: (seconds(increment)? * 1000.0) as u64,
+        })
+    }
+
+    /// The value of the TimeControl tag.
+    pub fn tag(&self) -> String {
+        if self.increment == 0 {
+            format!("{}", self.base as f64 / 1000.0)
+        } else {
+            format!(
+                "{}+{}",
+                self.base as f64 / 1000.0,
+                self.increment as f64 / 1000.0
+            )
+        }
+    }
+}
+
+/// Settings of the selfplay subcommand.
+pub struct SelfplaySettings {
+    /// Command lines of the two engines. The first one has white in odd games.
+    pub engines: [String; 2],
+    pub games: usize,
+    /// Time controls of the two engines, in the same order.
+    pub time_controls: [TimeControl; 2],
+    /// Plies after which an unfinished game is adjudicated a draw.
+    pub max_plies: usize,
+    pub output: Option<String>,
+}
+
+/// A finished engine game, with moves in coordinate notation.
+pub struct PlayedGame {
+    pub white: String,
+    pub black: String,
+    pub moves: Vec<String>,
+    pub result: &'static str,
+    /// Why the game ended, for the Termination tag.
+    pub termination: &'static str,
+}
+
+/// The legal move matching a coordinate move from the engine, in its canonical form.
+fn legal_move(position: &Position, mv: &str) -> Option<(u8, u8, Option<Piece>)> {
+    let mv = parse_coordinate_move(mv)?;
+    position
+        .legal_moves()
+        .into_iter()
+        .find(|legal| *legal == mv)
+}
+
+fn win_for(color: Color) -> &'static str {
+    if color == Color::White {
+        "1-0"
+    } else {
+        "0-1"
+    }
+}
+
+/// Plays one game between two started engines. Illegal moves and flag falls lose.
+pub fn play_game(
+    white: &mut Engine,
+    black: &mut Engine,
+    time_controls: [TimeControl; 2],
+    max_plies: usize,
+) -> io::Result<PlayedGame> {
+    white.new_game()?;
+    black.new_game()?;
+
+    let mut clocks = Clocks {
+        white: time_controls[0].base,
+        black: time_controls[1].base,
+        white_increment: time_controls[0].increment,
+        black_increment: time_controls[1].increment,
+    };
+    let mut position = Position::initial();
+    let mut moves = Vec::new();
+    let mut repetitions: HashMap<u64, usize> = HashMap::new();
+    repetitions.insert(position.zobrist(), 1);
+
+    let (result, termination) = loop {
+        let color = position.side_to_move;
+        if position.legal_moves().is_empty() {
+            break if position.in_check(color) {
+                (win_for(!color), "checkmate")
+            } else {
+                ("1/2-1/2", "stalemate")
+            };
+        }
+        if position.fifty_move_rule() {
+            break ("1/2-1/2", "fifty-move rule");
+        }
+        if moves.len() >= max_plies {
+            break ("1/2-1/2", "adjudication");
+        }
+
+        let (engine, increment) = if color == Color::White {
+            (&mut *white, clocks.white_increment)
+        } else {
+            (&mut *black, clocks.black_increment)
+        };
+        let started = Instant::now();
+        let reply = engine.best_move(&moves, &clocks)?;
+        let elapsed = started.elapsed().as_millis() as u64;
+
+        let clock = if color == Color::White {
+            &mut clocks.white
+        } else {
+            &mut clocks.black
+        };
+        if elapsed > *clock {
+            break (win_for(!color), "time forfeit");
+        }
+        *clock = *clock - elapsed + increment;
+
+        let Some((from, to, promotion)) = reply.as_deref().and_then(|mv| legal_move(&position, mv))
+        else {
+            break (win_for(!color), "illegal move");
+        };
+        moves.push(coordinate_move(from, to, promotion));
+        position = position.play(from, to, promotion);
+
+        let seen = repetitions.entry(position.zobrist()).or_insert(0);
+        *seen += 1;
+        if *seen >= 3 {
+            break ("1/2-1/2", "threefold repetition");
+        }
+    };
+
+    Ok(PlayedGame {
+        white: white.name.clone(),
+        black: black.name.clone(),
+        moves,
+        result,
+        termination,
+    })
+}
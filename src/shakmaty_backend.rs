@@ -0,0 +1,152 @@
+//! A board backed by the `shakmaty` crate, built with the `shakmaty` feature. shakmaty only
+//! answers legality for a full position, so the pieces and the en passant square are kept here
+//! and a position is set up from them whenever a move is checked.
+
+use chess::legal_moves::misc::{Color, Square};
+use shakmaty::{CastlingMode, Chess, FromSetup, Position as _, PositionError, Role, Setup};
+
+use crate::backend::BoardBackend;
+use crate::position::{Piece, Position};
+
+pub struct ShakmatyBackend {
+    board: shakmaty::Board,
+    /// Square skipped by a pawn double push on the last move.
+    en_passant: Option<Square>,
+}
+
+impl ShakmatyBackend {
+    pub fn new() -> Self {
+        ShakmatyBackend {
+            board: shakmaty::Board::new(),
+            en_passant: None,
+        }
+    }
+}
+
+impl Default for ShakmatyBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn to_square(square: Square) -> shakmaty::Square {
+    shakmaty::Square::new(u32::from(square))
+}
+
+fn to_piece(color: Color, piece: Piece) -> shakmaty::Piece {
+    let color = match color {
+        Color::White => shakmaty::Color::White,
+        Color::Black => shakmaty::Color::Black,
+    };
+    shakmaty::Piece {
+        color,
+        role: to_role(piece),
+    }
+}
+
+fn to_role(piece: Piece) -> Role {
+    match piece {
+        Piece::Pawn => Role::Pawn,
+        Piece::Knight => Role::Knight,
+        Piece::Bishop => Role::Bishop,
+        Piece::Rook => Role::Rook,
+        Piece::Queen => Role::Queen,
+        Piece::King => Role::King,
+    }
+}
+
+impl BoardBackend for ShakmatyBackend {
+    fn name(&self) -> &'static str {
+        "shakmaty"
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn pieces(&self, color: Color, piece: Piece) -> Vec<Square> {
+        self.board
+            .by_piece(to_piece(color, piece))
+            .into_iter()
+            .map(u8::from)
+            .collect()
+    }
+
+    fn is_possible(&self, from: Square, to: Square) -> bool {
+        let Some(moved) = self.board.piece_at(to_square(from)) else {
+            return false;
+        };
+        // The processor asks for either side, shakmaty answers for its side to move. Castling
+        // rights are left out, castling being played through `castle`.
+        let mut setup = Setup::empty();
+        setup.board = self.board.clone();
+        setup.turn = moved.color;
+        setup.ep_square = self.en_passant.map(to_square);
+        let position = Chess::from_setup(setup, CastlingMode::Standard)
+            .or_else(PositionError::ignore_invalid_ep_square)
+            .or_else(PositionError::ignore_too_much_material)
+            .or_else(PositionError::ignore_impossible_check);
+        position.is_ok_and(|position| {
+            position
+                .legal_moves()
+                .iter()
+                .any(|mv| mv.from() == Some(to_square(from)) && mv.to() == to_square(to))
+        })
+    }
+
+    fn play_move(&mut self, from: Square, to: Square, promotion: Option<Piece>) {
+        let Some(moved) = self.board.remove_piece_at(to_square(from)) else {
+            return;
+        };
+        self.en_passant = None;
+        if moved.role == Role::Pawn {
+            // A pawn moving aside to an empty square takes the pawn beside its origin
+            if from % 8 != to % 8 && self.board.piece_at(to_square(to)).is_none() {
+                self.board
+                    .remove_piece_at(to_square(from - from % 8 + to % 8));
+            }
+            if from.abs_diff(to) == 16 {
+                self.en_passant = Some((from + to) / 2);
+            }
+        }
+        let role = promotion.map_or(moved.role, to_role);
+        self.board.set_piece_at(
+            to_square(to),
+            shakmaty::Piece {
+                color: moved.color,
+                role,
+            },
+        );
+    }
+
+    fn castle(&mut self, castle: &str, color: Color) {
+        let rank = if color == Color::White { 0 } else { 56 };
+        let (king_to, rook_from, rook_to) = if castle == "O-O" {
+            (6, 7, 5)
+        } else {
+            (2, 0, 3)
+        };
+        for (from, to) in [(4, king_to), (rook_from, rook_to)] {
+            if let Some(piece) = self.board.remove_piece_at(to_square(rank + from)) {
+                self.board.set_piece_at(to_square(rank + to), piece);
+            }
+        }
+        self.en_passant = None;
+    }
+
+    fn supports_promotion(&self) -> bool {
+        true
+    }
+
+    fn set_position(&mut self, position: &Position) -> bool {
+        self.board = shakmaty::Board::empty();
+        for (square, occupant) in (0..).zip(position.squares) {
+            if let Some((color, piece)) = occupant {
+                self.board
+                    .set_piece_at(to_square(square), to_piece(color, piece));
+            }
+        }
+        self.en_passant = position.en_passant;
+        true
+    }
+}
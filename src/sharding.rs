@@ -0,0 +1,123 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use crate::filters::game_date;
+use crate::pgn_cleaner::RawGame;
+
+/// Files kept open at once; older ones are closed and reopened for appending when needed.
+const MAX_OPEN_SHARDS: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShardKey {
+    Eco,
+    Year,
+    Player,
+    Result,
+}
+
+impl ShardKey {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "eco" => Some(ShardKey::Eco),
+            "year" => Some(ShardKey::Year),
+            "player" => Some(ShardKey::Player),
+            "result" => Some(ShardKey::Result),
+            _ => None,
+        }
+    }
+
+    /// Shards a game belongs to. Player sharding puts a game in both players' shards.
+    pub fn keys(&self, game: &RawGame) -> Vec<String> {
+        let keys = match self {
+            ShardKey::Eco => vec![game.tag("ECO").map(str::to_string)],
+            ShardKey::Year => vec![game_date(game).map(|date| date.year.to_string())],
+            ShardKey::Player => vec![
+                game.tag("White").map(str::to_string),
+                game.tag("Black").map(str::to_string),
+            ],
+            ShardKey::Result => vec![game
+                .tag("Result")
+                .or_else(|| game.termination())
+                .map(str::to_string)],
+        };
+
+        let mut keys: Vec<String> = keys
+            .into_iter()
+            .map(|key| sanitize(key.as_deref().unwrap_or("unknown")))
+            .collect();
+        keys.dedup();
+        keys
+    }
+}
+
+/// Makes a key safe to use in a file name.
+fn sanitize(key: &str) -> String {
+    let key: String = key
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if key.is_empty() || key.chars().all(|c| c == '_') {
+        "unknown".to_string()
+    } else {
+        key
+    }
+}
+
+/// Routes output to `games_<key>.pgn` files in a directory.
+pub struct ShardWriter {
+    directory: PathBuf,
+    open: HashMap<String, BufWriter<File>>,
+    created: HashSet<String>,
+}
+
+impl ShardWriter {
+    pub fn new(directory: &str) -> io::Result<Self> {
+        fs::create_dir_all(directory)?;
+
+        Ok(ShardWriter {
+            directory: PathBuf::from(directory),
+            open: HashMap::new(),
+            created: HashSet::new(),
+        })
+    }
+
+    pub fn write(&mut self, key: &str, data: &str) -> io::Result<()> {
+        if !self.open.contains_key(key) {
+            if self.open.len() >= MAX_OPEN_SHARDS {
+                self.flush()?;
+                self.open.clear();
+            }
+
+            let path = self.directory.join(format!("games_{key}.pgn"));
+            // Shards from earlier runs are overwritten, reopened ones appended to
+            let file = if self.created.insert(key.to_string()) {
+                File::create(path)?
+            } else {
+                OpenOptions::new().append(true).open(path)?
+            };
+            self.open.insert(key.to_string(), BufWriter::new(file));
+        }
+
+        self.open
+            .get_mut(key)
+            .expect("shard was just opened")
+            .write_all(data.as_bytes())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        for writer in self.open.values_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+/// Where the input PGN comes from. Sources yield the raw bytes of the input, so that game
+/// offsets match the concatenated input.
+pub trait Source {
+    /// Opens the input positioned `offset` bytes in.
+    fn open(&self, offset: u64) -> io::Result<Box<dyn Read>>;
+
+    /// Whether opening at an offset seeks there rather than reading up to it.
+    fn seekable(&self) -> bool {
+        false
+    }
+}
+
+/// Reads the whole input of a source from a byte offset.
+pub fn read_to_string(source: &dyn Source, offset: u64) -> io::Result<String> {
+    let mut pgn = String::new();
+    BufReader::new(source.open(offset)?).read_to_string(&mut pgn)?;
+    Ok(pgn)
+}
+
+/// Where each file of an input starts in the input they make up once concatenated.
+pub struct SourceMap {
+    files: Vec<(String, usize)>,
+}
+
+impl SourceMap {
+    /// The file holding a byte of the concatenated input, and the byte's offset in that file.
+    pub fn locate(&self, offset: usize) -> Option<(&str, usize)> {
+        let index = self.files.partition_point(|(_, start)| *start <= offset);
+        let (name, start) = self.files.get(index.checked_sub(1)?)?;
+        Some((name, offset - start))
+    }
+}
+
+/// Reads the whole input from a byte offset like [`read_to_string`], with the map of the files
+/// it is made of. Offsets in the map count from the start of the input, not from `offset`.
+pub fn read_with_map(input: Option<&str>, offset: usize) -> io::Result<(String, SourceMap)> {
+    let directory = input.map(Path::new).filter(|path| path.is_dir());
+    let Some(directory) = directory else {
+        let name = input.filter(|input| *input != "-").unwrap_or("stdin");
+        let pgn = read_to_string(open_source(input)?.as_ref(), offset as u64)?;
+        let files = vec![(name.to_string(), 0)];
+        return Ok((pgn, SourceMap { files }));
+    };
+
+    read_files_with_map(&Directory(directory.to_path_buf()).files()?, offset)
+}
+
+/// Reads files one after the other from a byte offset of their concatenation, with their map.
+pub fn read_files_with_map(paths: &[PathBuf], offset: usize) -> io::Result<(String, SourceMap)> {
+    let mut pgn = String::new();
+    let mut files = Vec::new();
+    for path in paths {
+        files.push((path.to_string_lossy().into_owned(), pgn.len()));
+        pgn.push_str(&read_to_string(open_source(path.to_str())?.as_ref(), 0)?);
+    }
+    pgn.drain(..offset.min(pgn.len()));
+    Ok((pgn, SourceMap { files }))
+}
+
+/// The paths listed one per line in a file, or in standard input for `-`, as `find` prints
+/// them. Blank lines are left out, and a missing file fails the list before any is read.
+pub fn read_file_list(list: &str) -> io::Result<Vec<PathBuf>> {
+    let mut text = String::new();
+    if list == "-" {
+        io::stdin().read_to_string(&mut text)?;
+    } else {
+        File::open(list)?.read_to_string(&mut text)?;
+    }
+    let paths: Vec<PathBuf> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    if let Some(missing) = paths.iter().find(|path| !path.exists()) {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Listed file {} does not exist", missing.display()),
+        ));
+    }
+    Ok(paths)
+}
+
+/// Picks the source for an input argument: standard input when there is none or it is `-`,
+/// then URLs, directories, compressed files and plain files.
+pub fn open_source(input: Option<&str>) -> io::Result<Box<dyn Source>> {
+    let Some(input) = input.filter(|input| *input != "-") else {
+        return Ok(Box::new(Stdin));
+    };
+    if input.starts_with("http://") || input.starts_with("https://") {
+        return Ok(Box::new(Http(input.to_string())));
+    }
+    #[cfg(feature = "s3")]
+    if crate::s3::is_s3_uri(input) {
+        return Ok(Box::new(crate::s3::S3Object(input.to_string())));
+    }
+    if input.contains("://") {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("No source can read {input}"),
+        ));
+    }
+
+    let path = PathBuf::from(input);
+    if path.is_dir() {
+        Ok(Box::new(Directory(path)))
+    } else if let Some(decompressor) = decompressor_for(&path) {
+        Ok(Box::new(Compressed { path, decompressor }))
+    } else {
+        Ok(Box::new(FileSource(path)))
+    }
+}
+
+/// Discards the first `offset` bytes of a reader that cannot seek.
+pub fn skip(mut reader: Box<dyn Read>, offset: u64) -> io::Result<Box<dyn Read>> {
+    io::copy(&mut reader.by_ref().take(offset), &mut io::sink())?;
+    Ok(reader)
+}
+
+pub struct FileSource(pub PathBuf);
+
+impl Source for FileSource {
+    fn open(&self, offset: u64) -> io::Result<Box<dyn Read>> {
+        let mut file = File::open(&self.0)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(file))
+    }
+
+    fn seekable(&self) -> bool {
+        true
+    }
+}
+
+pub struct Stdin;
+
+impl Source for Stdin {
+    fn open(&self, offset: u64) -> io::Result<Box<dyn Read>> {
+        println!("Enter PGN (press Ctrl+D when done):");
+        skip(Box::new(io::stdin()), offset)
+    }
+}
+
+/// The PGN files of a directory, compressed or not, read one after the other in name order.
+pub struct Directory(pub PathBuf);
+
+impl Directory {
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.0)? {
+            let path = entry?.path();
+            let stem = match decompressor_for(&path) {
+                Some(_) => path.file_stem().map(Path::new),
+                None => Some(path.as_path()),
+            };
+            if path.is_file() && stem.is_some_and(|stem| stem.extension() == Some("pgn".as_ref())) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+impl Source for Directory {
+    fn open(&self, offset: u64) -> io::Result<Box<dyn Read>> {
+        FileList(self.files()?).open(offset)
+    }
+}
+
+/// Files read one after the other in the order given, compressed or not.
+pub struct FileList(pub Vec<PathBuf>);
+
+impl Source for FileList {
+    fn open(&self, offset: u64) -> io::Result<Box<dyn Read>> {
+        let mut reader: Box<dyn Read> = Box::new(io::empty());
+        for path in &self.0 {
+            let file = open_source(path.to_str())?.open(0)?;
+            reader = Box::new(reader.chain(file));
+        }
+        skip(reader, offset)
+    }
+}
+
+/// A compressed file, decompressed on the fly by the matching command line tool.
+pub struct Compressed {
+    pub path: PathBuf,
+    pub decompressor: &'static str,
+}
+
+const DECOMPRESSORS: [(&str, &str); 4] = [
+    ("gz", "gzip"),
+    ("zst", "zstd"),
+    ("bz2", "bzip2"),
+    ("xz", "xz"),
+];
+
+pub fn decompressor_for(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?;
+    DECOMPRESSORS
+        .iter()
+        .find(|(known, _)| extension == *known)
+        .map(|(_, decompressor)| *decompressor)
+}
+
+impl Source for Compressed {
+    fn open(&self, offset: u64) -> io::Result<Box<dyn Read>> {
+        let path = self.path.as_os_str();
+        let decompressor = spawn(self.decompressor, &["-dc".as_ref(), path], Stdio::null())?;
+        skip(Box::new(ProcessOutput(vec![decompressor])), offset)
+    }
+}
+
+/// A PGN downloaded over HTTP(S) by curl while it is read, decompressed on the fly when the
+/// URL names a compressed file such as a Lichess monthly dump. Like the decompressors, curl
+/// runs as a process rather than as a client crate, which would bring in TLS and a zstd
+/// binding; it has to be on the PATH.
+pub struct Http(pub String);
+
+impl Source for Http {
+    fn open(&self, offset: u64) -> io::Result<Box<dyn Read>> {
+        let args = ["--silent", "--show-error", "--fail", "--location", &self.0];
+        let args: Vec<&OsStr> = args.iter().map(OsStr::new).collect();
+        let mut pipeline = vec![spawn("curl", &args, Stdio::null())?];
+
+        let path = self.0.split(['?', '#']).next().unwrap_or_default();
+        if let Some(decompressor) = decompressor_for(Path::new(path)) {
+            let download = pipeline[0]
+                .1
+                .stdout
+                .take()
+                .map_or_else(Stdio::null, Stdio::from);
+            pipeline.push(spawn(decompressor, &["-dc".as_ref()], download)?);
+        }
+        skip(Box::new(ProcessOutput(pipeline)), offset)
+    }
+}
+
+pub fn spawn(
+    program: &'static str,
+    args: &[&OsStr],
+    stdin: Stdio,
+) -> io::Result<(&'static str, Child)> {
+    let child = Command::new(program)
+        .args(args)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|error| io::Error::new(error.kind(), format!("Cannot run {program}: {error}")))?;
+    Ok((program, child))
+}
+
+/// The standard output of the last of a pipeline of child processes, failing at the end if
+/// any of them did.
+pub struct ProcessOutput(pub Vec<(&'static str, Child)>);
+
+impl Read for ProcessOutput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let stdout = self
+            .0
+            .last_mut()
+            .and_then(|(_, child)| child.stdout.as_mut());
+        let read = match stdout {
+            Some(stdout) => stdout.read(buf)?,
+            None => 0,
+        };
+        if read == 0 && !buf.is_empty() {
+            for (program, child) in &mut self.0 {
+                child.stdout = None;
+                let status = child.wait()?;
+                if !status.success() {
+                    return Err(io::Error::other(format!("{program} failed: {status}")));
+                }
+            }
+        }
+        Ok(read)
+    }
+}
@@ -0,0 +1,53 @@
+//! Games gathered into a multi-chapter PGN for import as a Lichess study, one chapter per game.
+//! Chapters are named from a template of tags, and the name is written in the ChapterName tag
+//! Lichess uses in its own study exports; annotations are kept as they are.
+
+use std::borrow::Cow;
+
+use crate::pgn_ast::{Game, TagPair};
+
+/// The most chapters a Lichess study holds.
+pub const LICHESS_MAX_CHAPTERS: usize = 64;
+
+/// Chapter names when no template is given.
+pub const DEFAULT_CHAPTER_TEMPLATE: &str = "{White} - {Black}";
+
+/// Fills a template with the tags of a game: `{Tag}` becomes the value of Tag, or `?` when the
+/// game has none. Braces that enclose no tag name are kept as written.
+pub fn chapter_name(template: &str, game: &Game) -> String {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        name.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find('}') {
+            Some(close) if close > 0 && !after[..close].contains(['{', ' ']) => {
+                name.push_str(game.tag(&after[..close]).unwrap_or("?"));
+                rest = &after[close + 1..];
+            }
+            _ => {
+                name.push('{');
+                rest = after;
+            }
+        }
+    }
+    name.push_str(rest);
+    name.trim().to_string()
+}
+
+/// Makes a game a chapter: its ChapterName tag, and the StudyName tag when the study is named,
+/// replace any it had.
+pub fn make_chapter(game: &mut Game, study: Option<&str>, chapter: String) {
+    game.tags
+        .retain(|tag| tag.name != "ChapterName" && tag.name != "StudyName");
+    if let Some(study) = study {
+        game.tags.push(TagPair {
+            name: "StudyName",
+            value: Cow::Owned(study.to_string()),
+        });
+    }
+    game.tags.push(TagPair {
+        name: "ChapterName",
+        value: Cow::Owned(chapter),
+    });
+}
@@ -0,0 +1,81 @@
+use std::borrow::Cow;
+
+use crate::pgn_ast::Game;
+
+/// Whether a tag name matches a pattern where `*` stands for any run of characters and `?` for
+/// a single one, such as `*RatingDiff`.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Position after the last `*` in the pattern, and where the name resumes from it
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` take one more character
+                Some((after, from)) => {
+                    star = Some((after, from + 1));
+                    p = after;
+                    n = from + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Which tags are written out: those matching a `keep` pattern when any is given, minus those
+/// matching a `drop` pattern.
+#[derive(Clone, Default, Debug)]
+pub struct TagSelection {
+    pub keep: Vec<String>,
+    pub drop: Vec<String>,
+}
+
+impl TagSelection {
+    /// Splits a comma-separated list of tag patterns.
+    pub fn parse_list(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keep.is_empty() && self.drop.is_empty()
+    }
+
+    pub fn wants(&self, tag: &str) -> bool {
+        let matches =
+            |patterns: &[String]| patterns.iter().any(|pattern| wildcard_match(pattern, tag));
+        (self.keep.is_empty() || matches(&self.keep)) && !matches(&self.drop)
+    }
+
+    pub fn select<'a>(&self, tags: &'a [(String, String)]) -> Cow<'a, [(String, String)]> {
+        if self.is_empty() {
+            return Cow::Borrowed(tags);
+        }
+        tags.iter()
+            .filter(|(tag, _)| self.wants(tag))
+            .cloned()
+            .collect()
+    }
+
+    pub fn select_game(&self, game: &mut Game) {
+        game.tags.retain(|tag| self.wants(tag.name));
+    }
+}
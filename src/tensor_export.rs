@@ -0,0 +1,79 @@
+//! Binary export of positions as bitboard planes for neural-network training.
+//!
+//! The file starts with the magic `PGNT` and a version byte, followed by fixed-size records:
+//!
+//! | bytes | content                                                                   |
+//! |-------|---------------------------------------------------------------------------|
+//! | 96    | 12 planes as little-endian u64 bitboards: white P N B R Q K, then black  |
+//! | 1     | side to move (0 = white, 1 = black)                                       |
+//! | 1     | castling rights as KQkq bits (white king side is bit 0)                   |
+//! | 2     | played move as from and to square (a1 = 0, h8 = 63)                       |
+//! | 1     | game result from white's point of view as i8 (1, 0, -1)                   |
+
+use std::io::{self, Write};
+
+use chess::legal_moves::misc::Color;
+use chess::utils::string_to_square;
+
+use crate::position::{Piece, Position};
+
+pub const MAGIC: &[u8; 4] = b"PGNT";
+pub const VERSION: u8 = 1;
+pub const RECORD_SIZE: usize = 12 * 8 + 5;
+
+pub fn write_header<W: Write>(writer: &mut W) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])
+}
+
+/// Result label from white's point of view; None for unfinished games.
+pub fn result_label(result: &str) -> Option<i8> {
+    match result {
+        "1-0" => Some(1),
+        "0-1" => Some(-1),
+        "1/2-1/2" => Some(0),
+        _ => None,
+    }
+}
+
+pub fn encode_position(position: &Position, mv: &str, result: i8) -> [u8; RECORD_SIZE] {
+    let mut record = [0; RECORD_SIZE];
+
+    for (color_index, color) in [Color::White, Color::Black].into_iter().enumerate() {
+        for piece in Piece::ALL {
+            let offset = (color_index * 6 + piece.index()) * 8;
+            record[offset..offset + 8]
+                .copy_from_slice(&position.bitboard(color, piece).to_le_bytes());
+        }
+    }
+
+    record[96] = if position.side_to_move == Color::White {
+        0
+    } else {
+        1
+    };
+    record[97] = position.castling.0;
+    record[98] = string_to_square(&mv[0..2]);
+    record[99] = string_to_square(&mv[2..4]);
+    record[100] = result as u8;
+
+    record
+}
+
+/// Writes one record per played move of a game. Games without a decisive or drawn result are skipped.
+pub fn write_game<W: Write>(
+    writer: &mut W,
+    positions: &[Position],
+    moves: &[String],
+    result: &str,
+) -> io::Result<()> {
+    let Some(result) = result_label(result) else {
+        return Ok(());
+    };
+
+    for (position, mv) in positions.iter().zip(moves) {
+        writer.write_all(&encode_position(position, mv, result))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,706 @@
+/// The positions reached by playing coordinate moves from a FEN, the start and final ones
+/// included, along with the moves as the processor gives them.
+fn replay_positions(fen: &str, moves: &[&str]) -> (Vec<crate::position::Position>, Vec<String>) {
+    use crate::position::{parse_coordinate_move, Position};
+
+    let mut positions = vec![Position::from_fen(fen).unwrap()];
+    for mv in moves {
+        let (from, to, promotion) = parse_coordinate_move(mv).unwrap();
+        positions.push(positions.last().unwrap().play(from, to, promotion));
+    }
+    (positions, moves.iter().map(|mv| mv.to_string()).collect())
+}
+
+#[test]
+fn test_phases() {
+    use crate::phases::{phases, Phase};
+    use crate::position::Position;
+
+    let start = Position::initial();
+    let castled =
+        Position::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4")
+            .unwrap();
+    let rook_ending = Position::from_fen("8/5pk1/8/8/8/8/r4PPP/3R2K1 w - - 0 40").unwrap();
+
+    assert_eq!(Phase::of(&start), Phase::Opening);
+    assert_eq!(Phase::of(&castled), Phase::Middlegame);
+    assert_eq!(Phase::of(&rook_ending), Phase::Endgame);
+
+    // Phases never go back
+    assert_eq!(
+        phases(&[start, rook_ending, castled]),
+        vec![Phase::Opening, Phase::Endgame, Phase::Endgame]
+    );
+}
+
+#[test]
+fn test_game_captures() {
+    use crate::captures::GameCaptures;
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    let games = split_games("1. e4 d5 2. exd5 Qxd5 3. Nc3 Qe5+ 4. Qe2 Qxe2+ 5. Bxe2 *");
+    let mut processor = PgnProcessor::new();
+    let moves = processor.process_game(&games[0]);
+    let captures = GameCaptures::of(processor.positions(), &moves);
+
+    assert_eq!(captures.captures, 4);
+    assert_eq!(captures.first_capture_ply, Some(3));
+    assert_eq!(captures.queen_trade_ply, Some(9));
+    // Over 10 positions: a pawn up after 2. exd5, a queen down after 4... Qxe2+
+    assert_eq!(captures.average_imbalance, (1.0 + 9.0) / 10.0);
+}
+
+#[test]
+fn test_pawn_structures() {
+    use crate::pawn_structure::{PawnStructure, StructureTemplate};
+    use crate::position::Position;
+
+    let structure = |fen: &str| PawnStructure::of(&Position::from_fen(fen).unwrap());
+    let iqp = StructureTemplate::parse("IQP").unwrap();
+    let isolated = structure("r1bq1rk1/pp2bppp/2n1pn2/8/3P4/2NB1N2/PP3PPP/R1BQ1RK1 w - - 0 10");
+    let supported = structure("r1bq1rk1/pp2bppp/2n1pn2/8/3P4/2NBPN2/PP3PPP/R1BQ1RK1 w - - 0 10");
+    assert!(iqp.matches(&isolated));
+    assert!(!iqp.matches(&supported));
+    assert_ne!(isolated.fingerprint(), supported.fingerprint());
+    assert_eq!(isolated.placement(), "8/pp3ppp/4p3/8/3P4/8/PP3PPP/8");
+
+    // Custom masks use the same syntax; pieces other than pawns are not allowed
+    let custom = StructureTemplate::parse("8/8/8/8/4P3/8/8/8").unwrap();
+    assert!(custom.matches(&structure(
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+    )));
+    assert!(StructureTemplate::parse("8/8/8/8/4N3/8/8/8").is_err());
+    assert!(StructureTemplate::parse("8/8/8").is_err());
+}
+
+#[test]
+fn test_endgame_classification() {
+    use crate::endgames::{classify, endgame_class};
+    use crate::position::Position;
+
+    let class = |fen: &str| classify(&Position::from_fen(fen).unwrap());
+    assert_eq!(
+        class("8/8/4k3/8/4P3/4K3/r7/R7 w - - 0 60").as_deref(),
+        Some("R+P vs R")
+    );
+    // The stronger side comes first, whatever its color
+    assert_eq!(
+        class("8/8/4k3/2pp4/8/4K3/8/8 w - - 0 60").as_deref(),
+        Some("2P vs K")
+    );
+    assert_eq!(
+        class("8/5p2/4k1b1/8/4P3/2B1K3/8/8 w - - 0 60").as_deref(),
+        Some("B+P vs B+P, opposite-colored bishops")
+    );
+    assert_eq!(
+        class("8/5p2/4kb2/8/4P3/2B1K3/8/8 w - - 0 60").as_deref(),
+        Some("B+P vs B+P")
+    );
+    assert_eq!(classify(&Position::initial()), None);
+    assert_eq!(endgame_class(&[Position::initial()]), None);
+}
+
+#[test]
+fn test_motifs() {
+    use crate::patterns::{detect, Motif};
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    let replay = |fen: &str, moves: &[&str]| -> Vec<Motif> {
+        let (positions, moves) = replay_positions(fen, moves);
+        detect(&positions, &moves)
+    };
+
+    let games = split_games("1. e4 c6 2. d4 d5 3. Nc3 dxe4 4. Nxe4 Nd7 5. Qe2 Ngf6 6. Nd6# 1-0");
+    let mut processor = PgnProcessor::new();
+    let moves = processor.process_game(&games[0]);
+    assert_eq!(
+        detect(processor.positions(), &moves),
+        vec![Motif::SmotheredMate]
+    );
+
+    assert_eq!(
+        replay("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &["a1a8"]),
+        vec![Motif::BackRankMate]
+    );
+    assert_eq!(
+        replay(
+            "6k1/1p3pR1/5B2/8/8/8/8/6K1 b - - 0 1",
+            &["g8h8", "g7f7", "h8g8", "f7g7", "g8h8", "g7b7"]
+        ),
+        vec![Motif::Windmill]
+    );
+    assert_eq!(
+        replay("8/1P4k1/8/8/8/8/6p1/K7 w - - 0 1", &["b7b8", "g2g1"]),
+        vec![Motif::PromotionRace]
+    );
+}
+
+#[test]
+fn test_move_tags() {
+    use crate::patterns::{move_tags, MoveTag};
+    use crate::position::Position;
+    use chess::utils::string_to_square;
+
+    let tags = |fen: &str, mv: &str| -> Vec<MoveTag> {
+        let (from, to) = (string_to_square(&mv[0..2]), string_to_square(&mv[2..4]));
+        move_tags(
+            &Position::from_fen(fen).unwrap().play(from, to, None),
+            from,
+            to,
+        )
+    };
+
+    assert_eq!(tags(&Position::initial().to_fen(), "e2e4"), vec![]);
+    assert_eq!(
+        tags("r3k3/8/8/3N4/8/8/8/4K3 w - - 0 1", "d5c7"),
+        vec![MoveTag::Check, MoveTag::Fork]
+    );
+    // A knight attacking a knight and a bishop is no fork
+    assert_eq!(tags("4k3/8/8/1n1b4/8/8/8/N3K3 w - - 0 1", "a1c3"), vec![]);
+    assert_eq!(
+        tags("4k3/8/8/8/4B3/8/4R3/4K3 w - - 0 1", "e4b7"),
+        vec![MoveTag::Check, MoveTag::DiscoveredCheck]
+    );
+    assert_eq!(
+        tags("4k3/8/8/8/4B3/8/4R3/4K3 w - - 0 1", "e4b5"),
+        vec![
+            MoveTag::Check,
+            MoveTag::DoubleCheck,
+            MoveTag::DiscoveredCheck
+        ]
+    );
+    // The castled rook checks by itself
+    assert_eq!(
+        tags("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"),
+        vec![MoveTag::Check]
+    );
+}
+
+#[test]
+fn test_sacrifices() {
+    use crate::position::{Piece, Position};
+    use crate::sacrifices::{sacrifices, SacrificeFilter};
+
+    let replay = |fen: &str, moves: &[&str]| {
+        let (positions, moves) = replay_positions(fen, moves);
+        sacrifices(&positions, &moves)
+    };
+
+    assert!(replay(&Position::initial().to_fen(), &["e2e4", "e7e5"]).is_empty());
+
+    // The greek gift gives a bishop for a pawn
+    let greek_gift = replay(
+        "r1bq1rk1/pppn1ppp/4p3/3pP3/1b1P4/2NB1N2/PPP2PPP/R2QK2R w KQ - 0 8",
+        &["d3h7", "g8h7"],
+    );
+    assert_eq!(greek_gift.len(), 1);
+    assert_eq!(greek_gift[0].ply, 1);
+    assert_eq!(greek_gift[0].piece, Piece::Bishop);
+    assert_eq!(greek_gift[0].loss, 2);
+    assert_eq!(greek_gift[0].label(), "8. bishop");
+
+    // A queen taking a pawn the rook behind it guards only once is lost for a rook
+    let queen = replay("3r2k1/8/8/3p4/8/8/3Q4/3R2K1 w - - 0 20", &["d2d5"]);
+    assert_eq!(queen.len(), 1);
+    assert_eq!(queen[0].piece, Piece::Queen);
+    assert_eq!(queen[0].loss, 3);
+
+    // Mates are no sacrifices, the piece cannot be taken
+    assert!(replay(
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        &["h5f7"]
+    )
+    .is_empty());
+
+    assert_eq!(
+        SacrificeFilter::parse("queen:25"),
+        Ok(SacrificeFilter {
+            piece: Some(Piece::Queen),
+            before_move: Some(25)
+        })
+    );
+    assert!(SacrificeFilter::parse("wizard").is_err());
+    assert!(SacrificeFilter::parse("bishop:10")
+        .unwrap()
+        .matches(&greek_gift));
+    assert!(!SacrificeFilter::parse("bishop:8")
+        .unwrap()
+        .matches(&greek_gift));
+    assert!(!SacrificeFilter::parse("queen")
+        .unwrap()
+        .matches(&greek_gift));
+    assert!(SacrificeFilter::parse("any").unwrap().matches(&queen));
+}
+
+#[test]
+fn test_static_exchange() {
+    use crate::position::{Piece, Position};
+    use crate::see::{exchange_gain, see};
+    use chess::legal_moves::misc::Color;
+    use chess::utils::string_to_square;
+
+    let see_move = |fen: &str, mv: &str, promotion: Option<Piece>| {
+        let position = Position::from_fen(fen).unwrap();
+        see(
+            &position,
+            string_to_square(&mv[0..2]),
+            string_to_square(&mv[2..4]),
+            promotion,
+        )
+    };
+
+    // An undefended pawn is won outright, a defended one traded off
+    assert_eq!(
+        see_move("4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1", "d1d5", None),
+        1
+    );
+    assert_eq!(
+        see_move("4k3/8/4p3/3p4/8/8/8/3RK3 w - - 0 1", "d1d5", None),
+        -4
+    );
+    // The rook behind the queen joins in once the queen has taken
+    assert_eq!(
+        see_move("3r2k1/3r4/8/3p4/8/8/3Q4/3R2K1 w - - 0 1", "d2d5", None),
+        -8
+    );
+    // A quiet move to a guarded square loses the piece
+    assert_eq!(
+        see_move("4k3/8/4p3/8/8/2N5/8/4K3 w - - 0 1", "c3d5", None),
+        -3
+    );
+    // En passant and promotions
+    assert_eq!(
+        see_move("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", None),
+        1
+    );
+    assert_eq!(
+        see_move(
+            "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1",
+            "a7b8",
+            Some(Piece::Queen)
+        ),
+        13
+    );
+    assert_eq!(
+        see_move(
+            "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1",
+            "a7a8",
+            Some(Piece::Queen)
+        ),
+        -1
+    );
+    // The king does not take a defended piece
+    let position = Position::from_fen("4k3/4N3/8/8/8/8/4R3/4K3 b - - 0 1").unwrap();
+    assert_eq!(
+        exchange_gain(&position, string_to_square("e7"), Color::Black),
+        0
+    );
+}
+
+#[test]
+fn test_position_features() {
+    use crate::features::PositionFeatures;
+    use crate::position::Position;
+
+    let start = PositionFeatures::of(&Position::initial());
+    assert_eq!(start.material, [39, 39]);
+    assert_eq!(start.mobility, [20, 20]);
+    assert_eq!(start.king_zone_attacks, [0, 0]);
+    assert_eq!(start.king_shelter, [3, 3]);
+
+    // A queen and three pawns against two bishops and a bare king
+    let position = Position::from_fen("4k3/8/8/7Q/8/8/5PPP/2b1b1K1 w - - 0 1").unwrap();
+    let features = PositionFeatures::of(&position);
+    assert_eq!(features.material_balance(), 6);
+    assert_eq!(features.bishop_pair, [false, true]);
+    assert_eq!(features.king_shelter, [3, 0]);
+    assert!(features.king_zone_attacks[1] > 0);
+}
+
+#[test]
+fn test_static_evaluation() {
+    use crate::eval::{checking_moves, evaluate, hanging_pieces, Quiescence};
+    use crate::position::Position;
+    use chess::legal_moves::misc::Color;
+
+    let play = |moves: &[&str]| {
+        moves.iter().fold(Position::initial(), |position, san| {
+            let (from, to, promotion) = position.parse_san(san).unwrap();
+            position.play(from, to, promotion)
+        })
+    };
+
+    let start = Position::initial();
+    assert_eq!(evaluate(&start), 0);
+    assert_eq!(Quiescence::of(&start), Quiescence::Quiet);
+    assert!(evaluate(&play(&["e4"])) > 0);
+    assert_eq!(evaluate(&play(&["e4", "e5"])), 0);
+
+    // Both centre pawns are defended by their queens
+    assert_eq!(Quiescence::of(&play(&["d4", "d5"])), Quiescence::Quiet);
+    // The e4 pawn hangs to the d5 pawn
+    let scandinavian = play(&["e4", "d5"]);
+    assert_eq!(hanging_pieces(&scandinavian, Color::White), vec![28]);
+    assert_eq!(Quiescence::of(&scandinavian), Quiescence::Tactical);
+    // Bxf7+ is available
+    let italian = play(&["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "d3", "d6"]);
+    assert_eq!(checking_moves(&italian), 1);
+    assert_eq!(Quiescence::of(&italian).name(), "tactical");
+
+    // A queen up
+    let position = Position::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+    assert!(evaluate(&position) > 800);
+}
+
+#[test]
+fn test_accuracy() {
+    use crate::accuracy::{
+        eval_annotation, mainline_evals, move_accuracy, win_percent, GameAccuracy,
+    };
+    use crate::pgn_ast::parse_movetext;
+    use crate::uci::Score;
+
+    assert_eq!(
+        eval_annotation("[%eval 0.31] [%clk 0:03:00]"),
+        Some(Score::Centipawns(31))
+    );
+    assert_eq!(eval_annotation("[%eval #-3]"), Some(Score::Mate(-3)));
+    assert_eq!(win_percent(Score::Centipawns(0)), 50.0);
+    assert!(win_percent(Score::Mate(2)) > 97.0);
+    assert_eq!(move_accuracy(60.0, 65.0), 100.0);
+    assert!(move_accuracy(60.0, 10.0) < 15.0);
+
+    let (line, _) = parse_movetext(
+        "1. e4 {[%eval 0.3]} e5 {[%eval 0.3]} 2. Qh5 {[%eval 0.1]} Nc6 {[%eval 0.1]} 3. Bc4 {[%eval 0.1]} Nf6 {[%eval #1]} 4. Qxf7# 1-0",
+    );
+    let evals = mainline_evals(&line);
+    assert_eq!(evals[6], Some(Score::Centipawns(1000)));
+    let accuracy = GameAccuracy::of(&evals);
+    assert!(accuracy.white.unwrap() > 90.0);
+    assert!(accuracy.black.unwrap() < 50.0);
+}
+
+#[test]
+fn test_cheat_screen() {
+    use crate::cheat_screen::{clock_annotation, move_times, CheatScreen, Measure};
+
+    assert_eq!(
+        clock_annotation("[%eval 0.2] [%clk 0:03:05.5]"),
+        Some(185.5)
+    );
+    let clocks = [Some(178.0), Some(175.0), None, Some(170.0), Some(170.0)];
+    assert_eq!(move_times(&clocks, 180.0, 2.0), [vec![4.0], vec![7.0, 7.0]]);
+
+    let mut screen = CheatScreen::new();
+    for (name, loss) in [
+        ("a", 40),
+        ("b", 45),
+        ("c", 35),
+        ("d", 50),
+        ("e", 42),
+        ("f", 38),
+        ("bot", 2),
+    ] {
+        screen.player(name).losses = vec![loss; 30];
+    }
+    let flagged: Vec<(&str, Vec<Measure>)> = screen
+        .results(20)
+        .into_iter()
+        .filter(|result| !result.flags.is_empty())
+        .map(|result| (result.name, result.flags))
+        .collect();
+    assert_eq!(flagged, vec![("bot", vec![Measure::EvalLoss])]);
+}
+
+#[test]
+fn test_result_inference() {
+    use crate::pgn_ast::GameResult;
+    use crate::position::Position;
+    use crate::result_inference::{infer_result, is_missing, Inference};
+
+    assert!(is_missing(None));
+    assert!(is_missing(Some("*")));
+    assert!(is_missing(Some("?")));
+    assert!(!is_missing(Some("1/2-1/2")));
+
+    let fools_mate =
+        Position::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+            .unwrap();
+    assert_eq!(
+        infer_result(&fools_mate, Some("Qh4")),
+        Some((GameResult::BlackWins, Inference::Checkmate))
+    );
+
+    let stalemate = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    assert_eq!(
+        infer_result(&stalemate, Some("Qf7")),
+        Some((GameResult::Draw, Inference::Stalemate))
+    );
+
+    // A mate claimed by the annotation alone
+    let quiet = Position::from_fen("7k/8/6K1/8/8/8/8/R7 b - - 0 1").unwrap();
+    assert_eq!(infer_result(&quiet, Some("Ra1")), None);
+    assert_eq!(
+        infer_result(&quiet, Some("Ra1#")),
+        Some((GameResult::WhiteWins, Inference::MateAnnotation))
+    );
+}
+
+#[test]
+fn test_fen_analysis() {
+    use crate::eval::Quiescence;
+    use crate::fen_analysis::{fen_lines, white_centipawns, PositionClass, Wdl};
+    use crate::phases::Phase;
+    use crate::position::Position;
+    use crate::uci::{Score, Search};
+
+    let input = "# KRvK\n8/8/8/4k3/8/8/8/R3K3 b Q -\n\nnot a fen\n";
+    let lines: Vec<_> = fen_lines(input).collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].0, 2);
+    assert!(lines[1].1.is_err());
+
+    let position = lines[0].1.clone().unwrap();
+    let class = PositionClass::of(&position);
+    assert_eq!(class.phase, Phase::Endgame);
+    assert_eq!(class.endgame.as_deref(), Some("R vs K"));
+    assert!(class.static_eval > 400);
+    assert_eq!(class.quiescence, Quiescence::Quiet);
+
+    // Black to move: engine scores are turned to white's point of view
+    assert_eq!(white_centipawns(&position, Score::Centipawns(-30)), 30);
+
+    let search = |score, tbhits| Search {
+        best_move: None,
+        score: Some(score),
+        tbhits,
+    };
+    assert_eq!(
+        Wdl::of(&position, &search(Score::Mate(-12), 5)),
+        Some(Wdl::Loss)
+    );
+    assert_eq!(
+        Wdl::of(&position, &search(Score::Centipawns(-19_980), 5)),
+        Some(Wdl::Loss)
+    );
+    assert_eq!(
+        Wdl::of(&position, &search(Score::Centipawns(-2), 5)),
+        Some(Wdl::Draw)
+    );
+    assert_eq!(Wdl::of(&position, &search(Score::Mate(-12), 0)), None);
+    assert_eq!(
+        Wdl::of(&Position::initial(), &search(Score::Mate(3), 5)),
+        None
+    );
+}
+
+#[test]
+fn test_material_trajectory() {
+    use crate::material::MaterialTrajectory;
+    use crate::PgnProcessor;
+
+    let mut processor = PgnProcessor::new();
+    processor.process_line(&["e4", "d5", "exd5", "Qxd5", "Nc3", "Qxg2"]);
+    let trajectory = MaterialTrajectory::of(processor.positions());
+
+    // One entry for the start and one after each ply
+    assert_eq!(trajectory.white.counts[0], [8, 8, 8, 8, 7, 7, 6]);
+    assert_eq!(trajectory.black.counts[0], [8, 8, 8, 7, 7, 7, 7]);
+    assert_eq!(trajectory.white.points, [39, 39, 39, 39, 38, 38, 37]);
+    assert_eq!(trajectory.black.counts[4], [1; 7]);
+
+    let json = trajectory.to_json(3);
+    assert!(json.starts_with("{\"game\":3,\"white\":{\"P\":[8,8,8,8,7,7,6],\"N\":[2,"));
+    assert!(json.ends_with("\"points\":[39,39,39,38,38,38,38]}}"));
+}
+
+#[test]
+fn test_piece_lifetimes() {
+    use crate::piece_lifetimes::{piece_lives, LifetimeStats};
+
+    let replay = |fen: &str, moves: &[&str]| {
+        let (positions, moves) = replay_positions(fen, moves);
+        piece_lives(&positions, &moves)
+    };
+
+    // En passant, castling moving the rook, and a capture by a promoting pawn
+    let lives = replay(
+        "r3k3/1P6/8/8/3p4/8/4P3/4K2R w Kk - 0 1",
+        &["e2e4", "d4e3", "e1g1", "e3e2", "b7a8q"],
+    );
+    let life = |label: &str| lives.iter().find(|life| life.label() == label).unwrap();
+    assert_eq!(life("Pe2").captured, Some(2));
+    assert_eq!(life("Pd4").path.len(), 2);
+    assert_eq!(life("Rh1").path, [life("Ke1").path[0] - 1]);
+    assert_eq!(life("Ra8").captured, Some(5));
+    assert_eq!(
+        life("Pb7")
+            .promoted
+            .map(|(piece, ply)| (piece.letter(), ply)),
+        Some(('Q', 5))
+    );
+    assert!(life("Pb7")
+        .to_json()
+        .contains("\"promoted\":{\"piece\":\"Q\",\"ply\":5}"));
+
+    let mut stats = LifetimeStats::new();
+    stats.add(&lives);
+    stats.add(&replay("r3k3/1P6/8/8/3p4/8/4P3/4K2R w Kk - 0 1", &[]));
+    let tsv = stats.to_tsv();
+    assert!(tsv.contains("Pe2\twhite\t2\t0.500\t2.0\t0.5\t0\n"));
+    assert!(tsv.contains("Pb7\twhite\t2\t0.000\t\t0.5\t1\n"));
+}
+
+#[test]
+fn test_rating_estimate() {
+    use crate::accuracy::mainline_evals;
+    use crate::pgn_ast::parse_movetext;
+    use crate::rating_estimate::{centipawn_losses, Estimate, RatingEstimates};
+
+    let (line, _) = parse_movetext(
+        "1. e4 {[%eval 0.3]} e5 {[%eval 0.3]} 2. Qh5 {[%eval 0.1]} Nc6 {[%eval 0.1]} 3. Bc4 {[%eval 0.1]} Nf6 {[%eval #1]} 4. Qxf7# 1-0",
+    );
+    let evals = mainline_evals(&line);
+    // The mate is capped at ten pawns, so Nf6 loses 990 rather than a mate score
+    assert_eq!(
+        centipawn_losses(&evals),
+        [vec![0, 20, 0, 0], vec![0, 0, 990]]
+    );
+    assert_eq!(
+        centipawn_losses(&[None, Some(crate::uci::Score::Centipawns(-50))]),
+        [vec![], vec![]]
+    );
+
+    // Too few moves for an Elo
+    let black = Estimate::of(&[0, 0, 990]).unwrap();
+    assert_eq!(
+        (black.moves, black.average_loss, black.elo),
+        (3, 330.0, None)
+    );
+    assert!((black.mistake_rate - 100.0 / 3.0).abs() < 1e-9);
+    assert_eq!(Estimate::of(&[]), None);
+
+    // 11 centipawns on average reads 2620 and 10% mistakes 1800
+    let mut losses = vec![0; 18];
+    losses.extend([110, 110]);
+    assert_eq!(Estimate::of(&losses).unwrap().elo, Some(2210));
+
+    let mut estimates = RatingEstimates::new();
+    estimates.add_game(1, ["A", "B"], [Some(1500), None], &evals);
+    assert_eq!(
+        estimates.to_json(),
+        concat!(
+            r#"{"games":[{"game":1,"#,
+            r#""white":{"name":"A","elo":1500,"estimate":{"moves":4,"average_loss":5.0,"mistake_rate":0.0,"estimated_elo":null}},"#,
+            r#""black":{"name":"B","elo":null,"estimate":{"moves":3,"average_loss":330.0,"mistake_rate":33.3,"estimated_elo":null}}}],"#,
+            r#""players":[{"name":"A","games":1,"average_elo":1500,"estimate":{"moves":4,"average_loss":5.0,"mistake_rate":0.0,"estimated_elo":null}},"#,
+            r#"{"name":"B","games":1,"average_elo":null,"estimate":{"moves":3,"average_loss":330.0,"mistake_rate":33.3,"estimated_elo":null}}]}"#,
+        )
+    );
+}
+
+#[test]
+fn test_heatmaps() {
+    use crate::heatmaps::Heatmaps;
+    use crate::json::{parse, Value};
+    use crate::PgnProcessor;
+
+    let mut processor = PgnProcessor::new();
+    let moves = processor.process_line(&["e4", "e5", "Nf3"]);
+    let mut heatmaps = Heatmaps::new();
+    heatmaps.add_game(processor.positions(), &moves);
+    heatmaps.add_game(processor.positions(), &moves);
+
+    let maps = parse(&heatmaps.to_json()).unwrap();
+    // Boards run from rank 8 down, so a square's row is 8 minus its rank
+    let count = |color: &str, piece: &str, kind: &str, square: &str| {
+        let board = maps.get(color).unwrap().get(piece).unwrap().get(kind);
+        let Some(Value::Array(rows)) = board else {
+            panic!("no {kind} board for {color} {piece}");
+        };
+        let (file, rank) = (square.as_bytes()[0] - b'a', square.as_bytes()[1] - b'1');
+        let Value::Array(row) = &rows[7 - rank as usize] else {
+            panic!("malformed row");
+        };
+        row[file as usize].clone()
+    };
+
+    // Four positions per game: before each of the three moves and the final one
+    for (color, piece, square, times) in [
+        ("white", "pawn", "e2", 1),
+        ("white", "pawn", "e4", 3),
+        ("white", "pawn", "d2", 4),
+        ("white", "knight", "g1", 3),
+        ("white", "knight", "f3", 1),
+        ("black", "pawn", "e7", 2),
+        ("black", "pawn", "e5", 2),
+        ("black", "king", "e8", 4),
+        ("black", "pawn", "e4", 0),
+    ] {
+        let expected = Value::Number(2.0 * times as f64);
+        let counted = count(color, piece, "occupation", square);
+        assert_eq!(counted, expected, "{color} {piece} on {square}");
+    }
+    for (color, piece, square, times) in [
+        ("white", "pawn", "e4", 1),
+        ("white", "knight", "f3", 1),
+        ("black", "pawn", "e5", 1),
+        ("white", "pawn", "e2", 0),
+        ("black", "knight", "f6", 0),
+    ] {
+        let expected = Value::Number(2.0 * times as f64);
+        let counted = count(color, piece, "destinations", square);
+        assert_eq!(counted, expected, "{color} {piece} to {square}");
+    }
+}
+
+#[test]
+fn test_castling_stats() {
+    use crate::castling_stats::{CastlingSide, CastlingStats, GameCastling};
+    use crate::PgnProcessor;
+
+    let castling = |line: &[&str]| {
+        let mut processor = PgnProcessor::new();
+        let moves = processor.process_line(line);
+        assert_eq!(moves.len(), line.len());
+        GameCastling::of(processor.positions(), &moves)
+    };
+
+    let king_side = castling(&["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "O-O"]);
+    assert_eq!(king_side.castled, [Some((CastlingSide::King, 7)), None]);
+    assert!(!king_side.opposite());
+
+    let opposite = castling(&[
+        "d4", "d5", "Nc3", "Nf6", "Bf4", "e6", "Qd2", "Be7", "O-O-O", "O-O",
+    ]);
+    assert_eq!(
+        opposite.castled,
+        [
+            Some((CastlingSide::Queen, 9)),
+            Some((CastlingSide::King, 10))
+        ]
+    );
+    assert!(opposite.opposite());
+
+    // A king walking one square is not castling
+    let none = castling(&["e4", "e5", "Ke2", "Ke7"]);
+    assert_eq!(none.castled, [None, None]);
+
+    let mut stats = CastlingStats::new();
+    for game in [king_side, opposite, none] {
+        stats.add_game(&game);
+    }
+    assert_eq!(
+        stats.to_json(),
+        concat!(
+            r#"{"games":3,"opposite_castling":1,"#,
+            r#""white":{"king_side":1,"queen_side":1,"never":1,"average_ply":8.0},"#,
+            r#""black":{"king_side":1,"queen_side":0,"never":2,"average_ply":10.0}}"#,
+        )
+    );
+    assert!(CastlingStats::new()
+        .to_json()
+        .contains(r#""never":0,"average_ply":null"#));
+}
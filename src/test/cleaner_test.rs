@@ -0,0 +1,716 @@
+#[test]
+fn test_degenerate_input() {
+    use crate::pgn_cleaner::{mainline_moves, split_games};
+    use crate::PgnProcessor;
+
+    assert!(split_games("").is_empty());
+    assert!(split_games("\n  \n").is_empty());
+
+    let header_only = split_games("[Event \"Abandoned\"]\n[Result \"*\"]\n");
+    assert_eq!(header_only.len(), 1);
+    assert_eq!(header_only[0].tag("Event"), Some("Abandoned"));
+    assert!(mainline_moves(&header_only[0].movetext).is_empty());
+
+    let mut processor = PgnProcessor::new();
+    assert!(processor.process_game(&header_only[0]).is_empty());
+    assert!(processor.take_warnings().is_empty());
+
+    // Movetext that does not start at move 1, glued move numbers and black move numbers
+    let moves = mainline_moves("12... e5 13.Nf3!? Nc6 {fine} 14. Bb5+ $1 (14. Bc4) a6 *");
+    assert_eq!(moves, vec!["e5", "Nf3", "Nc6", "Bb5", "a6"]);
+}
+
+#[test]
+fn test_game_reader() {
+    use std::io::BufReader;
+
+    use crate::pgn_cleaner::{split_games, GameReader};
+
+    let input = "[Event \"A\"]\n\n1. e4 {a comment\nover two lines} e5 1-0\n\n\
+                 1. d4 d5 *\n[Event \"C\"]\n\n1. c4 ";
+    let expected = split_games(input);
+    // A small buffer splits lines across reads
+    let mut reader = GameReader::new(BufReader::with_capacity(4, input.as_bytes()), false);
+    let mut games = Vec::new();
+    while let Some(game) = reader.next() {
+        games.push(game.unwrap());
+        assert_eq!(reader.games(), games.len());
+    }
+
+    assert_eq!(games.len(), 3);
+    for (game, expected) in games.iter().zip(&expected) {
+        assert_eq!(game.tags, expected.tags);
+        assert_eq!(game.movetext, expected.movetext);
+        assert_eq!(game.movetext_lines, expected.movetext_lines);
+        assert_eq!(
+            (game.offset, game.end, game.line),
+            (expected.offset, expected.end, expected.line)
+        );
+    }
+    assert_eq!(reader.consumed(), input.len());
+    assert!(reader.next().is_none());
+
+    // Input that is not UTF-8 fails once, then ends the games
+    let mut reader = GameReader::new(&b"1. e4 *\n1. d4 \xff\n"[..], false);
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_err());
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn test_comments_are_opaque() {
+    use crate::pgn_cleaner::{mainline_moves, split_games};
+
+    let pgn = "[Event \"Annotated\"]
+
+1. e4 e5 {better was 1... c5; see 2. Nf3 d6} 2. Nf3 { a comment
+[spanning] lines, ending with
+1-0
+} Nc6 ; rest-of-line comment with { brace and 3. Bb5
+3. Bb5 a6 1-0
+
+[Event \"Next\"]
+
+1. d4 *";
+    let games = split_games(pgn);
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].tags.len(), 1);
+    assert_eq!(games[0].termination(), Some("1-0"));
+    assert_eq!(
+        mainline_moves(&games[0].movetext),
+        vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]
+    );
+    assert_eq!(mainline_moves(&games[1].movetext), vec!["d4"]);
+}
+
+#[test]
+fn test_variations() {
+    use crate::pgn_cleaner::variations;
+
+    let lines = variations("1. e4 e5 (1... c5 2. Nf3 (2. Nc3) d6) 2. Nf3 Nc6 (2... d6) *");
+    let moves: Vec<(Vec<&str>, usize)> = lines
+        .into_iter()
+        .map(|variation| (variation.moves, variation.branch_ply))
+        .collect();
+    assert_eq!(
+        moves,
+        vec![
+            (vec!["e4", "c5", "Nf3", "d6"], 1),
+            (vec!["e4", "c5", "Nc3"], 2),
+            (vec!["e4", "e5", "Nf3", "d6"], 3),
+        ]
+    );
+}
+
+#[test]
+fn test_move_tree() {
+    use crate::pgn_ast::parse_movetext;
+    use crate::tree::longest_line;
+
+    let movetext = "1. e4 e5 (1... c5 2. Nf3 (2. Nc3) d6 3. d4) 2. Nf3 *";
+    let (tree, _) = parse_movetext(movetext);
+    assert_eq!(tree.sans(), vec!["e4", "e5", "Nf3"]);
+
+    let sicilian = &tree.moves[1].variations[0];
+    assert_eq!(sicilian.moves.len(), 4);
+    assert_eq!(sicilian.moves[1].variations[0].moves[0].san, "Nc3");
+
+    assert_eq!(longest_line(movetext), vec!["e4", "c5", "Nf3", "d6", "d4"]);
+    assert_eq!(longest_line("1. e4 e5 (1... c5) *"), vec!["e4", "e5"]);
+}
+
+#[test]
+fn test_recovery() {
+    use crate::legality::{find_recovery, Recovery};
+    use crate::pgn_cleaner::split_games;
+    use crate::position::Position;
+    use crate::PgnProcessor;
+
+    let start = Position::initial();
+    assert_eq!(
+        find_recovery(&start, &["e9", "e4", "e5", "Nf3"]),
+        Some(Recovery::Skip(1))
+    );
+    assert_eq!(
+        find_recovery(&start, &["Nf4", "Nc6", "e4"]),
+        Some(Recovery::Replace("Nf3".to_string()))
+    );
+    assert_eq!(find_recovery(&start, &["Qh5", "Qh4", "Qxf7"]), None);
+
+    let games = split_games("1. e4 e5 2. Nf3 Qxx Nc6 3. Bb5 *");
+    let mut processor = PgnProcessor::new();
+    processor.set_recover(true);
+    assert_eq!(processor.process_game(&games[0]).len(), 5);
+
+    // A bare piece letter is a bad token, not the end of the run
+    let games = split_games("1. e4 e5 2. N Nf3 Nc6 *");
+    assert_eq!(processor.process_game(&games[0]).len(), 4);
+    processor.set_recover(false);
+    let games = split_games("1. e4 N *");
+    assert_eq!(processor.process_game(&games[0]), vec!["e2e4"]);
+}
+
+#[test]
+fn test_malformed_targets() {
+    use crate::backend::BackendKind;
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    // Squares off the board are warnings on either backend, with or without --recover
+    for backend in [BackendKind::Chess, BackendKind::Mailbox] {
+        for token in ["e9", "Ke0", "Nf3-", "Rh1xh9", "Nz3", "ixd5"] {
+            let games = split_games(&format!("1. e4 e5 2. {token} *"));
+            let mut processor = PgnProcessor::new();
+            processor.set_backend(backend);
+            assert_eq!(processor.process_game(&games[0]), vec!["e2e4", "e7e5"]);
+            let warnings = processor.take_warnings();
+            assert_eq!(warnings.len(), 1, "{token}");
+            assert_eq!((warnings[0].ply, warnings[0].token.as_str()), (3, token));
+            assert!(
+                warnings[0].message.contains(token),
+                "{}",
+                warnings[0].message
+            );
+
+            let games = split_games(&format!("1. e4 e5 2. {token} Nf3 Nc6 *"));
+            processor.set_recover(true);
+            assert_eq!(processor.process_game(&games[0]).len(), 4, "{token}");
+        }
+    }
+}
+
+#[test]
+fn test_trace_hook() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    let games = split_games("1. Nf3 Nc6 2. Rg1 *");
+    let mut processor = PgnProcessor::new();
+    processor.process_game(&games[0]);
+
+    // Traces go to the hook alone, line by line
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&lines);
+    processor.set_trace_hook(move |line| sink.borrow_mut().push(line.to_string()));
+    processor.process_game(&games[0]);
+    assert_eq!(
+        lines.borrow()[..4],
+        [
+            "Nf3:",
+            "  b1 rejected: cannot reach target",
+            "  g1 possible",
+            "  -> g1f3"
+        ]
+    );
+    assert_eq!(lines.borrow().last().unwrap(), "  -> h1g1");
+    assert_eq!(lines.borrow().len(), 12);
+}
+
+#[test]
+fn test_long_algebraic_input() {
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    let games = split_games("1. e2-e4 d7-d5 2. e4xd5 Qd8xd5 3. Nb1-c3 Qd5-a5 *");
+    let mut processor = PgnProcessor::new();
+    assert_eq!(
+        processor.process_game(&games[0]),
+        vec!["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a5"]
+    );
+
+    // The origin square must hold the named piece of the side to move
+    let games = split_games("1. e2-e4 e2-e4 *");
+    assert_eq!(processor.process_game(&games[0]), vec!["e2e4"]);
+}
+
+#[test]
+fn test_token_offsets() {
+    use crate::pgn_cleaner::{Token, Tokens};
+
+    let movetext = "1.e4 {best by test} e5 (1... c5) 2. Nf3 $1 *";
+    let tokens: Vec<_> = Tokens::new(movetext).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (0, Token::MoveNumber("1.")),
+            (2, Token::Move("e4")),
+            (5, Token::Comment("best by test")),
+            (20, Token::Move("e5")),
+            (23, Token::VariationStart),
+            (24, Token::MoveNumber("1...")),
+            (29, Token::Move("c5")),
+            (31, Token::VariationEnd),
+            (33, Token::MoveNumber("2.")),
+            (36, Token::Move("Nf3")),
+            (40, Token::Nag("$1")),
+            (43, Token::Termination("*")),
+        ]
+    );
+    for (offset, token) in tokens {
+        if let Token::Move(san) = token {
+            assert!(movetext[offset..].starts_with(san));
+        }
+    }
+}
+
+#[test]
+fn test_token_locations() {
+    use crate::pgn_cleaner::{mainline_spans, split_games};
+    use crate::PgnProcessor;
+
+    let input = "[Event \"A\"]\n\n1. e4 e5 *\n\n[Event \"B\"]\n\n1. d4 d5\n   2. c4 {Queen's\ngambit} e6 3. Nc6 *\n";
+    let games = split_games(input);
+    let spans = mainline_spans(&games[1].movetext);
+    assert_eq!(games[1].location(spans[0].0), Some((7, 4)));
+    assert_eq!(games[1].location(spans[2].0), Some((8, 7)));
+    assert_eq!(games[1].location(spans[4].0), Some((9, 15)));
+
+    let mut processor = PgnProcessor::new();
+    processor.process_game(&games[1]);
+    let warnings = processor.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].token_index, 4);
+}
+
+#[test]
+fn test_pgn_ast() {
+    use crate::pgn_ast::{Game, GameResult};
+    use crate::pgn_cleaner::split_games;
+    use crate::writer::write_parsed_game;
+
+    let input = "[Event \"A\"]\n\n{Start} 1. e4+! $1 {Best} (1. d4 d5) 1... e5 (1... c5 {Sicilian}) 2. Nf3 0-1\n";
+    let games = split_games(input);
+    let game = Game::parse(&games[0]);
+    assert_eq!(game.tag("Event"), Some("A"));
+    assert_eq!(game.result, Some(GameResult::BlackWins));
+    assert_eq!(game.moves.comments, vec!["Start"]);
+
+    let first = &game.moves.moves[0];
+    assert_eq!((first.san, first.suffix), ("e4", "+!"));
+    assert_eq!(first.nags, vec!["$1"]);
+    assert_eq!(first.comments, vec!["Best"]);
+    assert_eq!(first.variations[0].sans(), vec!["d4", "d5"]);
+
+    let movetext = "{Start} 1. e4+! $1 {Best} (1. d4 d5) 1... e5 (1... c5 {Sicilian}) 2. Nf3 0-1";
+    assert_eq!(game.movetext(), movetext);
+
+    let mut output = String::new();
+    write_parsed_game(&game, &mut output);
+    let reparsed = split_games(&output);
+    assert_eq!(Game::parse(&reparsed[0]), game);
+}
+
+#[test]
+fn test_engine_comments() {
+    use crate::comments::{is_engine_comment, strip_engine_comments};
+    use crate::pgn_ast::parse_movetext;
+
+    assert!(is_engine_comment("+0.45/18 1.2s 19. Nf5 Qd7 20.Qg4"));
+    assert!(is_engine_comment("(0.23 → 1.05) Mistake. Nc6 was best."));
+    assert!(!is_engine_comment("Nf5 was the best try"));
+    assert!(!is_engine_comment("A sharp line."));
+
+    let movetext = "1. e4 {[%eval 0.31] [%clk 0:03:00]} e5 {[%eval 0.25]} 2. Nf3 {Good} f6 {Blunder. Nc6 was best.} (2... Nc6 {+0.3/20}) *";
+    let (mut line, _) = parse_movetext(movetext);
+    assert_eq!(strip_engine_comments(&mut line), 3);
+    assert_eq!(line.moves[0].comments, vec!["[%clk 0:03:00]"]);
+    assert!(line.moves[1].comments.is_empty());
+    assert_eq!(line.moves[2].comments, vec!["Good"]);
+    assert!(line.moves[3].variations.is_empty());
+}
+
+#[test]
+fn test_comment_language() {
+    use crate::language::{detect_language, transliterate};
+
+    assert_eq!(
+        detect_language("White is better after this move"),
+        Some("en")
+    );
+    assert_eq!(detect_language("Der beste Zug ist das"), Some("de"));
+    assert_eq!(detect_language("Лучший ход"), Some("ru"));
+    assert_eq!(detect_language("[%clk 0:01:00] +0.3"), None);
+
+    assert_eq!(transliterate("Карпов, Анатолий"), "Karpov, Anatoly");
+    assert_eq!(transliterate("Жуков"), "Zhukov");
+    assert_eq!(transliterate("Carlsen"), "Carlsen");
+}
+
+#[test]
+fn test_disambiguation_lint() {
+    use crate::disambiguation::{lint_game, normalize, Disambiguation};
+    use crate::pgn_cleaner::split_games;
+
+    let mut games = split_games("1. Ng1f3 d5 2. e2-e4+! dxe4 3. d4 Nbd7 4. Nd2 *");
+    let issues = lint_game(&games[0]);
+    let found: Vec<(&str, Disambiguation, Option<&str>)> = issues
+        .iter()
+        .map(|issue| {
+            (
+                issue.written.as_str(),
+                issue.kind,
+                issue.canonical.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("Ng1f3", Disambiguation::Over, Some("Nf3")),
+            ("e2-e4", Disambiguation::Over, Some("e4")),
+            ("Nbd7", Disambiguation::Over, Some("Nd7")),
+            ("Nd2", Disambiguation::Under, None),
+        ]
+    );
+    assert_eq!(
+        issues[3].describe(),
+        "Nd2 is under-disambiguated, several pieces can play it"
+    );
+
+    assert_eq!(normalize(&mut games[0], &issues), 3);
+    assert!(games[0]
+        .movetext
+        .starts_with("1. Nf3 d5 2. e4+! dxe4 3. d4 Nd7 4. Nd2 *"));
+}
+
+#[test]
+fn test_tolerant_results() {
+    use crate::pgn_ast::{Game, GameResult};
+    use crate::pgn_cleaner::{normalize_result, split_games};
+
+    for (token, result) in [
+        ("1-0", Some("1-0")),
+        ("1\u{2013}0", Some("1-0")),
+        ("1-O", Some("1-0")),
+        ("0-1 ", Some("0-1")),
+        ("0-1.", Some("0-1")),
+        ("O-1", Some("0-1")),
+        ("1:0", Some("1-0")),
+        ("\u{bd}-\u{bd}", Some("1/2-1/2")),
+        ("0.5-0.5", Some("1/2-1/2")),
+        ("*", Some("*")),
+        ("0-0", None),
+        ("O-O", None),
+        ("1-1", None),
+        ("h", None),
+    ] {
+        assert_eq!(normalize_result(token), result, "{token}");
+    }
+
+    let games = split_games(
+        "[Result \"1-0 \"]\n\n1. e4 e5 2. Qh5 Nc6 1\u{2013}0\n\n[Result \"?\"]\n\n1. d4 d5 1-O\n",
+    );
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].tag("Result"), Some("1-0"));
+    assert_eq!(games[0].termination(), Some("1-0"));
+    assert_eq!(games[1].tag("Result"), Some("?"));
+    assert_eq!(games[1].termination(), Some("1-0"));
+    assert_eq!(Game::parse(&games[1]).result, Some(GameResult::WhiteWins));
+}
+
+#[test]
+fn test_unicode_punctuation() {
+    use crate::pgn_cleaner::{normalize_unicode, split_games};
+
+    assert_eq!(normalize_unicode("1. e4 e5"), "1. e4 e5");
+    assert_eq!(
+        normalize_unicode("5. O\u{2013}O\u{a0}Nf6 {\u{201c}solid\u{201d}} 6. Bd7\u{d7}f5"),
+        "5. O-O Nf6 {\"solid\"} 6. Bd7xf5"
+    );
+
+    let games = split_games(
+        "[White \u{201c}Carlsen, Magnus\u{201d}]\n\n1.\u{a0}e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6\n5. O\u{2013}O Be7 {It\u{2019}s \u{201c}closed\u{201d}} 6. Re1 1\u{2026} b5 1\u{2013}0\n",
+    );
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].tag("White"), Some("Carlsen, Magnus"));
+    assert_eq!(games[0].termination(), Some("1-0"));
+    assert!(games[0].movetext.contains("5. O-O Be7 {It's \"closed\"}"));
+    // Characters map one to one, so locations still point into the input line
+    let offset = games[0].movetext.find("Re1").unwrap();
+    assert_eq!(games[0].location(offset), Some((4, 31)));
+}
+
+#[test]
+fn test_livechess_snapshots() {
+    use crate::livechess::LiveChessIngest;
+    use crate::pgn_cleaner::split_games;
+
+    let snapshot = |round: &str, movetext: &str| {
+        format!(
+            "[Event \"Club\"]\n[Round \"{round}\"]\n[White \"A\"]\n[Black \"B\"]\n\n{movetext}\n\n"
+        )
+    };
+    let input = [
+        snapshot("1.1", "1. e4 *"),
+        snapshot("1.2", "1. d4 *"),
+        snapshot("1.1", "1. e4 e5 *"),
+        snapshot("1.1", "1. e4 e5 *"),
+        // A misread move corrected by the operator
+        snapshot("1.2", "1. d4 d6 *"),
+        snapshot("1.2", "1. d4 d5 2. c4 *"),
+        snapshot("1.1", "1. e4 e5 2. Nf3 1-0"),
+    ]
+    .concat();
+
+    let mut ingest = LiveChessIngest::new();
+    for game in split_games(&input) {
+        ingest.add(game);
+    }
+    assert_eq!(ingest.duplicates, 1);
+    let boards = ingest.boards();
+    assert_eq!(boards.len(), 2);
+    assert_eq!((boards[0].snapshots, boards[0].rewrites), (4, 0));
+    assert_eq!((boards[1].snapshots, boards[1].rewrites), (3, 1));
+
+    let games = ingest.into_games();
+    assert_eq!(games[0].movetext.trim(), "1. e4 e5 2. Nf3 1-0");
+    assert_eq!(games[1].movetext.trim(), "1. d4 d5 2. c4 *");
+}
+
+#[test]
+fn test_move_metadata_json() {
+    use crate::json::{self, Value};
+    use crate::pgn_ast::Game;
+    use crate::pgn_cleaner::split_games;
+    use crate::tree::game_json;
+    use crate::PgnProcessor;
+
+    let games = split_games("1. e4 f5 2. exf5 g5 (2... Kf7 3. Qh5+) 3. Qh5# 1-0\n");
+    let mut processor = PgnProcessor::new();
+    let tree = json::parse(&game_json(&Game::parse(&games[0]), "1-0", &mut processor)).unwrap();
+    let Some(Value::Array(moves)) = tree.get("moves") else {
+        panic!("no moves in {tree:?}");
+    };
+    let field = |mv: &Value, key: &str| mv.get(key).cloned().unwrap();
+
+    assert_eq!(field(&moves[0], "piece"), Value::String("pawn".to_string()));
+    assert_eq!(field(&moves[0], "captured"), Value::Null);
+    assert_eq!(
+        field(&moves[2], "captured"),
+        Value::String("pawn".to_string())
+    );
+    assert_eq!(field(&moves[2], "is_check"), Value::Bool(false));
+    assert_eq!(
+        field(&moves[4], "piece"),
+        Value::String("queen".to_string())
+    );
+    assert_eq!(field(&moves[4], "is_check"), Value::Bool(true));
+    assert_eq!(field(&moves[4], "is_mate"), Value::Bool(true));
+    assert_eq!(field(&moves[4], "is_castle"), Value::Bool(false));
+    assert_eq!(field(&moves[4], "is_promotion"), Value::Bool(false));
+    assert_eq!(
+        field(&moves[4], "tags"),
+        Value::Array(vec![Value::String("check".to_string())])
+    );
+
+    // Moves of side lines are described too
+    let Some(Value::Array(variations)) = moves[3].get("variations") else {
+        panic!("no variations in {:?}", moves[3]);
+    };
+    let Value::Array(side_line) = &variations[0] else {
+        panic!("not a line: {:?}", variations[0]);
+    };
+    assert_eq!(
+        field(&side_line[0], "piece"),
+        Value::String("king".to_string())
+    );
+    assert_eq!(field(&side_line[1], "is_mate"), Value::Bool(false));
+    assert_eq!(field(&side_line[1], "is_check"), Value::Bool(true));
+}
+
+#[test]
+fn test_header_scan() {
+    use crate::pgn_cleaner::{load_movetext, scan_headers, split_games};
+
+    let input = "[Event \"A\"]\n\n1. e4 { [not a tag] \n[Event \"B\"] } e5 1-0\n\n\
+                 1. d4 d5 *\n[Event \"C\"]\n[White \"x\"]\n\n  1. c4 ; 0-1\n%escaped 1-0\n  c5 0-1\n";
+    let full = split_games(input);
+    let mut scanned = scan_headers(input);
+    assert_eq!(scanned.len(), 3);
+    assert!(scanned.iter().all(|game| game.movetext.is_empty()));
+
+    for (scanned, full) in scanned.iter_mut().zip(&full) {
+        assert_eq!(scanned.tags, full.tags);
+        assert_eq!((scanned.offset, scanned.end), (full.offset, full.end));
+        load_movetext(input, scanned);
+        assert_eq!(scanned.movetext, full.movetext);
+        assert_eq!(scanned.movetext_lines, full.movetext_lines);
+    }
+    assert_eq!(full[2].movetext_lines[1].line, 10);
+    assert_eq!(full[2].movetext_lines.len(), 4);
+    assert_eq!(&input[full[1].offset..full[1].end], "1. d4 d5 *\n");
+}
+
+#[test]
+fn test_duplicate_policies() {
+    use crate::duplicates::{duplicate_key, ConflictPolicy, Deduplicator};
+    use crate::pgn_ast::Game;
+    use crate::pgn_cleaner::split_games;
+
+    let pgn = "[Date \"2020.01.01\"]\n[White \"X\"]\n[Black \"Y\"]\n\n1. e4 {A} e5 1-0\n\n\
+               [White \"Z\"]\n[Black \"Y\"]\n\n1. e4 e5 *\n\n\
+               [Date \"2021.??.??\"]\n[White \"X\"]\n[Black \"Y\"]\n\n{Intro} 1. e4 {B} e5 {C} 1-0\n\n\
+               [Date \"2019.01.01\"]\n[White \"X\"]\n[Black \"Y\"]\n\n1. e4 {A} e5 1-0\n";
+    let games = split_games(pgn);
+    let dedupe = |policy| {
+        let mut deduplicator = Deduplicator::new(policy);
+        for raw in &games {
+            let game = Game::parse(raw);
+            deduplicator.add(duplicate_key(&game), game);
+        }
+        deduplicator
+    };
+
+    // Players and moves identify copies; the game between other players is kept
+    let first = dedupe(ConflictPolicy::First);
+    assert_eq!(first.duplicates, 2);
+    let kept = first.into_games();
+    assert_eq!(kept.len(), 2);
+    assert_eq!(kept[0].tag("Date"), Some("2020.01.01"));
+    assert_eq!(kept[1].tag("White"), Some("Z"));
+
+    let newest = dedupe(ConflictPolicy::Newest).into_games();
+    assert_eq!(newest[0].tag("Date"), Some("2021.??.??"));
+
+    let merged = dedupe(ConflictPolicy::MergeComments);
+    assert_eq!(merged.merged_comments, 3);
+    let kept = merged.into_games();
+    assert_eq!(kept[0].tag("Date"), Some("2020.01.01"));
+    assert_eq!(kept[0].movetext(), "{Intro} 1. e4 {A} {B} 1... e5 {C} 1-0");
+}
+
+#[test]
+fn test_verify_pv() {
+    use crate::pgn_ast::Game;
+    use crate::pgn_cleaner::split_games;
+    use crate::position::Position;
+    use crate::pv::{split_pv, verify_pvs};
+
+    assert_eq!(
+        split_pv("+0.45/18 19.Nf5 Qd7 20. Qg4"),
+        Some((vec!["+0.45/18"], vec!["Nf5", "Qd7", "Qg4"]))
+    );
+    assert_eq!(split_pv("Nf5 was the plan"), None);
+    assert_eq!(split_pv("+0.45/18"), None);
+
+    let games = split_games(
+        "1. e4 {+0.3/20 1... e5 2.Nf3} e5 {g1f3 b8c6} 2. Nf3 {2... Nf6 3. Bb8} \
+         (2. Bc4 {Nf6 d3}) Nc6 {Good move} *\n",
+    );
+    let mut game = Game::parse(&games[0]);
+    let mut issues = Vec::new();
+    let normalized = verify_pvs(&mut game.moves, Position::initial(), 0, &mut issues);
+
+    // Lines after a move start from the position it leads to, side lines from theirs
+    assert_eq!(normalized, 3);
+    assert_eq!(
+        game.movetext(),
+        "1. e4 {+0.3/20 1... e5 2. Nf3} 1... e5 {2. Nf3 Nc6} 2. Nf3 {2... Nf6 3. Bb8} \
+         (2. Bc4 {2... Nf6 3. d3}) 2... Nc6 {Good move} *"
+    );
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].ply, 3);
+    assert_eq!(issues[0].error, "Illegal move Bb8");
+}
+
+#[test]
+fn test_diff() {
+    use crate::diff::diff_databases;
+    use crate::pgn_cleaner::split_games;
+
+    let left = split_games(
+        "[Event \"Club\"]
+[Round \"1\"]
+
+1. e4 e5 2. Nf3 {main} Nc6 *
+
+[Event \"Club\"]
+
+1. d4 d5 *
+
+[Event \"Club\"]
+
+1. c4 *",
+    );
+    let right = split_games(
+        "[Event \"Club championship\"]
+[Round \"1\"]
+[Annotator \"X\"]
+
+1. e4 e5 2. Nf3 {best} Nc6 3. Bb5 *
+
+[Event \"Club\"]
+
+1. d4 Nf6 2. c4 *",
+    );
+
+    assert_eq!(
+        diff_databases(&left, &right),
+        vec![
+            "Game count differs: 3 vs 2",
+            "Game 1: header Event: \"Club\" vs \"Club championship\"",
+            "Game 1: header Annotator: only in right (X)",
+            "Game 1: ply 3 (2. Nf3): comment [\"main\"] vs [\"best\"]",
+            "Game 1: length: 4 plies vs 5 plies",
+            "Game 2: ply 2 (1...): move d5 vs Nf6",
+            "Game 3: only in left",
+        ]
+    );
+    assert!(diff_databases(&left, &left).is_empty());
+    assert_eq!(
+        diff_databases(&right, &left).last().unwrap(),
+        "Game 3: only in right"
+    );
+}
+
+#[test]
+fn test_lenient_captures() {
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    let play = |pgn: &str, lenient: bool| {
+        let mut processor = PgnProcessor::new();
+        processor.set_lenient_captures(lenient);
+        let moves = processor.process_game(&split_games(pgn)[0]);
+        let warnings: Vec<(String, String)> = processor
+            .take_warnings()
+            .into_iter()
+            .map(|warning| (warning.token, warning.message))
+            .collect();
+        (moves.len(), warnings)
+    };
+    let mismatch = |token: &str, read_as: &str| {
+        vec![(
+            token.to_string(),
+            format!("Capture marker mismatch, read as {read_as}"),
+        )]
+    };
+
+    // A pawn move without its `x`, or with a bare one, has no candidate when strict
+    for (pgn, token, read_as) in [
+        ("1. e4 d5 2. ed5 Qxd5 *", "ed5", "exd5"),
+        ("1. e4 e5 2. xd3 Nc6 *", "xd3", "d3"),
+    ] {
+        let (moves, warnings) = play(pgn, false);
+        assert_eq!((moves, warnings[0].0.as_str()), (2, token), "{pgn}");
+        assert_eq!(play(pgn, true), (4, mismatch(token, read_as)), "{pgn}");
+    }
+
+    // Moves naming their piece or pawn file resolve either way; only lenient resolution
+    // reports the marker
+    for (pgn, moves, token, read_as) in [
+        ("1. e4 e5 2. dxd3 Nc6 *", 4, "dxd3", "d3"),
+        ("1. e4 d5 2. Nc3 Nf6 3. Nd5 *", 5, "Nd5", "Nxd5"),
+        ("1. e4 d5 2. exd5 Qd5 *", 4, "Qd5", "Qxd5"),
+        ("1. e4 e5 2. Nxf3 Nc6 *", 4, "Nxf3", "Nf3"),
+    ] {
+        assert_eq!(play(pgn, false), (moves, vec![]), "{pgn}");
+        assert_eq!(play(pgn, true), (moves, mismatch(token, read_as)), "{pgn}");
+    }
+
+    // Correct markers raise nothing
+    assert_eq!(play("1. e4 d5 2. exd5 Qxd5 *", true), (4, vec![]));
+}
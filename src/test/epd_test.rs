@@ -0,0 +1,56 @@
+#[test]
+fn test_epd_record() {
+    use crate::epd::EpdRecord;
+
+    let record = EpdRecord::parse(
+        "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";",
+    )
+    .unwrap();
+    assert_eq!(record.id(), Some("WAC.001"));
+    assert_eq!(record.operands("bm"), Some(&["Qg6".to_string()][..]));
+    assert_eq!(record.solved_by("g3g6"), Some(true));
+    assert_eq!(record.solved_by("f1f7"), Some(false));
+
+    let avoid = EpdRecord::parse("4k3/8/8/8/8/8/4P3/4K3 w - - am e3 Kd1; hmvc 7;").unwrap();
+    assert_eq!(avoid.position.halfmove_clock, 7);
+    assert_eq!(avoid.solved_by("e2e4"), Some(true));
+    assert_eq!(avoid.solved_by("e2e3"), Some(false));
+    assert_eq!(avoid.solved_by("e1d1"), Some(false));
+
+    let plain = EpdRecord::parse("4k3/8/8/8/8/8/8/4K3 w - - c0 \"a; b\";").unwrap();
+    assert_eq!(plain.operands("c0"), Some(&["a; b".to_string()][..]));
+    assert_eq!(plain.solved_by("e1d1"), None);
+
+    assert!(EpdRecord::parse("4k3/8/8 w").is_err());
+}
+
+#[test]
+fn test_parse_san() {
+    use crate::position::{coordinate_move, Position};
+
+    let position =
+        Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+    let coordinate = |san: &str| {
+        position
+            .parse_san(san)
+            .map(|(from, to, promotion)| coordinate_move(from, to, promotion))
+    };
+    assert_eq!(coordinate("O-O").as_deref(), Ok("e1g1"));
+    assert_eq!(coordinate("O-O-O+").as_deref(), Ok("e1c1"));
+    assert_eq!(coordinate("dxe6").as_deref(), Ok("d5e6"));
+    assert_eq!(coordinate("Nxf7!").as_deref(), Ok("e5f7"));
+    assert_eq!(coordinate("Bxa6").as_deref(), Ok("e2a6"));
+    assert_eq!(coordinate("Qxh3").as_deref(), Ok("f3h3"));
+
+    let rooks = Position::from_fen("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1").unwrap();
+    assert!(rooks.parse_san("Ra3").unwrap_err().starts_with("Ambiguous"));
+    assert_eq!(rooks.parse_san("R1a3"), Ok((0, 16, None)));
+    assert_eq!(rooks.parse_san("R5a3"), Ok((32, 16, None)));
+
+    let promotion = Position::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let (from, to, piece) = promotion.parse_san("axb8=N").unwrap();
+    assert_eq!(coordinate_move(from, to, piece), "a7b8n");
+    let (from, to, piece) = promotion.parse_san("a8Q").unwrap();
+    assert_eq!(coordinate_move(from, to, piece), "a7a8q");
+}
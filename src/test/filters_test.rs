@@ -0,0 +1,175 @@
+#[test]
+fn test_date_filter() {
+    use crate::filters::{GameFilter, PgnDate};
+    use crate::pgn_cleaner::split_games;
+
+    let pgn = "[Event \"Candidates 2020\"]
+[Date \"2020.??.??\"]
+
+1. e4 *
+
+[Event \"Tata Steel\"]
+[Date \"2021.01.16\"]
+
+1. d4 *
+
+[Event \"Casual\"]
+[Date \"????.??.??\"]
+
+1. c4 *";
+    let games = split_games(pgn);
+
+    let filter = GameFilter {
+        after: PgnDate::parse("2020-01-01"),
+        before: PgnDate::parse("2021-01-01"),
+        ..GameFilter::default()
+    };
+    let matched: Vec<bool> = games.iter().map(|game| filter.matches(game)).collect();
+    assert_eq!(matched, vec![true, false, false]);
+
+    let filter = GameFilter {
+        event: Some("candidates".to_string()),
+        ..GameFilter::default()
+    };
+    let matched: Vec<bool> = games.iter().map(|game| filter.matches(game)).collect();
+    assert_eq!(matched, vec![true, false, false]);
+}
+
+#[test]
+fn test_time_class_and_elo_filters() {
+    use crate::filters::{EloMode, GameFilter, TimeClass};
+    use crate::pgn_cleaner::split_games;
+
+    // Base + 40 × increment, with each boundary starting the slower class
+    let class = TimeClass::from_time_control;
+    assert_eq!(class("60+0"), Some(TimeClass::Bullet));
+    assert_eq!(class("120+1"), Some(TimeClass::Bullet));
+    assert_eq!(class("140+1"), Some(TimeClass::Blitz));
+    assert_eq!(class("179"), Some(TimeClass::Bullet));
+    assert_eq!(class("180"), Some(TimeClass::Blitz));
+    assert_eq!(class("300+3"), Some(TimeClass::Blitz));
+    assert_eq!(class("400+2"), Some(TimeClass::Rapid));
+    assert_eq!(class("1499"), Some(TimeClass::Rapid));
+    assert_eq!(class("900+15"), Some(TimeClass::Classical));
+    assert_eq!(class("40/7200:3600"), Some(TimeClass::Classical));
+    assert_eq!(class("*60"), Some(TimeClass::Bullet));
+    assert_eq!(class("-"), None);
+    assert_eq!(class("?"), None);
+    assert_eq!(TimeClass::parse("Rapid"), Some(TimeClass::Rapid));
+    assert_eq!(TimeClass::parse("correspondence"), None);
+
+    let games = split_games(
+        "[WhiteElo \"2100\"]
+[BlackElo \"1800\"]
+[TimeControl \"180+2\"]
+
+1. e4 *
+
+[WhiteElo \"2200\"]
+[BlackElo \"2300\"]
+[TimeControl \"-\"]
+
+1. d4 *
+
+[WhiteElo \"?\"]
+[BlackElo \"2050\"]
+
+1. c4 *",
+    );
+    let matched = |filter: &GameFilter| -> Vec<bool> {
+        games.iter().map(|game| filter.matches(game)).collect()
+    };
+
+    let both = GameFilter {
+        min_elo: Some(2000),
+        ..GameFilter::default()
+    };
+    assert_eq!(matched(&both), vec![false, true, false]);
+    let either = GameFilter {
+        min_elo: Some(2000),
+        elo_mode: EloMode::Either,
+        ..GameFilter::default()
+    };
+    assert_eq!(matched(&either), vec![true, true, true]);
+    let capped = GameFilter {
+        max_elo: Some(2250),
+        elo_mode: EloMode::Either,
+        ..GameFilter::default()
+    };
+    assert_eq!(matched(&capped), vec![true, true, true]);
+    let capped = GameFilter {
+        max_elo: Some(2250),
+        ..GameFilter::default()
+    };
+    assert_eq!(matched(&capped), vec![true, false, false]);
+
+    // Games without a usable TimeControl match no class
+    let blitz = GameFilter {
+        time_class: Some(TimeClass::Blitz),
+        ..GameFilter::default()
+    };
+    assert_eq!(matched(&blitz), vec![true, false, false]);
+}
+
+#[test]
+fn test_length_and_termination_filters() {
+    use crate::filters::GameFilter;
+    use crate::pgn_cleaner::split_games;
+
+    let filter = GameFilter {
+        min_plies: Some(10),
+        max_plies: Some(20),
+        ..GameFilter::default()
+    };
+    assert!(!filter.matches_plies(9));
+    assert!(filter.matches_plies(10));
+    assert!(filter.matches_plies(20));
+    assert!(!filter.matches_plies(21));
+    assert!(GameFilter::default().matches_plies(0));
+
+    let games = split_games(
+        "[Termination \"Normal\"]
+
+1. e4 *
+
+[Termination \"abandoned\"]
+
+1. d4 *
+
+[Termination \"Time forfeit\"]
+
+1. c4 *
+
+1. Nf3 *",
+    );
+    let filter = GameFilter {
+        excluded_terminations: vec!["Abandoned".to_string(), "Time forfeit".to_string()],
+        ..GameFilter::default()
+    };
+    let matched: Vec<bool> = games.iter().map(|game| filter.matches(game)).collect();
+    // Matched case-insensitively, games without the tag being kept
+    assert_eq!(matched, vec![true, false, false, true]);
+}
+
+#[test]
+fn test_tag_selection() {
+    use crate::tag_selection::{wildcard_match, TagSelection};
+
+    assert!(wildcard_match("*RatingDiff", "WhiteRatingDiff"));
+    assert!(wildcard_match("UTC*", "UTCTime"));
+    assert!(wildcard_match("Res?lt", "Result"));
+    assert!(wildcard_match("*a*a*", "banana"));
+    assert!(!wildcard_match("*Diff", "DiffWhite"));
+    assert!(!wildcard_match("White", "WhiteElo"));
+
+    let selection = TagSelection {
+        keep: TagSelection::parse_list("White*, Black*"),
+        drop: TagSelection::parse_list("*Diff"),
+    };
+    let tags: Vec<(String, String)> = ["White", "WhiteRatingDiff", "BlackElo", "Event"]
+        .map(|tag| (tag.to_string(), "?".to_string()))
+        .to_vec();
+    let selected = selection.select(&tags);
+    let selected: Vec<&str> = selected.iter().map(|(tag, _)| tag.as_str()).collect();
+    assert_eq!(selected, vec!["White", "BlackElo"]);
+}
@@ -1,2 +1,20 @@
 #[cfg(test)]
+pub mod analysis_test;
+#[cfg(test)]
+pub mod cleaner_test;
+#[cfg(test)]
+pub mod epd_test;
+#[cfg(test)]
+pub mod filters_test;
+#[cfg(test)]
+pub mod notation_test;
+#[cfg(test)]
+pub mod openings_test;
+#[cfg(test)]
 pub mod pgn_test;
+#[cfg(test)]
+pub mod position_test;
+#[cfg(test)]
+pub mod roster_test;
+#[cfg(test)]
+pub mod worker_test;
@@ -0,0 +1,357 @@
+#[test]
+fn test_iccf_input() {
+    use crate::notation::{parse_iccf, InputNotation};
+    use crate::pgn_cleaner::split_games;
+    use crate::position::{Piece, Position};
+    use crate::PgnProcessor;
+
+    assert_eq!(parse_iccf("5254"), Some((12, 28, None)));
+    assert_eq!(parse_iccf("17181"), Some((48, 56, Some(Piece::Queen))));
+    assert_eq!(parse_iccf("5294"), None);
+    assert_eq!(parse_iccf("e4"), None);
+
+    let start = Position::initial();
+    let iccf = InputNotation::Iccf;
+    assert_eq!(iccf.to_algebraic(&start, "7163").as_deref(), Some("Nf3"));
+    assert_eq!(iccf.to_algebraic(&start, "5255").as_deref(), Some("e2-e5"));
+
+    let games = split_games("1. 5254 5755 2. 7163 2836 3. 6152 7866 4. 5171 *");
+    let mut processor = PgnProcessor::new();
+    processor.set_input_notation(iccf);
+    assert_eq!(processor.process_game(&games[0])[6..], ["e1g1"]);
+}
+
+#[test]
+fn test_descriptive_input() {
+    use crate::notation::parse_descriptive;
+    use crate::pgn_cleaner::split_games;
+    use crate::position::{Piece, Position};
+    use crate::PgnProcessor;
+
+    let start = Position::initial();
+    assert_eq!(parse_descriptive(&start, "P-K4"), Some((12, 28, None)));
+    assert_eq!(parse_descriptive(&start, "N-KB3"), Some((6, 21, None)));
+    // Both knights reach a third rank bishop file
+    assert_eq!(parse_descriptive(&start, "N-B3"), None);
+
+    // Ranks count from the side to move
+    let black = Position::from_fen("4k3/3p4/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+    assert_eq!(parse_descriptive(&black, "P-Q4"), Some((51, 35, None)));
+
+    let en_passant = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+    assert_eq!(
+        parse_descriptive(&en_passant, "PxPe.p."),
+        Some((36, 43, None))
+    );
+
+    let promotion = Position::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(
+        parse_descriptive(&promotion, "P-R8(Q)"),
+        Some((48, 56, Some(Piece::Queen)))
+    );
+    assert_eq!(
+        parse_descriptive(&promotion, "PxR=N"),
+        Some((48, 57, Some(Piece::Knight)))
+    );
+
+    let games = split_games(
+        "1. P-K4 P-K4 2. N-KB3 P-Q3 3. P-Q4 B-N5 4. PxP BxN 5. QxB PxP 6. B-QB4 N-KB3 \
+         7. Q-QN3 Q-K2 8. N-B3 P-B3 9. B-KN5 P-QN4 10. NxP PxN 11. BxNPch QN-Q2 \
+         12. O-O-O R-Q1 13. RxN RxR 14. R-Q1 Q-K3 15. BxRch NxB 16. Q-N8ch NxQ 17. R-Q8mate 1-0",
+    );
+    let mut processor = PgnProcessor::new();
+    processor.set_input_notation(crate::notation::InputNotation::Descriptive);
+    let moves = processor.process_game(&games[0]);
+    assert_eq!(moves.len(), 33);
+    assert_eq!(moves[21], "b8d7");
+}
+
+#[test]
+fn test_output_notations() {
+    use crate::notation::{output_notation, write_moves};
+    use crate::position::{Piece, Position};
+
+    let start = Position::initial();
+    let after_e4 = start.play(12, 28, None);
+    let positions = [start, after_e4];
+    let moves = ["e2e4".to_string(), "g8f6".to_string()];
+    let written = |name: &str| write_moves(output_notation(name).unwrap(), &positions, &moves);
+
+    assert_eq!(written("uci"), ["e2e4", "g8f6"]);
+    assert_eq!(written("san"), ["e4", "Nf6"]);
+    assert_eq!(written("lan"), ["e2-e4", "Ng8-f6"]);
+    assert_eq!(written("iccf"), ["5254", "7866"]);
+    assert_eq!(written("figurine"), ["e4", "♘f6"]);
+    assert_eq!(written("spoken"), ["e4", "knight f6"]);
+    assert!(output_notation("descriptive").is_none());
+
+    let promotion = Position::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let lan = output_notation("lan").unwrap();
+    assert_eq!(
+        lan.write(&promotion, 48, 57, Some(Piece::Knight)),
+        "a7xb8=N"
+    );
+    let iccf = output_notation("iccf").unwrap();
+    assert_eq!(iccf.write(&promotion, 48, 56, Some(Piece::Queen)), "17181");
+
+    let spoken = output_notation("spoken").unwrap();
+    assert_eq!(spoken.separator(), ", ");
+    assert_eq!(
+        spoken.write(&promotion, 48, 57, Some(Piece::Knight)),
+        "a takes b8 promotes to knight"
+    );
+    let en_passant = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 1").unwrap();
+    assert_eq!(
+        spoken.write(&en_passant, 36, 43, None),
+        "e takes d6 en passant"
+    );
+    assert_eq!(spoken.write(&en_passant, 4, 6, None), "short castle");
+    assert_eq!(spoken.write(&en_passant, 7, 63, None), "rook h8 check");
+}
+
+#[test]
+fn test_diff_notation() {
+    use crate::notation::{output_notation, write_moves};
+    use crate::position::{Piece, Position};
+
+    let diff = output_notation("diff").unwrap();
+    assert_eq!(diff.separator(), "\n");
+
+    let start = Position::initial();
+    let positions = [start, start.play(12, 28, None)];
+    let moves = ["e2e4".to_string(), "g8f6".to_string()];
+    assert_eq!(
+        write_moves(diff, &positions, &moves),
+        ["-e2 +e4P", "-g8 +f6n"]
+    );
+
+    let en_passant = Position::from_fen("r3k3/8/8/3pP3/8/8/8/4K2R w Kq d6 0 1").unwrap();
+    assert_eq!(diff.write(&en_passant, 36, 43, None), "-e5 xd5p +d6P");
+    assert_eq!(diff.write(&en_passant, 4, 6, None), "-e1 +g1K -h1 +f1R");
+    assert_eq!(diff.write(&en_passant, 7, 63, None), "-h1 +h8R");
+
+    let mut black = en_passant;
+    black.side_to_move = chess::legal_moves::misc::Color::Black;
+    black.en_passant = None;
+    assert_eq!(diff.write(&black, 60, 58, None), "-e8 +c8k -a8 +d8r");
+
+    let promotion = Position::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(
+        diff.write(&promotion, 48, 57, Some(Piece::Queen)),
+        "-a7 xb8r +b8Q"
+    );
+}
+
+#[test]
+fn test_input_format_detection() {
+    use crate::backend::BackendKind;
+    use crate::input_format::InputFormat;
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    assert_eq!(
+        InputFormat::detect("\n[Event \"?\"]\n\n1. e4 *"),
+        InputFormat::Pgn
+    );
+    assert_eq!(InputFormat::detect("1. e4 e5 2. Nf3"), InputFormat::San);
+    assert_eq!(InputFormat::detect("e2e4 e7e5 g1f3"), InputFormat::Uci);
+    assert_eq!(
+        InputFormat::detect("8/8/8/8/8/8/8/K6k w - - 0 1 Kb2"),
+        InputFormat::FenMoves
+    );
+    // A prefix of a stream is only judged once it holds a whole non-empty line
+    assert_eq!(
+        InputFormat::detect_prefix(b"\ne2e4 e7e5\nd2d4"),
+        Some(InputFormat::Uci)
+    );
+    assert_eq!(InputFormat::detect_prefix(b"\n\n[Event \"?"), None);
+    assert_eq!(InputFormat::detect_prefix(b""), None);
+
+    let pgn = InputFormat::Uci
+        .to_pgn("e2e4 e7e5 g1f3\n\nd2d4 g8f6 c2c4 e7e6 b1c3 f8b4\n".to_string())
+        .unwrap();
+    assert_eq!(
+        pgn,
+        "1. e4 e5 2. Nf3 *\n\n1. d4 Nf6 2. c4 e6 3. Nc3 Bb4 *\n\n"
+    );
+    assert_eq!(
+        InputFormat::Uci.to_pgn("e2e5".to_string()),
+        Err("Line 1: Illegal move e2e5".to_string())
+    );
+
+    let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+    let pgn = InputFormat::FenMoves
+        .to_pgn(format!("{fen} moves f1b5 a7a6 Bxc6"))
+        .unwrap();
+    assert_eq!(
+        pgn,
+        format!("[SetUp \"1\"]\n[FEN \"{fen}\"]\n\n3. Bb5 a6 4. Bxc6 *\n\n")
+    );
+
+    // Set-up positions need a backend that can place pieces
+    let games = split_games(&pgn);
+    let mut processor = PgnProcessor::new();
+    assert!(processor.process_game(&games[0]).is_empty());
+    assert!(processor.is_corrupt());
+    processor.set_backend(BackendKind::Mailbox);
+    assert_eq!(processor.process_game(&games[0]), ["f1b5", "a7a6", "b5c6"]);
+    assert!(!processor.is_corrupt());
+    assert_eq!(processor.fen_at(0).as_deref(), Some(fen));
+    assert!(processor.board_at(1).is_none());
+
+    // Coordinate lines replayed from another start record it in tags
+    let start = crate::position::Position::from_fen(fen).unwrap();
+    let pgn = InputFormat::Uci
+        .to_pgn_from("f1b5 a7a6\n".to_string(), &start)
+        .unwrap();
+    assert_eq!(
+        pgn,
+        format!("[SetUp \"1\"]\n[FEN \"{fen}\"]\n\n3. Bb5 a6 *\n\n")
+    );
+    assert_eq!(
+        InputFormat::San.to_pgn_from("Bb5 a6".to_string(), &start),
+        Ok("Bb5 a6".to_string())
+    );
+}
+
+#[test]
+fn test_san_tokens() {
+    use crate::position::Position;
+    use crate::san_tokens::{game_tokens, write_sequence, Vocabulary};
+
+    // A needlessly disambiguated knight move is written the usual way
+    let moves: Vec<String> = ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3"]
+        .map(str::to_string)
+        .to_vec();
+    let mut positions = vec![Position::initial()];
+    for mv in &moves {
+        let (from, to, promotion) = crate::position::parse_coordinate_move(mv).unwrap();
+        let next = positions.last().unwrap().play(from, to, promotion);
+        positions.push(next);
+    }
+    let tokens = game_tokens(&positions, &moves);
+    assert_eq!(tokens, ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3"]);
+
+    let mut text = Vec::new();
+    write_sequence(&mut text, 3, &tokens[..2], false).unwrap();
+    write_sequence(&mut text, 3, &tokens[..2], true).unwrap();
+    assert_eq!(
+        String::from_utf8(text).unwrap(),
+        "3\tNf3 Nf6\n{\"game\":3,\"tokens\":[\"Nf3\",\"Nf6\"]}\n"
+    );
+
+    let mut vocabulary = Vocabulary::new();
+    vocabulary.add(&tokens);
+    assert_eq!(vocabulary.len(), 4);
+    assert_eq!(vocabulary.to_text(), "Nf3\t2\nNf6\t1\nNg1\t1\nNg8\t1\n");
+    assert_eq!(
+        vocabulary.to_json(),
+        "{\"Nf3\":2,\"Nf6\":1,\"Ng1\":1,\"Ng8\":1}"
+    );
+}
+
+#[test]
+fn test_move_table() {
+    use crate::move_table::{game_rows, write_game, write_header};
+    use crate::PgnProcessor;
+
+    let mut processor = PgnProcessor::new();
+    let moves = processor.process_line(&["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "O-O"]);
+    let rows = game_rows(processor.positions(), &moves);
+    assert_eq!(rows.len(), 7);
+    assert_eq!((rows[3].ply, rows[3].move_number), (4, 2));
+    assert_eq!(
+        (rows[6].san.as_str(), rows[6].uci.as_str()),
+        ("O-O", "e1g1")
+    );
+
+    let mut tsv = Vec::new();
+    write_header(&mut tsv, false).unwrap();
+    write_game(&mut tsv, 5, &rows[..2], false).unwrap();
+    assert_eq!(
+        String::from_utf8(tsv).unwrap(),
+        "game\tply\tmove\tcolor\tsan\tuci\n5\t1\t1\twhite\te4\te2e4\n5\t2\t1\tblack\te5\te7e5\n"
+    );
+
+    // Fixed-width columns start at the same offset on every line
+    let mut text = Vec::new();
+    write_header(&mut text, true).unwrap();
+    write_game(&mut text, 12, &rows[4..5], true).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[1], "12       5     3     white Bb5     f1b5");
+    assert_eq!(lines[0].find("san"), lines[1].find("Bb5"));
+}
+
+#[test]
+fn test_color_split() {
+    use crate::backend::BackendKind;
+    use crate::color_split::ColorSplit;
+    use crate::PgnProcessor;
+
+    let mut processor = PgnProcessor::new();
+    let moves = processor.process_line(&["e4", "e5", "Nf3"]);
+    let split = ColorSplit::of(processor.positions(), &moves);
+    assert_eq!(split.first_move, 1);
+    assert_eq!(
+        split.white,
+        [Some("e2e4".to_string()), Some("g1f3".to_string())]
+    );
+    assert_eq!(split.black, ["e7e5"]);
+
+    // A game set up with black to move has no white move in its first full move
+    processor.set_backend(BackendKind::Mailbox);
+    let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 3";
+    let moves = processor.process_line_from(fen, &["Nf6", "O-O", "Nxe4"]);
+    let split = ColorSplit::of(processor.positions(), &moves);
+    assert_eq!(split.first_move, 3);
+    assert_eq!(split.white, [None, Some("e1g1".to_string())]);
+    assert_eq!(split.black, ["g8f6", "f6e4"]);
+
+    let mut text = Vec::new();
+    split.write(&mut text, 7, false).unwrap();
+    split.write(&mut text, 7, true).unwrap();
+    assert_eq!(
+        String::from_utf8(text).unwrap(),
+        "7\t3\t-- e1g1\tg8f6 f6e4\n\
+         {\"game\":7,\"first_move\":3,\"white\":[null,\"e1g1\"],\"black\":[\"g8f6\",\"f6e4\"]}\n"
+    );
+    assert_eq!(ColorSplit::of(&[], &[]).first_move, 1);
+}
+
+#[test]
+fn test_fen_injection() {
+    use crate::annotations::FenInjection;
+    use crate::backend::BackendKind;
+    use crate::notation::output_notation;
+    use crate::PgnProcessor;
+
+    let san = output_notation("san").unwrap();
+    let mut processor = PgnProcessor::new();
+    let moves = processor.process_line(&["e4", "d5", "exd5", "Qxd5", "Nc3"]);
+    let every_two = FenInjection {
+        every: Some(2),
+        ..FenInjection::default()
+    };
+    assert_eq!(
+        every_two.render(processor.positions(), &moves, san),
+        "e4 d5 { FEN: rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2 } exd5 Qxd5 \
+         { FEN: rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3 } Nc3"
+    );
+    let captures = FenInjection {
+        captures: true,
+        ..FenInjection::default()
+    };
+    let rendered = captures.render(processor.positions(), &moves, san);
+    assert_eq!(rendered.matches("{ FEN:").count(), 2);
+    assert!(rendered.starts_with("e4 d5 exd5 { FEN:"));
+
+    // Plies are counted from the set up position, which has black to move
+    processor.set_backend(BackendKind::Mailbox);
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 40";
+    let moves = processor.process_line_from(fen, &["Kd7", "e4", "Kc6"]);
+    assert_eq!(
+        every_two.render(processor.positions(), &moves, san),
+        "Kd7 e4 { FEN: 8/3k4/8/8/4P3/8/8/4K3 b - e3 0 41 } Kc6"
+    );
+}
@@ -0,0 +1,174 @@
+#[test]
+fn test_opening_tree() {
+    use crate::openings::OpeningTree;
+
+    let line = |moves: &str| -> Vec<String> { moves.split(' ').map(str::to_string).collect() };
+    let mut tree = OpeningTree::new(2);
+    tree.add_game(&line("e2e4 e7e5 g1f3"), "1-0");
+    tree.add_game(&line("e2e4 c7c5"), "0-1");
+    tree.add_game(&line("d2d4"), "1/2-1/2");
+
+    assert_eq!(tree.nodes[""].games, 3);
+    assert_eq!(tree.nodes["e2e4"].games, 2);
+    assert_eq!(tree.nodes["e2e4 e7e5"].white_wins, 1);
+    assert!(!tree.nodes.contains_key("e2e4 e7e5 g1f3"));
+    assert_eq!(tree.nodes["d2d4"].practical_score(), 0.5);
+
+    // Without evaluations nothing can be compared
+    assert!(tree.divergent(1).is_empty());
+
+    tree.nodes.get_mut("e2e4").unwrap().eval = Some(0);
+    tree.nodes.get_mut("d2d4").unwrap().eval = Some(400);
+    let divergent = tree.divergent(1);
+    assert_eq!(divergent[0].0, "d2d4");
+    assert!((divergent[0].1.expected_score().unwrap() - 10.0 / 11.0).abs() < 1e-9);
+    assert_eq!(divergent[1].0, "e2e4");
+    assert_eq!(tree.divergent(3).len(), 0);
+}
+
+#[test]
+fn test_eco_verification() {
+    use crate::eco::{fix_tags, mismatched_tags, EcoClassifier};
+    use crate::pgn_cleaner::RawGame;
+    use crate::position::{parse_coordinate_move, Position};
+
+    let positions = |moves: &str| -> Vec<Position> {
+        let mut positions = vec![Position::initial()];
+        for mv in moves.split(' ') {
+            let (from, to, promotion) = parse_coordinate_move(mv).unwrap();
+            positions.push(positions.last().unwrap().play(from, to, promotion));
+        }
+        positions
+    };
+    let classifier = EcoClassifier::new();
+
+    // The Najdorf is recognised past its last table position
+    let najdorf = classifier
+        .classify(&positions(
+            "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6 c1e3",
+        ))
+        .unwrap();
+    assert_eq!(najdorf.code, "B90");
+    // A Nimzo-Indian reached from the English
+    let nimzo = classifier
+        .classify(&positions("c2c4 e7e6 b1c3 g8f6 d2d4 f8b4"))
+        .unwrap();
+    assert_eq!(nimzo.name, "Nimzo-Indian Defense");
+    assert!(classifier.classify(&positions("e2e4 e7e5")).is_none());
+
+    let mut game = RawGame::new(0);
+    game.tags = vec![
+        ("ECO".to_string(), "B92".to_string()),
+        ("Opening".to_string(), "Sicilian, Najdorf".to_string()),
+    ];
+    assert!(mismatched_tags(&game, najdorf).is_empty());
+
+    game.tags = vec![
+        ("ECO".to_string(), "C11".to_string()),
+        ("Opening".to_string(), "French Defence".to_string()),
+        ("Variation".to_string(), "Classical".to_string()),
+    ];
+    let mismatched = mismatched_tags(&game, najdorf);
+    assert_eq!(mismatched, ["ECO", "Opening"]);
+    fix_tags(&mut game, najdorf, &mismatched);
+    assert_eq!(game.tag("ECO"), Some("B90"));
+    assert_eq!(
+        game.tag("Opening"),
+        Some("Sicilian Defense: Najdorf Variation")
+    );
+    assert_eq!(game.tag("Variation"), None);
+}
+
+#[test]
+fn test_prep_report() {
+    use crate::openings::OpeningTree;
+    use crate::prep::{children, main_lines, tree_report};
+
+    let line = |moves: &str| -> Vec<String> { moves.split(' ').map(str::to_string).collect() };
+    let mut tree = OpeningTree::new(3);
+    tree.add_game(&line("e2e4 c7c5 g1f3"), "0-1");
+    tree.add_game(&line("e2e4 c7c5 g1f3"), "1/2-1/2");
+    tree.add_game(&line("e2e4 c7c5 b1c3"), "1-0");
+    tree.add_game(&line("d2d4 g8f6 c2c4"), "0-1");
+
+    let first: Vec<&str> = children(&tree, "")
+        .into_iter()
+        .map(|(line, _)| line)
+        .collect();
+    assert_eq!(first, ["e2e4", "d2d4"]);
+    assert_eq!(children(&tree, "e2e4 c7c5").len(), 2);
+
+    assert_eq!(main_lines(&tree, 2), ["e2e4 c7c5 g1f3"]);
+    assert_eq!(main_lines(&tree, 1).len(), 3);
+
+    // Scores are the opponent's, here black
+    let report = tree_report(&tree, false, 2);
+    assert_eq!(
+        report,
+        "1.e4 3 games (75%), score 50%\n  1...c5 3 games (100%), score 50%\n    2.Nf3 2 games (67%), score 75%\n"
+    );
+}
+
+#[test]
+fn test_polyglot_book() {
+    use crate::polyglot::{annotate_book_moves, PolyglotBook, PolyglotKeys, RANDOM_COUNT};
+    use crate::position::Position;
+    use crate::sampling::Rng;
+
+    // Stand-in numbers: keys only need to be consistent between the book and the probe
+    let mut rng = Rng::new(7);
+    let randoms: Vec<String> = (0..RANDOM_COUNT)
+        .map(|_| format!("0x{:016X}", rng.next_u64()))
+        .collect();
+    let keys = PolyglotKeys::parse(&randoms.join(",\n")).unwrap();
+    assert!(PolyglotKeys::parse("0x1, 0x2").is_err());
+
+    let start = Position::initial();
+    let e4 = start.play(12, 28, None);
+    let e5 = e4.play(52, 36, None);
+    // The en passant square of 1. e4 is left out, no black pawn being able to take
+    let mut without_en_passant = e4;
+    without_en_passant.en_passant = None;
+    assert_eq!(keys.key(&e4), keys.key(&without_en_passant));
+    assert_ne!(keys.key(&e4), keys.key(&start));
+
+    let castling = Position::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+    let entry = |position: &Position, from: u16, to: u16| {
+        let mut bytes = keys.key(position).to_be_bytes().to_vec();
+        bytes.extend((from << 6 | to).to_be_bytes());
+        bytes.extend([0, 1, 0, 0, 0, 0]);
+        bytes
+    };
+    // e2e4 and d2d4 from the start, e7e5 after 1. e4, and O-O written as e1h1
+    let book = PolyglotBook::from_bytes(
+        &[
+            entry(&e4, 52, 36),
+            entry(&start, 12, 28),
+            entry(&castling, 4, 7),
+            entry(&start, 11, 27),
+        ]
+        .concat(),
+    )
+    .unwrap();
+    assert_eq!(book.len(), 4);
+    assert!(PolyglotBook::from_bytes(&[0; 17]).is_err());
+
+    let moves: Vec<String> = book
+        .moves(&keys, &start)
+        .into_iter()
+        .map(|(mv, _)| mv)
+        .collect();
+    assert_eq!(moves, ["e2e4", "d2d4"]);
+    assert_eq!(book.moves(&keys, &castling), [("e1g1".to_string(), 1)]);
+
+    let game = ["e2e4", "e7e5", "g1f3"].map(str::to_string);
+    let positions = [start, e4, e5];
+    assert_eq!(book.plies_in_book(&keys, &positions, &game), 2);
+
+    let mut written = game.to_vec();
+    annotate_book_moves(&mut written, 2);
+    assert_eq!(
+        written,
+        ["e2e4 { book }", "e7e5 { book }", "g1f3 { out of book }"]
+    );
+}
@@ -20,3 +20,599 @@ Kg7 22. Bf6+ N5xf6 23. Qg5 Nh5 24. Rxf7+ 1-0";
         }
     }
 }
+
+#[test]
+fn test_position_at() {
+    use crate::pgn_cleaner::split_games;
+    use crate::position::Position;
+    use crate::PgnProcessor;
+    use chess::legal_moves::misc::Color;
+
+    let games = split_games("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O *");
+    let mut processor = PgnProcessor::new();
+    processor.process_game(&games[0]);
+
+    assert_eq!(
+        processor.fen_at(0).unwrap(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+    assert_eq!(
+        processor.fen_at(1).unwrap(),
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+    );
+    assert_eq!(
+        processor.fen_at(7).unwrap(),
+        "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4"
+    );
+    assert!(processor.position_at(8).is_none());
+
+    let replayed = Position::from_board(&processor.board_at(7).unwrap(), Color::Black);
+    assert_eq!(
+        replayed.placement(),
+        processor.position_at(7).unwrap().placement()
+    );
+}
+
+#[test]
+fn test_board_backends() {
+    use crate::backend::BackendKind;
+    use crate::cli::take_backend;
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    let pgn = "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. O-O f5 5. exf5 e4 6. f6 exf3 7. fxg7 fxg2 \
+               8. gxh8=N gxf1=Q+ 9. Kxf1 *";
+    let games = split_games(pgn);
+
+    let mut processor = PgnProcessor::new();
+    let moves = processor.process_game(&games[0]);
+    assert_eq!(moves.len(), 14);
+    assert!(processor.take_warnings()[0]
+        .message
+        .contains("Promotion is not supported"));
+
+    let mut processor = PgnProcessor::new();
+    processor.set_backend(BackendKind::Mailbox);
+    processor.set_check_invariants(true);
+    let moves = processor.process_game(&games[0]);
+    assert_eq!(moves[14..], ["g7h8n", "g2f1q", "g1f1"]);
+    assert!(processor.take_warnings().is_empty());
+    assert_eq!(
+        processor.fen_at(17).unwrap(),
+        "r1bqkbnN/1ppp3p/p1n5/1B6/8/8/PPPP1P1P/RNBQ1K2 b q - 0 9"
+    );
+
+    // Both backends agree on ordinary games
+    let games = split_games("1. d4 Nf6 2. c4 e6 3. Nc3 Bb4 4. e3 O-O 5. Bd3 d5 6. cxd5 exd5 *");
+    let mut chess = PgnProcessor::new();
+    assert_eq!(
+        chess.process_game(&games[0]),
+        processor.process_game(&games[0])
+    );
+
+    // --backend applies to every command, wherever it is given
+    let mut args = ["prep", "--backend", "mailbox", "in.pgn"]
+        .map(str::to_string)
+        .to_vec();
+    assert_eq!(take_backend(&mut args), Ok(BackendKind::Mailbox));
+    assert_eq!(args, ["prep", "in.pgn"]);
+    let mut args = vec!["game".to_string(), "in.pgn".to_string()];
+    assert_eq!(take_backend(&mut args), Ok(BackendKind::Chess));
+    assert!(take_backend(&mut vec!["--backend".to_string()]).is_err());
+    assert!(take_backend(&mut ["--backend", "x88"].map(str::to_string).to_vec()).is_err());
+}
+
+#[cfg(feature = "shakmaty")]
+#[test]
+fn test_shakmaty_backend() {
+    use crate::backend::BackendKind;
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    // Promotions, en passant, castling and a set-up position, played as the mailbox plays them
+    let pgn = "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. O-O f5 5. exf5 e4 6. f6 exf3 7. fxg7 fxg2 \
+               8. gxh8=N gxf1=Q+ 9. Kxf1 *\n\n\
+               1. d4 Nf6 2. d5 e5 3. dxe6 Bb4+ 4. c3 O-O 5. exd7 Nbxd7 *\n\n\
+               [SetUp \"1\"]\n[FEN \"4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1\"]\n\n1... dxe3 2. Kf1 e2+ *\n";
+    let mut shakmaty = PgnProcessor::new();
+    shakmaty.set_backend(BackendKind::Shakmaty);
+    shakmaty.set_check_invariants(true);
+    let mut mailbox = PgnProcessor::new();
+    mailbox.set_backend(BackendKind::Mailbox);
+    for game in split_games(pgn) {
+        assert_eq!(shakmaty.process_game(&game), mailbox.process_game(&game));
+        assert!(shakmaty.take_warnings().is_empty());
+    }
+    assert_eq!(BackendKind::parse("shakmaty"), Some(BackendKind::Shakmaty));
+}
+
+#[cfg(feature = "tensors")]
+#[test]
+fn test_tensor_export() {
+    use crate::tensor_export::{write_game, write_header, RECORD_SIZE};
+    use crate::PgnProcessor;
+
+    let mut processor = PgnProcessor::new();
+    let moves = processor.process_line(&["e4", "e5", "Nf3"]);
+    let mut tensors = Vec::new();
+    write_header(&mut tensors).unwrap();
+    write_game(&mut tensors, processor.positions(), &moves, "0-1").unwrap();
+    write_game(&mut tensors, processor.positions(), &moves, "*").unwrap();
+    assert_eq!(&tensors[..5], b"PGNT\x01");
+    assert_eq!(tensors.len(), 5 + 3 * RECORD_SIZE);
+
+    let records: Vec<&[u8]> = tensors[5..].chunks(RECORD_SIZE).collect();
+    let plane = |record: &[u8], index: usize| {
+        u64::from_le_bytes(record[index * 8..index * 8 + 8].try_into().unwrap())
+    };
+    // Planes run white pawn to king, then black, with a1 as the lowest bit
+    let start = records[0];
+    assert_eq!(plane(start, 0), 0xff00);
+    assert_eq!(plane(start, 1), 0x42);
+    assert_eq!(plane(start, 5), 1 << 4);
+    assert_eq!(plane(start, 6), 0x00ff << 48);
+    assert_eq!(plane(start, 11), 1 << 60);
+    assert_eq!(start[96..], [0, 0b1111, 12, 28, 0xff]);
+
+    let after_e4 = records[1];
+    assert_eq!(plane(after_e4, 0), 0xef00 | 1 << 28);
+    assert_eq!(after_e4[96..], [1, 0b1111, 52, 36, 0xff]);
+    assert_eq!(records[2][98..100], [6, 21]);
+}
+
+#[test]
+fn test_ply_limit() {
+    use crate::generator::san_movetext;
+    use crate::pgn_cleaner::split_games;
+    use crate::pgn_preprocessor::DEFAULT_PLY_LIMIT;
+    use crate::writer::numbered_moves;
+    use crate::PgnProcessor;
+
+    // 400 plies of knights shuffling, numbered up to move 200
+    let mut movetext = String::new();
+    for round in 0..100 {
+        movetext.push_str(&format!(
+            "{}. Nf3 Nf6 {}. Ng1 Ng8 ",
+            2 * round + 1,
+            2 * round + 2
+        ));
+    }
+    movetext.push('*');
+    let games = split_games(&movetext);
+
+    let mut processor = PgnProcessor::new();
+    assert_eq!(processor.ply_limit(), DEFAULT_PLY_LIMIT);
+    let moves = processor.process_game(&games[0]);
+    assert_eq!(moves.len(), 400);
+    assert!(!processor.ply_limit_exceeded() && !processor.is_corrupt());
+    assert_eq!(
+        processor.fen_at(400).unwrap().rsplit(' ').next(),
+        Some("201")
+    );
+    assert!(san_movetext(&moves).contains(" 200. Ng1 Ng8"));
+    // The listing crunch prints numbers every move, past 256 plies and into three digits
+    let listing = numbered_moves(&moves);
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines.len(), 200);
+    assert_eq!(lines[0], "1. g1f3 g8f6 ");
+    assert_eq!(lines[99], "100. f3g1 f6g8 ");
+    assert_eq!(lines[128], "129. g1f3 g8f6 ");
+    assert_eq!(lines[199], "200. f3g1 f6g8 ");
+    assert_eq!(
+        numbered_moves(&moves[..201]).lines().last(),
+        Some("101. g1f3 ")
+    );
+
+    processor.set_ply_limit(300);
+    let moves = processor.process_game(&games[0]);
+    assert_eq!(moves.len(), 300);
+    assert!(processor.ply_limit_exceeded() && processor.is_corrupt());
+    let warnings = processor.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].ply, 301);
+    assert_eq!(warnings[0].message, "Game exceeds the limit of 300 plies");
+
+    // The next game starts afresh
+    processor.process_line(&["e4"]);
+    assert!(!processor.ply_limit_exceeded());
+}
+
+#[test]
+fn test_binary_encoding() {
+    use crate::binary::{decode, encode_game, MAGIC};
+
+    let tags = vec![
+        ("Event".to_string(), "Ending".to_string()),
+        ("Result".to_string(), "1-0".to_string()),
+        ("SetUp".to_string(), "1".to_string()),
+        (
+            "FEN".to_string(),
+            "4k3/P7/8/8/8/8/8/4K3 b - - 0 50".to_string(),
+        ),
+    ];
+    let moves: Vec<String> = ["e8d7", "a7a8q"].map(str::to_string).to_vec();
+    let mut bytes = MAGIC.to_vec();
+    encode_game(&mut bytes, &tags, &moves).unwrap();
+    let first_game = bytes.len();
+    encode_game(&mut bytes, &[], &["e2e4".to_string()]).unwrap();
+    // The tag and move counts, then a byte for the move
+    assert_eq!(bytes.len() - first_game, 3);
+
+    let games = decode(&bytes).unwrap();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].tags, tags);
+    assert_eq!(games[0].moves, moves);
+    assert_eq!(games[0].movetext().unwrap(), "50... Kd7 51. a8=Q 1-0");
+    assert_eq!(games[1].movetext().unwrap(), "1. e4 *");
+
+    assert!(encode_game(&mut bytes, &[], &["e2e5".to_string()]).is_err());
+    assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+    assert!(decode(b"PGN").is_err());
+}
+
+#[test]
+fn test_binary_malformed() {
+    use crate::binary::{decode, MAGIC};
+
+    let file = |body: &[u8]| [MAGIC.as_slice(), body].concat();
+    let huge = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+
+    // No tags, then a move count far beyond the file
+    assert!(decode(&file(&[[0x00].as_slice(), &huge].concat())).is_err());
+    // A tag whose length runs past the end, or overflows the offset
+    assert!(decode(&file(&[[0x01].as_slice(), &huge].concat())).is_err());
+    let longest = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+    assert!(decode(&file(&[[0x01, 0x00].as_slice(), &longest].concat())).is_err());
+    // A number that never ends
+    assert!(decode(&file(&[0xff; 12])).is_err());
+    // A move index with no legal move behind it
+    assert!(decode(&file(&[0x00, 0x01, 0x7f])).is_err());
+}
+
+#[test]
+fn test_known_games() {
+    use crate::backend::BackendKind;
+    use crate::binary::{encode_game, MAGIC};
+    use crate::incremental::{game_hash, KnownGames};
+
+    let tags = |white: &str| {
+        vec![
+            ("White".to_string(), white.to_string()),
+            ("Black".to_string(), "B".to_string()),
+        ]
+    };
+    let moves: Vec<String> = ["e2e4", "e7e5"].map(str::to_string).to_vec();
+    let hash = game_hash(&tags("A"), &moves);
+    assert_ne!(hash, game_hash(&tags("C"), &moves));
+    assert_ne!(hash, game_hash(&tags("A"), &moves[..1]));
+
+    // Crunched outputs in coordinate moves or SAN, and the binary encoding
+    let coordinate = KnownGames::load(
+        b"[White \"A\"]\n[Black \"B\"]\n\ne2e4 e7e5\n\n",
+        BackendKind::Chess,
+    )
+    .unwrap();
+    let san = KnownGames::load(
+        b"[White \"A\"]\n[Black \"B\"]\n\n1. e4 e5 *\n\n",
+        BackendKind::Mailbox,
+    )
+    .unwrap();
+    let mut bytes = MAGIC.to_vec();
+    encode_game(&mut bytes, &tags("A"), &moves).unwrap();
+    let binary = KnownGames::load(&bytes, BackendKind::Chess).unwrap();
+    for known in [coordinate, san, binary] {
+        assert_eq!(known.len(), 1);
+        assert!(known.contains(hash));
+    }
+
+    let mut known = KnownGames::new();
+    assert!(known.insert(hash));
+    assert!(!known.insert(hash));
+}
+
+#[test]
+fn test_source_map() {
+    use crate::pgn_cleaner::split_games;
+    use crate::source::read_with_map;
+
+    let directory = std::env::temp_dir().join(format!("source-map-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let first = "[Event \"A\"]\n\n1. e4 e5 1-0\n\n[Event \"B\"]\n\n1. d4 *\n";
+    std::fs::write(directory.join("1.pgn"), first).unwrap();
+    std::fs::write(directory.join("2.pgn"), "\n[Event \"C\"]\n1. c4 0-1\n").unwrap();
+    std::fs::write(directory.join("notes.txt"), "not a game").unwrap();
+
+    let (input, map) = read_with_map(directory.to_str(), 0).unwrap();
+    let games = split_games(&input);
+    let ranges: Vec<(String, usize, usize)> = games
+        .iter()
+        .map(|game| {
+            let (file, start) = map.locate(game.offset).unwrap();
+            let name = file.rsplit('/').next().unwrap().to_string();
+            (name, start, start + game.end - game.offset)
+        })
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            ("1.pgn".to_string(), 0, 26),
+            ("1.pgn".to_string(), 27, 48),
+            ("2.pgn".to_string(), 1, 23),
+        ]
+    );
+    assert_eq!(&first[27..48], "[Event \"B\"]\n\n1. d4 *\n");
+
+    // Resuming reads from an offset, the map still counting from the start
+    let (resumed, map) = read_with_map(directory.to_str(), 27).unwrap();
+    assert!(resumed.starts_with("[Event \"B\"]"));
+    assert_eq!(map.locate(49).unwrap().1, 1);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_two_pass() {
+    use crate::pgn_cleaner::split_games;
+    use crate::source::{Directory, FileSource, Source};
+    use crate::two_pass::{read_games, scan_matching};
+
+    let directory = std::env::temp_dir().join(format!("two-pass-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let pgn =
+        "[Event \"A\"]\n\n1. e4 e5 1-0\n\n[Event \"B\"]\n\n1. d4 { [%clk 0:01:00] }\nd5 *\n\n\
+               [Event \"A\"]\n\n1. c4 0-1\n";
+    std::fs::write(directory.join("games.pgn"), pgn).unwrap();
+    let expected: Vec<_> = split_games(pgn)
+        .into_iter()
+        .filter(|game| game.tag("Event") == Some("A"))
+        .collect();
+
+    // A file is read by seeking to the games, a directory by skipping the bytes between them
+    let sources: [Box<dyn Source>; 2] = [
+        Box::new(FileSource(directory.join("games.pgn"))),
+        Box::new(Directory(directory.clone())),
+    ];
+    for source in sources {
+        let (mut games, numbers, scanned) =
+            scan_matching(source.as_ref(), |game| game.tag("Event") == Some("A")).unwrap();
+        assert_eq!((numbers, scanned), (vec![1, 3], 3));
+        read_games(source.as_ref(), &mut games).unwrap();
+        for (game, expected) in games.iter().zip(&expected) {
+            assert_eq!(game.movetext, expected.movetext);
+            assert_eq!(game.movetext_lines, expected.movetext_lines);
+            assert_eq!(game.offset, expected.offset);
+        }
+    }
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_game_index() {
+    use crate::game_index::{read_game, write_index, GameIndex, IndexEntry};
+    use crate::pgn_cleaner::split_games;
+    use crate::source::FileSource;
+
+    let path = std::env::temp_dir().join(format!("game-index-{}.pgn", std::process::id()));
+    let pgn = "[White \"A\"]\n\n1. e4 e5 1-0\n\n[White \"B\"]\n\n1. d4 d5 *\n\n[Black \"C\"]\n\n1. c4 0-1\n";
+    std::fs::write(&path, pgn).unwrap();
+    let tags = vec!["White".to_string()];
+    let entries: Vec<IndexEntry> = split_games(pgn)
+        .iter()
+        .enumerate()
+        .map(|(index, game)| IndexEntry {
+            offset: game.offset as u64,
+            end: game.end as u64,
+            hash: index as u64,
+            values: vec![game.tag("White").unwrap_or_default().to_string()],
+        })
+        .collect();
+
+    let bytes = write_index(pgn.len() as u64, &tags, &entries);
+    let mut index = GameIndex::open(std::io::Cursor::new(bytes)).unwrap();
+    assert_eq!((index.len(), index.input_len), (3, pgn.len() as u64));
+    assert_eq!(index.tags, tags);
+    assert_eq!(index.entry(0).unwrap(), None);
+    assert_eq!(index.entry(4).unwrap(), None);
+
+    // Entries are read in any order, each game from its byte range alone
+    let third = index.entry(3).unwrap().unwrap();
+    assert_eq!((third.hash, third.values), (2, vec![String::new()]));
+    let second = index.entry(2).unwrap().unwrap();
+    assert_eq!(second.values, vec!["B".to_string()]);
+    let text = read_game(&FileSource(path.clone()), &second).unwrap();
+    assert_eq!(text, "[White \"B\"]\n\n1. d4 d5 *\n");
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(GameIndex::open(std::io::Cursor::new(b"PGNC\x01".to_vec())).is_err());
+}
+
+#[test]
+fn test_file_list() {
+    use crate::source::{read_file_list, read_files_with_map, read_to_string, FileList};
+
+    let directory = std::env::temp_dir().join(format!("file-list-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let (first, second) = (directory.join("b.pgn"), directory.join("a.pgn"));
+    std::fs::write(&first, "1. e4 e5 *\n\n").unwrap();
+    std::fs::write(&second, "1. d4 d5 *\n").unwrap();
+    let list = directory.join("list.txt");
+    std::fs::write(
+        &list,
+        format!("{}\n\n  {}  \n", first.display(), second.display()),
+    )
+    .unwrap();
+
+    // Files are read in the order listed, not by name
+    let paths = read_file_list(list.to_str().unwrap()).unwrap();
+    assert_eq!(paths, vec![first.clone(), second.clone()]);
+    let pgn = read_to_string(&FileList(paths.clone()), 0).unwrap();
+    assert_eq!(pgn, "1. e4 e5 *\n\n1. d4 d5 *\n");
+    let (_, map) = read_files_with_map(&paths, 0).unwrap();
+    assert_eq!(map.locate(13), Some((second.to_str().unwrap(), 1)));
+
+    std::fs::write(&list, "missing.pgn\n").unwrap();
+    assert!(read_file_list(list.to_str().unwrap()).is_err());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_study_chapters() {
+    use crate::pgn_ast::Game;
+    use crate::pgn_cleaner::split_games;
+    use crate::study::{chapter_name, make_chapter, DEFAULT_CHAPTER_TEMPLATE};
+
+    let games = split_games(
+        "[White \"Carlsen\"]\n[Black \"Nepo\"]\n[Round \"6\"]\n[ChapterName \"Old\"]\n\n1. d4 {Solid} *\n",
+    );
+    let mut game = Game::parse(&games[0]);
+    assert_eq!(
+        chapter_name(DEFAULT_CHAPTER_TEMPLATE, &game),
+        "Carlsen - Nepo"
+    );
+    assert_eq!(
+        chapter_name("R{Round}: {White}-{Black} {Date} {x y}", &game),
+        "R6: Carlsen-Nepo ? {x y}"
+    );
+
+    make_chapter(&mut game, Some("WCh"), "Game 6".to_string());
+    let mut pgn = String::new();
+    crate::writer::write_parsed_game(&game, &mut pgn);
+    assert_eq!(
+        pgn,
+        "[White \"Carlsen\"]\n[Black \"Nepo\"]\n[Round \"6\"]\n[StudyName \"WCh\"]\n\
+         [ChapterName \"Game 6\"]\n\n1. d4 {Solid} *\n\n"
+    );
+}
+
+#[test]
+fn test_lichess_import() {
+    use crate::lichess::{import_config, study_id};
+
+    assert_eq!(study_id("AbCd1234"), Some("AbCd1234"));
+    assert_eq!(
+        study_id("https://lichess.org/study/AbCd1234/XyZ98765#12"),
+        Some("AbCd1234")
+    );
+    assert_eq!(study_id("https://lichess.org/AbCd1234"), None);
+    assert_eq!(study_id("short"), None);
+
+    // The chapter and token are quoted for curl's configuration syntax
+    assert_eq!(
+        import_config("lip_x", "A \"B\"", "[White \"A\"]\n\n1. e4 *\n"),
+        "header = \"Authorization: Bearer lip_x\"\n\
+         data-urlencode = \"name=A \\\"B\\\"\"\n\
+         data-urlencode = \"pgn=[White \\\"A\\\"]\\n\\n1. e4 *\\n\"\n"
+    );
+}
+
+#[test]
+fn test_unique_positions() {
+    use crate::dataset::UniquePositions;
+    use crate::PgnProcessor;
+
+    let mut processor = PgnProcessor::new();
+    let mut unique = UniquePositions::new();
+    let mut output = String::new();
+    let mut collect = |line: &[&str], result: &str, output: &mut String| {
+        let moves = processor.process_line(line);
+        unique.collect(processor.positions(), &moves, result, output);
+    };
+
+    collect(&["e4", "e5", "Nf3"], "1-0", &mut output);
+    assert_eq!(output.lines().count(), 3);
+    // Only the position after 1. e4 e5 2. Nf3 Nc6 is new, and 2... Nc6 is played from it
+    collect(&["e4", "e5", "Nf3", "Nc6", "Bb5"], "0-1", &mut output);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert!(
+        lines[0].starts_with("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\te2e4\t1-0")
+    );
+    assert!(lines[3].ends_with("\tb8c6\t0-1"));
+    assert!(lines[4].ends_with("\tf1b5\t0-1"));
+
+    // A transposition reaches the position before 3. Bb5 again
+    collect(&["Nf3", "e5", "e4", "Nc6", "Bb5"], "*", &mut output);
+    assert_eq!(output.lines().count(), 8);
+    assert!(!output.contains("\tf1b5\t*"));
+
+    // Quiet mode leaves out the position in check
+    let mut quiet = UniquePositions::new();
+    quiet.set_quiet_only(true);
+    let mut output = String::new();
+    let moves = processor.process_line(&["d4", "e5", "Nf3", "Bb4+", "c3"]);
+    quiet.collect(processor.positions(), &moves, "*", &mut output);
+    assert!(output.contains("\td2d4\t*"));
+    assert!(!output.contains("\tc2c3\t*"));
+}
+
+#[test]
+fn test_sharding() {
+    use crate::pgn_cleaner::split_games;
+    use crate::sharding::{ShardKey, ShardWriter};
+
+    let games = split_games(
+        "[White \"Carlsen, Magnus\"]
+[Black \"Nakamura, Hikaru\"]
+[Date \"2021.05.03\"]
+[ECO \"B90\"]
+[Result \"1-0\"]
+
+1. e4 1-0
+
+[White \"Carlsen, Magnus\"]
+[Black \"Carlsen, Magnus\"]
+[UTCDate \"2019.01.01\"]
+
+1. d4 1/2-1/2
+
+[White \"\"]
+[Date \"????.??.??\"]
+[ECO \"?\"]
+
+1. c4 *",
+    );
+    let keys =
+        |key: ShardKey| -> Vec<Vec<String>> { games.iter().map(|game| key.keys(game)).collect() };
+
+    assert_eq!(
+        keys(ShardKey::Eco),
+        [vec!["B90"], vec!["unknown"], vec!["unknown"]]
+    );
+    assert_eq!(
+        keys(ShardKey::Year),
+        [vec!["2021"], vec!["2019"], vec!["unknown"]]
+    );
+    // A game goes to both players' shards, once when they are the same
+    assert_eq!(
+        keys(ShardKey::Player),
+        [
+            vec!["Carlsen__Magnus", "Nakamura__Hikaru"],
+            vec!["Carlsen__Magnus"],
+            vec!["unknown"]
+        ]
+    );
+    // Without a Result tag the termination marker decides
+    assert_eq!(
+        keys(ShardKey::Result),
+        [vec!["1-0"], vec!["1_2-1_2"], vec!["unknown"]]
+    );
+    assert_eq!(ShardKey::parse("opening"), None);
+
+    let directory = std::env::temp_dir().join(format!("shards-{}", std::process::id()));
+    let mut shards = ShardWriter::new(directory.join("out").to_str().unwrap()).unwrap();
+    for (game, keys) in games.iter().zip(keys(ShardKey::Player)) {
+        for key in keys {
+            shards.write(&key, &game.movetext).unwrap();
+        }
+    }
+    shards.flush().unwrap();
+    let shard = |key: &str| {
+        std::fs::read_to_string(directory.join("out").join(format!("games_{key}.pgn"))).unwrap()
+    };
+    assert_eq!(
+        shard("Carlsen__Magnus"),
+        format!("{}{}", games[0].movetext, games[1].movetext)
+    );
+    assert_eq!(shard("Nakamura__Hikaru"), games[0].movetext);
+    assert_eq!(shard("unknown"), games[2].movetext);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,303 @@
+#[test]
+fn test_fen_round_trip() {
+    use crate::position::Position;
+
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1",
+        "8/8/4k3/8/8/4K3/8/8 b - - 0 1",
+    ] {
+        let position = Position::from_fen(fen).unwrap();
+        assert_eq!(position.to_fen(), fen);
+        assert!(Position::from_fen(&position.to_fen()).unwrap() == position);
+    }
+
+    assert!(Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1").is_err());
+    assert!(
+        Position::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err()
+    );
+    assert!(
+        Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1").is_err()
+    );
+}
+
+#[test]
+fn test_invariants_hold_through_castling_and_en_passant() {
+    use crate::pgn_cleaner::split_games;
+    use crate::PgnProcessor;
+
+    let games = split_games("1. e4 Nf6 2. e5 d5 3. exd6 Nc6 4. Nf3 Bg4 5. Be2 Qd7 6. O-O O-O-O *");
+    let mut processor = PgnProcessor::new();
+    processor.set_check_invariants(true);
+
+    let moves = processor.process_game(&games[0]);
+    assert_eq!(moves.len(), 12);
+    assert_eq!(moves[4], "e5d6");
+    assert_eq!(processor.take_warnings(), vec![]);
+}
+
+#[test]
+fn test_sanity_errors() {
+    use crate::position::Position;
+
+    let sane = Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    assert!(sane.unwrap().sanity_errors().is_empty());
+
+    let no_black_king = Position::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(no_black_king.sanity_errors(), vec!["black has 0 kings"]);
+
+    let mover_in_check = Position::from_fen("4k3/8/8/8/8/8/4r3/4K3 b - - 0 1").unwrap();
+    assert_eq!(
+        mover_in_check.sanity_errors(),
+        vec!["the side that just moved is in check"]
+    );
+
+    let too_many_queens = Position::from_fen("4k3/8/8/8/8/8/PPPPPPPP/QQ2K3 w - - 0 1").unwrap();
+    assert_eq!(
+        too_many_queens.sanity_errors(),
+        vec!["white has 1 promoted pieces with 8 pawns left"]
+    );
+}
+
+#[test]
+fn test_legal_moves() {
+    use crate::position::Position;
+
+    assert_eq!(Position::initial().legal_moves().len(), 20);
+
+    // Kiwipete, a standard move generation test position
+    let kiwipete =
+        Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    assert_eq!(kiwipete.unwrap().legal_moves().len(), 48);
+
+    // Castling through an attacked square is not allowed, the other side still is
+    let attacked = Position::from_fen("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1").unwrap();
+    let castles: Vec<_> = attacked
+        .legal_moves()
+        .into_iter()
+        .filter(|(from, to, _)| *from == 4 && to.abs_diff(*from) == 2)
+        .collect();
+    assert_eq!(castles, vec![(4, 2, None)]);
+
+    let promotion = Position::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let promotions = promotion
+        .legal_moves()
+        .into_iter()
+        .filter(|(from, _, _)| *from == 48)
+        .count();
+    assert_eq!(promotions, 4);
+}
+
+#[test]
+fn test_explain_illegal() {
+    use crate::legality::explain_illegal;
+    use crate::position::Position;
+
+    let explain = |fen: &str, san: &str| explain_illegal(&Position::from_fen(fen).unwrap(), san);
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    assert_eq!(
+        explain(start, "Bc4"),
+        "Bc4 impossible: bishop on f1 is blocked by piece on e2"
+    );
+    assert_eq!(
+        explain(start, "Nd2"),
+        "Nd2 impossible: d2 is occupied by own pawn"
+    );
+    assert_eq!(
+        explain(start, "exd3"),
+        "exd3 impossible: pawn on e2 has nothing to capture on d3"
+    );
+    assert_eq!(
+        explain(start, "O-O"),
+        "O-O impossible: blocked by piece on f1"
+    );
+    assert_eq!(
+        explain("4k3/8/8/4p3/4P3/8/8/4K3 w - - 0 1", "e5"),
+        "e5 impossible: pawn on e4 is blocked by piece on e5"
+    );
+    assert_eq!(
+        explain("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1", "Nf4"),
+        "Nf4 impossible: knight on e2 is pinned"
+    );
+    assert_eq!(
+        explain("4k3/8/8/8/8/8/8/N3K2N w - - 0 1", "Nf3"),
+        "Nf3 impossible: knight on a1 cannot reach f3; knight on h1 cannot reach f3"
+    );
+    assert_eq!(
+        explain("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1", "e4"),
+        "e4 impossible: no pawn on e"
+    );
+    assert_eq!(
+        explain("4k3/8/8/8/8/8/8/4K3 w - - 0 1", "Qd4"),
+        "Qd4 impossible: no white queen on the board"
+    );
+}
+
+#[test]
+fn test_suggest_move() {
+    use crate::legality::suggest_move;
+    use crate::pgn_cleaner::split_games;
+    use crate::pgn_preprocessor::RepairMode;
+    use crate::position::Position;
+    use crate::san::to_san;
+    use crate::PgnProcessor;
+
+    let start = Position::initial();
+    assert_eq!(suggest_move(&start, "Nf4").as_deref(), Some("Nf3"));
+    assert_eq!(suggest_move(&start, "e5").as_deref(), Some("e4"));
+
+    let rooks = Position::from_fen("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1").unwrap();
+    assert_eq!(to_san(&rooks, 0, 16, None), "R1a3");
+    assert_eq!(to_san(&rooks, 32, 56, None), "Ra8+");
+
+    let games = split_games("1. e4 e5 2. Nf4 Nc6 *");
+    let mut processor = PgnProcessor::new();
+    processor.set_repair(RepairMode::Apply);
+    assert_eq!(
+        processor.process_game(&games[0]),
+        vec!["e2e4", "e7e5", "g1f3", "b8c6"]
+    );
+    let warnings = processor.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.ends_with("replaced by Nf3"));
+}
+
+#[test]
+fn test_mirror_game() {
+    use crate::mirror::{mirror_move, mirror_position, mirror_tags};
+    use crate::position::{coordinate_move, Position};
+
+    let position =
+        Position::from_fen("r3k2r/pppq1ppp/8/3pP3/8/8/PPP2PPP/R3K2R w Kq d6 0 9").unwrap();
+    let mirrored = mirror_position(&position);
+    assert_eq!(
+        mirrored.to_fen(),
+        "r3k2r/ppp2ppp/8/8/3Pp3/8/PPPQ1PPP/R3K2R b Qk d3 0 9"
+    );
+    assert!(mirror_position(&mirrored) == position);
+
+    // Every legal move has its mirror image legal in the mirrored position
+    let mut moves: Vec<String> = position
+        .legal_moves()
+        .into_iter()
+        .map(|(from, to, promotion)| mirror_move(&coordinate_move(from, to, promotion)).unwrap())
+        .collect();
+    let mut mirrored_moves: Vec<String> = mirrored
+        .legal_moves()
+        .into_iter()
+        .map(|(from, to, promotion)| coordinate_move(from, to, promotion))
+        .collect();
+    moves.sort();
+    mirrored_moves.sort();
+    assert_eq!(moves, mirrored_moves);
+    assert_eq!(mirror_move("e7e8q").as_deref(), Some("e2e1q"));
+
+    let tags: Vec<(String, String)> = [
+        ("White", "Anna"),
+        ("Black", "Boris"),
+        ("BlackElo", "2400"),
+        ("Result", "1-0"),
+        ("ECO", "C20"),
+    ]
+    .map(|(tag, value)| (tag.to_string(), value.to_string()))
+    .to_vec();
+    let tags = mirror_tags(&tags, &Position::initial());
+    let tag = |name: &str| {
+        tags.iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    };
+    assert_eq!(tag("White"), Some("Boris"));
+    assert_eq!(tag("WhiteElo"), Some("2400"));
+    assert_eq!(tag("Result"), Some("0-1"));
+    assert_eq!(tag("ECO"), None);
+    assert_eq!(
+        tag("FEN"),
+        Some("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1")
+    );
+}
+
+#[test]
+fn test_random_games() {
+    use crate::generator::{random_game, san_movetext};
+    use crate::openings::OpeningTree;
+    use crate::pgn_cleaner::split_games;
+    use crate::position::{parse_coordinate_move, Position};
+    use crate::sampling::Rng;
+    use crate::PgnProcessor;
+
+    let mut book = OpeningTree::new(4);
+    let line: Vec<String> = ["e2e4", "c7c5", "g1f3", "d7d6"]
+        .map(str::to_string)
+        .to_vec();
+    book.add_game(&line, "1-0");
+
+    let mut rng = Rng::new(42);
+    for _ in 0..5 {
+        let game = random_game(&mut rng, Some(&book), 60);
+        assert_eq!(game.moves[..4], line[..]);
+        assert!(game.moves.len() <= 60);
+
+        let mut position = Position::initial();
+        for mv in &game.moves {
+            let mv = parse_coordinate_move(mv).unwrap();
+            assert!(position.legal_moves().contains(&mv));
+            position = position.play(mv.0, mv.1, mv.2);
+        }
+
+        let movetext = format!("{} {}", san_movetext(&game.moves), game.result);
+        let parsed = split_games(&movetext);
+        assert_eq!(PgnProcessor::new().process_game(&parsed[0]), game.moves);
+    }
+
+    let first = random_game(&mut Rng::new(7), None, 40);
+    let second = random_game(&mut Rng::new(7), None, 40);
+    assert_eq!(first.moves, second.moves);
+}
+
+#[test]
+fn test_perft() {
+    use crate::backend::BackendKind;
+    use crate::perft::{
+        backend_divide, backend_perft, check_crate, divide, known_nodes, perft, KNOWN_PERFT,
+    };
+    use crate::position::Position;
+
+    for known in &KNOWN_PERFT {
+        let position = Position::from_fen(known.fen).unwrap();
+        assert_eq!(perft(&position, 2), known.nodes[1], "{}", known.name);
+        assert_eq!(
+            known_nodes(&position, 2),
+            Some((known.name, known.nodes[1]))
+        );
+    }
+
+    let moves = divide(&Position::initial(), 3);
+    assert_eq!(moves.len(), 20);
+    assert_eq!(moves.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8902);
+    assert_eq!(known_nodes(&Position::initial(), 9), None);
+
+    let check = check_crate(2);
+    assert_eq!(check.positions, 21);
+    assert!(check.mismatches.is_empty(), "{:?}", check.mismatches);
+
+    // Counts on the boards SAN is resolved against
+    for known in &KNOWN_PERFT {
+        let position = Position::from_fen(known.fen).unwrap();
+        assert_eq!(
+            backend_perft(BackendKind::Mailbox, &position, 2),
+            Ok(known.nodes[1]),
+            "{}",
+            known.name
+        );
+    }
+    let initial = Position::initial();
+    assert_eq!(backend_perft(BackendKind::Chess, &initial, 3), Ok(8902));
+    let moves = backend_divide(BackendKind::Chess, &initial, 2).unwrap();
+    assert_eq!(moves.len(), 20);
+    assert!(moves.contains(&("g1f3".to_string(), 20)));
+    let kiwipete = Position::from_fen(KNOWN_PERFT[1].fen).unwrap();
+    assert!(backend_perft(BackendKind::Chess, &kiwipete, 1).is_err());
+}
@@ -0,0 +1,242 @@
+#[test]
+fn test_complete_roster() {
+    use crate::pgn_cleaner::split_games;
+    use crate::roster::{complete_roster, missing_tags};
+
+    let pgn = "[White \"Anderssen\"]
+[Black \"Kieseritzky\"]
+[ECO \"C33\"]
+
+1. e4 e5 2. f4 exf4 0-1";
+
+    let mut games = split_games(pgn);
+    assert_eq!(games.len(), 1);
+    assert_eq!(
+        missing_tags(&games[0]),
+        vec!["Event", "Site", "Date", "Round", "Result"]
+    );
+
+    complete_roster(&mut games[0]);
+    assert!(missing_tags(&games[0]).is_empty());
+    assert_eq!(games[0].tags[0], ("Event".to_string(), "?".to_string()));
+    assert_eq!(games[0].tag("Date"), Some("????.??.??"));
+    assert_eq!(games[0].tag("White"), Some("Anderssen"));
+    assert_eq!(games[0].tag("Result"), Some("0-1"));
+    assert_eq!(games[0].tags[7], ("ECO".to_string(), "C33".to_string()));
+}
+
+#[test]
+fn test_event_standings() {
+    use crate::events::{group_events, parse_round};
+    use crate::pgn_cleaner::split_games;
+
+    assert_eq!(parse_round("3.1"), Some(vec![3, 1]));
+    assert_eq!(parse_round("?"), None);
+
+    let game = |round: &str, white: &str, black: &str, result: &str| {
+        format!(
+            "[Event \"Open\"]\n[Site \"Here\"]\n[Date \"2024.03.0{}\"]\n[EventDate \"2024.03.01\"]\n[Round \"{round}\"]\n[White \"{white}\"]\n[Black \"{black}\"]\n[Result \"{result}\"]\n\n{result}\n\n",
+            &round[..1]
+        )
+    };
+    let input = [
+        game("2", "A", "C", "1/2-1/2"),
+        game("10", "C", "B", "*"),
+        game("1.2", "B", "A", "0-1"),
+        game("1.1", "A", "B", "1-0"),
+    ]
+    .concat()
+        + "[Event \"Other\"]\n[Site \"Here\"]\n[Date \"2024.03.01\"]\n\n*\n";
+
+    let events = group_events(&split_games(&input));
+    assert_eq!(events.len(), 2);
+    let rounds: Vec<&str> = events[0]
+        .games
+        .iter()
+        .map(|game| game.round.as_str())
+        .collect();
+    assert_eq!(rounds, ["1.1", "1.2", "2", "10"]);
+    assert_eq!(events[0].rounds(), [1, 2, 10]);
+
+    let standings = events[0].standings();
+    let table: Vec<(&str, u32)> = standings
+        .iter()
+        .map(|standing| (standing.player.as_str(), standing.half_points))
+        .collect();
+    assert_eq!(table, [("A", 5), ("C", 1), ("B", 0)]);
+    assert_eq!(standings[2].games.len(), 3);
+
+    let text = events[0].to_text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[1], "Rank  Player    Pts      R1   R2  R10");
+    assert_eq!(lines[2], "   1  A         2.5  +3w+3b  =2w");
+    assert_eq!(lines[3], "   2  C         0.5          =1b  *3w");
+}
+
+#[test]
+fn test_crosstable_tie_breaks() {
+    use crate::crosstable::{Crosstable, CrosstableFormat};
+    use crate::events::group_events;
+    use crate::pgn_cleaner::split_games;
+
+    let game = |round: u32, white: &str, black: &str, result: &str| {
+        format!(
+            "[Event \"Club\"]\n[Round \"{round}\"]\n[White \"{white}\"]\n[Black \"{black}\"]\n[Result \"{result}\"]\n\n{result}\n\n"
+        )
+    };
+    // A and C tie on points and Buchholz, Sonneborn-Berger puts A ahead
+    let input = [
+        game(1, "A", "B", "1-0"),
+        game(1, "C", "D", "1-0"),
+        game(2, "B", "D", "1-0"),
+        game(2, "A", "C", "1/2-1/2"),
+        game(3, "C", "B", "1/2-1/2"),
+        game(3, "D", "A", "1/2-1/2"),
+    ]
+    .concat();
+
+    let crosstable = Crosstable::of(&group_events(&split_games(&input))[0]);
+    let rows: Vec<(&str, f64, f64)> = crosstable
+        .rows
+        .iter()
+        .map(|row| {
+            (
+                row.standing.player.as_str(),
+                row.buchholz,
+                row.sonneborn_berger,
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("A", 4.0, 2.75),
+            ("C", 4.0, 2.25),
+            ("B", 4.5, 1.5),
+            ("D", 5.5, 1.0)
+        ]
+    );
+
+    let markdown = crosstable.render(CrosstableFormat::Markdown);
+    assert!(markdown.contains("| 2 | C | 2 | +4w | =1b | =3w | 4 | 2.25 |\n"));
+    let html = crosstable.render(CrosstableFormat::Html);
+    assert!(html.contains("<tr><td>1</td><td>A</td><td>2</td>"));
+}
+
+#[test]
+fn test_head_to_head() {
+    use crate::head_to_head::{first_player_white, HeadToHead};
+    use crate::pgn_ast::GameResult;
+    use crate::pgn_cleaner::split_games;
+
+    let games = split_games(
+        "[White \"Carlsen, Magnus\"]\n[Black \"Nakamura, Hikaru\"]\n\n*\n\n\
+         [White \"Nakamura, Hikaru\"]\n[Black \"Carlsen, Magnus\"]\n\n*\n\n\
+         [White \"Nakamura, Hikaru\"]\n[Black \"Caruana, Fabiano\"]\n\n*\n",
+    );
+    let players = ["carlsen".to_string(), "NAKAMURA".to_string()];
+    let sides: Vec<Option<bool>> = games
+        .iter()
+        .map(|game| first_player_white(game, &players))
+        .collect();
+    assert_eq!(sides, [Some(true), Some(false), None]);
+
+    let mut head_to_head = HeadToHead::new(players);
+    head_to_head.add_game(true, Some(GameResult::WhiteWins), "B90");
+    head_to_head.add_game(false, Some(GameResult::WhiteWins), "B90");
+    head_to_head.add_game(false, Some(GameResult::Draw), "D37");
+    head_to_head.add_game(true, None, "D37");
+
+    let total = head_to_head.total();
+    assert_eq!((total.games, total.half_points()), (4, 3));
+    assert_eq!(head_to_head.by_color[1].losses, 1);
+    assert_eq!(head_to_head.by_opening["B90"].opponent_half_points(), 2);
+    assert!(head_to_head
+        .summary()
+        .starts_with("carlsen vs NAKAMURA: 4 games, 1.5-1.5 (+1 =1 -1)\n"));
+}
+
+#[test]
+fn test_redaction() {
+    use crate::cli::parse_args;
+    use crate::redact::{Redaction, TagChange};
+
+    let tags = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(tag, value)| (tag.to_string(), value.to_string()))
+            .collect()
+    };
+    let game = tags(&[
+        ("Event", "Titled Arena"),
+        ("Site", "https://lichess.org/AbCdEfGh?ref=x"),
+        ("White", "Carlsen, Magnus"),
+        ("Black", "?"),
+        ("WhiteFideId", "1503014"),
+        ("BlackId", "-"),
+        ("Annotator", "Someone"),
+    ]);
+
+    // Names become "?" in the roster, other identifying tags go, Site keeps its origin
+    let mut stripped = game.clone();
+    Redaction::Strip.redact_tags(&mut stripped);
+    assert_eq!(
+        stripped,
+        tags(&[
+            ("Event", "Titled Arena"),
+            ("Site", "https://lichess.org"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("BlackId", "-"),
+        ])
+    );
+
+    // Pseudonyms are stable for a salt and differ between salts
+    let hash = |salt: &str| Redaction::Hash {
+        salt: salt.to_string(),
+    };
+    let mut hashed = game.clone();
+    hash("pepper").redact_tags(&mut hashed);
+    let white = &hashed[2].1;
+    assert!(white.starts_with("anon-") && white.len() == 15, "{white}");
+    assert_eq!(hashed[3].1, "?");
+    assert!(hashed[4].1.starts_with("anon-"));
+    assert_eq!(hashed[5].1, "-");
+    assert_eq!(hashed.len(), game.len());
+    assert_eq!(
+        hash("pepper").tag_change("Black", " Carlsen, Magnus "),
+        TagChange::Replace(white.clone())
+    );
+    assert_ne!(
+        hash("salt").tag_change("White", "Carlsen, Magnus"),
+        TagChange::Replace(white.clone())
+    );
+    assert_eq!(
+        Redaction::Strip.tag_change("Site", "Oslo NOR"),
+        TagChange::Keep
+    );
+    assert_eq!(
+        Redaction::Strip.tag_change("Site", "https://lichess.org"),
+        TagChange::Keep
+    );
+
+    let parse = |args: &[&str]| {
+        let args = ["in.pgn"].iter().chain(args).map(|arg| arg.to_string());
+        parse_args(args).map(|options| options.redact)
+    };
+    assert_eq!(parse(&["--redact", "strip"]), Ok(Some(Redaction::Strip)));
+    assert_eq!(parse(&["--redact", "hash"]), Ok(Some(hash(""))));
+    // A salt implies hash mode, and is kept whichever order the flags come in
+    assert_eq!(parse(&["--redact-salt", "s"]), Ok(Some(hash("s"))));
+    assert_eq!(
+        parse(&["--redact-salt", "s", "--redact", "hash"]),
+        Ok(Some(hash("s")))
+    );
+    assert_eq!(
+        parse(&["--redact", "hash", "--redact-salt", "s"]),
+        Ok(Some(hash("s")))
+    );
+    assert!(parse(&["--redact", "strip", "--redact-salt", "s"]).is_err());
+    assert!(parse(&["--redact-salt", "s", "--redact", "strip"]).is_err());
+    assert!(parse(&["--redact", "blur"]).is_err());
+}
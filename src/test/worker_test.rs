@@ -0,0 +1,134 @@
+#[test]
+fn test_json_parse() {
+    use crate::json::{parse, Value};
+
+    let value = parse(r#" {"a": [1, -2.5e1, true, null], "b": "x\"é\n", "c": {}} "#).unwrap();
+    assert_eq!(
+        value.get("a"),
+        Some(&Value::Array(vec![
+            Value::Number(1.0),
+            Value::Number(-25.0),
+            Value::Bool(true),
+            Value::Null
+        ]))
+    );
+    assert_eq!(value.get("b").and_then(Value::as_str), Some("x\"é\n"));
+    assert_eq!(value.get("c"), Some(&Value::Object(vec![])));
+
+    assert!(parse(r#"{"a": 1"#).is_err());
+    assert!(parse("[1, 2] 3").is_err());
+    assert!(parse(r#""open"#).is_err());
+}
+
+#[test]
+fn test_worker_jobs() {
+    use crate::worker::{serve, Job, Outcome};
+
+    let job = Job::parse(r#"{"id": 7, "input": "in.pgn", "format": "san", "args": ["--trace"]}"#);
+    assert_eq!(
+        job,
+        Ok(Job {
+            id: Some("7".to_string()),
+            args: ["in.pgn", "--format", "san", "--trace"]
+                .map(String::from)
+                .to_vec(),
+        })
+    );
+    assert!(Job::parse(r#"{"id": "x"}"#).is_err());
+    assert!(Job::parse(r#"{"input": "in.pgn", "format": "morse"}"#).is_err());
+
+    let jobs = "{\"id\": \"a\", \"input\": \"a.pgn\"}\n\n{\"id\": \"b\", \"input\": 3}\n";
+    let mut results = Vec::new();
+    let mut ran = Vec::new();
+    serve(jobs.as_bytes(), &mut results, |job| {
+        ran.push(job.args.clone());
+        Outcome::Done { warnings: 2 }
+    })
+    .unwrap();
+
+    assert_eq!(ran, vec![vec!["a.pgn".to_string()]]);
+    assert_eq!(
+        String::from_utf8(results).unwrap(),
+        "{\"id\":\"a\",\"status\":\"done\",\"warnings\":2}\n\
+         {\"id\":\"b\",\"status\":\"failed\",\"message\":\"Field input must be a string\"}\n"
+    );
+}
+
+#[test]
+fn test_output_queue() {
+    use crate::checkpoint::Checkpoint;
+    use crate::output_queue::OutputQueue;
+    use std::fs::{self, File};
+    use std::io::BufWriter;
+
+    let path = std::env::temp_dir().join(format!("queue-test-{}.pgn", std::process::id()));
+    let checkpoint = format!("{}.checkpoint", path.display());
+    let file = BufWriter::new(File::create(&path).unwrap());
+
+    // A cap smaller than a game still lets every game through
+    let mut queue = OutputQueue::spawn(file, 4);
+    for game in 0..100 {
+        queue.push(format!("game {game}\n")).unwrap();
+    }
+    queue.checkpoint(&checkpoint, 123, 100).unwrap();
+    queue.push("last\n".to_string()).unwrap();
+    let length = queue.finish().unwrap();
+
+    let written = fs::read_to_string(&path).unwrap();
+    assert_eq!(written.len() as u64, length);
+    assert!(written.starts_with("game 0\ngame 1\n") && written.ends_with("game 99\nlast\n"));
+    assert_eq!(
+        Checkpoint::load(&checkpoint).unwrap(),
+        Checkpoint {
+            input_offset: 123,
+            games: 100,
+            output_len: length - 5,
+        }
+    );
+    fs::remove_file(path).unwrap();
+    fs::remove_file(checkpoint).unwrap();
+}
+
+#[test]
+fn test_canonical_output() {
+    use crate::cli::parse_args;
+    use crate::json::decimal;
+
+    let args = ["in.pgn", "out.pgn", "--sample", "3", "--canonical"].map(str::to_string);
+    let options = parse_args(args.into_iter()).unwrap();
+    assert!(options.canonical);
+    assert_eq!(options.seed, None);
+
+    assert_eq!(decimal(2.0 / 3.0, 2), "0.67");
+    assert_eq!(decimal(-0.04, 1), "0.0");
+    assert_eq!(decimal(-0.06, 1), "-0.1");
+    assert_eq!(decimal(0.0, 0), "0");
+}
+
+#[test]
+fn test_game_profiler() {
+    use std::time::Duration;
+
+    use crate::profiling::{GameProfiler, GameTiming};
+
+    let mut profiler = GameProfiler::new(2);
+    for (game_number, millis) in [(1, 5), (2, 40), (3, 1), (4, 40), (5, 7)] {
+        profiler.record(GameTiming {
+            game_number,
+            plies: 10 * game_number,
+            movetext_bytes: 100,
+            elapsed: Duration::from_millis(millis),
+        });
+    }
+
+    let slowest: Vec<usize> = profiler
+        .slowest()
+        .iter()
+        .map(|timing| timing.game_number)
+        .collect();
+    assert_eq!(slowest, [2, 4]);
+    let report = profiler.report();
+    assert!(report.starts_with("Processed 5 games in 0.093s, slowest 2:\n"));
+    assert!(report
+        .contains("  game 4: 40.000 ms, 40 plies, 100 bytes of movetext (43.0% of the time)\n"));
+}
@@ -0,0 +1,150 @@
+use crate::json;
+use crate::patterns::move_tags;
+use crate::pgn_ast::{Game, MoveNode};
+use crate::pgn_cleaner::{mainline_moves, variations};
+use crate::phases::phases;
+use crate::position::{parse_coordinate_move, Piece, Position};
+use crate::sacrifices::sacrificed;
+use crate::PgnProcessor;
+
+/// Which part of the move tree of annotated games is written.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum LineSelection {
+    #[default]
+    Mainline,
+    /// The longest line among the mainline and the side lines, the mainline winning ties.
+    Longest,
+    /// The whole tree as one JSON object per game.
+    Tree,
+}
+
+impl LineSelection {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "mainline" => Some(LineSelection::Mainline),
+            "longest" => Some(LineSelection::Longest),
+            "tree" => Some(LineSelection::Tree),
+            _ => None,
+        }
+    }
+}
+
+/// SAN moves of the longest line from the start of the game.
+pub fn longest_line(movetext: &str) -> Vec<&str> {
+    let mut longest = mainline_moves(movetext);
+
+    for variation in variations(movetext) {
+        if variation.moves.len() > longest.len() {
+            longest = variation.moves;
+        }
+    }
+
+    longest
+}
+
+/// The game as a JSON object with its tags, result and full move tree. Each move has its SAN
+/// and, when it could be played, its coordinate form, the game phase it leads to and the
+/// facts of `move_metadata`.
+pub fn game_json(game: &Game, result: &str, processor: &mut PgnProcessor) -> String {
+    let tags = json::object(
+        game.tags
+            .iter()
+            .map(|tag| (tag.name, json::string(&tag.value))),
+    );
+    let moves = line_json(&mut Vec::new(), &game.moves.moves, processor);
+
+    json::object([
+        ("tags", tags),
+        ("result", json::string(result)),
+        ("moves", moves),
+    ])
+}
+
+/// Encodes a line of the tree reached by playing `path` from the initial position.
+fn line_json<'a>(
+    path: &mut Vec<&'a str>,
+    line: &[MoveNode<'a>],
+    processor: &mut PgnProcessor,
+) -> String {
+    let branch = path.len();
+    path.extend(line.iter().map(|node| node.san));
+    let processed = processor.process_line(path);
+    let positions = processor.positions();
+    let phases = phases(positions);
+    let metadata: Vec<_> = processed
+        .iter()
+        .enumerate()
+        .map(|(ply, mv)| move_metadata(&positions[ply], &positions[ply + 1], mv))
+        .collect();
+    path.truncate(branch);
+
+    let mut nodes = Vec::new();
+    for (i, node) in line.iter().enumerate() {
+        let mv = processed
+            .get(branch + i)
+            .map_or("null".to_string(), |mv| json::string(mv));
+        let mut fields = vec![("san", json::string(node.san)), ("move", mv)];
+        if let Some(phase) = phases.get(branch + i + 1) {
+            fields.push(("phase", json::string(phase.name())));
+        }
+        if let Some(metadata) = metadata.get(branch + i) {
+            fields.extend(metadata.iter().cloned());
+        }
+
+        if !node.variations.is_empty() {
+            let variations: Vec<String> = node
+                .variations
+                .iter()
+                .map(|variation| line_json(path, &variation.moves, processor))
+                .collect();
+            fields.push(("variations", json::array(variations)));
+        }
+        nodes.push(json::object(fields));
+
+        path.push(node.san);
+    }
+    path.truncate(branch);
+
+    json::array(nodes)
+}
+
+/// What a coordinate move does, worked out on the positions before and after it: the moving
+/// piece, the piece it captures (null if none), whether it checks, mates, castles or
+/// promotes, its tactical tags and the piece it sacrifices (null if none).
+pub fn move_metadata(before: &Position, after: &Position, mv: &str) -> Vec<(&'static str, String)> {
+    let Some((from, to, promotion)) = parse_coordinate_move(mv) else {
+        return Vec::new();
+    };
+    let piece = before.piece_at(from).map(|(_, piece)| piece);
+    // En passant captures a pawn off the target square
+    let captured = before
+        .is_capture(from, to)
+        .then(|| before.piece_at(to).map_or(Piece::Pawn, |(_, piece)| piece));
+    let name =
+        |piece: Option<Piece>| piece.map_or("null".to_string(), |piece| json::string(piece.name()));
+    let flag = |value: bool| value.to_string();
+
+    vec![
+        ("piece", name(piece)),
+        ("captured", name(captured)),
+        ("is_check", flag(after.in_check(after.side_to_move))),
+        ("is_mate", flag(after.is_checkmate())),
+        (
+            "is_castle",
+            flag(piece == Some(Piece::King) && from.abs_diff(to) == 2),
+        ),
+        ("is_promotion", flag(promotion.is_some())),
+        (
+            "tags",
+            json::array(
+                move_tags(after, from, to)
+                    .iter()
+                    .map(|tag| json::string(tag.name())),
+            ),
+        ),
+        (
+            "sacrifice",
+            name(sacrificed(before, after, from, to).map(|(piece, _)| piece)),
+        ),
+    ]
+}
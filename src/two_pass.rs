@@ -0,0 +1,58 @@
+//! Pulling a few games out of a large dump in two passes. The first streams the input through a
+//! header scan, keeping the byte ranges of the games passing the header filters and nothing
+//! else; the second reads those ranges alone, seeking to them when the input allows it.
+
+use std::io::{self, BufReader, Read};
+
+use crate::pgn_cleaner::{fill_movetext, GameReader, RawGame};
+use crate::source::Source;
+
+/// The games of the input that `keep` accepts, with their tags but no movetext, and their
+/// 1-based numbers in the input; then the number of games scanned.
+pub fn scan_matching(
+    source: &dyn Source,
+    mut keep: impl FnMut(&RawGame) -> bool,
+) -> io::Result<(Vec<RawGame>, Vec<usize>, usize)> {
+    let (mut matching, mut numbers, mut scanned) = (Vec::new(), Vec::new(), 0);
+    for game in GameReader::new(BufReader::new(source.open(0)?), true) {
+        let game = game?;
+        scanned += 1;
+        if keep(&game) {
+            matching.push(game);
+            numbers.push(scanned);
+        }
+    }
+    Ok((matching, numbers, scanned))
+}
+
+/// Reads the movetext of games found by [`scan_matching`], in input order.
+pub fn read_games(source: &dyn Source, games: &mut [RawGame]) -> io::Result<()> {
+    // Sources that cannot seek are read once, skipping the bytes between games
+    let mut sequential = None;
+    let mut position = 0;
+
+    for game in games {
+        let length = (game.end - game.offset) as u64;
+        let mut bytes = Vec::new();
+        if source.seekable() {
+            source
+                .open(game.offset as u64)?
+                .take(length)
+                .read_to_end(&mut bytes)?;
+        } else {
+            let reader = match &mut sequential {
+                Some(reader) => reader,
+                None => sequential.insert(source.open(0)?),
+            };
+            let gap = (game.offset - position) as u64;
+            io::copy(&mut reader.take(gap), &mut io::sink())?;
+            reader.take(length).read_to_end(&mut bytes)?;
+            position = game.end;
+        }
+        let text = String::from_utf8(bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Input is not UTF-8"))?;
+        fill_movetext(&text, game);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// Remaining time and increment of both players, in milliseconds.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Clocks {
+    pub white: u64,
+    pub black: u64,
+    pub white_increment: u64,
+    pub black_increment: u64,
+}
+
+/// Evaluation reported by an engine, from the point of view of the side to move.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Score {
+    Centipawns(i32),
+    /// Mate in this many moves; negative when the side to move gets mated.
+    Mate(i32),
+}
+
+impl Score {
+    /// The score of an `info` line such as `info depth 20 score cp 31 pv e2e4`.
+    fn parse_info(line: &str) -> Option<Score> {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("info") {
+            return None;
+        }
+        words.find(|word| *word == "score")?;
+        let kind = words.next()?;
+        let value = words.next()?.parse().ok()?;
+
+        match kind {
+            "cp" => Some(Score::Centipawns(value)),
+            "mate" => Some(Score::Mate(value)),
+            _ => None,
+        }
+    }
+
+    /// Centipawns, with mates mapped beyond any material score.
+    pub fn centipawns(&self) -> i32 {
+        match *self {
+            Score::Centipawns(centipawns) => centipawns,
+            Score::Mate(moves) if moves > 0 => 100_000 - moves,
+            Score::Mate(moves) => -100_000 - moves,
+        }
+    }
+}
+
+/// Outcome of a search.
+pub struct Search {
+    /// The move chosen, in coordinate notation; None when the engine has no move.
+    pub best_move: Option<String>,
+    pub score: Option<Score>,
+    /// Tablebase hits reported by the deepest search, 0 when the engine reports none.
+    pub tbhits: u64,
+}
+
+/// A chess engine speaking UCI over its standard input and output.
+pub struct Engine {
+    /// Name reported by the engine, or its command line.
+    pub name: String,
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+}
+
+fn engine_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Engine {
+    /// Starts the engine from a command line such as `stockfish` or `lc0 --threads=2`.
+    pub fn start(command_line: &str) -> io::Result<Engine> {
+        let mut words = command_line.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| engine_error("Empty engine command".to_string()))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let (Some(input), Some(output)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(engine_error(format!("Cannot talk to {command_line}")));
+        };
+        let mut engine = Engine {
+            name: command_line.to_string(),
+            child,
+            input,
+            output: BufReader::new(output),
+        };
+
+        engine.send("uci")?;
+        let lines = engine.read_until("uciok")?;
+        if let Some(name) = lines.iter().find_map(|line| line.strip_prefix("id name ")) {
+            engine.name = name.trim().to_string();
+        }
+
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.input, "{command}")?;
+        self.input.flush()
+    }
+
+    /// Reads lines up to and including the first one starting with `keyword`.
+    fn read_until(&mut self, keyword: &str) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+
+        loop {
+            let mut line = String::new();
+            if self.output.read_line(&mut line)? == 0 {
+                return Err(engine_error(format!(
+                    "{} exited while waiting for {keyword}",
+                    self.name
+                )));
+            }
+            let line = line.trim().to_string();
+            let done = line.split_whitespace().next() == Some(keyword);
+            lines.push(line);
+            if done {
+                return Ok(lines);
+            }
+        }
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {name} value {value}"))
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        self.read_until("readyok").map(|_| ())
+    }
+
+    /// Searches the position reached by `moves` from `fen` (the starting position when None)
+    /// with the given `go` arguments, such as `movetime 1000`.
+    pub fn search(&mut self, fen: Option<&str>, moves: &[String], go: &str) -> io::Result<Search> {
+        let mut command = match fen {
+            Some(fen) => format!("position fen {fen}"),
+            None => "position startpos".to_string(),
+        };
+        if !moves.is_empty() {
+            command.push_str(&format!(" moves {}", moves.join(" ")));
+        }
+        self.send(&command)?;
+        self.send(&format!("go {go}"))?;
+
+        let lines = self.read_until("bestmove")?;
+        let best_move = lines
+            .last()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .filter(|mv| !["(none)", "0000"].contains(mv))
+            .map(str::to_string);
+        // The last reported score belongs to the deepest search
+        let score = lines.iter().rev().find_map(|line| Score::parse_info(line));
+        let tbhits = lines
+            .iter()
+            .rev()
+            .find_map(|line| {
+                let mut words = line.split_whitespace().skip_while(|word| *word != "tbhits");
+                words.nth(1)?.parse().ok()
+            })
+            .unwrap_or(0);
+
+        Ok(Search {
+            best_move,
+            score,
+            tbhits,
+        })
+    }
+
+    /// The engine's move after the given coordinate moves from the starting position,
+    /// or None when it reports having no move.
+    pub fn best_move(&mut self, moves: &[String], clocks: &Clocks) -> io::Result<Option<String>> {
+        let go = format!(
+            "wtime {} btime {} winc {} binc {}",
+            clocks.white, clocks.black, clocks.white_increment, clocks.black_increment
+        );
+        self.search(None, moves, &go).map(|search| search.best_move)
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        if self.send("quit").is_err() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,136 @@
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process::{Command, Stdio};
+
+use crate::cli::{self, Command as CliCommand};
+use crate::json::{self, Value};
+
+/// A crunch job read from one NDJSON line, such as
+/// `{"id": "june", "input": "june.pgn", "output": "june.out.pgn", "format": "san", "args": ["--skip-empty-games"]}`.
+/// Only `input` is required; `args` holds any further crunch option.
+#[derive(Debug, PartialEq)]
+pub struct Job {
+    pub id: Option<String>,
+    /// Command line of the crunch run.
+    pub args: Vec<String>,
+}
+
+impl Job {
+    pub fn parse(line: &str) -> Result<Job, String> {
+        let value = json::parse(line)?;
+        let text = |key: &str| match value.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(text)) => Ok(Some(text.clone())),
+            Some(_) => Err(format!("Field {key} must be a string")),
+        };
+
+        let id = match value.get("id") {
+            Some(Value::Number(number)) => Some(number.to_string()),
+            _ => text("id")?,
+        };
+        let mut args = vec![text("input")?.ok_or("Missing input")?];
+        args.extend(text("output")?);
+        if let Some(format) = text("format")? {
+            args.extend(["--format".to_string(), format]);
+        }
+        match value.get("args") {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(values)) => {
+                for value in values {
+                    let arg = value.as_str().ok_or("Field args must hold strings")?;
+                    args.push(arg.to_string());
+                }
+            }
+            Some(_) => return Err("Field args must be an array".to_string()),
+        }
+
+        // Reject bad options before running anything
+        match cli::parse_command(args.clone().into_iter())? {
+            CliCommand::Crunch(_) => Ok(Job { id, args }),
+            _ => Err("Jobs can only crunch games".to_string()),
+        }
+    }
+}
+
+/// How a job went.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Done { warnings: usize },
+    Failed { message: String },
+}
+
+/// The NDJSON result record of a job.
+pub fn result_record(id: Option<&str>, outcome: &Outcome) -> String {
+    let id = id.map_or_else(|| "null".to_string(), json::string);
+    match outcome {
+        Outcome::Done { warnings } => json::object([
+            ("id", id),
+            ("status", json::string("done")),
+            ("warnings", warnings.to_string()),
+        ]),
+        Outcome::Failed { message } => json::object([
+            ("id", id),
+            ("status", json::string("failed")),
+            ("message", json::string(message)),
+        ]),
+    }
+}
+
+/// Runs a job in a child process of this executable, so that its console output stays out
+/// of the result stream.
+pub fn run_job(job: &Job) -> Outcome {
+    let output = env::current_exe().and_then(|executable| {
+        Command::new(executable)
+            .args(&job.args)
+            .stdin(Stdio::null())
+            .output()
+    });
+    let output = match output {
+        Ok(output) => output,
+        Err(error) => {
+            return Outcome::Failed {
+                message: error.to_string(),
+            }
+        }
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        let warnings = stderr
+            .lines()
+            .filter(|line| line.starts_with("Warning:"))
+            .count();
+        Outcome::Done { warnings }
+    } else {
+        let message = stderr.lines().last().unwrap_or_default();
+        Outcome::Failed {
+            message: format!("{} ({})", message, output.status),
+        }
+    }
+}
+
+/// Processes the jobs of an NDJSON stream one after the other, writing a result record per
+/// non-empty line.
+pub fn serve<R: BufRead, W: Write, F: FnMut(&Job) -> Outcome>(
+    reader: R,
+    mut writer: W,
+    mut run: F,
+) -> io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = match Job::parse(&line) {
+            Ok(job) => result_record(job.id.as_deref(), &run(&job)),
+            Err(message) => {
+                let value = json::parse(&line).ok();
+                let id = value.as_ref().and_then(|value| value.get("id")?.as_str());
+                result_record(id, &Outcome::Failed { message })
+            }
+        };
+        writeln!(writer, "{record}")?;
+        writer.flush()?;
+    }
+    Ok(())
+}
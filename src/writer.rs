@@ -0,0 +1,44 @@
+use crate::pgn_ast::Game;
+
+/// Appends a game in export form: the tag pairs, a blank line, then the movetext.
+pub fn write_game(tags: &[(String, String)], movetext: &str, output: &mut String) {
+    for (tag, value) in tags {
+        write_tag(tag, value, output);
+    }
+    if !tags.is_empty() {
+        output.push('\n');
+    }
+    output.push_str(movetext);
+    output.push_str("\n\n");
+}
+
+/// Appends a parsed game in export form, side lines, comments and NAGs included.
+pub fn write_parsed_game(game: &Game, output: &mut String) {
+    for tag in &game.tags {
+        write_tag(tag.name, &tag.value, output);
+    }
+    if !game.tags.is_empty() {
+        output.push('\n');
+    }
+    output.push_str(&game.movetext());
+    output.push_str("\n\n");
+}
+
+/// Processed moves listed a move number per line, as `1. e2e4 e7e5`, each move followed by a
+/// space.
+pub fn numbered_moves(moves: &[String]) -> String {
+    let mut listing = String::new();
+    for (index, pair) in moves.chunks(2).enumerate() {
+        listing.push_str(&format!("{}. ", index + 1));
+        for mv in pair {
+            listing.push_str(mv);
+            listing.push(' ');
+        }
+        listing.push('\n');
+    }
+    listing
+}
+
+fn write_tag(tag: &str, value: &str, output: &mut String) {
+    output.push_str(&format!("[{tag} \"{}\"]\n", value.replace('"', "\\\"")));
+}
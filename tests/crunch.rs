@@ -0,0 +1,148 @@
+//! Runs of the built binary, for what only `crunch` as a whole does: resuming from
+//! checkpoints, reproducible sampling and expanding variations.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const FIRST_GAMES: &str = "[Event \"E\"]
+[White \"A\"]
+[Black \"B\"]
+[Result \"*\"]
+
+1. e4 e5 2. Nf3 Nc6 *
+
+[Event \"E\"]
+[White \"C\"]
+[Black \"D\"]
+[Result \"*\"]
+
+1. d4 d5 *
+
+";
+
+const LAST_GAMES: &str = "[Event \"E\"]
+[White \"F\"]
+[Black \"G\"]
+[Result \"*\"]
+
+1. c4 e5 2. Nc3 Nf6 *
+
+[Event \"E\"]
+[White \"H\"]
+[Black \"I\"]
+[Result \"*\"]
+
+1. Nf3 d5 *
+";
+
+/// A directory of its own for each test.
+fn scratch(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("crunch-{name}-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn crunch(args: &[&Path], flags: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_pgn-crunker"))
+        .args(args)
+        .args(flags)
+        .output()
+        .unwrap();
+    assert!(
+        status.status.success(),
+        "crunch failed: {}",
+        String::from_utf8_lossy(&status.stderr)
+    );
+}
+
+#[test]
+fn resume_matches_uninterrupted_run() {
+    use pgn_crunker::checkpoint::Checkpoint;
+
+    let directory = scratch("resume");
+    let (first, all) = (directory.join("first.pgn"), directory.join("all.pgn"));
+    fs::write(&first, FIRST_GAMES).unwrap();
+    fs::write(&all, format!("{FIRST_GAMES}{LAST_GAMES}")).unwrap();
+    let (expected, resumed) = (
+        directory.join("expected.pgn"),
+        directory.join("resumed.pgn"),
+    );
+    crunch(&[&all, &expected], &[]);
+
+    // A run stopped after the first two games: the input up to there is the same bytes, so
+    // its last checkpoint is the one a run over the whole input takes before game 3
+    crunch(&[&first, &resumed], &["--checkpoint-every", "1"]);
+    let checkpoint_path = Checkpoint::path_for(resumed.to_str().unwrap());
+    let checkpoint = Checkpoint::load(&checkpoint_path).unwrap();
+    assert_eq!(checkpoint.input_offset, FIRST_GAMES.len());
+    assert_eq!(checkpoint.games, 2);
+    // Output written after the checkpoint was taken is dropped on resuming
+    let mut partial = fs::read_to_string(&resumed).unwrap();
+    partial.push_str("[Event \"written after the checkpoint\"]\n");
+    fs::write(&resumed, partial).unwrap();
+
+    crunch(&[&all, &resumed], &["--resume", "--checkpoint-every", "1"]);
+    assert_eq!(
+        fs::read_to_string(&resumed).unwrap(),
+        fs::read_to_string(&expected).unwrap()
+    );
+    let checkpoint = Checkpoint::load(&checkpoint_path).unwrap();
+    assert_eq!(
+        checkpoint.input_offset,
+        FIRST_GAMES.len() + LAST_GAMES.len()
+    );
+    assert_eq!(checkpoint.games, 4);
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn canonical_sampling_is_reproducible() {
+    let directory = scratch("canonical");
+    let input = directory.join("input.pgn");
+    fs::write(&input, format!("{FIRST_GAMES}{LAST_GAMES}").repeat(5)).unwrap();
+    let outputs = [directory.join("first.pgn"), directory.join("second.pgn")];
+
+    for output in &outputs {
+        let run = Command::new(env!("CARGO_BIN_EXE_pgn-crunker"))
+            .args([&input, output])
+            .args(["--sample", "3", "--canonical"])
+            .output()
+            .unwrap();
+        assert!(run.status.success());
+        // The seed is reported, as for clock seeded runs, so it can be passed with --seed
+        assert!(String::from_utf8_lossy(&run.stderr)
+            .contains("Sampling 3 games with seed 0, the --canonical default"));
+    }
+
+    let first = fs::read(&outputs[0]).unwrap();
+    assert_eq!(first, fs::read(&outputs[1]).unwrap());
+    assert_eq!(String::from_utf8_lossy(&first).matches("[Event").count(), 3);
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn variations_of_set_up_games_start_from_their_fen() {
+    let directory = scratch("variations");
+    let (input, output) = (directory.join("input.pgn"), directory.join("output.pgn"));
+    fs::write(
+        &input,
+        "[Event \"E\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\
+         [Result \"*\"]\n\n1. Kd2 (1. Kf2 Kf7) Kd7 2. Ke3 (2. Kc3 Kc7) *\n",
+    )
+    .unwrap();
+    crunch(
+        &[&input, &output],
+        &["--expand-variations", "--backend", "mailbox"],
+    );
+
+    let output = fs::read_to_string(&output).unwrap();
+    let games: Vec<&str> = output.split("\n\n[").collect();
+    assert_eq!(games.len(), 3);
+    // A line branching off at the first move still starts from the game's position
+    assert!(games[1].contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]"));
+    assert!(games[1].ends_with("e1f2 e8f7"));
+    assert!(games[2].contains("[FEN \"8/3k4/8/8/8/8/3KP3/8 w - - 2 2\"]"));
+    assert!(games[2].trim_end().ends_with("d2c3 d7c7"));
+    fs::remove_dir_all(&directory).unwrap();
+}